@@ -5,9 +5,11 @@ use std::time::Instant;
 use clap::{ArgAction, Parser};
 use serde::Serialize;
 
+use zirc_compiler::Compiler;
 use zirc_interpreter::Interpreter;
 use zirc_lexer::Lexer;
 use zirc_parser::Parser as ZircParser;
+use zirc_vm::Vm;
 
 #[derive(Parser, Debug)]
 #[command(name = "zirc-bench", about = "Run Zirc benchmarks")] 
@@ -36,14 +38,26 @@ struct Cli {
     #[arg(long = "silent", default_value_t = true)]
     silent: bool,
 
+    /// Execution backend: interp (tree-walking interpreter) or vm (bytecode compiler + VM)
+    #[arg(short = 'b', long = "backend", value_enum, default_value_t = Backend::Interp)]
+    backend: Backend,
+
     /// List discovered tests and exit
     #[arg(long = "list", default_value_t = false)]
     list: bool,
 }
 
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Backend {
+    Interp,
+    Vm,
+}
+
 #[derive(Debug, Serialize)]
 struct BenchResult {
     name: String,
+    backend: Backend,
     iterations: u32,
     avg_total_ms: f64,
     min_total_ms: f64,
@@ -103,15 +117,33 @@ fn read_script(path: &Path) -> String {
     fs::read_to_string(path).unwrap_or_else(|e| panic!("Failed to read {}: {}", path.display(), e))
 }
 
-fn measure_script(src: &str, iterations: u32, warmup: u32) -> (Vec<f64>, Vec<f64>, Vec<f64>, Vec<f64>, u64) {
+/// Runs a parsed program on the chosen backend and returns the bytes allocated
+/// for strings and lists (interpreter only; the VM does not track allocations,
+/// so it reports 0).
+/// For the VM, compilation is counted as part of execution.
+fn execute(program: zirc_syntax::ast::Program, backend: Backend) -> u64 {
+    match backend {
+        Backend::Interp => {
+            let mut interp = Interpreter::new();
+            interp.run(program).expect("runtime error");
+            interp.memory_stats().total_bytes() as u64
+        }
+        Backend::Vm => {
+            let bytecode = Compiler::new().compile(program).expect("compile error");
+            Vm::new().run(&bytecode).expect("runtime error");
+            0
+        }
+    }
+}
+
+fn measure_script(src: &str, iterations: u32, warmup: u32, backend: Backend) -> (Vec<f64>, Vec<f64>, Vec<f64>, Vec<f64>, u64) {
     // Warmup
     for _ in 0..warmup {
         let mut lexer = Lexer::new(src);
         let tokens = lexer.tokenize().expect("lex error");
         let mut parser = ZircParser::new(tokens);
         let program = parser.parse_program().expect("parse error");
-        let mut interp = Interpreter::new();
-        interp.run(program).expect("runtime error");
+        execute(program, backend);
     }
 
     let mut totals = Vec::with_capacity(iterations as usize);
@@ -134,10 +166,7 @@ fn measure_script(src: &str, iterations: u32, warmup: u32) -> (Vec<f64>, Vec<f64
         let t_parse = t.elapsed();
 
         t = Instant::now();
-        let mut interp = Interpreter::new();
-        interp.run(program).expect("runtime error");
-        let mem = interp.memory_stats();
-        last_mem_bytes = mem.bytes_allocated as u64;
+        last_mem_bytes = execute(program, backend);
         let t_exec = t.elapsed();
 
         let total = t0.elapsed();
@@ -202,7 +231,7 @@ fn main() {
 
     for case in &scripts {
         let src = read_script(&case.path);
-        let (totals, lexes, parses, execs, mem_bytes) = measure_script(&src, cli.iterations, cli.warmup);
+        let (totals, lexes, parses, execs, mem_bytes) = measure_script(&src, cli.iterations, cli.warmup, cli.backend);
         let (avg_t, min_t, max_t) = stats(&totals);
         let (avg_l, _, _) = stats(&lexes);
         let (avg_p, _, _) = stats(&parses);
@@ -216,6 +245,7 @@ fn main() {
 
         results.push(BenchResult {
             name: case.name.clone(),
+            backend: cli.backend,
             iterations: cli.iterations,
             avg_total_ms: avg_t,
             min_total_ms: min_t,
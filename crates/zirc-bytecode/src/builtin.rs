@@ -1,35 +1,146 @@
 //! Builtin function identifiers.
 
+/// A builtin called by `BuiltinCall`. In `.zbc` files a builtin is stored as
+/// its position in [`Builtin::ALL`], so reordering or inserting variants
+/// needs a new [`FORMAT_VERSION`](crate::encode::FORMAT_VERSION).
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Builtin {
     Show,
     ShowF,
+    Format,
     Prompt,
+    PromptTrimmed,
     Rf,
     Wf,
     Len,
     Push,
     Pop,
     Slice,
+    Left,
+    Right,
+    Get,
+    Set,
+    Keys,
+    Values,
+    Sort,
+    Reverse,
+    Range,
     // Mathematical functions
     Abs,
     Min,
     Max,
     Pow,
     Sqrt,
+    Floor,
+    Ceil,
+    Round,
     // Convertion functions
     Hex,
     Bin,
+    ToBase,
     // String functions
     Upper,
     Lower,
     Trim,
     Split,
     Join,
+    Replace,
+    StartsWith,
+    EndsWith,
+    Contains,
+    IndexOf,
     // Type conversion
     Int,
     Str,
+    Bool,
+    Chr,
+    Ord,
     // Utility functions
     Type,
+    Panic,
+    Assert,
+    AssertEq,
+    ScriptPath,
+    ScriptDir,
+    TimeNs,
+    Copy,
+    Hash,
 }
 
+impl Builtin {
+    /// Every builtin, in declaration order.
+    pub const ALL: [Builtin; 55] = [
+        Builtin::Show, Builtin::ShowF, Builtin::Format, Builtin::Prompt, Builtin::PromptTrimmed,
+        Builtin::Rf, Builtin::Wf, Builtin::Len, Builtin::Push, Builtin::Pop, Builtin::Slice,
+        Builtin::Left, Builtin::Right, Builtin::Get, Builtin::Set, Builtin::Keys, Builtin::Values,
+        Builtin::Sort, Builtin::Reverse, Builtin::Range, Builtin::Abs, Builtin::Min, Builtin::Max,
+        Builtin::Pow, Builtin::Sqrt, Builtin::Floor, Builtin::Ceil, Builtin::Round, Builtin::Hex,
+        Builtin::Bin, Builtin::ToBase, Builtin::Upper, Builtin::Lower, Builtin::Trim,
+        Builtin::Split, Builtin::Join, Builtin::Replace, Builtin::StartsWith, Builtin::EndsWith,
+        Builtin::Contains, Builtin::IndexOf, Builtin::Int, Builtin::Str, Builtin::Bool,
+        Builtin::Chr, Builtin::Ord, Builtin::Type, Builtin::Panic, Builtin::Assert,
+        Builtin::AssertEq, Builtin::ScriptPath, Builtin::ScriptDir, Builtin::TimeNs, Builtin::Copy,
+        Builtin::Hash,
+    ];
+
+    /// The name Zirc code calls the builtin by.
+    pub fn name(self) -> &'static str {
+        match self {
+            Builtin::Show => "show",
+            Builtin::ShowF => "showf",
+            Builtin::Format => "format",
+            Builtin::Prompt => "prompt",
+            Builtin::PromptTrimmed => "prompt_trimmed",
+            Builtin::Rf => "rf",
+            Builtin::Wf => "wf",
+            Builtin::Len => "len",
+            Builtin::Push => "push",
+            Builtin::Pop => "pop",
+            Builtin::Slice => "slice",
+            Builtin::Left => "left",
+            Builtin::Right => "right",
+            Builtin::Get => "get",
+            Builtin::Set => "set",
+            Builtin::Keys => "keys",
+            Builtin::Values => "values",
+            Builtin::Sort => "sort",
+            Builtin::Reverse => "reverse",
+            Builtin::Range => "range",
+            Builtin::Abs => "abs",
+            Builtin::Min => "min",
+            Builtin::Max => "max",
+            Builtin::Pow => "pow",
+            Builtin::Sqrt => "sqrt",
+            Builtin::Floor => "floor",
+            Builtin::Ceil => "ceil",
+            Builtin::Round => "round",
+            Builtin::Hex => "hex",
+            Builtin::Bin => "bin",
+            Builtin::ToBase => "to_base",
+            Builtin::Upper => "upper",
+            Builtin::Lower => "lower",
+            Builtin::Trim => "trim",
+            Builtin::Split => "split",
+            Builtin::Join => "join",
+            Builtin::Replace => "replace",
+            Builtin::StartsWith => "starts_with",
+            Builtin::EndsWith => "ends_with",
+            Builtin::Contains => "contains",
+            Builtin::IndexOf => "index_of",
+            Builtin::Int => "int",
+            Builtin::Str => "str",
+            Builtin::Bool => "bool",
+            Builtin::Chr => "chr",
+            Builtin::Ord => "ord",
+            Builtin::Type => "type",
+            Builtin::Panic => "panic",
+            Builtin::Assert => "assert",
+            Builtin::AssertEq => "assert_eq",
+            Builtin::ScriptPath => "script_path",
+            Builtin::ScriptDir => "script_dir",
+            Builtin::TimeNs => "time_ns",
+            Builtin::Copy => "copy",
+            Builtin::Hash => "hash",
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! A readable listing of a compiled [`Program`], for debugging codegen.
+//!
+//! Each function starts with a header line, `main` first, followed by one
+//! line per instruction: its 4-digit index and the instruction with operands
+//! resolved where possible. Jumps show their target index, calls show the
+//! callee's name and argument count, and `PushConst` shows the constant.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use zirc_bytecode::{disassemble, Builtin, Function, Instruction, Program};
+//!
+//! let main = Function {
+//!     name: "main".to_string(),
+//!     arity: 0,
+//!     local_count: 0,
+//!     constants: Vec::new(),
+//!     code: vec![
+//!         Instruction::PushBool(true),
+//!         Instruction::JumpIfFalse(4),
+//!         Instruction::PushInt(1),
+//!         Instruction::BuiltinCall(Builtin::Show, 1),
+//!         Instruction::Halt,
+//!     ],
+//! };
+//! let program = Program { functions: Vec::new(), main, natives: Vec::new() };
+//! assert_eq!(disassemble(&program), "\
+//! fn main (arity 0, locals 0)
+//! 0000  PushBool true
+//! 0001  JumpIfFalse -> 0004
+//! 0002  PushInt 1
+//! 0003  BuiltinCall show/1
+//! 0004  Halt
+//! ");
+//! ```
+
+use std::fmt::Write;
+
+use crate::instruction::Instruction;
+use crate::program::{Function, Program};
+use crate::value::Value;
+
+/// Renders every function in `program` in uniform index order, separated by
+/// blank lines.
+pub fn disassemble(program: &Program) -> String {
+    let mut out = String::new();
+    for (i, f) in program.all_functions().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        writeln!(out, "fn {} (arity {}, locals {})", f.name, f.arity, f.local_count).expect("writing to a String cannot fail");
+        for (pc, instr) in f.code.iter().enumerate() {
+            writeln!(out, "{:04}  {}", pc, instruction(program, f, instr)).expect("writing to a String cannot fail");
+        }
+    }
+    out
+}
+
+fn instruction(program: &Program, f: &Function, instr: &Instruction) -> String {
+    use Instruction::*;
+    match instr {
+        PushInt(n) => format!("PushInt {}", n),
+        PushStr(s) => format!("PushStr {:?}", s),
+        PushConst(i) => match f.constants.get(*i as usize) {
+            Some(v) => format!("PushConst {} = {}", i, constant(v)),
+            None => format!("PushConst {} = <missing>", i),
+        },
+        PushBool(b) => format!("PushBool {}", b),
+        MakeList(n) => format!("MakeList {}", n),
+        MakeMap(n) => format!("MakeMap {}", n),
+        SetIndex(n) => format!("SetIndex {}", n),
+        Unpack(n) => format!("Unpack {}", n),
+        LoadLocal(i) => format!("LoadLocal {}", i),
+        StoreLocal(i) => format!("StoreLocal {}", i),
+        LoadGlobal(name) => format!("LoadGlobal {}", name),
+        StoreGlobal(name) => format!("StoreGlobal {}", name),
+        Jump(t) => format!("Jump -> {:04}", t),
+        JumpIfFalse(t) => format!("JumpIfFalse -> {:04}", t),
+        JumpIfTrue(t) => format!("JumpIfTrue -> {:04}", t),
+        AddOrJump(t) => format!("AddOrJump -> {:04}", t),
+        Call(fi, argc) => match program.functions.get(*fi) {
+            Some(callee) => format!("Call {}/{}", callee.name, argc),
+            None => format!("Call #{}/{}", fi, argc),
+        },
+        BuiltinCall(b, argc) => format!("BuiltinCall {}/{}", b.name(), argc),
+        NativeCall(i, argc) => match program.natives.get(*i as usize) {
+            Some(name) => format!("NativeCall {}/{}", name, argc),
+            None => format!("NativeCall #{}/{}", i, argc),
+        },
+        other => format!("{:?}", other),
+    }
+}
+
+/// Strings are quoted so they stand apart from names; other values print as
+/// `show()` would.
+fn constant(v: &Value) -> String {
+    match v {
+        Value::Str(s) => format!("{:?}", s),
+        other => other.to_string(),
+    }
+}
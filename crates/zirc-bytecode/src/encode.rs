@@ -0,0 +1,376 @@
+//! The `.zbc` binary encoding of a [`Program`].
+//!
+//! A file is the magic bytes `ZBC\0`, the format version as a little-endian
+//! `u16`, then the program: its natives, `main`, and the other functions.
+//! Integers are little-endian; counts, lengths and `usize` operands are `u64`.
+//! Strings are a length followed by UTF-8 bytes, and lists of anything are a
+//! count followed by the items. Instructions and constant values each start
+//! with a one-byte tag.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use zirc_bytecode::{Function, Instruction, Program};
+//!
+//! let main = Function {
+//!     name: "main".to_string(),
+//!     arity: 0,
+//!     local_count: 0,
+//!     constants: Vec::new(),
+//!     code: vec![Instruction::PushInt(7), Instruction::Halt],
+//! };
+//! let program = Program { functions: Vec::new(), main, natives: Vec::new() };
+//!
+//! let bytes = program.to_bytes();
+//! assert!(bytes.starts_with(b"ZBC\0"));
+//! assert_eq!(Program::from_bytes(&bytes).unwrap(), program);
+//! assert_eq!(Program::from_bytes(b"ZIRC").unwrap_err().msg, "Not a Zirc bytecode file");
+//! ```
+
+use zirc_syntax::error::{Result, error};
+
+use crate::builtin::Builtin;
+use crate::instruction::Instruction;
+use crate::program::{Function, Program};
+use crate::value::Value;
+
+/// The first bytes of every `.zbc` file.
+pub const MAGIC: &[u8; 4] = b"ZBC\0";
+
+/// The encoding version written by [`Program::to_bytes`]; other versions are
+/// rejected when loading.
+pub const FORMAT_VERSION: u16 = 1;
+
+pub(crate) fn write_program(program: &Program) -> Vec<u8> {
+    let mut w = Writer(MAGIC.to_vec());
+    w.bytes(&FORMAT_VERSION.to_le_bytes());
+    w.len(program.natives.len());
+    for name in &program.natives {
+        w.str(name);
+    }
+    w.function(&program.main);
+    w.len(program.functions.len());
+    for f in &program.functions {
+        w.function(f);
+    }
+    w.0
+}
+
+pub(crate) fn read_program(bytes: &[u8]) -> Result<Program> {
+    let mut r = Reader { bytes, pos: 0 };
+    if r.take(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+        return error("Not a Zirc bytecode file");
+    }
+    let version = r.u16()?;
+    if version != FORMAT_VERSION {
+        return error(format!("Unsupported bytecode version {} (expected {})", version, FORMAT_VERSION));
+    }
+    let natives = r.list(Reader::str)?;
+    let main = r.function()?;
+    let functions = r.list(Reader::function)?;
+    if r.pos != bytes.len() {
+        return error("Unexpected data after the end of the bytecode");
+    }
+    let program = Program { functions, main, natives };
+    for f in program.all_functions() {
+        if let Err(e) = check_function(&program, f) {
+            return error(format!("Invalid bytecode in function '{}': {}", f.name, e.msg));
+        }
+    }
+    Ok(program)
+}
+
+/// Checks the operands the VM indexes with, so a well-formed file with bad
+/// contents is rejected on load instead of misbehaving at run time.
+fn check_function(program: &Program, f: &Function) -> Result<()> {
+    if f.local_count < f.arity {
+        return error(format!("{} locals cannot hold {} parameters", f.local_count, f.arity));
+    }
+    for instr in &f.code {
+        match *instr {
+            Instruction::Jump(t) | Instruction::JumpIfFalse(t) | Instruction::JumpIfTrue(t) | Instruction::AddOrJump(t) if t > f.code.len() => {
+                return error(format!("jump target {} is past the end of the code", t));
+            }
+            Instruction::PushConst(i) if i as usize >= f.constants.len() => {
+                return error(format!("constant index {} is out of range", i));
+            }
+            Instruction::Call(i, _) if i >= program.functions.len() => {
+                return error(format!("function index {} is out of range", i));
+            }
+            Instruction::NativeCall(i, _) if i as usize >= program.natives.len() => {
+                return error(format!("native index {} is out of range", i));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn bytes(&mut self, b: &[u8]) {
+        self.0.extend_from_slice(b);
+    }
+
+    fn u8(&mut self, n: u8) {
+        self.0.push(n);
+    }
+
+    fn u16(&mut self, n: u16) {
+        self.bytes(&n.to_le_bytes());
+    }
+
+    fn u32(&mut self, n: u32) {
+        self.bytes(&n.to_le_bytes());
+    }
+
+    fn len(&mut self, n: usize) {
+        self.bytes(&(n as u64).to_le_bytes());
+    }
+
+    fn str(&mut self, s: &str) {
+        self.len(s.len());
+        self.bytes(s.as_bytes());
+    }
+
+    fn function(&mut self, f: &Function) {
+        self.str(&f.name);
+        self.len(f.arity);
+        self.len(f.local_count);
+        self.len(f.constants.len());
+        for v in &f.constants {
+            self.value(v);
+        }
+        self.len(f.code.len());
+        for instr in &f.code {
+            self.instruction(instr);
+        }
+    }
+
+    fn value(&mut self, v: &Value) {
+        match v {
+            Value::Int(n) => { self.u8(0); self.bytes(&n.to_le_bytes()) }
+            Value::Float(x) => { self.u8(1); self.bytes(&x.to_bits().to_le_bytes()) }
+            Value::Str(s) => { self.u8(2); self.str(s) }
+            Value::Bool(b) => { self.u8(3); self.u8(*b as u8) }
+            Value::List(items) => {
+                self.u8(4);
+                self.len(items.len());
+                for it in items {
+                    self.value(it);
+                }
+            }
+            Value::Map(entries) => {
+                self.u8(5);
+                self.len(entries.len());
+                for (k, v) in entries {
+                    self.str(k);
+                    self.value(v);
+                }
+            }
+            Value::Unit => self.u8(6),
+        }
+    }
+
+    fn instruction(&mut self, instr: &Instruction) {
+        use Instruction::*;
+        match instr {
+            PushInt(n) => { self.u8(0); self.bytes(&n.to_le_bytes()) }
+            PushStr(s) => { self.u8(1); self.str(s) }
+            PushConst(i) => { self.u8(2); self.u32(*i) }
+            PushBool(b) => { self.u8(3); self.u8(*b as u8) }
+            PushUnit => self.u8(4),
+            MakeList(n) => { self.u8(5); self.len(*n) }
+            MakeMap(n) => { self.u8(6); self.len(*n) }
+            Index => self.u8(7),
+            SetIndex(n) => { self.u8(8); self.len(*n) }
+            Unpack(n) => { self.u8(9); self.len(*n) }
+            IterList => self.u8(10),
+            CheckStep => self.u8(11),
+            ListPush => self.u8(12),
+            ListPop => self.u8(13),
+            LoadLocal(i) => { self.u8(14); self.u16(*i) }
+            StoreLocal(i) => { self.u8(15); self.u16(*i) }
+            LoadGlobal(name) => { self.u8(16); self.str(name) }
+            StoreGlobal(name) => { self.u8(17); self.str(name) }
+            Pop => self.u8(18),
+            Add => self.u8(19),
+            Sub => self.u8(20),
+            Mul => self.u8(21),
+            Div => self.u8(22),
+            Mod => self.u8(23),
+            Neg => self.u8(24),
+            BitAnd => self.u8(25),
+            BitOr => self.u8(26),
+            BitXor => self.u8(27),
+            Shl => self.u8(28),
+            Shr => self.u8(29),
+            Eq => self.u8(30),
+            Ne => self.u8(31),
+            Lt => self.u8(32),
+            Le => self.u8(33),
+            Gt => self.u8(34),
+            Ge => self.u8(35),
+            Not => self.u8(36),
+            Jump(t) => { self.u8(37); self.len(*t) }
+            JumpIfFalse(t) => { self.u8(38); self.len(*t) }
+            JumpIfTrue(t) => { self.u8(39); self.len(*t) }
+            AddOrJump(t) => { self.u8(45); self.len(*t) }
+            Call(f, argc) => { self.u8(40); self.len(*f); self.len(*argc) }
+            BuiltinCall(b, argc) => { self.u8(41); self.u8(*b as u8); self.len(*argc) }
+            NativeCall(i, argc) => { self.u8(42); self.u32(*i); self.len(*argc) }
+            Return => self.u8(43),
+            Halt => self.u8(44),
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        match self.bytes.get(self.pos..self.pos.saturating_add(n)) {
+            Some(b) => {
+                self.pos += n;
+                Ok(b)
+            }
+            None => error("Bytecode file is truncated"),
+        }
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut out = [0; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn i64(&mut self) -> Result<i64> {
+        Ok(i64::from_le_bytes(self.array()?))
+    }
+
+    fn len(&mut self) -> Result<usize> {
+        usize::try_from(u64::from_le_bytes(self.array()?)).or_else(|_| error("Bytecode count does not fit in memory"))
+    }
+
+    fn bool(&mut self) -> Result<bool> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => error(format!("Invalid bool {} in bytecode", other)),
+        }
+    }
+
+    fn str(&mut self) -> Result<String> {
+        let n = self.len()?;
+        String::from_utf8(self.take(n)?.to_vec()).or_else(|_| error("Invalid UTF-8 string in bytecode"))
+    }
+
+    /// A count followed by that many items. The count is not trusted for
+    /// preallocation, since a corrupt file could claim any size.
+    fn list<T>(&mut self, mut item: impl FnMut(&mut Self) -> Result<T>) -> Result<Vec<T>> {
+        let n = self.len()?;
+        let mut out = Vec::new();
+        for _ in 0..n {
+            out.push(item(self)?);
+        }
+        Ok(out)
+    }
+
+    fn function(&mut self) -> Result<Function> {
+        Ok(Function {
+            name: self.str()?,
+            arity: self.len()?,
+            local_count: self.len()?,
+            constants: self.list(Reader::value)?,
+            code: self.list(Reader::instruction)?,
+        })
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        Ok(match self.u8()? {
+            0 => Value::Int(self.i64()?),
+            1 => Value::Float(f64::from_bits(u64::from_le_bytes(self.array()?))),
+            2 => Value::Str(self.str()?),
+            3 => Value::Bool(self.bool()?),
+            4 => Value::List(self.list(Reader::value)?),
+            5 => Value::Map(self.list(|r| Ok((r.str()?, r.value()?)))?),
+            6 => Value::Unit,
+            tag => return error(format!("Invalid value tag {} in bytecode", tag)),
+        })
+    }
+
+    fn instruction(&mut self) -> Result<Instruction> {
+        use Instruction::*;
+        Ok(match self.u8()? {
+            0 => PushInt(self.i64()?),
+            1 => PushStr(self.str()?),
+            2 => PushConst(self.u32()?),
+            3 => PushBool(self.bool()?),
+            4 => PushUnit,
+            5 => MakeList(self.len()?),
+            6 => MakeMap(self.len()?),
+            7 => Index,
+            8 => SetIndex(self.len()?),
+            9 => Unpack(self.len()?),
+            10 => IterList,
+            11 => CheckStep,
+            12 => ListPush,
+            13 => ListPop,
+            14 => LoadLocal(self.u16()?),
+            15 => StoreLocal(self.u16()?),
+            16 => LoadGlobal(self.str()?),
+            17 => StoreGlobal(self.str()?),
+            18 => Pop,
+            19 => Add,
+            20 => Sub,
+            21 => Mul,
+            22 => Div,
+            23 => Mod,
+            24 => Neg,
+            25 => BitAnd,
+            26 => BitOr,
+            27 => BitXor,
+            28 => Shl,
+            29 => Shr,
+            30 => Eq,
+            31 => Ne,
+            32 => Lt,
+            33 => Le,
+            34 => Gt,
+            35 => Ge,
+            36 => Not,
+            37 => Jump(self.len()?),
+            38 => JumpIfFalse(self.len()?),
+            39 => JumpIfTrue(self.len()?),
+            40 => Call(self.len()?, self.len()?),
+            41 => {
+                let code = self.u8()?;
+                match Builtin::ALL.get(code as usize) {
+                    Some(b) => BuiltinCall(*b, self.len()?),
+                    None => return error(format!("Invalid builtin {} in bytecode", code)),
+                }
+            }
+            42 => NativeCall(self.u32()?, self.len()?),
+            43 => Return,
+            44 => Halt,
+            45 => AddOrJump(self.len()?),
+            op => return error(format!("Invalid opcode {} in bytecode", op)),
+        })
+    }
+}
@@ -6,13 +6,21 @@ use crate::builtin::Builtin;
 pub enum Instruction {
     // Constants
     PushInt(i64),
-    PushStr(String),
+    PushStr(String), // inline literal; the compiler emits PushConst instead
+    PushConst(u32),  // pushes a clone of the current function's constants[i]
     PushBool(bool),
     PushUnit,
 
     // Data structures
     MakeList(usize), // pops N items -> pushes List in original order
+    MakeMap(usize),  // pops N key/value pairs -> pushes Map; a repeated key keeps its first position and last value
     Index,           // pops index, base -> pushes element
+    SetIndex(usize), // pops value, N indices, base -> pushes base with base[i1]..[iN] replaced
+    Unpack(usize),   // pops a list of exactly N items -> pushes them last-first
+    IterList,        // pops a list or string -> pushes it as a list (a string becomes its chars)
+    CheckStep,       // pops a for-loop step -> pushes it back; errors unless it is a nonzero int
+    ListPush,        // pops value, list -> pushes the list with value appended
+    ListPop,         // pops list -> pushes its last item, then the list without it
 
     // Locals
     LoadLocal(u16),
@@ -30,6 +38,15 @@ pub enum Instruction {
     Sub,
     Mul,
     Div,
+    Mod,
+    Neg,
+
+    // Bitwise (ints only)
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
 
     // Comparisons
     Eq,
@@ -47,10 +64,12 @@ pub enum Instruction {
     Jump(usize),
     JumpIfFalse(usize),
     JumpIfTrue(usize),
+    AddOrJump(usize), // pops b, a -> pushes a + b, or jumps instead if the int sum overflows (a for loop's step)
 
     // Calls
     Call(usize, usize),     // (function_index, arg_count)
     BuiltinCall(Builtin, usize),
+    NativeCall(u32, usize), // (index into Program::natives, arg_count); host function on the VM
     Return,                 // expects a value on stack (push Unit beforehand if none)
 
     // Program control
@@ -7,11 +7,14 @@ pub mod value;
 pub mod builtin;
 pub mod instruction;
 pub mod program;
+pub mod encode;
+pub mod disasm;
 
 pub use value::Value;
 pub use builtin::Builtin;
 pub use instruction::Instruction;
 pub use program::{Function, Program};
+pub use disasm::disassemble;
 
 #[cfg(test)]
 mod tests {
@@ -67,10 +70,10 @@ mod tests {
             Builtin::Slice,
         ];
         
-        // Just test that they can be cloned and compared
+        // Just test that they can be copied and compared
         for builtin in builtins {
-            let cloned = builtin.clone();
-            assert_eq!(builtin, cloned);
+            let copied = builtin;
+            assert_eq!(builtin, copied);
         }
     }
 
@@ -86,6 +89,8 @@ mod tests {
             Instruction::Sub,
             Instruction::Mul,
             Instruction::Div,
+            Instruction::Mod,
+            Instruction::Neg,
             Instruction::Eq,
             Instruction::Jump(10),
             Instruction::Call(0, 2),
@@ -103,6 +108,7 @@ mod tests {
             name: "test_func".to_string(),
             arity: 2,
             local_count: 5,
+            constants: Vec::new(),
             code: vec![
                 Instruction::LoadLocal(0),
                 Instruction::LoadLocal(1),
@@ -123,6 +129,7 @@ mod tests {
             name: "main".to_string(),
             arity: 0,
             local_count: 1,
+            constants: Vec::new(),
             code: vec![Instruction::PushInt(42), Instruction::Return],
         };
         
@@ -130,17 +137,25 @@ mod tests {
             name: "helper".to_string(),
             arity: 1,
             local_count: 2,
+            constants: Vec::new(),
             code: vec![Instruction::LoadLocal(0), Instruction::Return],
         };
         
         let program = Program {
             functions: vec![helper_func],
             main: main_func,
+            natives: Vec::new(),
         };
         
         assert_eq!(program.functions.len(), 1);
         assert_eq!(program.main.name, "main");
         assert_eq!(program.functions[0].name, "helper");
+
+        let names: Vec<&str> = program.all_functions().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["main", "helper"]);
+        assert_eq!(program.function(Program::MAIN).map(|f| f.arity), Some(0));
+        assert_eq!(program.function(1).map(|f| f.name.as_str()), Some("helper"));
+        assert!(program.function(2).is_none());
     }
 
     #[test]
@@ -157,4 +172,149 @@ mod tests {
         let cloned = nested.clone();
         assert_eq!(nested, cloned);
     }
+
+    #[test]
+    fn test_builtin_all_is_in_declaration_order() {
+        for (i, b) in Builtin::ALL.iter().enumerate() {
+            assert_eq!(*b as usize, i, "{:?}", b);
+        }
+        assert_eq!(Builtin::StartsWith.name(), "starts_with");
+    }
+
+    #[test]
+    fn test_disassemble_resolves_operands() {
+        let helper = Function {
+            name: "helper".to_string(),
+            arity: 1,
+            local_count: 1,
+            constants: Vec::new(),
+            code: vec![Instruction::LoadLocal(0), Instruction::Return],
+        };
+        let main = Function {
+            name: "main".to_string(),
+            arity: 0,
+            local_count: 0,
+            constants: vec![Value::Str("hi".to_string()), Value::Float(1.5)],
+            code: vec![
+                Instruction::PushConst(0),
+                Instruction::Call(0, 1),
+                Instruction::PushConst(1),
+                Instruction::NativeCall(0, 2),
+                Instruction::StoreGlobal("x".to_string()),
+                Instruction::Call(5, 0),
+            ],
+        };
+        let program = Program { functions: vec![helper], main, natives: vec!["host".to_string()] };
+        assert_eq!(disassemble(&program), "\
+fn main (arity 0, locals 0)
+0000  PushConst 0 = \"hi\"
+0001  Call helper/1
+0002  PushConst 1 = 1.5
+0003  NativeCall host/2
+0004  StoreGlobal x
+0005  Call #5/0
+
+fn helper (arity 1, locals 1)
+0000  LoadLocal 0
+0001  Return
+");
+    }
+
+    #[test]
+    fn test_program_bytes_round_trip() {
+        let mut code = vec![
+            Instruction::PushInt(-5), Instruction::PushStr("é".to_string()), Instruction::PushConst(1),
+            Instruction::PushBool(true), Instruction::PushUnit, Instruction::MakeList(3), Instruction::MakeMap(1),
+            Instruction::Index, Instruction::SetIndex(2), Instruction::Unpack(2), Instruction::IterList,
+            Instruction::CheckStep, Instruction::ListPush, Instruction::ListPop, Instruction::LoadLocal(300),
+            Instruction::StoreLocal(1), Instruction::LoadGlobal("g".to_string()), Instruction::StoreGlobal("g".to_string()),
+            Instruction::Pop, Instruction::Add, Instruction::Sub, Instruction::Mul, Instruction::Div, Instruction::Mod,
+            Instruction::Neg, Instruction::BitAnd, Instruction::BitOr, Instruction::BitXor, Instruction::Shl,
+            Instruction::Shr, Instruction::Eq, Instruction::Ne, Instruction::Lt, Instruction::Le, Instruction::Gt,
+            Instruction::Ge, Instruction::Not, Instruction::Jump(7), Instruction::JumpIfFalse(8),
+            Instruction::JumpIfTrue(9), Instruction::AddOrJump(10), Instruction::Call(0, 2), Instruction::NativeCall(0, 1),
+        ];
+        code.extend(Builtin::ALL.iter().map(|b| Instruction::BuiltinCall(*b, 1)));
+        code.extend([Instruction::Return, Instruction::Halt]);
+        let main = Function {
+            name: "main".to_string(),
+            arity: 0,
+            local_count: 2,
+            constants: vec![
+                Value::Int(i64::MIN),
+                Value::Float(-0.5),
+                Value::Str("text".to_string()),
+                Value::Bool(false),
+                Value::List(vec![Value::Unit, Value::List(vec![])]),
+                Value::Map(vec![("k".to_string(), Value::Float(f64::INFINITY))]),
+            ],
+            code,
+        };
+        let helper = Function {
+            name: "helper".to_string(),
+            arity: 2,
+            local_count: 3,
+            constants: Vec::new(),
+            code: vec![Instruction::LoadLocal(0), Instruction::LoadLocal(1), Instruction::Add, Instruction::Return],
+        };
+        let program = Program { functions: vec![helper], main, natives: vec!["host".to_string()] };
+
+        let bytes = program.to_bytes();
+        assert_eq!(Program::from_bytes(&bytes).unwrap(), program);
+
+        // Every proper prefix is rejected, as is anything appended
+        for n in 0..bytes.len() {
+            assert!(Program::from_bytes(&bytes[..n]).is_err(), "prefix of {} bytes", n);
+        }
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert_eq!(Program::from_bytes(&longer).unwrap_err().msg, "Unexpected data after the end of the bytecode");
+    }
+
+    #[test]
+    fn test_program_from_bytes_checks_header() {
+        let program = Program {
+            functions: Vec::new(),
+            main: Function { name: "main".to_string(), arity: 0, local_count: 0, constants: Vec::new(), code: vec![Instruction::Halt] },
+            natives: Vec::new(),
+        };
+        let mut bytes = program.to_bytes();
+        assert_eq!(&bytes[..4], encode::MAGIC);
+
+        bytes[4] = 99;
+        assert_eq!(Program::from_bytes(&bytes).unwrap_err().msg, "Unsupported bytecode version 99 (expected 1)");
+        assert_eq!(Program::from_bytes(b"").unwrap_err().msg, "Not a Zirc bytecode file");
+        assert_eq!(Program::from_bytes(b"let x = 1\n").unwrap_err().msg, "Not a Zirc bytecode file");
+    }
+
+    #[test]
+    fn test_program_from_bytes_checks_operands() {
+        let add = Function {
+            name: "add".to_string(),
+            arity: 2,
+            local_count: 2,
+            constants: Vec::new(),
+            code: vec![Instruction::LoadLocal(0), Instruction::LoadLocal(1), Instruction::Add, Instruction::Return],
+        };
+        let main = Function {
+            name: "main".to_string(),
+            arity: 0,
+            local_count: 0,
+            constants: vec![Value::Int(1), Value::Int(2)],
+            code: vec![Instruction::PushConst(0), Instruction::PushConst(1), Instruction::Call(0, 2), Instruction::Pop, Instruction::Jump(5)],
+        };
+        let program = Program { functions: vec![add], main, natives: Vec::new() };
+        assert_eq!(Program::from_bytes(&program.to_bytes()).unwrap(), program);
+
+        let reject = |change: &dyn Fn(&mut Program), msg: &str| {
+            let mut bad = program.clone();
+            change(&mut bad);
+            assert_eq!(Program::from_bytes(&bad.to_bytes()).unwrap_err().msg, msg);
+        };
+        reject(&|p| p.functions[0].local_count = 0, "Invalid bytecode in function 'add': 0 locals cannot hold 2 parameters");
+        reject(&|p| p.main.code[4] = Instruction::Jump(6), "Invalid bytecode in function 'main': jump target 6 is past the end of the code");
+        reject(&|p| p.main.code[1] = Instruction::PushConst(2), "Invalid bytecode in function 'main': constant index 2 is out of range");
+        reject(&|p| p.main.code[2] = Instruction::Call(1, 2), "Invalid bytecode in function 'main': function index 1 is out of range");
+        reject(&|p| p.main.code[3] = Instruction::NativeCall(0, 0), "Invalid bytecode in function 'main': native index 0 is out of range");
+    }
 }
@@ -1,18 +1,58 @@
 //! Program components for Zirc bytecode.
 
+use zirc_syntax::error::Result;
+
+use crate::encode;
 use crate::instruction::Instruction;
+use crate::value::Value;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Function {
     pub name: String,
     pub arity: usize,
     pub local_count: usize,
+    /// Deduplicated literal pool; `PushConst(i)` pushes `constants[i]`.
+    pub constants: Vec<Value>,
     pub code: Vec<Instruction>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Program {
     pub functions: Vec<Function>,
     pub main: Function,
+    /// Host function names referenced by `NativeCall`, resolved by the VM at run time.
+    pub natives: Vec<String>,
 }
 
+
+impl Program {
+    /// Index of `main` in the uniform numbering of [`Program::function`]. The
+    /// target of `Call(i)` is `functions[i]`, at uniform index `i + 1`.
+    pub const MAIN: usize = 0;
+
+    /// Every function in uniform index order: `main` first, then `functions`.
+    pub fn all_functions(&self) -> impl Iterator<Item = &Function> {
+        std::iter::once(&self.main).chain(&self.functions)
+    }
+
+    /// Returns the function at uniform `index`, or `None` past the end.
+    pub fn function(&self, index: usize) -> Option<&Function> {
+        match index.checked_sub(1) {
+            None => Some(&self.main),
+            Some(i) => self.functions.get(i),
+        }
+    }
+
+    /// Encodes the program in the versioned `.zbc` format described in
+    /// [`encode`](crate::encode).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode::write_program(self)
+    }
+
+    /// Decodes a program written by [`Program::to_bytes`]. Fails on a wrong
+    /// magic header or version, on truncated or corrupt data, and on operands
+    /// that point outside the program, such as a jump past the end of the code.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Program> {
+        encode::read_program(bytes)
+    }
+}
@@ -1,11 +1,5 @@
 //! Value type for Zirc bytecode programs.
+//!
+//! The VM shares its value type with the interpreter; see [`zirc_syntax::value`].
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum Value {
-    Int(i64),
-    Str(String),
-    Bool(bool),
-    List(Vec<Value>),
-    Unit,
-}
-
+pub use zirc_syntax::value::Value;
@@ -1,4 +1,126 @@
-use owo_colors::OwoColorize;
+use std::fmt;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use owo_colors::Style;
+use zirc_syntax::builtins;
+
+/// Whether terminal output is colored, from `--color=always|auto|never`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorChoice { Always, Auto, Never }
+
+impl ColorChoice {
+    /// Reads `--color=WHEN` or `--color WHEN` from the arguments; defaults to `Auto`.
+    pub fn from_args(args: &[String]) -> Result<ColorChoice, String> {
+        let mut i = 1usize;
+        while i < args.len() {
+            let when = if let Some(v) = args[i].strip_prefix("--color=") {
+                Some(v)
+            } else if args[i] == "--color" {
+                args.get(i + 1).map(|s| s.as_str())
+            } else {
+                None
+            };
+            if args[i].starts_with("--color") {
+                return match when {
+                    Some("always") => Ok(ColorChoice::Always),
+                    Some("auto") => Ok(ColorChoice::Auto),
+                    Some("never") => Ok(ColorChoice::Never),
+                    Some(other) => Err(format!("invalid --color value '{}' (expected always, auto or never)", other)),
+                    None => Err("--color expects a value: always, auto or never".to_string()),
+                };
+            }
+            i += 1;
+        }
+        Ok(ColorChoice::Auto)
+    }
+
+    /// Decides whether to color output.
+    ///
+    /// `always` wins over `NO_COLOR`; `never` and `NO_COLOR` each disable color;
+    /// otherwise `auto` colors only when both stdout and stderr are terminals.
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::env::var_os("NO_COLOR").map_or(true, |v| v.is_empty())
+                    && std::io::stdout().is_terminal()
+                    && std::io::stderr().is_terminal()
+            }
+        }
+    }
+}
+
+/// Resolves `--color` from the arguments and applies it, exiting on a bad value.
+pub fn init_color(args: &[String]) {
+    match ColorChoice::from_args(args) {
+        Ok(choice) => set_color(choice.enabled()),
+        Err(msg) => {
+            eprintln!("error: {}", msg);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Stack size for the thread that runs scripts. The interpreter recurses
+/// natively on every Zirc call, so this is what lets it reach its call-depth
+/// limit instead of overflowing the native stack, even in a debug build.
+const RUNNER_STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// Runs `f` on a thread with a [`RUNNER_STACK_SIZE`] stack and waits for it,
+/// exiting with Rust's panic status if it panics.
+pub fn run_with_large_stack(f: impl FnOnce() + Send + 'static) {
+    let runner = std::thread::Builder::new().stack_size(RUNNER_STACK_SIZE).spawn(f);
+    match runner.map(|handle| handle.join()) {
+        Ok(Ok(())) => {}
+        Ok(Err(_)) => std::process::exit(101),
+        Err(e) => {
+            eprintln!("error: cannot start the runner thread: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Sets the color decision used by every [`Colorize`] call; done once at startup.
+pub fn set_color(enabled: bool) {
+    COLOR.store(enabled, Ordering::Relaxed);
+}
+
+/// A value rendered with a style, or plainly when color is disabled.
+pub struct Painted<'a, T: ?Sized> {
+    inner: &'a T,
+    style: Style,
+}
+
+impl<T: fmt::Display + ?Sized> fmt::Display for Painted<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if COLOR.load(Ordering::Relaxed) {
+            write!(f, "{}", self.style.style(self.inner))
+        } else {
+            write!(f, "{}", self.inner)
+        }
+    }
+}
+
+macro_rules! colorize_methods {
+    ($($name:ident),* $(,)?) => {
+        /// Color helpers that honor the startup color decision (see [`set_color`]).
+        pub trait Colorize: fmt::Display {
+            $(
+                fn $name(&self) -> Painted<'_, Self> {
+                    Painted { inner: self, style: Style::new().$name() }
+                }
+            )*
+        }
+    };
+}
+
+colorize_methods!(bold, dimmed, red, green, yellow, cyan, white, bright_black, bright_blue, bright_white);
+
+impl<T: fmt::Display + ?Sized> Colorize for T {}
 
 pub fn provide_error_suggestions(err_msg: &str) {
     // Variable-related errors
@@ -10,7 +132,7 @@ pub fn provide_error_suggestions(err_msg: &str) {
     // Function-related errors
     else if err_msg.contains("Undefined function") {
         eprintln!("{}", "💡 Help: Check if the function name is spelled correctly or if it's defined.".yellow());
-        eprintln!("    {}", "Available built-ins: show, showf, len, abs, min, max, pow, sqrt, upper, lower, trim, split, join, int, str, type".bright_black());
+        eprintln!("    {}", "Available built-ins: show, showf, format, len, abs, min, max, pow, sqrt, floor, ceil, round, upper, lower, trim, split, join, replace, starts_with, ends_with, contains, index_of, sort, reverse, range, int, str, bool, chr, ord, type".bright_black());
         eprintln!("    {}", "Example: fun my_func(x): x * 2 end".bright_black());
         
         // Suggest common typos
@@ -26,7 +148,7 @@ pub fn provide_error_suggestions(err_msg: &str) {
     // Type-related errors
     else if err_msg.contains("Type mismatch") {
         eprintln!("{}", "💡 Help: Make sure the value matches the declared type annotation.".yellow());
-        eprintln!("    {}", "Zirc has types: int, string, bool, list, unit".bright_black());
+        eprintln!("    {}", "Zirc has types: int, float, string, bool, list, unit".bright_black());
         eprintln!("    {}", "Example: let x: int = 42".bright_black());
     }
     
@@ -41,17 +163,25 @@ pub fn provide_error_suggestions(err_msg: &str) {
         eprintln!("{}", "💡 Help: Arithmetic operations work only with numbers.".yellow());
         eprintln!("    {}", "Example: 10 - 3, 4 * 5, 15 / 3".bright_black());
     }
-    else if err_msg.contains("division by zero") {
+    else if err_msg.contains("integer overflow") {
+        eprintln!("{}", "💡 Help: The result does not fit in a 64-bit int.".yellow());
+        eprintln!("    {}", "Ints range from -9223372036854775808 to 9223372036854775807; use floats for larger magnitudes".bright_black());
+    }
+    else if err_msg.contains(builtins::DIVISION_BY_ZERO) {
         eprintln!("{}", "💡 Help: You cannot divide by zero.".yellow());
         eprintln!("    {}", "Check if the divisor is zero before the operation.".bright_black());
     }
     
     // Index errors
-    else if err_msg.contains("index out of bounds") {
+    else if err_msg.contains(builtins::INDEX_OUT_OF_BOUNDS) {
         eprintln!("{}", "💡 Help: Index is outside the valid range.".yellow());
         eprintln!("    {}", "• Lists and strings are 0-indexed".bright_black());
         eprintln!("    {}", "• Use len() to check size: if i < len(my_list): my_list[i] end".bright_black());
     }
+    else if err_msg.contains(builtins::POP_EMPTY_LIST) {
+        eprintln!("{}", "💡 Help: The list has no items left to pop.".yellow());
+        eprintln!("    {}", "Check first: if len(my_list) > 0: pop(my_list) end".bright_black());
+    }
     
     // Syntax errors
     else if err_msg.contains("Unexpected token") {
@@ -83,6 +213,10 @@ pub fn provide_error_suggestions(err_msg: &str) {
         eprintln!("{}", "💡 Help: 'continue' can only be used inside while or for loops.".yellow());
         eprintln!("    {}", "Example: for i in 0..10: if i == 5: continue end end".bright_black());
     }
+    else if err_msg.contains("'return' outside of function") {
+        eprintln!("{}", "💡 Help: 'return' can only be used inside a function body.".yellow());
+        eprintln!("    {}", "The last expression of a script is its result; use --print-result to show it".bright_black());
+    }
     
     // Function call errors
     else if err_msg.contains("expected") && err_msg.contains("args") {
@@ -101,15 +235,15 @@ pub fn provide_error_suggestions(err_msg: &str) {
     }
     
     // Built-in function specific errors
-    else if err_msg.contains("showf missing") {
+    else if err_msg.contains(": format expects at least") {
         eprintln!("{}", "💡 Help: Format string needs more arguments.".yellow());
-        eprintln!("    {}", "Use %d for numbers, %s for strings: showf(\"Number: %d\", 42)".bright_black());
+        eprintln!("    {}", "Use %d for numbers, %s for strings, %v for any value: showf(\"Number: %d\", 42)".bright_black());
     }
-    else if err_msg.contains("sqrt() argument cannot be negative") {
+    else if err_msg.contains(builtins::SQRT_NEGATIVE) {
         eprintln!("{}", "💡 Help: Square root of negative numbers is not supported.".yellow());
         eprintln!("    {}", "Use abs() first if needed: sqrt(abs(x))".bright_black());
     }
-    else if err_msg.contains("pow() exponent cannot be negative") {
+    else if err_msg.contains(builtins::POW_NEGATIVE_EXPONENT) {
         eprintln!("{}", "💡 Help: Negative exponents are not supported in pow().".yellow());
         eprintln!("    {}", "Use only non-negative integers: pow(2, 3) = 8".bright_black());
     }
@@ -121,10 +255,18 @@ pub fn provide_error_suggestions(err_msg: &str) {
     }
     else if err_msg.contains("Invalid number") {
         eprintln!("{}", "💡 Help: Number format is not recognized.".yellow());
-        eprintln!("    {}", "Use integers like: 42, 100, -5".bright_black());
+        eprintln!("    {}", "Use integers like 42 or floats like 3.5 and 1.0e-5".bright_black());
+    }
+    else if err_msg.contains("integer literal too large") {
+        eprintln!("{}", "💡 Help: Integers must fit in 64 bits (at most 9223372036854775807).".yellow());
+        eprintln!("    {}", "Write the number as a float instead: 1.0e20".bright_black());
     }
     
     // Stack/memory errors
+    else if err_msg.contains(builtins::MAX_RECURSION_DEPTH) {
+        eprintln!("{}", "💡 Help: Function calls nested too deeply - is a recursive function missing its base case?".yellow());
+        eprintln!("    {}", "Check that every recursive call moves toward a case that returns without recursing".bright_black());
+    }
     else if err_msg.contains("stack underflow") || err_msg.contains("stack overflow") {
         eprintln!("{}", "💡 Help: Internal VM error - this might be a compiler bug.".yellow());
         eprintln!("    {}", "Try using the interpreter backend: zirc-cli --backend interp file.zirc".bright_black());
@@ -3,8 +3,8 @@ mod repl;
 
 use std::fs;
 
-use owo_colors::OwoColorize;
-use zirc_interpreter::Interpreter;
+use crate::common::Colorize;
+use zirc_interpreter::{Env, Interpreter, Value};
 use zirc_lexer::Lexer;
 use zirc_parser::Parser;
 use zirc_syntax::error::Error;
@@ -13,7 +13,7 @@ use zirc_syntax::error::Error;
 use zirc_compiler::Compiler;
 use zirc_vm::Vm;
 
-use crate::common::provide_error_suggestions;
+use crate::common::{init_color, provide_error_suggestions};
 
 fn render_error(kind: &str, source: &str, err: &Error) {
     eprintln!("{}: {}", kind.red().bold(), err.msg.red());
@@ -29,7 +29,7 @@ fn render_error(kind: &str, source: &str, err: &Error) {
             if col > 1 {
                 marker.push_str(&" ".repeat(col - 1));
             }
-            marker.push_str("^");
+            marker.push('^');
             eprintln!("{}{}", marker.red(), " error here".red());
             eprintln!("     |");
         }
@@ -39,6 +39,20 @@ fn render_error(kind: &str, source: &str, err: &Error) {
     provide_error_suggestions(&err.msg);
 }
 
+/// `--print-result`: prints the last top-level expression's value. Unit is
+/// skipped, so a script ending in `show(...)` is not printed twice.
+fn print_final_value(last: Option<Value>) {
+    if let Some(val) = last.filter(|v| *v != Value::Unit) {
+        println!("{}", val);
+    }
+}
+
+fn print_warnings(compiler: &mut Compiler) {
+    for w in compiler.take_warnings() {
+        eprintln!("{}: {}", "warning".yellow().bold(), w);
+    }
+}
+
 fn parse_backend(args: &[String]) -> String {
     // default backend is interpreter; allow --backend vm or env var ZIRC_BACKEND=vm
     if let Ok(b) = std::env::var("ZIRC_BACKEND") {
@@ -54,11 +68,11 @@ fn parse_backend(args: &[String]) -> String {
     "interp".to_string()
 }
 
-fn parse_path<'a>(args: &'a [String]) -> Option<&'a str> {
+fn parse_path(args: &[String]) -> Option<&str> {
     let mut i = 1usize;
     while i < args.len() {
         match args[i].as_str() {
-            "--backend" | "-b" => { i += 2; }
+            "--backend" | "-b" | "--color" | "--emit-bytecode" | "-o" => { i += 2; }
             s if s.starts_with('-') => { i += 1; }
             _ => { return Some(args[i].as_str()); }
         }
@@ -81,6 +95,10 @@ fn normalize_path(p: &str) -> std::path::PathBuf {
 }
 
 fn main() {
+    common::run_with_large_stack(run_cli);
+}
+
+fn run_cli() {
     let args: Vec<String> = std::env::args().collect();
 
     // Support --version / -V for installer validation and quick checks
@@ -89,6 +107,8 @@ fn main() {
         return;
     }
 
+    init_color(&args);
+
     if args.len() < 2 {
         let backend = parse_backend(&args);
         let mode = if backend == "vm" { repl::Backend::Vm } else { repl::Backend::Interp };
@@ -97,6 +117,32 @@ fn main() {
     }
 
     let backend = parse_backend(&args);
+    let strict = args.iter().any(|a| a == "--strict");
+    let print_result = args.iter().any(|a| a == "--print-result");
+    let watch = args.iter().any(|a| a == "--watch");
+    let disasm = args.iter().any(|a| a == "--disasm");
+    let dump_ast = args.iter().any(|a| a == "--dump-ast");
+    let emit_bytecode = args.iter().position(|a| a == "--emit-bytecode").map(|i| args.get(i + 1));
+
+    if args[1] == "test" {
+        let dir = parse_path(&args[1..]).unwrap_or(".");
+        std::process::exit(run_tests(dir, &backend, strict));
+    }
+    if args[1] == "compile" {
+        let out = args.iter().position(|a| a == "-o").map(|i| args.get(i + 1));
+        let code = match (parse_path(&args[1..]), out) {
+            (None, _) => {
+                eprintln!("{}: {}", "error".red().bold(), "compile expects a script path".red());
+                1
+            }
+            (Some(_), Some(None)) => {
+                eprintln!("{}: {}", "error".red().bold(), "-o expects an output path".red());
+                1
+            }
+            (Some(path), out) => compile_file(path, out.flatten().map(String::as_str), strict),
+        };
+        std::process::exit(code);
+    }
 
     // first non-flag arg treated as path, skipping flag values
     let path_str = match parse_path(&args) {
@@ -116,6 +162,20 @@ fn main() {
         );
         std::process::exit(1);
     }
+    // Absolute, so script_dir() stays meaningful if the script changes directory
+    let script_path = std::env::current_dir().map(|cwd| cwd.join(&path_buf)).unwrap_or_else(|_| path_buf.clone());
+
+    // Compiled bytecode always runs on the VM, skipping lexing and parsing
+    if path_buf.extension().is_some_and(|e| e == "zbc") {
+        match run_bytecode(&path_buf, script_path) {
+            Ok(last) => if print_result { print_final_value(last) },
+            Err(_) => std::process::exit(1),
+        }
+        return;
+    }
+    if watch {
+        std::process::exit(watch_script(&path_buf, &backend, strict, print_result));
+    }
     let src = match fs::read_to_string(&path_buf) {
         Ok(s) => s,
         Err(e) => {
@@ -128,43 +188,243 @@ fn main() {
         }
     };
 
-    let mut lexer = Lexer::new(&src);
-    let tokens = match lexer.tokenize() {
-        Ok(t) => t,
-        Err(e) => {
-            render_error("Lex error", &src, &e);
+    if dump_ast {
+        let parsed = Lexer::new(&src).tokenize()
+            .map_err(|e| ("Lex error", e))
+            .and_then(|tokens| Parser::new(tokens).parse_program().map_err(|e| ("Parse error", e)));
+        match parsed {
+            Ok(program) => print!("{}", zirc_syntax::pretty::pretty_print(&program)),
+            Err((kind, e)) => {
+                render_error(kind, &src, &e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if disasm {
+        match compile_source(&src, strict) {
+            Some(bprog) => print!("{}", zirc_bytecode::disassemble(&bprog)),
+            None => std::process::exit(1),
+        }
+        return;
+    }
+    match emit_bytecode {
+        Some(Some(out)) => std::process::exit(emit_bytecode_file(&src, std::path::Path::new(out), strict)),
+        Some(None) => {
+            eprintln!("{}: {}", "error".red().bold(), "--emit-bytecode expects an output path".red());
             std::process::exit(1);
         }
+        None => {}
+    }
+    match run_script(&src, script_path, &backend, strict) {
+        Ok(last) => if print_result { print_final_value(last) },
+        Err(_) => std::process::exit(1),
+    }
+}
+
+/// `--watch`: runs the script, then again each time it is saved, until
+/// interrupted. Errors are reported and the watch goes on. Returns the process
+/// exit code, which is only reached if watching itself fails.
+fn watch_script(path: &std::path::Path, backend: &str, strict: bool, print_result: bool) -> i32 {
+    use notify::Watcher;
+
+    let script_path = std::env::current_dir().map(|cwd| cwd.join(path)).unwrap_or_else(|_| path.to_path_buf());
+    let run = || match fs::read_to_string(path) {
+        Ok(src) => {
+            if let Ok(last) = run_script(&src, script_path.clone(), backend, strict) {
+                if print_result { print_final_value(last) }
+            }
+        }
+        Err(e) => eprintln!("{}: {}", "error".red().bold(), format!("Failed to read {}: {}", path.display(), e).red()),
     };
 
-    let mut parser = Parser::new(tokens);
-    let program = match parser.parse_program() {
-        Ok(p) => p,
+    // Watch the directory, not the file: many editors save by replacing the file.
+    // Started before the first run so a save during that run is not missed.
+    let (tx, rx) = std::sync::mpsc::channel();
+    let watcher = notify::recommended_watcher(tx).and_then(|mut w| {
+        let dir = script_path.parent().unwrap_or(std::path::Path::new("."));
+        w.watch(dir, notify::RecursiveMode::NonRecursive).map(|_| w)
+    });
+    let _watcher = match watcher {
+        Ok(w) => w,
         Err(e) => {
-            render_error("Parse error", &src, &e);
-            std::process::exit(1);
+            eprintln!("{}: {}", "error".red().bold(), format!("Cannot watch {}: {}", path.display(), e).red());
+            return 1;
         }
     };
 
-    if backend == "vm" {
-        let mut compiler = Compiler::new();
-        let bprog = match compiler.compile(program) {
-            Ok(p) => p,
-            Err(e) => {
-                render_error("Compile error", &src, &e);
-                std::process::exit(1);
+    run();
+    while let Ok(event) = rx.recv() {
+        match event {
+            Ok(event) if is_change_to(&event, &script_path) => {
+                // One save often fires several events; let them settle and run once
+                while rx.recv_timeout(std::time::Duration::from_millis(100)).is_ok() {}
+                println!("{}", format!("--- {} changed, re-running ---", path.display()).cyan());
+                run();
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("{}: {}", "watch error".yellow().bold(), e),
+        }
+    }
+    0
+}
+
+/// Whether `event`, from a watch on the script's directory, created or modified the script.
+fn is_change_to(event: &notify::Event, script: &std::path::Path) -> bool {
+    matches!(event.kind, notify::EventKind::Create(_) | notify::EventKind::Modify(_))
+        && event.paths.iter().any(|p| p.file_name() == script.file_name())
+}
+
+/// `zirc test <dir>`: runs every script under `dir` and reports which ones
+/// failed. When any file is named `test_*.zirc`, only those run, so helper
+/// scripts can sit next to them. Returns the process exit code.
+fn run_tests(dir: &str, backend: &str, strict: bool) -> i32 {
+    let root = normalize_path(dir);
+    if !root.is_dir() {
+        eprintln!("{}: {}", "error".red().bold(), format!("Directory not found: {}", dir).red());
+        return 1;
+    }
+    let mut scripts = Vec::new();
+    collect_scripts(&root, &mut scripts);
+    scripts.sort();
+    let is_test = |p: &std::path::PathBuf| p.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with("test_"));
+    if scripts.iter().any(is_test) {
+        scripts.retain(is_test);
+    }
+    if scripts.is_empty() {
+        eprintln!("{}: {}", "error".red().bold(), format!("No .zirc files found in {}", dir).red());
+        return 1;
+    }
+
+    let mut failed = 0;
+    for path in &scripts {
+        let result = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e)).and_then(|src| {
+            let script_path = std::env::current_dir().map(|cwd| cwd.join(path)).unwrap_or_else(|_| path.clone());
+            run_script(&src, script_path, backend, strict)
+        });
+        match result {
+            Ok(_) => println!("{} {}", "PASS".green().bold(), path.display()),
+            Err(msg) => {
+                failed += 1;
+                println!("{} {}: {}", "FAIL".red().bold(), path.display(), msg);
             }
-        };
-        let mut vm = Vm::new();
-        if let Err(e) = vm.run(&bprog) {
-            render_error("VM error", &src, &e);
-            std::process::exit(1);
         }
+    }
+    let summary = format!("{} passed, {} failed", scripts.len() - failed, failed);
+    if failed > 0 {
+        println!("{}", summary.red());
+        1
     } else {
+        println!("{}", summary.green());
+        0
+    }
+}
+
+fn collect_scripts(dir: &std::path::Path, out: &mut Vec<std::path::PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_scripts(&path, out);
+        } else if path.extension().is_some_and(|e| e == "zirc") {
+            out.push(path);
+        }
+    }
+}
+
+/// Lexes, parses, checks and runs `src` on `backend`. Errors are rendered to
+/// stderr as they happen; the returned message is for callers that summarize.
+fn run_script(src: &str, script_path: std::path::PathBuf, backend: &str, strict: bool) -> Result<Option<Value>, String> {
+    let fail = |kind: &str, e: Error| {
+        render_error(kind, src, &e);
+        e.msg
+    };
+
+    let mut lexer = Lexer::new(src);
+    let tokens = lexer.tokenize().map_err(|e| fail("Lex error", e))?;
+
+    let mut parser = Parser::new(tokens);
+    let program = parser.parse_program().map_err(|e| fail("Parse error", e))?;
+
+    // Compiling for the VM prints the lint's warnings; --strict turns them into
+    // errors, and is the only way the interpreter runs the lint at all
+    let mut compiler = Compiler::new();
+    compiler.set_strict(strict);
+
+    if backend == "vm" {
+        let bprog = compiler.compile(program).map_err(|e| fail("Compile error", e))?;
+        print_warnings(&mut compiler);
+        let mut vm = Vm::new();
+        vm.set_script_path(script_path);
+        vm.run(&bprog).map_err(|e| fail("VM error", e))
+    } else {
+        if strict { compiler.check(&program).map_err(|e| fail("Compile error", e))?; }
         let mut interp = Interpreter::new();
-        if let Err(e) = interp.run(program) {
-            render_error("Runtime error", &src, &e);
-            std::process::exit(1);
+        interp.set_script_path(script_path);
+        interp.run_with_env(program, &mut Env::new_root()).map_err(|e| fail("Runtime error", e))
+    }
+}
+
+/// Lexes, parses and compiles `src` for the VM without running it. Errors and
+/// warnings are rendered to stderr; `None` means compiling failed.
+fn compile_source(src: &str, strict: bool) -> Option<zirc_bytecode::Program> {
+    let compiled = Lexer::new(src).tokenize()
+        .map_err(|e| ("Lex error", e))
+        .and_then(|tokens| Parser::new(tokens).parse_program().map_err(|e| ("Parse error", e)))
+        .and_then(|program| {
+            let mut compiler = Compiler::new();
+            compiler.set_strict(strict);
+            let bprog = compiler.compile(program).map_err(|e| ("Compile error", e))?;
+            print_warnings(&mut compiler);
+            Ok(bprog)
+        });
+    match compiled {
+        Ok(p) => Some(p),
+        Err((kind, e)) => {
+            render_error(kind, src, &e);
+            None
+        }
+    }
+}
+
+/// `zirc compile <file> [-o <out>]`: writes the script's bytecode to `out`,
+/// by default next to the script with a `.zbc` extension. Returns the process
+/// exit code.
+fn compile_file(path: &str, out: Option<&str>, strict: bool) -> i32 {
+    let path_buf = normalize_path(path);
+    let src = match fs::read_to_string(&path_buf) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{}: {}", "error".red().bold(), format!("Failed to read {}: {}", path_buf.display(), e).red());
+            return 1;
+        }
+    };
+    let out = out.map(std::path::PathBuf::from).unwrap_or_else(|| path_buf.with_extension("zbc"));
+    emit_bytecode_file(&src, &out, strict)
+}
+
+/// `--emit-bytecode <out>`: compiles `src` and writes it to `out` as a `.zbc`
+/// file instead of running it. Returns the process exit code.
+fn emit_bytecode_file(src: &str, out: &std::path::Path, strict: bool) -> i32 {
+    let Some(bprog) = compile_source(src, strict) else { return 1 };
+    match fs::write(out, bprog.to_bytes()) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}: {}", "error".red().bold(), format!("Failed to write {}: {}", out.display(), e).red());
+            1
         }
     }
 }
+
+/// Loads a `.zbc` file written by `zirc compile` or `--emit-bytecode` and runs it on the VM.
+fn run_bytecode(path: &std::path::Path, script_path: std::path::PathBuf) -> Result<Option<Value>, String> {
+    let fail = |kind: &str, e: Error| {
+        render_error(kind, "", &e);
+        e.msg
+    };
+    let bytes = fs::read(path).map_err(|e| fail("error", Error::new(format!("Failed to read {}: {}", path.display(), e))))?;
+    let bprog = zirc_bytecode::Program::from_bytes(&bytes).map_err(|e| fail("Bytecode error", e))?;
+    let mut vm = Vm::new();
+    vm.set_script_path(script_path);
+    vm.run(&bprog).map_err(|e| fail("VM error", e))
+}
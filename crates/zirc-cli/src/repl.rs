@@ -1,8 +1,9 @@
 use std::io::{self, Write};
 
+#[cfg(windows)]
 use windows::Win32::System::Console::{GetConsoleCP, SetConsoleOutputCP};
 
-use owo_colors::OwoColorize;
+use crate::common::Colorize;
 use zirc_interpreter::{Env, Interpreter, MemoryStats, Value};
 use zirc_lexer::Lexer;
 use zirc_parser::Parser;
@@ -35,14 +36,18 @@ pub fn start_repl_with_backend(backend: Backend) {
 fn repl_interpreter() {
     let mut interpreter = Interpreter::new();
     let mut env = Env::new_root();
+    let mut depth: Option<usize> = None;
 
-    let utf8_cp: u32 = 65001;
-    let result = unsafe { SetConsoleOutputCP(utf8_cp) };
-    if !result.as_bool() {
-        let err_code = unsafe { GetConsoleCP() };
-        let err_msg = format!("Failed to set console code page to UTF-8. Error code: {}", err_code);
-        eprintln!("{}", err_msg.red());
-        return;
+    #[cfg(windows)]
+    {
+        let utf8_cp: u32 = 65001;
+        let result = unsafe { SetConsoleOutputCP(utf8_cp) };
+        if !result.as_bool() {
+            let err_code = unsafe { GetConsoleCP() };
+            let err_msg = format!("Failed to set console code page to UTF-8. Error code: {}", err_code);
+            eprintln!("{}", err_msg.red());
+            return;
+        }
     }
 
     let mut buffer = String::new();
@@ -67,6 +72,10 @@ fn repl_interpreter() {
                 ":funcs" => { print_funcs_interp(&interpreter); continue; }
                 ":mem" => { print_mem(&interpreter); continue; }
                 ":reset" => { interpreter.reset(); env = Env::new_root(); println!("{}", "State reset.".green()); continue; }
+                cmd if cmd.starts_with(":depth") => {
+                    if let Some(d) = set_depth_command(cmd) { depth = d; interpreter.set_display_depth(d); }
+                    continue;
+                }
                 _ => { println!("{}", "Unknown command. Type :help.".red()); continue; }
             }
         }
@@ -81,7 +90,7 @@ fn repl_interpreter() {
                 match parser.parse_program() {
                     Ok(program) => match interpreter.run_with_env(program, &mut env) {
                         Ok(last) => {
-                            if let Some(val) = last { if val != Value::Unit { println!("{}", format!("{}", val).bright_blue()); } }
+                            if let Some(val) = last { if val != Value::Unit { println!("{}", val.display_depth(depth).bright_blue()); } }
                         }
                         Err(e) => render_error("Runtime error", &buffer, &e),
                     },
@@ -97,6 +106,7 @@ fn repl_interpreter() {
 fn repl_vm() {
     let mut compiler = Compiler::new();
     let mut vm = Vm::new();
+    let mut depth: Option<usize> = None;
     let mut buffer = String::new();
 
     loop {
@@ -115,7 +125,11 @@ fn repl_vm() {
                 ":vars" => { print_vars_vm(&vm); continue; }
                 ":funcs" => { print_funcs_vm(&compiler); continue; }
                 ":mem" => { println!("{}", "<no memory stats in VM>".dimmed()); continue; }
-                ":reset" => { compiler = Compiler::new(); vm = Vm::new(); println!("{}", "State reset.".yellow()); continue; }
+                ":reset" => { compiler = Compiler::new(); vm = Vm::new(); vm.set_display_depth(depth); println!("{}", "State reset.".yellow()); continue; }
+                cmd if cmd.starts_with(":depth") => {
+                    if let Some(d) = set_depth_command(cmd) { depth = d; vm.set_display_depth(d); }
+                    continue;
+                }
                 _ => { println!("{}", "Unknown command. Type :help.".red()); continue; }
             }
         }
@@ -131,7 +145,7 @@ fn repl_vm() {
                     Ok(program) => match compiler.compile(program) {
                         Ok(bprog) => match vm.run(&bprog) {
                             Ok(last) => {
-                                if let Some(val) = last { println!("{}", format_vm_value(&val).bright_blue()); }
+                                if let Some(val) = last { if val != Value::Unit { println!("{}", val.display_depth(depth).bright_blue()); } }
                             }
                             Err(e) => render_error("VM error", &buffer, &e),
                         },
@@ -142,6 +156,8 @@ fn repl_vm() {
             }
             Err(e) => render_error("Lex error", &buffer, &e),
         }
+        // Later entries may use what this one declared, so per-entry warnings are noise
+        let _ = compiler.take_warnings();
         buffer.clear();
     }
 }
@@ -159,11 +175,25 @@ fn print_help() {
         "  {}  Show memory stats (interpreter only)\n  {}  Clear state (env/functions/mem)",
         ":mem".yellow(), ":reset".yellow()
     );
+    println!(
+        "  {}  Elide lists nested deeper than N levels as [...] ('off' to show all)",
+        ":depth N".yellow()
+    );
+}
+
+/// Parses `:depth N` / `:depth off` and reports the new setting; `None` if invalid.
+fn set_depth_command(cmd: &str) -> Option<Option<usize>> {
+    match cmd.trim_start_matches(":depth").trim() {
+        "off" => { println!("{}", "Display depth: unlimited".green()); Some(None) }
+        n => match n.parse::<usize>() {
+            Ok(d) => { println!("{}", format!("Display depth: {}", d).green()); Some(Some(d)) }
+            Err(_) => { println!("{}", "Usage: :depth N | :depth off".red()); None }
+        },
+    }
 }
 
 fn print_vars_interp(env: &Env) {
-    let mut vars = env.vars_snapshot();
-    vars.sort_by(|a, b| a.0.cmp(&b.0));
+    let vars = env.vars_snapshot();
     if vars.is_empty() { println!("{}", "<no vars>".dimmed()); return; }
     for (k, v) in vars { println!("{} = {}", k.yellow(), format!("{}", v).bright_blue()); }
 }
@@ -187,27 +217,18 @@ fn print_funcs_vm(compiler: &Compiler) {
 }
 
 fn format_vm_value(v: &zirc_bytecode::Value) -> String {
-    match v {
-        zirc_bytecode::Value::Int(n) => n.to_string(),
-        zirc_bytecode::Value::Str(s) => s.clone(),
-        zirc_bytecode::Value::Bool(b) => if *b { "true".into() } else { "false".into() },
-        zirc_bytecode::Value::List(items) => {
-            let mut s = String::from("[");
-            for (i, it) in items.iter().enumerate() { if i > 0 { s.push_str(", "); } s.push_str(&format_vm_value(it)); }
-            s.push(']'); s
-        }
-        zirc_bytecode::Value::Unit => "<unit>".into(),
-    }
+    zirc_vm::display::display_value(v)
 }
 
 fn print_mem(interp: &Interpreter) {
-    let MemoryStats { strings_allocated, bytes_allocated, } = interp.memory_stats();
+    let MemoryStats { strings_allocated, bytes_allocated, lists_allocated, list_elements_allocated, list_bytes_allocated } = interp.memory_stats();
     println!("{}: {}", "strings".yellow(), strings_allocated);
     println!("{}: {} bytes", "bytes".yellow(), bytes_allocated);
+    println!("{}: {} ({} elements)", "lists".yellow(), lists_allocated, list_elements_allocated);
+    println!("{}: {} bytes", "list bytes".yellow(), list_bytes_allocated);
 }
 
 fn render_error(kind: &str, source: &str, err: &Error) {
-    use owo_colors::OwoColorize;
     eprintln!("{}: {}", kind.red().bold(), err.msg.red());
     if let (Some(line), Some(col)) = (err.line, err.col) {
         if let Some(src_line) = source.lines().nth(line - 1) {
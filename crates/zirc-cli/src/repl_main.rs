@@ -2,5 +2,9 @@ mod common;
 mod repl;
 
 fn main() {
-    repl::start_repl_with_backend(repl::Backend::Interp);
+    common::run_with_large_stack(|| {
+        let args: Vec<String> = std::env::args().collect();
+        common::init_color(&args);
+        repl::start_repl_with_backend(repl::Backend::Interp);
+    });
 }
@@ -0,0 +1,1212 @@
+//! Runs the same source through the interpreter and the bytecode VM and checks they agree.
+
+use assert_cmd::prelude::*;
+use std::process::Command;
+use zirc_interpreter::{Env, Interpreter};
+use zirc_lexer::Lexer;
+use zirc_parser::Parser;
+
+fn parse(src: &str) -> zirc_syntax::ast::Program {
+    let tokens = Lexer::new(src).tokenize().expect("lex");
+    Parser::new(tokens).parse_program().expect("parse")
+}
+
+fn run_interp(src: &str) -> Option<String> {
+    let mut interp = Interpreter::new();
+    interp.run_with_env(parse(src), &mut Env::new_root()).expect("interpreter run").map(|v| v.to_string())
+}
+
+fn run_vm(src: &str) -> Option<String> {
+    let program = zirc_compiler::Compiler::new().compile(parse(src)).expect("compile");
+    let mut vm = zirc_vm::Vm::new();
+    vm.run(&program).expect("vm run").map(|v| zirc_vm::display::display_value(&v))
+}
+
+/// Runs `src` through the `zirc` binary with the given backend and returns its stdout.
+fn cli_stdout(backend: &str, src: &str) -> String {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("main.zirc");
+    std::fs::write(&path, src).unwrap();
+    let out = Command::cargo_bin("zirc").unwrap()
+        .env_remove("ZIRC_BACKEND")
+        .args(["--backend", backend])
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "{} backend failed: {}", backend, String::from_utf8_lossy(&out.stderr));
+    String::from_utf8(out.stdout).unwrap()
+}
+
+/// Asserts both backends produce `expected` as the program's last value.
+fn assert_both(src: &str, expected: &str) {
+    assert_eq!(run_interp(src).as_deref(), Some(expected), "interpreter: {}", src);
+    assert_eq!(run_vm(src).as_deref(), Some(expected), "vm: {}", src);
+}
+
+fn interp_err(src: &str) -> String {
+    Interpreter::new().run_with_env(parse(src), &mut Env::new_root()).expect_err("interpreter run should fail").msg
+}
+
+/// The VM backend's error for `src`, whether compiling or running it failed.
+fn vm_err(src: &str) -> String {
+    match zirc_compiler::Compiler::new().compile(parse(src)) {
+        Ok(program) => zirc_vm::Vm::new().run(&program).expect_err("vm run should fail").msg,
+        Err(e) => e.msg,
+    }
+}
+
+/// Asserts both backends fail on `src` with the message `expected`.
+fn assert_both_err(src: &str, expected: &str) {
+    assert_eq!(interp_err(src), expected, "interpreter: {}", src);
+    assert_eq!(vm_err(src), expected, "vm: {}", src);
+}
+
+#[test]
+fn for_bounds_are_evaluated_once() {
+    let global = r#"
+        let n = 3
+        let count = 0
+        for i in 0..n:
+            n = n + 1
+            count = count + 1
+        end
+        count
+    "#;
+    assert_both(global, "3");
+
+    let in_function = r#"
+        fun loops(n):
+            let count = 0
+            for i in 0..n:
+                n = n - 1
+                count = count + 1
+            end
+            return count
+        end
+        loops(4)
+    "#;
+    assert_both(in_function, "4");
+}
+
+#[test]
+fn for_start_is_evaluated_before_end() {
+    let src = r#"
+        fun mark(s, v):
+            show(s)
+            return v
+        end
+        for i in mark("start", 0)..mark("end", 1):
+            show("body")
+        end
+    "#;
+    assert_eq!(cli_stdout("interp", src), "start\nend\nbody\n");
+    assert_eq!(cli_stdout("vm", src), "start\nend\nbody\n");
+}
+
+#[test]
+fn pooled_literals_behave_like_inline_ones() {
+    let src = r#"
+        fun greet(name):
+            return "hello " + name + "!"
+        end
+        let big = 10000000000
+        let s = greet("a") + greet("a") + str(big + big)
+        s
+    "#;
+    assert_both(src, "hello a!hello a!20000000000");
+}
+
+#[test]
+fn let_unpack_binds_in_order() {
+    let src = r#"
+        fun swap(pair):
+            let a, b = pair
+            return [b, a]
+        end
+        let x, y = swap([1, 2])
+        x * 10 + y
+    "#;
+    assert_both(src, "21");
+}
+
+#[test]
+fn host_functions_work_on_both_backends() {
+    let src = "let x = double(21)\ndouble(x)";
+    let double = |args: &[zirc_syntax::Value]| match args {
+        [zirc_syntax::Value::Int(n)] => Ok(zirc_syntax::Value::Int(n * 2)),
+        _ => zirc_syntax::error::error("double() expects one int"),
+    };
+
+    let mut interp = Interpreter::new();
+    interp.register_builtin("double", Box::new(double)).unwrap();
+    let result = interp.run_with_env(parse(src), &mut Env::new_root()).unwrap();
+    assert_eq!(result, Some(zirc_syntax::Value::Int(84)));
+
+    let mut compiler = zirc_compiler::Compiler::new();
+    compiler.declare_native("double").unwrap();
+    let program = compiler.compile(parse(src)).unwrap();
+    let mut vm = zirc_vm::Vm::new();
+    vm.register_native("double", Box::new(double)).unwrap();
+    assert_eq!(vm.run(&program).unwrap(), Some(zirc_syntax::Value::Int(84)));
+}
+
+#[test]
+fn if_without_else_yields_then_value_or_unit() {
+    assert_both("if true: 42 end", "42");
+    assert_both("if false: 42 end", "<unit>");
+    assert_both("1\nif false: 42 end", "<unit>");
+    assert_both("let n = 3\nif n > 2: n * 2 end", "6");
+}
+
+#[test]
+fn pow_is_exact_and_reports_overflow() {
+    assert_both("pow(3, 39)", "4052555153018976267");
+    assert_both("pow(2, 62)", "4611686018427387904");
+    assert_both_err("pow(3, 40)", "integer overflow in pow");
+}
+
+#[test]
+fn duplicate_function_is_rejected_regardless_of_arity() {
+    for src in ["fun f(x): x end\nfun f(x, y): x + y end\nf(1)", "fun f(x): x end\nfun f(y): y end\nf(1)"] {
+        assert_both_err(src, "Duplicate function 'f'");
+    }
+}
+
+#[test]
+fn showf_v_formats_any_value() {
+    let src = "showf(\"%v\", [1, 2, 3])\nshowf(\"%v %v\", 1, true)\nshowf(\"%v|%v\", \"s\", [[1], \"a\"])\n";
+    for backend in ["interp", "vm"] {
+        assert_eq!(cli_stdout(backend, src), "[1, 2, 3]\n1 true\ns|[[1], a]\n", "{} backend", backend);
+    }
+}
+
+#[test]
+fn floats_agree_across_backends() {
+    assert_both("1.5 + 2.25", "3.75");
+    assert_both("8 / 3", "2");
+    assert_both("8 / 2.0", "4.0");
+    assert_both("1.0e-5 * 2", "2e-5");
+    assert_both("sqrt(2.25)", "1.5");
+    assert_both("pow(2.0, 0.5) * pow(2.0, 0.5) > 1.99", "true");
+    assert_both("type(3.0)", "float");
+    assert_both("str(3.0)", "3.0");
+    assert_both("int(7.9)", "7");
+    assert_both("[1, 2.5] == [1.0, 2.5]", "true");
+    assert_both("let r = 0.0\nfor i in 0..4:\n r = r + 0.25\nend\nr", "1.0");
+    assert_both("1.5 + 2 == 3.5", "true");
+    // Int / int stays an int; a float operand makes the result a float, shown with its `.0`
+    assert_both("4 / 2", "2");
+    assert_both("4.0 / 2", "2.0");
+    assert_both("type(4.0 / 2)", "float");
+    assert_both("let x: float = 2.5\nx", "2.5");
+}
+
+#[test]
+fn modulo_agrees_across_backends() {
+    assert_both("10 % 3", "1");
+    assert_both("let n = 0\nfor i in 0..10:\n if i % 2 == 0: n = n + 1 end\nend\nn", "5");
+    assert_both("[10, 20, 30][7 % 3]", "20");
+}
+
+#[test]
+fn list_equality_agrees_across_backends() {
+    assert_both("[1, [2, 3]] == [1, [2, 3]]", "true");
+    assert_both("[1, [2, 3]] == [1, [2]]", "false");
+    assert_both("[1, 2] != [1, 2, 3]", "true");
+    assert_both("[] == []", "true");
+    assert_both("[[]] == [[], []]", "false");
+}
+
+#[test]
+fn left_and_right_agree_across_backends() {
+    assert_both("left(\"hello\", 3) == \"hel\"", "true");
+    assert_both("right(\"hello\", 2) == \"lo\"", "true");
+    assert_both("left(\"hello\", 99)", "hello");
+    assert_both("right([1, 2, 3], 2)", "[2, 3]");
+    assert_both_err("right(\"abc\", -2)", "right() count cannot be negative");
+    assert_both_err("left([1], -1)", "left() count cannot be negative");
+}
+
+#[test]
+fn get_and_set_agree_across_backends() {
+    assert_both("get([10, 20], 1, 0)", "20");
+    assert_both("get([10, 20], 2, 0)", "0");
+    assert_both("get([10, 20], -1, 0)", "0");
+    assert_both("let a = [1, 2]\nlet b = set(a, 1, [3])\nstr(a) + \" \" + str(b)", "[1, 2] [1, [3]]");
+    assert_both_err("set([1], 3, 0)", "index out of bounds");
+    assert_both("get({\"a\": 1}, \"a\", 0)", "1");
+    assert_both("get({\"a\": 1}, \"b\", 0) == 0", "true");
+    // An existing key keeps its place, a new one goes last, and the argument is untouched
+    assert_both("let m = {\"a\": 1, \"b\": 2}\nlet n = set(set(m, \"a\", 9), \"c\", 3)\nstr(m) + \" \" + str(n)", "{a: 1, b: 2} {a: 9, b: 2, c: 3}");
+    assert_both_err("get({\"a\": 1}, 0, 0)", "get() map key must be string, got Int(0)");
+    assert_both_err("set(\"ab\", 0, 0)", "set() expects list or map, got Str(\"ab\")");
+    assert_both_err("fun get(x):\n  return x\nend\nget(1)", "Function 'get' conflicts with a builtin");
+}
+
+#[test]
+fn copy_agrees_across_backends() {
+    assert_both("let a = [1, [2, 3]]\nlet b = copy(a)\nb = b + [4]\nstr(a) + \" \" + str(b)", "[1, [2, 3]] [1, [2, 3], 4]");
+    assert_both("copy(2.5)", "2.5");
+}
+
+#[test]
+fn underscore_discards_in_both_backends() {
+    assert_both("let _, x = [1, 2]\nx", "2");
+    let in_function = "fun f(_, _):\n let _ = 1\n let _, y = [2, 3]\n let n = 0\n for _ in 0..2:\n  for _ in 0..3:\n   n = n + y\n  end\n end\n return n\nend\nf(0, 0)";
+    assert_both(in_function, "18");
+    assert_both("let n = 0\nfor _ in 0..4:\n n = n + 1\nend\nn", "4");
+    assert_both_err("let _ = 1\n_", "Undefined variable '_'");
+    // A discarded value does not become the program's result
+    assert_both("1 + 2\nlet _ = 4", "<unit>");
+    assert_both("1 + 2\nif true:\n let _ = 4\nend", "<unit>");
+    assert_both("1 + 2\nif true:\n let _, _ = [4, 5]\nend", "<unit>");
+}
+
+#[test]
+fn top_level_return_is_rejected_by_both_backends() {
+    for backend in ["interp", "vm"] {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let path = tmp_dir.path().join("main.zirc");
+        std::fs::write(&path, "show(1)\nif false: return 5 end\n").unwrap();
+        let out = Command::cargo_bin("zirc").unwrap().env_remove("ZIRC_BACKEND").args(["--backend", backend]).arg(&path).output().unwrap();
+        assert!(!out.status.success(), "{} accepted a top-level return", backend);
+        // Rejected before anything runs
+        assert!(out.stdout.is_empty(), "{}", backend);
+        assert!(String::from_utf8_lossy(&out.stderr).contains("'return' outside of function"), "{}", backend);
+    }
+}
+
+#[test]
+fn multiple_return_values_unpack_in_both_backends() {
+    let src = "fun divmod(a, b):\n return a / b, a % b\nend\nlet q, r = divmod(17, 5)\nq * 10 + r";
+    assert_both(src, "32");
+}
+
+#[test]
+fn bitwise_agrees_across_backends() {
+    assert_both("6 & 3 == 2", "true");
+    assert_both("1 << 4 == 16", "true");
+    assert_both("0 ^ -1 == -1", "true");
+    assert_both("let flags = 0\nflags = flags | 1 << 3\nflags & 8 != 0", "true");
+    assert_both_err("1 << 64", "shift amount 64 out of range 0..64");
+    assert_both_err("1 >> -3", "shift amount -3 out of range 0..64");
+}
+
+#[test]
+fn unary_minus_agrees_across_backends() {
+    assert_both("-5 == 0 - 5", "true");
+    assert_both("let x = 3\n-x == -3", "true");
+    assert_both("-(2 * 3) == -6", "true");
+    assert_both("-2.5 * 2", "-5.0");
+    assert_both("let x = -3\n- -x", "-3");
+    for backend in ["interp", "vm"] {
+        assert_eq!(cli_stdout(backend, "let x = -3\nshow(-x)\nshow(-len([1, 2]))\n"), "3\n-2\n");
+    }
+    assert_both_err("-true", "Cannot negate Bool(true)");
+}
+
+#[test]
+fn variable_snapshots_share_sorted_order() {
+    let src = "let zeta = 1\nlet alpha = [2]\nlet mid = \"m\"\nlet beta = true";
+    let mut env = Env::new_root();
+    Interpreter::new().run_with_env(parse(src), &mut env).unwrap();
+    let interp_names: Vec<String> = env.vars_snapshot().into_iter().map(|(k, _)| k).collect();
+
+    let program = zirc_compiler::Compiler::new().compile(parse(src)).unwrap();
+    let mut vm = zirc_vm::Vm::new();
+    vm.run(&program).unwrap();
+    let vm_names: Vec<String> = vm.globals_snapshot().into_iter().map(|(k, _)| k).collect();
+
+    assert_eq!(interp_names, ["alpha", "beta", "mid", "zeta"]);
+    assert_eq!(interp_names, vm_names);
+}
+
+#[test]
+fn hash_agrees_across_backends() {
+    assert_both("hash(42)", "-8101521626687829867");
+    assert_both("hash(\"zirc\")", "-7998348951831333974");
+    assert_both("hash([1, \"a\"])", "-3563948975058001968");
+    assert_both("hash(\"ab\" + \"c\") == hash(\"abc\")", "true");
+    assert_both("hash(1) == hash(\"1\")", "false");
+    assert_both_err("hash(1.5)", "hash() expects int, string, bool or list, got Float(1.5)");
+}
+
+#[test]
+fn index_assignment_agrees_across_backends() {
+    assert_both("let a = [1, 2, 3]\na[1] = 9\na", "[1, 9, 3]");
+    assert_both("let g = [[1, 2], [3, 4]]\nlet i = 1\ng[i][i - 1] = \"x\"\ng", "[[1, 2], [x, 4]]");
+    assert_both("let s = \"héllo\"\ns[1] = \"e\"\ns", "hello");
+    let in_function = r#"
+        fun bump(xs):
+            for i in 0..len(xs):
+                xs[i] = xs[i] + 1
+            end
+            return xs
+        end
+        let xs = [1, 2]
+        str(bump(xs)) + " " + str(xs)
+    "#;
+    assert_both(in_function, "[2, 3] [1, 2]");
+
+    for (src, msg) in [
+        ("let a = [1]\na[1] = 0", "index out of bounds"),
+        ("let s = \"ab\"\ns[0] = \"\"", "string index assignment expects a 1-character string, got Str(\"\")"),
+        ("let a = [1]\na[\"0\"] = 2", "index expects int, got Str(\"0\")"),
+        ("let b = true\nb[0] = 1", "index assignment not supported for Bool(true)"),
+    ] {
+        assert_both_err(src, msg);
+    }
+}
+
+#[test]
+fn elif_chains_agree_across_backends() {
+    let classify = r#"
+        fun classify(n):
+            if n < 0:
+                return "neg"
+            elif n == 0:
+                return "zero"
+            else:
+                return "pos"
+            end
+        end
+    "#;
+    for (n, expected) in [("-3", "neg"), ("0", "zero"), ("8", "pos")] {
+        assert_both(&format!("{}classify({})", classify, n), expected);
+    }
+    // At top level, and with no final else
+    assert_both("let x = 5\nlet r = 0\nif x > 9: r = 1 elif x > 4: r = 2 elif x > 0: r = 3 end\nr", "2");
+    assert_both("let r = 0\nif false: r = 1 elif false: r = 2 end\nr", "0");
+}
+
+#[test]
+fn assertions_agree_across_backends() {
+    assert_both("assert(1 < 2)\nassert_eq(2, 2.0)\nassert_eq([1, \"a\"], [1, \"a\"])\n3", "3");
+    for (src, msg) in [
+        ("assert(1 > 2)", "assertion failed"),
+        ("assert(false, \"x is \" + str(4))", "assertion failed: x is 4"),
+        ("assert_eq(\"a\", \"b\")", "assertion failed: a != b"),
+        ("assert(1)", "assert() condition must be bool, got Int(1)"),
+    ] {
+        assert_both_err(src, msg);
+    }
+}
+
+#[test]
+fn index_assignment_errors_match_reads() {
+    for (write, read, msg) in [
+        ("let a = [1, 2]\na[2] = 0", "let a = [1, 2]\na[2]", "index out of bounds"),
+        ("let a = [1, 2]\na[-1] = 0", "let a = [1, 2]\na[-1]", "index out of bounds"),
+        ("let s = \"ab\"\ns[5] = \"c\"", "let s = \"ab\"\ns[5]", "index out of bounds"),
+        ("let a = [1]\na[true] = 0", "let a = [1]\na[true]", "index expects int, got Bool(true)"),
+    ] {
+        assert_both_err(write, msg);
+        assert_both_err(read, msg);
+    }
+}
+
+#[test]
+fn map_literals_agree_across_backends() {
+    assert_both("let m = {\"a\": 1, \"b\": [2]}\nm[\"b\"][0] + m[\"a\"]", "3");
+    assert_both("{\"b\": 1, \"a\": 2, \"b\": 3}", "{b: 3, a: 2}");
+    assert_both("let m = {\"x\": 1, \"y\": 2}\nstr(keys(m)) + str(len({}))", "[x, y]0");
+    assert_both("{\"a\": 1, \"b\": 2} == {\"b\": 2, \"a\": 1}", "true");
+
+    assert_both_err("{1: 2}", "map keys must be strings, got Int(1)");
+    assert_both_err("{\"a\": 1}[\"b\"]", "key 'b' not found in map");
+    assert_both_err("{\"a\": 1}[0]", "map key must be string, got Int(0)");
+}
+
+#[test]
+fn map_builtins_agree_across_backends() {
+    // Both backends also accept maps from host functions
+    let config = |_: &[zirc_syntax::Value]| Ok(zirc_syntax::Value::Map(vec![
+        ("name".to_string(), zirc_syntax::Value::Str("zirc".to_string())),
+        ("jobs".to_string(), zirc_syntax::Value::Int(4)),
+    ]));
+    let src = "let m = config()\nstr(keys(m)) + \" \" + str(values(m)) + \" \" + str(len(m)) + \" \" + type(m)";
+    let expected = zirc_syntax::Value::Str("[name, jobs] [zirc, 4] 2 map".to_string());
+
+    let mut interp = Interpreter::new();
+    interp.register_builtin("config", Box::new(config)).unwrap();
+    assert_eq!(interp.run_with_env(parse(src), &mut Env::new_root()).unwrap(), Some(expected.clone()));
+
+    let mut compiler = zirc_compiler::Compiler::new();
+    compiler.declare_native("config").unwrap();
+    let program = compiler.compile(parse(src)).unwrap();
+    let mut vm = zirc_vm::Vm::new();
+    vm.register_native("config", Box::new(config)).unwrap();
+    assert_eq!(vm.run(&program).unwrap(), Some(expected));
+
+    assert_both_err("keys(1)", "keys() expects map, got Int(1)");
+    assert_both_err("values(\"ab\")", "values() expects map, got Str(\"ab\")");
+}
+
+#[test]
+fn integer_overflow_errors_agree_across_backends() {
+    for (src, msg) in [
+        ("9223372036854775807 + 1", "integer overflow in addition"),
+        ("let min = 0 - 9223372036854775807 - 1\nmin - 1", "integer overflow in subtraction"),
+        ("let big = 4611686018427387904\nbig * 2", "integer overflow in multiplication"),
+        ("let min = 0 - 9223372036854775807 - 1\nmin / (0 - 1)", "integer overflow in division"),
+        ("let min = 0 - 9223372036854775807 - 1\n-min", "integer overflow in negation"),
+        ("abs(0 - 9223372036854775807 - 1)", "integer overflow in abs()"),
+    ] {
+        assert_both_err(src, msg);
+    }
+    assert_both("4611686018427387903 * 2 + 1", "9223372036854775807");
+}
+
+#[test]
+fn for_each_agrees_across_backends() {
+    assert_both("let total = 0\nfor x in [1, 2, 3]: total = total + x end\ntotal", "6");
+    assert_both("let out = \"\"\nfor c in \"abc\": out = c + out end\nout", "cba");
+    assert_both("let n = 0\nfor _ in [1, 2, 3]: n = n + 1 end\nn", "3");
+    let in_function = r#"
+        fun pairs(xs):
+            let out = []
+            for x in xs:
+                if x == 2: continue end
+                for y in xs:
+                    if y > x: break end
+                    out = out + [str(x) + str(y)]
+                end
+            end
+            return out
+        end
+        pairs([1, 2, 3])
+    "#;
+    assert_both(in_function, "[11, 31, 32, 33]");
+    // The iterable is evaluated once; changing the variable does not change the loop
+    assert_both("let xs = [1, 2]\nlet n = 0\nfor x in xs: xs = xs + [x]\nn = n + 1 end\nstr(n) + \" \" + str(xs)", "2 [1, 2, 1, 2]");
+
+    assert_both_err("for x in 3: show(x) end", "for-in expects list or string, got Int(3)");
+    assert_both_err("for x in false: show(x) end", "for-in expects list or string, got Bool(false)");
+    assert_both_err("for x in {\"a\": 1}: show(x) end", "for-in expects list or string, got Map([(\"a\", Int(1))])");
+}
+
+#[test]
+fn shared_runtime_errors_are_byte_identical() {
+    use zirc_syntax::builtins;
+
+    assert_both_err("let xs = []\npop(xs)", builtins::POP_EMPTY_LIST);
+
+    for (src, msg) in [
+        // A literal zero divisor is a compile error for the VM; see constant_division_by_zero_fails_to_compile
+        ("let zero = 0\n1 / zero", builtins::DIVISION_BY_ZERO),
+        ("[1][3]", builtins::INDEX_OUT_OF_BOUNDS),
+        ("9223372036854775807 + 1", builtins::OVERFLOW_ADD),
+        ("abs(-9223372036854775807 - 1)", builtins::OVERFLOW_ABS),
+        ("pow(2, -1)", builtins::POW_NEGATIVE_EXPONENT),
+        ("sqrt(-1)", builtins::SQRT_NEGATIVE),
+        ("slice([1], 1, 0)", builtins::SLICE_END_BEFORE_START),
+        ("assert(false)", builtins::ASSERTION_FAILED),
+    ] {
+        assert_both_err(src, msg);
+    }
+}
+
+#[test]
+fn compound_assignment_agrees_across_backends() {
+    assert_both("let i = 0\ni += 5\ni", "5");
+    assert_both("let i = 10\ni -= 2 + 1\ni *= 2\ni /= 7\ni", "2");
+    assert_both("let s = \"a\"\ns += \"b\"\ns", "ab");
+    let in_function = r#"
+        fun total(n):
+            let acc = 0
+            for i in 0..n: acc += i end
+            return acc
+        end
+        total(5)
+    "#;
+    assert_both(in_function, "10");
+}
+
+#[test]
+fn loops_yield_unit_as_the_program_result() {
+    assert_both("let i = 0\nwhile i < 3: i = i + 1\ni end", "<unit>");
+    assert_both("for i in 0..3: i * 2 end", "<unit>");
+    assert_both("for c in \"ab\": c end", "<unit>");
+    assert_both("while true: 5\nbreak end", "<unit>");
+    // A top-level `if` ends in its branch's result
+    assert_both("if true:\n 1 + 2\n let x = 4\nend", "<unit>");
+    assert_both("if false: 0 else:\n 1 + 2\n for i in 0..2: i end\nend", "<unit>");
+
+    // Only main's own statements set the result, not those inside a loop
+    let program = zirc_compiler::Compiler::new().compile(parse("let n = 0\nfor i in 0..3:\n let x = i\n if x > 0: n += x end\nend\nn")).unwrap();
+    let units = program.main.code.iter().filter(|i| **i == zirc_bytecode::Instruction::PushUnit).count();
+    assert_eq!(units, 2, "{:?}", program.main.code);
+}
+
+#[test]
+fn loop_variables_are_scoped_to_their_loop() {
+    for src in [
+        "for i in 0..5: if i == 2: break end end\ni",
+        "for i in [1, 2, 3]: break end\ni",
+        "let n = 0\nwhile true: for i in 0..3: n += i end break end\ni",
+    ] {
+        assert_both_err(src, "Undefined variable 'i'");
+    }
+
+    assert_both("let i = 100\nfor i in 0..5: if i == 2: break end end\ni", "100");
+    assert_both("let x = \"outer\"\nfor x in [1, 2]: x = x * 10 end\nx", "outer");
+    assert_both("let i = 1\nwhile i < 3: for i in 0..10: break end i += 1 end\ni", "3");
+    let in_function = r#"
+        fun f():
+            let i = 9
+            for i in 0..3: if i == 1: break end end
+            let seen = []
+            for i in ["a", "b"]: seen = seen + [i] end
+            return str(i) + " " + str(seen)
+        end
+        f()
+    "#;
+    assert_both(in_function, "9 [a, b]");
+}
+
+#[test]
+fn inclusive_ranges_agree_across_backends() {
+    assert_both("let n = 0\nfor i in 0..3: n += 1 end\nn", "3");
+    assert_both("let n = 0\nfor i in 0..=3: n += 1 end\nn", "4");
+    assert_both("let n = 0\nfor i in 0..=0: n += 1 end\nn", "1");
+    assert_both("let n = 0\nfor i in 5..=4: n += 1 end\nn", "0");
+    assert_both("let seen = []\nfor i in 0..=5: if i == 1: continue end if i == 4: break end seen = seen + [i] end\nseen", "[0, 2, 3]");
+    assert_both("fun tri(n):\nlet s = 0\nfor i in 1..=n: s += i end\nreturn s\nend\ntri(10)", "55");
+    // Reaching the end of i64's range ends the loop rather than overflowing
+    assert_both("let n = 0\nfor i in 9223372036854775806..=9223372036854775807: n += 1 end\nn", "2");
+    assert_both("let n = 0\nfor i in 0 - 9223372036854775807..=0 - 9223372036854775807 - 1 step -1: n += 1 end\nn", "2");
+    assert_both("let n = 0\nfor i in 9223372036854775805..=9223372036854775807 step 2: n += 1 end\nn", "2");
+    // Steps that jump over `end` near the limit stop the same way
+    assert_both("let n = 0\nfor i in 9223372036854775802..=9223372036854775807 step 2: n += 1 end\nn", "3");
+    assert_both("let n = 0\nfor i in 0 - 9223372036854775802..=0 - 9223372036854775807 - 1 step -4: n += 1 end\nn", "2");
+    assert_both("let n = 0\nfor i in 9223372036854775806..9223372036854775807 step 9: n += 1 end\nn", "1");
+}
+
+#[test]
+fn format_agrees_across_backends() {
+    assert_both("format(\"%s has %d items: %v\", \"xs\", 2, [1, 2])", "xs has 2 items: [1, 2]");
+    assert_both("let line = format(\"%d%%\", 50)\nline + \"!\"", "50%!");
+
+    // Same wording as showf, naming the builtin that was called
+    for (name, shown) in [("showf", "showf"), ("format", "format")] {
+        let src = format!("{}(\"%d and %d\", 1)", name);
+        let msg = format!("{}: format expects at least 2 arguments but got 1 (missing %d at position 7)", shown);
+        assert_both_err(&src, &msg);
+        assert_both_err(&format!("{}(true)", name), &format!("{} first argument must be a string", shown));
+    }
+
+    // format() returns the text without printing it
+    for backend in ["interp", "vm"] {
+        assert_eq!(cli_stdout(backend, "let s = format(\"%d\", 7)\nshow(s + s)"), "77\n");
+    }
+}
+
+#[test]
+fn bool_conversion_agrees_across_backends() {
+    for (src, expected) in [
+        ("bool(7)", "true"), ("bool(0)", "false"), ("bool(-0.0)", "false"), ("bool(2.5)", "true"),
+        ("bool(\"true\")", "true"), ("bool(\"false\")", "false"), ("bool(\"\")", "false"),
+        ("bool([[]])", "true"), ("bool([])", "false"), ("bool(show(0))", "false"),
+        ("bool(str(bool(1)))", "true"),
+    ] {
+        assert_both(src, expected);
+    }
+
+    for (src, msg) in [
+        ("bool(\"maybe\")", "Cannot convert 'maybe' to bool"),
+        ("bool()", "bool() expects exactly 1 argument"),
+        ("bool(1, 2)", "bool() expects exactly 1 argument"),
+    ] {
+        assert_both_err(src, msg);
+    }
+}
+
+#[test]
+fn contains_and_index_of_agree_across_backends() {
+    assert_both("contains([1, 2, 3], 2)", "true");
+    assert_both("contains([1, 2, 3], 9) == false", "true");
+    assert_both("contains([1, 2, 3], \"2\")", "false");
+    assert_both("contains(\"hello\", \"ell\")", "true");
+    assert_both("contains(\"zirc\", \"\")", "true");
+    assert_both("index_of(\"hello\", \"ll\")", "2");
+    assert_both("index_of(\"naïve café\", \"café\")", "6");
+    assert_both("let s = \"naïve\"\ns[index_of(s, \"v\")]", "v");
+    assert_both("index_of([[1], [2]], [2])", "1");
+    assert_both("index_of([1, 2], 3)", "-1");
+    assert_both("index_of([10, 20, 30], 20) == 1", "true");
+    assert_both("index_of(\"abcabc\", \"c\") == 2", "true");
+    assert_both("index_of(\"abc\", \"\")", "0");
+
+    for (src, msg) in [
+        ("contains(true, 1)", "contains() expects list or string, got Bool(true)"),
+        ("contains(42, 1)", "contains() expects list or string, got Int(42)"),
+        ("index_of(42, 1)", "index_of() expects list or string, got Int(42)"),
+        ("index_of(\"abc\", [1])", "index_of() needle must be string when searching a string, got List([Int(1)])"),
+        ("contains([1])", "contains() expects exactly 2 arguments: haystack and needle"),
+    ] {
+        assert_both_err(src, msg);
+    }
+}
+
+#[test]
+fn recursion_depth_limit_agrees_across_backends() {
+    // Kept small: the interpreter recurses natively and test threads have little stack
+    let interp = |src: &str| {
+        let mut interp = Interpreter::new();
+        interp.set_max_depth(10);
+        interp.run_with_env(parse(src), &mut Env::new_root()).map(|v| v.map(|v| v.to_string())).map_err(|e| e.msg)
+    };
+    let vm = |src: &str| {
+        let mut vm = zirc_vm::Vm::new();
+        vm.set_max_depth(10);
+        vm.run(&zirc_compiler::Compiler::new().compile(parse(src)).unwrap()).map(|v| v.map(|v| v.to_string())).map_err(|e| e.msg)
+    };
+    let depth = "fun f(n): if n == 0: return 0 end\nreturn f(n - 1) + 1 end\n";
+    for run in [&interp as &dyn Fn(&str) -> _, &vm] {
+        assert_eq!(run(&format!("{}f(9)", depth)), Ok(Some("9".to_string())));
+        assert_eq!(run(&format!("{}f(10)", depth)), Err("maximum recursion depth exceeded (10)".to_string()));
+        assert_eq!(run("fun loop(): loop() end\nloop()"), Err("maximum recursion depth exceeded (10)".to_string()));
+    }
+
+    // Both backends default to the same limit. The VM keeps frames on the heap,
+    // so it reaches it in any thread; the interpreter needs a larger stack.
+    let limit = zirc_syntax::builtins::DEFAULT_MAX_DEPTH;
+    let at_limit = format!("{}f({})", depth, limit - 1);
+    assert_eq!(run_vm(&at_limit), Some((limit - 1).to_string()));
+    assert_eq!(vm_err("fun loop(): loop() end\nloop()"), format!("maximum recursion depth exceeded ({})", limit));
+    let results = std::thread::Builder::new()
+        .stack_size(64 * 1024 * 1024)
+        .spawn(move || {
+            let run = |src: &str| Interpreter::new().run_with_env(parse(src), &mut Env::new_root()).map(|v| v.map(|v| v.to_string())).map_err(|e| e.msg);
+            (run(&at_limit), run("fun loop(): loop() end\nloop()"))
+        })
+        .unwrap()
+        .join()
+        .expect("the interpreter overflowed its thread's stack");
+    assert_eq!(results, (Ok(Some((limit - 1).to_string())), Err(format!("maximum recursion depth exceeded ({})", limit))));
+}
+
+#[test]
+fn unbounded_recursion_is_a_clean_error_in_the_cli() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("main.zirc");
+    std::fs::write(&path, "fun down(n): return down(n + 1) end\ndown(0)\n").unwrap();
+    let deep = dir.path().join("deep.zirc");
+    std::fs::write(&deep, "fun f(n): if n == 0: return 0 end\nreturn f(n - 1) + 1 end\nshow(f(999))\n").unwrap();
+    for backend in ["interp", "vm"] {
+        let out = Command::cargo_bin("zirc").unwrap().env_remove("ZIRC_BACKEND").args(["--backend", backend]).arg(&path).output().unwrap();
+        // An ordinary runtime error, not a stack overflow abort
+        assert_eq!(out.status.code(), Some(1), "{}", backend);
+        assert!(String::from_utf8_lossy(&out.stderr).contains("maximum recursion depth exceeded (1000)"), "{}", backend);
+
+        // Both backends reach the shared default limit
+        let out = Command::cargo_bin("zirc").unwrap().env_remove("ZIRC_BACKEND").args(["--backend", backend]).arg(&deep).output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&out.stdout), "999\n", "{}", backend);
+    }
+}
+
+#[test]
+fn rounding_agrees_across_backends() {
+    assert_both("floor(3.7) == 3", "true");
+    assert_both("ceil(3.2) == 4", "true");
+    assert_both("str(round(2.5)) + \" \" + str(round(-2.5)) + \" \" + str(round(2.4))", "3 -3 2");
+    assert_both("type(floor(-0.5)) + \" \" + str(floor(-0.5))", "int -1");
+    assert_both("ceil(-7)", "-7");
+
+    for (src, msg) in [
+        ("floor(\"3\")", "floor() expects a number, got Str(\"3\")"),
+        ("ceil(1.0e300)", "Cannot convert 1e300 to int"),
+        ("round()", "round() expects exactly 1 argument"),
+    ] {
+        assert_both_err(src, msg);
+    }
+}
+
+#[test]
+fn sort_agrees_across_backends() {
+    assert_both("sort([3, 1, 2]) == [1, 2, 3]", "true");
+    assert_both("sort([\"b\", \"a\"]) == [\"a\", \"b\"]", "true");
+    assert_both("sort([2.5, -1.0, 0.5])", "[-1.0, 0.5, 2.5]");
+    assert_both("sort([])", "[]");
+    assert_both("let xs = [2, 1]\nlet ys = sort(xs)\nstr(xs) + \" \" + str(ys)", "[2, 1] [1, 2]");
+
+    for (src, msg) in [
+        ("sort([1, \"a\"])", "sort() requires all elements to be the same comparable type"),
+        ("sort([1, 2.0])", "sort() requires all elements to be the same comparable type"),
+        ("sort([[1]])", "sort() requires all elements to be the same comparable type"),
+        ("sort(\"ba\")", "sort() expects list, got Str(\"ba\")"),
+        ("sort()", "sort() expects exactly 1 argument"),
+    ] {
+        assert_both_err(src, msg);
+    }
+}
+
+#[test]
+fn reverse_agrees_across_backends() {
+    assert_both("reverse([1, 2, 3]) == [3, 2, 1]", "true");
+    assert_both("reverse(\"abc\") == \"cba\"", "true");
+    assert_both("reverse(\"héllo\")", "olléh");
+    assert_both("reverse([])", "[]");
+    assert_both("let xs = [1, 2]\nlet ys = reverse(xs)\nstr(xs) + \" \" + str(ys)", "[1, 2] [2, 1]");
+
+    for (src, msg) in [
+        ("reverse(42)", "reverse() expects list or string, got Int(42)"),
+        ("reverse()", "reverse() expects exactly 1 argument"),
+        ("reverse([1], [2])", "reverse() expects exactly 1 argument"),
+    ] {
+        assert_both_err(src, msg);
+    }
+}
+
+#[test]
+fn range_agrees_across_backends() {
+    assert_both("range(0, 5) == [0, 1, 2, 3, 4]", "true");
+    assert_both("range(0, 10, 2)", "[0, 2, 4, 6, 8]");
+    assert_both("range(5, 0, -2)", "[5, 3, 1]");
+    assert_both("range(3, 3)", "[]");
+    assert_both("range(3, 0)", "[]");
+    assert_both("let xs = range(1, 4)\nreverse(sort(xs))", "[3, 2, 1]");
+
+    for (src, msg) in [
+        ("range(0, 5, 0)", "range() step cannot be zero"),
+        ("range(0, 2.5)", "range() arguments must be ints, got Float(2.5)"),
+        ("range(\"a\", 3)", "range() arguments must be ints, got Str(\"a\")"),
+        ("range(1)", "range() expects 2 or 3 arguments: start, end and optional step"),
+        ("range(1, 2, 3, 4)", "range() expects 2 or 3 arguments: start, end and optional step"),
+    ] {
+        assert_both_err(src, msg);
+    }
+}
+
+#[test]
+fn prompt_strips_only_the_line_terminator() {
+    let src = "let a = prompt()\nlet b = prompt()\nlet c = prompt_trimmed()\nshow(\"[\" + a + \"]\")\nshow(\"[\" + b + \"]\")\nshow(\"[\" + c + \"]\")\n";
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("main.zirc");
+    std::fs::write(&path, src).unwrap();
+    for backend in ["interp", "vm"] {
+        let out = assert_cmd::Command::cargo_bin("zirc").unwrap()
+            .env_remove("ZIRC_BACKEND")
+            .args(["--backend", backend])
+            .arg(&path)
+            .write_stdin(" hi \r\na\rb\n  there \r\n")
+            .output()
+            .unwrap();
+        assert!(out.status.success(), "{} backend failed: {}", backend, String::from_utf8_lossy(&out.stderr));
+        assert_eq!(String::from_utf8(out.stdout).unwrap(), "[ hi ]\n[a\rb]\n[there]\n", "{}", backend);
+    }
+}
+
+#[test]
+fn functions_return_their_last_value() {
+    assert_both("fun double(x): x * 2 end\ndouble(4)", "8");
+    assert_both("fun sign(x):\n if x < 0: -1 else: if x == 0: 0 else: 1 end end\nend\nstr(sign(-5)) + str(sign(0)) + str(sign(7))", "-101");
+    assert_both("fun f(): if false: 5 end end\nf()", "<unit>");
+    assert_both("fun f(): 5\nlet _ = 1 end\nf()", "<unit>");
+    assert_both("fun f(): for i in 0..2: i end end\nf()", "<unit>");
+}
+
+#[test]
+fn functions_read_top_level_variables() {
+    let src = "let base = 10\nfun add(x): x + base end\nshow(add(5))\n";
+    assert_eq!(cli_stdout("vm", src), "15\n");
+    assert_eq!(cli_stdout("interp", src), "15\n");
+    // Looked up when the function runs, not where it is defined
+    assert_both("fun scaled(x): x * factor end\nlet factor = 3\nscaled(2)", "6");
+    assert_both("let n = 1\nfun bump(): let n = 5\nn end\nbump() * 10 + n", "51");
+
+    let src = "fun f(): missing end\nf()";
+    assert_both_err(src, "Undefined variable 'missing'");
+}
+
+#[test]
+fn labelled_break_and_continue_agree_across_backends() {
+    let two_levels = r#"
+        let found = []
+        outer: for i in 0..5:
+            for x in [10, 20, 30]:
+                while true:
+                    if i * x == 40: break outer end
+                    break
+                end
+            end
+            push(found, i)
+        end
+        found
+    "#;
+    assert_both(two_levels, "[0, 1]");
+    let skip_rows = r#"
+        let seen = []
+        rows: for row in [[1, -1, 2], [3, 4], [-5, 6]]:
+            for x in row:
+                if x < 0: continue rows end
+                push(seen, x)
+            end
+        end
+        seen
+    "#;
+    assert_both(skip_rows, "[1, 3, 4]");
+    let in_function = r#"
+        fun first_pair(n):
+            let found = []
+            search: for a in 1..n:
+                for b in 1..n:
+                    if a * b == 6: found = [a, b]
+                    break search end
+                end
+            end
+            return found
+        end
+        first_pair(5)
+    "#;
+    assert_both(in_function, "[2, 3]");
+}
+
+#[test]
+fn replace_agrees_across_backends() {
+    assert_both("replace(\"a,b,c\", \",\", \";\")", "a;b;c");
+    assert_both("replace(\"aaa\", \"aa\", \"b\")", "ba");
+    assert_both("replace(\"naïve\", \"ï\", \"\")", "nave");
+
+    for (src, msg) in [
+        ("replace(\"abc\", \"\", \"x\")", "replace() pattern must not be empty"),
+        ("replace(\"abc\", 1, \"x\")", "replace() expects three strings"),
+        ("replace(\"abc\", \"b\")", "replace() expects exactly 3 arguments: string, from and to"),
+    ] {
+        assert_both_err(src, msg);
+    }
+}
+
+#[test]
+fn starts_with_and_ends_with_agree_across_backends() {
+    assert_both("starts_with(\"zirc-lang\", \"zirc\")", "true");
+    assert_both("starts_with(\"zirc\", \"lang\")", "false");
+    assert_both("ends_with(\"main.zirc\", \".zirc\")", "true");
+    assert_both("ends_with(\"naïve\", \"ïve\")", "true");
+    assert_both("str(starts_with(\"abc\", \"\")) + str(ends_with(\"\", \"a\"))", "truefalse");
+
+    for (src, msg) in [
+        ("starts_with(\"abc\", 1)", "starts_with() expects two strings"),
+        ("ends_with([1], \"a\")", "ends_with() expects two strings"),
+        ("starts_with(\"abc\")", "starts_with() expects exactly 2 arguments: string and prefix"),
+        ("ends_with(\"a\", \"b\", \"c\")", "ends_with() expects exactly 2 arguments: string and suffix"),
+    ] {
+        assert_both_err(src, msg);
+    }
+}
+
+#[test]
+fn range_steps_agree_across_backends() {
+    assert_both("let a = []\nfor i in 0..10 step 3: a += [i] end\na", "[0, 3, 6, 9]");
+    assert_both("let a = []\nfor i in 10..0 step -2: a += [i] end\na", "[10, 8, 6, 4, 2]");
+    assert_both("let a = []\nfor i in 10..=0 step -5: a += [i] end\na", "[10, 5, 0]");
+    assert_both("let a = []\nfor i in 0..5 step -1: a += [i] end\na", "[]");
+    // A step known only at runtime picks its direction when the loop starts
+    let runtime_step = r#"
+        fun walk(from, to, by):
+            let out = []
+            for i in from..=to step by:
+                if i == 4: continue end
+                out += [i]
+            end
+            return out
+        end
+        str(walk(0, 6, 2)) + " " + str(walk(6, 0, 0 - 2)) + " " + str(walk(0, 6, 0 - 1))
+    "#;
+    assert_both(runtime_step, "[0, 2, 6] [6, 2, 0] []");
+    assert_both("let step = 3\nlet n = 0\nfor i in 0..9 step step: n += 1 end\nn + step", "6");
+    // A step that would pass the end of i64's range ends the loop
+    let max = i64::MAX;
+    for (range, count) in [
+        (format!("{}..{} step 5", max - 7, max), 2),
+        (format!("{}..={} step 5", max - 7, max), 2),
+        (format!("{}..={} step 3", max - 7, max), 3),
+        (format!("0 - {}..0 - {} step -5", max - 7, max), 2),
+        (format!("{}..={} step big", max - 7, max), 1),
+    ] {
+        let src = format!("let big = {}\nlet n = 0\nfor i in {}: n += 1 end\nn", max, range);
+        assert_both(&src, &count.to_string());
+    }
+
+    for (src, msg) in [
+        ("for i in 0..3 step 0: show(i) end", zirc_syntax::builtins::FOR_STEP_ZERO),
+        ("let z = 0\nfor i in 0..3 step z: show(i) end", zirc_syntax::builtins::FOR_STEP_ZERO),
+        ("for i in 0..3 step \"1\": show(i) end", "for step must be int, got Str(\"1\")"),
+    ] {
+        assert_both_err(src, msg);
+    }
+}
+
+#[test]
+fn padded_strings_agree_across_backends() {
+    assert_both("format(\"%-5s|\", \"ab\")", "ab   |");
+    assert_both("format(\"%5s|\", \"ab\")", "   ab|");
+    let table = r#"
+        let out = ""
+        for row in [["apple", "3"], ["kiwi", "12"]]:
+            out += format("%-6s%3s;", row[0], row[1])
+        end
+        out
+    "#;
+    assert_both(table, "apple   3;kiwi   12;");
+}
+
+#[test]
+fn push_and_pop_agree_across_backends() {
+    assert_both("let xs = []\nfor i in 0..4: push(xs, i * i) end\nxs", "[0, 1, 4, 9]");
+    assert_both("let xs = [1, 2, 3]\nlet last = pop(xs)\nstr(last) + \" \" + str(xs)", "3 [1, 2]");
+    // push() reads its variable before evaluating the value, so the pop() inside is lost
+    assert_both("let xs = [[1]]\npush(xs, pop(xs) + [2])\nxs", "[[1], [1, 2]]");
+    let in_function = r#"
+        fun reversed(items):
+            let out = []
+            while len(items) > 0: push(out, pop(items)) end
+            return out
+        end
+        let xs = [1, 2, 3]
+        str(reversed(xs)) + " " + str(xs)
+    "#;
+    assert_both(in_function, "[3, 2, 1] [1, 2, 3]");
+    // push() returns the extended list, so assigning it back changes nothing more
+    assert_both("let xs = [1]\nlet r = push(xs, 2)\nstr(r) + \" \" + str(xs)", "[1, 2] [1, 2]");
+    assert_both("let xs = [1, 2]\nxs = push(xs, 3)\nxs", "[1, 2, 3]");
+    assert_both("fun f():\n let xs = []\n xs = push(xs, 1)\n return xs\nend\nf()", "[1]");
+}
+
+#[test]
+fn push_onto_an_expression_returns_the_new_list() {
+    assert_both("push([1, 2], 3)", "[1, 2, 3]");
+    assert_both("let xs = [1]\nlet ys = push(xs + [], 2)\nstr(xs) + \" \" + str(ys)", "[1] [1, 2]");
+    assert_both("let xs = []\nfor i in 0..3: xs = push(copy(xs), i) end\nxs", "[0, 1, 2]");
+    assert_both("len(push(push([], \"a\"), \"b\"))", "2");
+
+    for (src, msg) in [
+        ("let xs = []\npop(xs)", "Cannot pop from empty list"),
+        ("let n = 1\npush(n, 2)", "push() expects list, got Int(1)"),
+        ("let s = \"ab\"\npop(s)", "pop() expects list variable, got Str(\"ab\")"),
+        ("push(1 + 1, 2)", "push() expects list, got Int(2)"),
+        ("pop(first(), 1)", "pop() expects exactly 1 argument: list_variable"),
+        ("push(nope, 1)", "Undefined variable 'nope'"),
+    ] {
+        assert_both_err(src, msg);
+    }
+    // Not assignable: the VM rejects it before running anything
+    let err = zirc_compiler::Compiler::new().compile(parse("show(1)\npop([1, 2])")).unwrap_err();
+    assert_eq!(err.msg, "pop() first argument must be a variable name");
+}
+
+#[test]
+fn add_agrees_across_backends() {
+    assert_both("2 + 3", "5");
+    assert_both("1.5 + 2", "3.5");
+    assert_both("2 + 0.25", "2.25");
+    assert_both("\"ab\" + \"cd\"", "abcd");
+    assert_both("[1] + [2, 3]", "[1, 2, 3]");
+
+    // Every other pairing is an error, worded the same by both backends
+    let operands = [
+        ("1", "Int(1)"),
+        ("1.5", "Float(1.5)"),
+        ("\"s\"", "Str(\"s\")"),
+        ("true", "Bool(true)"),
+        ("[1]", "List([Int(1)])"),
+        ("{\"k\": 1}", "Map([(\"k\", Int(1))])"),
+        ("nothing()", "Unit"),
+    ];
+    for (i, (a, a_value)) in operands.iter().enumerate() {
+        for (j, (b, b_value)) in operands.iter().enumerate() {
+            // Numbers with numbers, and strings or lists with their own kind, add fine
+            if (i < 2 && j < 2) || (i == j && (*a == "\"s\"" || *a == "[1]")) {
+                continue;
+            }
+            let src = format!("fun nothing():\n    return\nend\n{} + {}", a, b);
+            assert_both_err(&src, &format!("Cannot add {} and {}", a_value, b_value));
+        }
+    }
+    let overflow = "9223372036854775807 + 1";
+    assert_both_err(overflow, zirc_syntax::builtins::OVERFLOW_ADD);
+}
+
+#[test]
+fn chr_and_ord_agree_across_backends() {
+    assert_both("ord(\"A\")", "65");
+    assert_both("ord(\"é\")", "233");
+    assert_both("chr(122) + chr(8364)", "z€");
+    assert_both("let s = \"héllo\"\nchr(ord(s[1]))", "é");
+
+    for (src, msg) in [
+        ("ord(\"\")", "ord() expects a one-char string, got \"\""),
+        ("ord(\"ab\")", "ord() expects a one-char string, got \"ab\""),
+        ("ord(1)", "ord() expects a string, got Int(1)"),
+        ("chr(-1)", "chr() code point -1 is out of range or a surrogate"),
+        ("chr(55296)", "chr() code point 55296 is out of range or a surrogate"),
+        ("chr(1114112)", "chr() code point 1114112 is out of range or a surrogate"),
+        ("chr(\"a\")", "chr() expects an int, got Str(\"a\")"),
+        ("chr()", "chr() expects exactly 1 argument"),
+    ] {
+        assert_both_err(src, msg);
+    }
+}
+
+#[test]
+fn public_vm_is_the_full_vm() {
+    // The crate root re-exports the one VM in zirc_vm::vm
+    let mut vm: zirc_vm::vm::Vm = zirc_vm::Vm::new();
+    let src = "let x = 16\nfun f():\n    return sqrt(x) + pow(2, 3) + len([1, 2] + [3])\nend\nlet y = f()\ny";
+    let program = zirc_compiler::Compiler::new().compile(parse(src)).unwrap();
+    assert_eq!(vm.run(&program).unwrap(), Some(zirc_syntax::Value::Float(15.0)));
+    assert_eq!(vm.globals_snapshot(), vec![
+        ("x".to_string(), zirc_syntax::Value::Int(16)),
+        ("y".to_string(), zirc_syntax::Value::Float(15.0)),
+    ]);
+}
+
+#[test]
+fn bytecode_round_trip_runs_identically() {
+    let src = r#"
+        let scores = {"ann": [3, 4], "bob": [5]}
+        fun total(xs):
+            let sum = 0.5
+            for x in xs:
+                sum += x
+            end
+            return sum
+        end
+        let out = []
+        for name in keys(scores):
+            push(out, format("%s=%v", name, total(scores[name])))
+        end
+        join(out, ", ") + " " + upper("ok")
+    "#;
+    let program = zirc_compiler::Compiler::new().compile(parse(src)).unwrap();
+    let loaded = zirc_bytecode::Program::from_bytes(&program.to_bytes()).unwrap();
+    assert_eq!(loaded, program);
+
+    let run = |p: &zirc_bytecode::Program| zirc_vm::Vm::new().run(p).unwrap().map(|v| v.to_string());
+    assert_eq!(run(&loaded), Some("ann=7.5, bob=5.5 OK".to_string()));
+    assert_eq!(run(&loaded), run(&program));
+}
+
+#[test]
+fn min_and_max_take_lists_or_several_numbers() {
+    assert_both("max([3, 1, 4, 1, 5])", "5");
+    assert_both("min([3, 1, 4, 1, 5])", "1");
+    assert_both("min(7, 2, 9)", "2");
+    assert_both("max(7, 2)", "7");
+    assert_both("max([4])", "4");
+    assert_both("max(1, 2.5, 2)", "2.5");
+    assert_both("min([3, 0.5])", "0.5");
+    // A float anywhere makes the result a float, as for two arguments
+    assert_both("max(3, 0.5)", "3.0");
+
+    for (src, msg) in [
+        ("min([])", "min() of empty list"),
+        ("max([])", "max() of empty list"),
+        ("min(1)", "min() expects a list or at least 2 numbers"),
+        ("max()", "max() expects a list or at least 2 numbers"),
+        ("min(1, \"a\")", "min() cannot compare Int(1) and Str(\"a\")"),
+        ("max([1, [2]])", "max() cannot compare Int(1) and List([Int(2)])"),
+        ("min([1], [2])", "min() cannot compare List([Int(1)]) and List([Int(1)])"),
+    ] {
+        assert_both_err(src, msg);
+    }
+    assert_both_err("max(3)", "max() expects a list or at least 2 numbers");
+}
+
+#[test]
+fn one_char_strings_order_by_code_point() {
+    // Zirc has no char type; indexing a string gives a one-char string
+    assert_both("\"a\" < \"b\"", "true");
+    assert_both("let s = \"zá\"\ns[1] > s[0]", "true");
+    assert_both("str(\"a\" >= \"a\") + str(\"B\" <= \"a\")", "truetrue");
+    assert_both("max(\"a\", \"z\")", "z");
+    assert_both("min([\"q\", \"é\", \"c\"])", "c");
+    assert_both("sort([\"é\", \"b\", \"a\"])", "[a, b, é]");
+    assert_both("ord(max(\"a\", \"z\")) - ord(\"a\")", "25");
+
+    for (src, msg) in [
+        ("\"a\" < 1", "< expects two numbers or two strings"),
+        ("2 >= \"b\"", ">= expects two numbers or two strings"),
+        ("max(\"a\", 1)", "max() cannot compare Str(\"a\") and Int(1)"),
+        ("min([\"a\", 0.5])", "min() cannot compare Str(\"a\") and Float(0.5)"),
+        ("sort([\"a\", 1])", "sort() requires all elements to be the same comparable type"),
+    ] {
+        assert_both_err(src, msg);
+    }
+}
+
+#[test]
+fn folded_constants_agree_across_backends() {
+    assert_both("2 + 3 * 4", "14");
+    assert_both("7 / 2 - 1.5", "1.5");
+    assert_both("\"ab\" + \"c\" == \"abc\"", "true");
+    assert_both("!(3 >= 3) == false", "true");
+    assert_both("-(-5) * 2.0", "10.0");
+    // 0.0 and -0.0 are equal but must not share a pooled constant
+    assert_both("str(0.0) + \" \" + str(-0.0)", "0.0 -0.0");
+
+    // Folded-away operands leave nothing behind in the constant pool
+    let program = zirc_compiler::Compiler::new().compile(parse("\"aa\" + \"bb\" + \"cc\" + \"dd\"")).unwrap();
+    assert_eq!(program.main.constants.len(), 1);
+}
+
+#[test]
+fn constant_division_by_zero_fails_to_compile() {
+    let err = zirc_compiler::Compiler::new().compile(parse("show(1)\nif false:\n    show(4 / (2 - 2))\nend")).unwrap_err();
+    assert_eq!(err.msg, zirc_syntax::builtins::DIVISION_BY_ZERO);
+    assert_eq!((err.line, err.col), (Some(3), Some(12)));
+
+    let err = zirc_compiler::Compiler::new().compile(parse("let a = 1\na + 1 / 0")).unwrap_err();
+    assert_eq!((err.line, err.col), (Some(2), Some(7)));
+}
+
+#[test]
+fn peephole_pass_shortens_fibonacci_without_changing_it() {
+    let src = r#"
+        fun fibs(count):
+            let out = []
+            let a = 0
+            let b = 1
+            let next = 0
+            for i in 0..count:
+                push(out, a)
+                next = a + b
+                a = b
+                b = next
+            end
+            out
+        end
+
+        fun fib(n):
+            if n < 2:
+                return n
+            end
+            fib(n - 1) + fib(n - 2)
+        end
+
+        let all = fibs(12)
+        let summary = [fib(11), all[11], len(all)]
+        summary
+    "#;
+    let compile = |optimize| {
+        let mut compiler = zirc_compiler::Compiler::new();
+        compiler.set_optimize(optimize);
+        compiler.compile(parse(src)).expect("compile")
+    };
+    let (plain, optimized) = (compile(false), compile(true));
+    let code_len = |p: &zirc_bytecode::Program| p.all_functions().map(|f| f.code.len()).sum::<usize>();
+    assert!(code_len(&optimized) < code_len(&plain), "{} vs {} instructions", code_len(&optimized), code_len(&plain));
+
+    let run = |p: &zirc_bytecode::Program| zirc_vm::Vm::new().run(p).expect("vm run").map(|v| zirc_vm::display::display_value(&v));
+    assert_eq!(run(&optimized), run(&plain));
+    assert_both(src, "[89, 89, 12]");
+}
+
+#[test]
+fn function_names_are_not_values() {
+    // Functions are not first-class yet, so naming one without calling it is
+    // an undefined variable on both backends rather than a value to show
+    for src in ["fun f(x, y):\n    x + y\nend\nshow(f)", "fun f(x):\n    x\nend\ntype(f)", "fun f():\n    1\nend\nf == f"] {
+        assert_both_err(src, "Undefined variable 'f'");
+    }
+}
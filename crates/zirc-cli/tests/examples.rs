@@ -16,7 +16,7 @@ fn workspace_root() -> PathBuf {
 #[test]
 fn runs_factorial_example() {
     let root = workspace_root();
-    let mut cmd = Command::cargo_bin("zirc-cli").unwrap();
+    let mut cmd = Command::cargo_bin("zirc").unwrap();
     cmd.arg(root.join("examples/others/factorial.zirc"));
     cmd.assert()
         .success()
@@ -26,13 +26,25 @@ fn runs_factorial_example() {
 #[test]
 fn runs_conditionals_example() {
     let root = workspace_root();
-    let mut cmd = Command::cargo_bin("zirc-cli").unwrap();
+    let mut cmd = Command::cargo_bin("zirc").unwrap();
     cmd.arg(root.join("examples/basic/conditionals.zirc"));
     cmd.assert()
         .success()
         .stdout(predicate::str::contains("3 is less than 5"));
 }
 
+#[test]
+fn runs_for_loop_example_on_both_backends() {
+    let root = workspace_root();
+    for backend in ["interp", "vm"] {
+        let mut cmd = Command::cargo_bin("zirc").unwrap();
+        cmd.env_remove("ZIRC_BACKEND").args(["--backend", backend]).arg(root.join("examples/basic/for_loop.zirc"));
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("sum = 12\nreversed = criz\n"));
+    }
+}
+
 #[test]
 fn parse_error_is_nonzero() {
     let bad = "fun x(\n"; // malformed on purpose
@@ -40,9 +52,406 @@ fn parse_error_is_nonzero() {
     let bad_path = tmp_dir.path().join("bad.zirc");
     std::fs::write(&bad_path, bad).unwrap();
 
-    let mut cmd = Command::cargo_bin("zirc-cli").unwrap();
+    let mut cmd = Command::cargo_bin("zirc").unwrap();
     cmd.arg(bad_path);
     cmd.assert()
         .failure()
         .stderr(predicate::str::contains("Parse error"));
 }
+
+#[test]
+fn integer_overflow_is_a_runtime_error_not_a_panic() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let path = tmp_dir.path().join("overflow.zirc");
+    std::fs::write(&path, "let big = 9223372036854775807\nshow(big)\nlet n = big\nn += 1\nshow(n)\n").unwrap();
+
+    for backend in ["interp", "vm"] {
+        let mut cmd = Command::cargo_bin("zirc").unwrap();
+        cmd.env_remove("ZIRC_BACKEND").args(["--backend", backend]).arg(&path);
+        cmd.assert()
+            .code(1)
+            .stdout("9223372036854775807\n")
+            .stderr(predicate::str::contains("integer overflow in addition"))
+            .stderr(predicate::str::contains("panicked").not());
+    }
+}
+
+#[test]
+fn script_dir_matches_file_directory() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let script = tmp_dir.path().join("where.zirc");
+    std::fs::write(&script, "show(script_dir())\nshow(script_path())\n").unwrap();
+    let expected = format!("{}\n{}\n", tmp_dir.path().display(), script.display());
+
+    for backend in ["interp", "vm"] {
+        let mut cmd = Command::cargo_bin("zirc").unwrap();
+        cmd.env_remove("ZIRC_BACKEND").args(["--backend", backend]).arg(&script);
+        cmd.assert().success().stdout(expected.clone());
+    }
+}
+
+fn failing_script_stderr(color_args: &[&str], no_color: bool) -> String {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let path = tmp_dir.path().join("fail.zirc");
+    std::fs::write(&path, "show(undefined_var)\n").unwrap();
+    let mut cmd = Command::cargo_bin("zirc").unwrap();
+    if no_color { cmd.env("NO_COLOR", "1"); } else { cmd.env_remove("NO_COLOR"); }
+    let out = cmd.args(color_args).arg(&path).output().unwrap();
+    assert!(!out.status.success());
+    String::from_utf8(out.stderr).unwrap()
+}
+
+#[test]
+fn color_never_emits_no_escapes() {
+    let stderr = failing_script_stderr(&["--color=never"], false);
+    assert!(stderr.contains("Undefined variable"));
+    assert!(!stderr.contains('\x1b'), "unexpected escapes: {:?}", stderr);
+
+    let stderr = failing_script_stderr(&["--color", "never"], false);
+    assert!(!stderr.contains('\x1b'), "unexpected escapes: {:?}", stderr);
+}
+
+#[test]
+fn color_always_overrides_no_color() {
+    let stderr = failing_script_stderr(&["--color=always"], true);
+    assert!(stderr.contains("\x1b["), "expected escapes: {:?}", stderr);
+
+    let stderr = failing_script_stderr(&[], true);
+    assert!(!stderr.contains('\x1b'), "unexpected escapes: {:?}", stderr);
+}
+
+#[test]
+fn color_rejects_unknown_value() {
+    let mut cmd = Command::cargo_bin("zirc").unwrap();
+    cmd.arg("--color=sometimes").arg("whatever.zirc");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid --color value 'sometimes'"));
+}
+
+#[test]
+fn strict_fails_on_unused_variable() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let path = tmp_dir.path().join("unused.zirc");
+    std::fs::write(&path, "let unused = 1\nshow(\"ok\")\n").unwrap();
+
+    // Without --strict, only compiling for the VM warns
+    let mut cmd = Command::cargo_bin("zirc").unwrap();
+    cmd.env_remove("ZIRC_BACKEND").args(["--backend", "vm"]).arg(&path);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("ok"))
+        .stderr(predicate::str::contains("warning: unused variable 'unused'"));
+    let mut cmd = Command::cargo_bin("zirc").unwrap();
+    cmd.env_remove("ZIRC_BACKEND").args(["--backend", "interp"]).arg(&path);
+    cmd.assert().success().stdout(predicate::str::contains("ok")).stderr("");
+
+    for backend in ["interp", "vm"] {
+
+        let mut cmd = Command::cargo_bin("zirc").unwrap();
+        cmd.env_remove("ZIRC_BACKEND").args(["--backend", backend, "--strict"]).arg(&path);
+        cmd.assert()
+            .failure()
+            .stdout(predicate::str::contains("ok").not())
+            .stderr(predicate::str::contains("strict mode: unused variable 'unused'"));
+    }
+}
+
+#[test]
+fn print_result_prints_final_value_once() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let calc = tmp_dir.path().join("calc.zirc");
+    std::fs::write(&calc, "let x = 6\nx * 7\n").unwrap();
+    let shown = tmp_dir.path().join("shown.zirc");
+    std::fs::write(&shown, "show(42)\n").unwrap();
+    // A trailing binding or assignment leaves unit, which is not printed
+    let mut bound = Vec::new();
+    for (i, src) in ["1 + 2\nlet y = 4\n", "let x = 0\n1 + 2\nx = 5\n", "let x = 1\n1 + 2\nx += 1\n", "let l = [1]\n1 + 2\nl[0] = 5\n"].iter().enumerate() {
+        let path = tmp_dir.path().join(format!("bound{}.zirc", i));
+        std::fs::write(&path, src).unwrap();
+        bound.push(path);
+    }
+
+    for backend in ["interp", "vm"] {
+        let mut cmd = Command::cargo_bin("zirc").unwrap();
+        cmd.env_remove("ZIRC_BACKEND").args(["--backend", backend, "--print-result"]).arg(&calc);
+        cmd.assert().success().stdout("42\n");
+
+        let mut cmd = Command::cargo_bin("zirc").unwrap();
+        cmd.env_remove("ZIRC_BACKEND").args(["--backend", backend]).arg(&calc);
+        cmd.assert().success().stdout("");
+
+        let mut cmd = Command::cargo_bin("zirc").unwrap();
+        cmd.env_remove("ZIRC_BACKEND").args(["--backend", backend, "--print-result"]).arg(&shown);
+        cmd.assert().success().stdout("42\n");
+
+        for path in &bound {
+            let mut cmd = Command::cargo_bin("zirc").unwrap();
+            cmd.env_remove("ZIRC_BACKEND").args(["--backend", backend, "--print-result"]).arg(path);
+            cmd.assert().success().stdout("");
+        }
+    }
+}
+
+#[test]
+fn lex_error_caret_points_at_character() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let path = tmp_dir.path().join("bad_char.zirc");
+    std::fs::write(&path, "let x = @\n").unwrap();
+    let out = Command::cargo_bin("zirc").unwrap().arg("--color=never").arg(&path).output().unwrap();
+    assert!(!out.status.success());
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.contains("Lex error: Unexpected character '@'"), "{}", stderr);
+    assert!(stderr.contains("line 1, column 9"), "{}", stderr);
+
+    // The caret sits under the `@` in the echoed source line
+    let lines: Vec<&str> = stderr.lines().collect();
+    let src_at = lines.iter().position(|l| l.ends_with("let x = @")).unwrap();
+    let at_col = lines[src_at].find('@').unwrap();
+    assert_eq!(lines[src_at + 1].find('^'), Some(at_col), "{}", stderr);
+}
+
+#[test]
+fn runtime_error_caret_points_at_index() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let path = tmp_dir.path().join("bad_index.zirc");
+    std::fs::write(&path, "let xs = [1, 2]\nshow(xs[5])\n").unwrap();
+    let out = Command::cargo_bin("zirc").unwrap().arg("--color=never").arg(&path).env_remove("ZIRC_BACKEND").output().unwrap();
+    assert!(!out.status.success());
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.contains("Runtime error: index out of bounds"), "{}", stderr);
+    assert!(stderr.contains("line 2, column 8"), "{}", stderr);
+
+    let lines: Vec<&str> = stderr.lines().collect();
+    let src_at = lines.iter().position(|l| l.ends_with("show(xs[5])")).unwrap();
+    let bracket_col = lines[src_at].find('[').unwrap();
+    assert_eq!(lines[src_at + 1].find('^'), Some(bracket_col), "{}", stderr);
+}
+
+#[test]
+fn test_subcommand_reports_assertion_results() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    std::fs::write(tmp_dir.path().join("test_pass.zirc"), "assert_eq(1 + 1, 2)\nassert(len(\"ab\") == 2)\n").unwrap();
+    std::fs::write(tmp_dir.path().join("test_fail.zirc"), "assert_eq([1, 2], [1, 3])\n").unwrap();
+    // Not run: a test_ file is present, so only test_ files are discovered
+    std::fs::write(tmp_dir.path().join("helper.zirc"), "panic(\"not a test\")\n").unwrap();
+
+    for backend in ["interp", "vm"] {
+        let mut cmd = Command::cargo_bin("zirc").unwrap();
+        cmd.env_remove("ZIRC_BACKEND").args(["test", "--backend", backend]).arg(tmp_dir.path());
+        cmd.assert()
+            .failure()
+            .stdout(predicate::str::contains("PASS").and(predicate::str::contains("test_pass.zirc")))
+            .stdout(predicate::str::contains("test_fail.zirc: assertion failed: [1, 2] != [1, 3]"))
+            .stdout(predicate::str::contains("1 passed, 1 failed"))
+            .stdout(predicate::str::contains("helper.zirc").not());
+    }
+
+    std::fs::remove_file(tmp_dir.path().join("test_fail.zirc")).unwrap();
+    let mut cmd = Command::cargo_bin("zirc").unwrap();
+    cmd.env_remove("ZIRC_BACKEND").arg("test").arg(tmp_dir.path());
+    cmd.assert().success().stdout(predicate::str::contains("1 passed, 0 failed"));
+}
+
+#[test]
+fn repl_survives_division_by_zero() {
+    for backend in ["interp", "vm"] {
+        // With no script, `zirc` starts the REPL on the chosen backend
+        let mut cmd = assert_cmd::Command::cargo_bin("zirc").unwrap();
+        cmd.env_remove("ZIRC_BACKEND").args(["--backend", backend]).write_stdin("10 / 0\n40 + 2\n");
+        cmd.assert()
+            .success()
+            .stderr(predicate::str::contains("division by zero"))
+            .stdout(predicate::str::contains("42"));
+    }
+}
+
+#[test]
+fn watch_reruns_script_on_save() {
+    use std::io::BufRead;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let script = tmp_dir.path().join("live.zirc");
+    std::fs::write(&script, "show(\"v1\")\n").unwrap();
+
+    let mut child = Command::cargo_bin("zirc").unwrap()
+        .env_remove("ZIRC_BACKEND")
+        .arg("--watch")
+        .arg(&script)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let (tx, rx) = mpsc::channel();
+    for stream in [Box::new(child.stdout.take().unwrap()) as Box<dyn std::io::Read + Send>, Box::new(child.stderr.take().unwrap())] {
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            for line in std::io::BufReader::new(stream).lines().map_while(Result::ok) {
+                let _ = tx.send(line);
+            }
+        });
+    }
+    let wait_for = |needle: &str| loop {
+        match rx.recv_timeout(Duration::from_secs(10)) {
+            Ok(line) if line.contains(needle) => break,
+            Ok(_) => {}
+            Err(_) => panic!("timed out waiting for {:?}", needle),
+        }
+    };
+
+    wait_for("v1");
+    std::fs::write(&script, "show(\"v2\")\n").unwrap();
+    wait_for("changed, re-running");
+    wait_for("v2");
+    // A failing run is reported and the watcher keeps going
+    std::fs::write(&script, "show(1 / 0)\n").unwrap();
+    wait_for("division by zero");
+    std::fs::write(&script, "show(\"v3\")\n").unwrap();
+    wait_for("v3");
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn emitted_bytecode_runs_like_the_source() {
+    let root = workspace_root();
+    let source = root.join("examples/others/factorial.zirc");
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let zbc = tmp_dir.path().join("factorial.zbc");
+
+    let mut cmd = Command::cargo_bin("zirc").unwrap();
+    cmd.arg(&source).arg("--emit-bytecode").arg(&zbc);
+    cmd.assert().success().stdout("");
+
+    let expected = Command::cargo_bin("zirc").unwrap()
+        .env_remove("ZIRC_BACKEND").args(["--backend", "vm"]).arg(&source)
+        .output().unwrap();
+    assert!(expected.status.success());
+    // The interpreter backend setting is ignored for bytecode
+    let mut cmd = Command::cargo_bin("zirc").unwrap();
+    cmd.env_remove("ZIRC_BACKEND").args(["--backend", "interp"]).arg(&zbc);
+    cmd.assert().success().stdout(String::from_utf8(expected.stdout).unwrap());
+
+    std::fs::write(&zbc, "show(1)\n").unwrap();
+    let mut cmd = Command::cargo_bin("zirc").unwrap();
+    cmd.arg(&zbc);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Not a Zirc bytecode file"));
+}
+
+#[test]
+fn compile_subcommand_writes_bytecode_to_run_later() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let script = tmp_dir.path().join("greet.zirc");
+    std::fs::write(&script, "fun greet(name):\n    \"hi \" + name\nend\nshow(greet(\"zirc\"))\n").unwrap();
+
+    // -o names the output; without it the .zbc lands next to the script
+    let named = tmp_dir.path().join("out.zbc");
+    let mut cmd = Command::cargo_bin("zirc").unwrap();
+    cmd.arg("compile").arg(&script).arg("-o").arg(&named);
+    cmd.assert().success().stdout("");
+    let mut cmd = Command::cargo_bin("zirc").unwrap();
+    cmd.arg("compile").arg(&script);
+    cmd.assert().success().stdout("");
+    let beside = tmp_dir.path().join("greet.zbc");
+    assert_eq!(std::fs::read(&named).unwrap(), std::fs::read(&beside).unwrap());
+
+    let mut cmd = Command::cargo_bin("zirc").unwrap();
+    cmd.env_remove("ZIRC_BACKEND").args(["--backend", "vm"]).arg(&named);
+    cmd.assert().success().stdout("hi zirc\n");
+
+    // A file that decodes but would index past greet's locals is rejected on load
+    let mut program = zirc_bytecode::Program::from_bytes(&std::fs::read(&named).unwrap()).unwrap();
+    program.functions[0].local_count = 0;
+    let broken = tmp_dir.path().join("broken.zbc");
+    std::fs::write(&broken, program.to_bytes()).unwrap();
+    let mut cmd = Command::cargo_bin("zirc").unwrap();
+    cmd.arg(&broken);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid bytecode in function 'greet': 0 locals cannot hold 1 parameters"));
+
+    // A cut-off file is reported, not a panic
+    let bytes = std::fs::read(&named).unwrap();
+    std::fs::write(&named, &bytes[..bytes.len() / 2]).unwrap();
+    let mut cmd = Command::cargo_bin("zirc").unwrap();
+    cmd.arg(&named);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Bytecode file is truncated"));
+
+    let mut cmd = Command::cargo_bin("zirc").unwrap();
+    cmd.arg("compile").arg(&script).arg("-o");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("-o expects an output path"));
+}
+
+#[test]
+fn disasm_lists_bytecode_instead_of_running() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let script = tmp_dir.path().join("loop.zirc");
+    std::fs::write(&script, "let n = 3\nif n > 2:\n    show(\"big\")\nend\nwhile n > 0:\n    n = n - 1\nend\n").unwrap();
+
+    let out = Command::cargo_bin("zirc").unwrap().arg("--disasm").arg(&script).output().unwrap();
+    assert!(out.status.success());
+    let listing = String::from_utf8(out.stdout).unwrap();
+    for line in [
+        "fn __main (arity 0, locals 0)",
+        "0001  StoreGlobal n",
+        "0007  JumpIfFalse -> 0011",
+        "0008  PushConst 0 = \"big\"",
+        "0009  BuiltinCall show/1",
+        "0014  JumpIfFalse -> 0020",
+        "0019  Jump -> 0011",
+    ] {
+        assert!(listing.lines().any(|l| l == line), "missing {:?} in:\n{}", line, listing);
+    }
+    assert!(!listing.contains("big\n"), "the script ran:\n{}", listing);
+}
+
+#[test]
+fn dump_ast_prints_the_tree_instead_of_running() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let script = tmp_dir.path().join("tree.zirc");
+    std::fs::write(&script, "show(1 * 2 + 3)\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("zirc").unwrap();
+    cmd.arg("--dump-ast").arg(&script);
+    cmd.assert().success().stdout("\
+Program
+  ExprStmt
+    Call show @1:1
+      BinaryAdd
+        BinaryMul
+          LiteralInt 1
+          LiteralInt 2
+        LiteralInt 3
+");
+
+    std::fs::write(&script, "show(\n").unwrap();
+    let mut cmd = Command::cargo_bin("zirc").unwrap();
+    cmd.arg("--dump-ast").arg(&script);
+    cmd.assert().failure().stderr(predicate::str::contains("Parse error"));
+}
+
+#[test]
+fn caret_counts_chars_in_non_ascii_lines() {
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let path = tmp_dir.path().join("accents.zirc");
+    std::fs::write(&path, "show(\"héllo wörld 😀\") @\n").unwrap();
+    let out = Command::cargo_bin("zirc").unwrap().arg("--color=never").arg(&path).output().unwrap();
+    assert!(!out.status.success());
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.contains("line 1, column 23"), "{}", stderr);
+
+    // The echoed line has multi-byte chars before the `@`; the caret lines up by char
+    let lines: Vec<&str> = stderr.lines().collect();
+    let src_at = lines.iter().position(|l| l.ends_with("😀\") @")).unwrap();
+    let char_col = |line: &str, c: char| line.chars().position(|x| x == c);
+    assert_eq!(char_col(lines[src_at + 1], '^'), char_col(lines[src_at], '@'), "{}", stderr);
+}
@@ -2,31 +2,51 @@
 
 use std::collections::HashMap;
 
-use zirc_bytecode::{Function as BcFunction, Instruction as BC};
+use zirc_bytecode::{Function as BcFunction, Instruction as BC, Value};
 use zirc_syntax::ast::*;
 use zirc_syntax::error::{Result, error};
 
 use crate::compiler::{Compiler, builtin_of};
+use crate::{fold, peephole};
 
 pub(crate) struct FuncBuilder {
     name: String,
     arity: usize,
     code: Vec<BC>,
+    constants: Vec<Value>,
     locals: Locals,
     // loop stack
     loop_stack: Vec<LoopCtx>,
-    // when true, compile idents/lets/assigns to globals if not local
+    // when true, compile lets/assigns to globals if not local; reads of
+    // non-locals load a global in either mode
     global_mode: bool,
+    // slot that `_` bindings are stored into, allocated on first use
+    discard_slot: Option<u16>,
 }
 
 impl FuncBuilder {
 pub(crate) fn new(name: String, arity: usize, global_mode: bool) -> Self {
         // Locals start at 0; params will occupy slots [0..arity)
-        Self { name, arity, code: Vec::new(), locals: Locals::new(0), loop_stack: Vec::new(), global_mode }
+        Self { name, arity, code: Vec::new(), constants: Vec::new(), locals: Locals::new(0), loop_stack: Vec::new(), global_mode, discard_slot: None }
     }
 
-    pub(crate) fn finish(self) -> BcFunction {
-        BcFunction { name: self.name, arity: self.arity, local_count: self.locals.max_alloc as usize, code: self.code }
+    /// Builds the function, running the peephole pass over its code when `optimize` is set.
+    pub(crate) fn finish(self, optimize: bool) -> BcFunction {
+        let code = if optimize { peephole::optimize(self.code, self.global_mode) } else { self.code };
+        let (constants, code) = drop_unused_constants(self.constants, code);
+        BcFunction { name: self.name, arity: self.arity, local_count: self.locals.max_alloc as usize, constants, code }
+    }
+
+    /// Returns the pool slot for `v`, reusing an existing slot for an equal literal.
+    fn intern(&mut self, v: Value) -> u32 {
+        // Floats match by bits: `-0.0 == 0.0`, but they print differently
+        let same = |c: &Value| match (c, &v) {
+            (Value::Float(x), Value::Float(y)) => x.to_bits() == y.to_bits(),
+            (c, v) => c == v,
+        };
+        if let Some(i) = self.constants.iter().position(same) { return i as u32; }
+        self.constants.push(v);
+        (self.constants.len() - 1) as u32
     }
 
     pub(crate) fn emit(&mut self, i: BC) -> usize { self.code.push(i); self.code.len() - 1 }
@@ -35,7 +55,7 @@ pub(crate) fn new(name: String, arity: usize, global_mode: bool) -> Self {
     fn patch_to_here(&mut self, at: usize) -> Result<()> {
         let tgt = self.here();
         match &mut self.code[at] {
-            BC::Jump(ref mut x) | BC::JumpIfFalse(ref mut x) | BC::JumpIfTrue(ref mut x) => { *x = tgt; Ok(()) }
+            BC::Jump(ref mut x) | BC::JumpIfFalse(ref mut x) | BC::JumpIfTrue(ref mut x) | BC::AddOrJump(ref mut x) => { *x = tgt; Ok(()) }
             other => error(format!("cannot patch at {:?}", other)),
         }
     }
@@ -46,51 +66,163 @@ pub(crate) fn new(name: String, arity: usize, global_mode: bool) -> Self {
 
     fn declare_var(&mut self, name: String) -> Result<u16> { self.locals.declare(name) }
 
+    /// Stores the value on top of the stack in a new binding; `_` just discards it.
+    fn bind_var(&mut self, name: &str) -> Result<()> {
+        if name == "_" {
+            // Not `Pop`, which would make the value the VM's last value
+            let slot = *self.discard_slot.get_or_insert_with(|| self.locals.alloc_temp());
+            self.emit(BC::StoreLocal(slot));
+        } else if self.global_mode {
+            self.emit(BC::StoreGlobal(name.to_string()));
+        } else {
+            let slot = self.declare_var(name.to_string())?;
+            self.emit(BC::StoreLocal(slot));
+        }
+        Ok(())
+    }
+
+    /// Makes unit the program's last value, as the interpreter reports after a
+    /// loop or a false `if`.
+    fn seed_unit_result(&mut self) {
+        self.emit(BC::PushUnit);
+        self.emit(BC::Pop);
+    }
+
+    /// Stores the top of the stack into an existing variable.
+    fn store_var(&mut self, name: &str) -> Result<()> {
+        if let Ok(slot) = self.resolve_var(name) {
+            self.emit(BC::StoreLocal(slot));
+        } else if self.global_mode {
+            self.emit(BC::StoreGlobal(name.to_string()));
+        } else {
+            return error(format!("Undefined variable '{}'", name));
+        }
+        Ok(())
+    }
+
     fn resolve_var(&self, name: &str) -> Result<u16> { self.locals.resolve(name).ok_or_else(|| zirc_syntax::error::Error::new(format!("Undefined variable '{}'", name))) }
 
+    /// Emits a function body that returns the value of its last statement,
+    /// as the interpreter does: an expression's value, the taken branch's
+    /// value for an `if`, and unit for anything else.
+    pub(crate) fn emit_returning_body(&mut self, c: &Compiler, body: &[Stmt]) -> Result<()> {
+        let Some((last, init)) = body.split_last() else {
+            self.emit(BC::PushUnit);
+            self.emit(BC::Return);
+            return Ok(());
+        };
+        for s in init { self.emit_stmt(c, s)?; }
+        match last {
+            Stmt::ExprStmt(e) => {
+                self.emit_expr(c, e)?;
+                self.emit(BC::Return);
+            }
+            Stmt::If { cond, then_body, else_body } => {
+                self.emit_expr(c, cond)?;
+                let jf_at = self.emit(BC::JumpIfFalse(0));
+                self.emit_returning_body(c, then_body)?;
+                self.patch_to_here(jf_at)?;
+                self.emit_returning_body(c, else_body)?;
+            }
+            other => {
+                self.emit_stmt(c, other)?;
+                self.emit(BC::PushUnit);
+                self.emit(BC::Return);
+            }
+        }
+        Ok(())
+    }
+
+    /// Emits a statement of main's item list. Only an expression or an `if`
+    /// yields a value in the interpreter; anything else, such as a binding or
+    /// a loop, makes unit the program's last value. A top-level `if`'s
+    /// branches give the program's result, so they are main's statements too.
+    pub(crate) fn emit_main_stmt(&mut self, c: &Compiler, s: &Stmt) -> Result<()> {
+        match s {
+            Stmt::ExprStmt(_) => self.emit_stmt(c, s),
+            Stmt::If { cond, then_body, else_body } => self.emit_if(c, cond, then_body, else_body, true),
+            _ => {
+                self.emit_stmt(c, s)?;
+                self.seed_unit_result();
+                Ok(())
+            }
+        }
+    }
+
+    /// Emits an `if`, with its branches as main's statements when `in_main`.
+    fn emit_if(&mut self, c: &Compiler, cond: &Expr, then_body: &[Stmt], else_body: &[Stmt], in_main: bool) -> Result<()> {
+        let emit_body = |b: &mut Self, body: &[Stmt]| -> Result<()> {
+            for s in body {
+                if in_main { b.emit_main_stmt(c, s)?; } else { b.emit_stmt(c, s)?; }
+            }
+            Ok(())
+        };
+        if else_body.is_empty() {
+            // No else: skip the jump over an empty else. In main, seed the last
+            // value with unit so a false `if` yields unit, as in the interpreter.
+            if in_main { self.seed_unit_result(); }
+            self.emit_expr(c, cond)?;
+            let jf_at = self.emit(BC::JumpIfFalse(0));
+            emit_body(self, then_body)?;
+            return self.patch_to_here(jf_at);
+        }
+        self.emit_expr(c, cond)?;
+        let jf_at = self.emit(BC::JumpIfFalse(0));
+        emit_body(self, then_body)?;
+        let jend_at = self.emit(BC::Jump(0));
+        self.patch_to_here(jf_at)?; // else starts here
+        emit_body(self, else_body)?;
+        self.patch_to_here(jend_at)
+    }
+
     pub(crate) fn emit_stmt(&mut self, c: &Compiler, s: &Stmt) -> Result<()> {
         match s {
             Stmt::Let { name, expr, .. } => {
                 self.emit_expr(c, expr)?;
-                if self.global_mode {
-                    self.emit(BC::StoreGlobal(name.clone()));
-                } else {
-                    let slot = self.declare_var(name.clone())?;
-                    self.emit(BC::StoreLocal(slot));
-                }
+                self.bind_var(name)
+            }
+            Stmt::LetUnpack { names, expr } => {
+                self.emit_expr(c, expr)?;
+                // Unpack leaves the first item on top, so stores run in name order
+                self.emit(BC::Unpack(names.len()));
+                for name in names { self.bind_var(name)?; }
                 Ok(())
             }
             Stmt::Assign { name, expr } => {
                 self.emit_expr(c, expr)?;
-                if let Ok(slot) = self.resolve_var(name) {
-                    self.emit(BC::StoreLocal(slot));
-                } else if self.global_mode {
-                    self.emit(BC::StoreGlobal(name.clone()));
-                } else {
-                    return error(format!("Undefined variable '{}'", name));
+                self.store_var(name)
+            }
+            Stmt::CompoundAssign { name, span, op, expr } => {
+                self.emit_expr(c, &op.desugar(name, *span, expr))?;
+                self.store_var(name)
+            }
+            Stmt::IndexAssign { base, index, expr } => {
+                let mut indices = vec![index];
+                let mut root = base;
+                while let Expr::Index(b, i, _) = root {
+                    indices.push(i);
+                    root = b;
                 }
-                Ok(())
+                let Expr::Ident(name, _) = root else { return error("index assignment target must be a variable"); };
+                self.emit_expr(c, root)?;
+                for i in indices.iter().rev() { self.emit_expr(c, i)?; }
+                self.emit_expr(c, expr)?;
+                // The whole updated value is stored back, as `name = ...` would
+                self.emit(BC::SetIndex(indices.len()));
+                self.store_var(name)
             }
+            Stmt::Return(_) if self.global_mode => error("'return' outside of function"),
             Stmt::Return(opt) => {
                 if let Some(e) = opt { self.emit_expr(c, e)?; } else { self.emit(BC::PushUnit); }
                 self.emit(BC::Return);
                 Ok(())
             }
-            Stmt::If { cond, then_body, else_body } => {
-                self.emit_expr(c, cond)?;
-                let jf_at = self.emit(BC::JumpIfFalse(0));
-                for s in then_body { self.emit_stmt(c, s)?; }
-                let jend_at = self.emit(BC::Jump(0));
-                self.patch_to_here(jf_at)?; // else starts here
-                for s in else_body { self.emit_stmt(c, s)?; }
-                self.patch_to_here(jend_at)?;
-                Ok(())
-            }
-            Stmt::While { cond, body } => {
+            Stmt::If { cond, then_body, else_body } => self.emit_if(c, cond, then_body, else_body, false),
+            Stmt::While { label, cond, body } => {
                 let loop_start = self.here();
                 self.emit_expr(c, cond)?;
                 let jf_at = self.emit(BC::JumpIfFalse(0));
-                self.loop_stack.push(LoopCtx::new());
+                self.loop_stack.push(LoopCtx::new(label.clone()));
                 for s in body { self.emit_stmt(c, s)?; }
                 // continue target is loop_start
                 let ctx = self.loop_stack.pop().unwrap();
@@ -105,26 +237,62 @@ pub(crate) fn new(name: String, arity: usize, global_mode: bool) -> Self {
                 for at in ctx.breaks { self.code[at] = BC::Jump(end); }
                 Ok(())
             }
-            Stmt::For { var, start, end, body } => {
-                // If in global mode, use globals for the loop var; otherwise, use a local.
+            Stmt::For { label, var, start, end, inclusive, step, body } => {
+                // The loop var lives in its own local slot, even at top level, and is
+                // unbound after the loop so a same-named outer variable is untouched.
+                // Both bounds are evaluated once, start first, before the loop var is bound
+                // (matching the interpreter); the body cannot change the iteration count.
                 let end_slot = self.locals.alloc_temp();
+                self.emit_expr(c, start)?;
                 self.emit_expr(c, end)?; self.emit(BC::StoreLocal(end_slot));
-                if self.global_mode {
-                    self.emit_expr(c, start)?; self.emit(BC::StoreGlobal(var.clone()));
-                } else {
-                    let i_slot = self.declare_var(var.clone())?;
-                    self.emit_expr(c, start)?; self.emit(BC::StoreLocal(i_slot));
-                }
+                // A step is evaluated and checked once, after the bounds; its sign picks
+                // the loop test, at compile time when it is a literal
+                let step_slot = match step {
+                    Some(step) => {
+                        let slot = self.locals.alloc_temp();
+                        self.emit_expr(c, step)?;
+                        self.emit(BC::CheckStep);
+                        self.emit(BC::StoreLocal(slot));
+                        Some(slot)
+                    }
+                    None => None,
+                };
+                let counts_up = match step {
+                    None => Some(true),
+                    Some(Expr::LiteralInt(n)) => Some(*n >= 0),
+                    Some(Expr::Negate(inner)) => match inner.as_ref() { Expr::LiteralInt(n) => Some(*n <= 0), _ => None },
+                    Some(_) => None,
+                };
+                let (up_test, down_test) = if *inclusive { (BC::Le, BC::Ge) } else { (BC::Lt, BC::Gt) };
+                let (i_slot, shadowed) = self.locals.bind_loop_var(var);
+                let store_i = BC::StoreLocal(i_slot);
+                let load_i = BC::LoadLocal(i_slot);
+                self.emit(store_i.clone());
                 let loop_start = self.here();
-                if self.global_mode {
-                    self.emit(BC::LoadGlobal(var.clone()));
-                } else {
-                    let slot = self.resolve_var(var)?; self.emit(BC::LoadLocal(slot));
+                match (counts_up, step_slot) {
+                    (None, Some(slot)) => {
+                        self.emit(BC::LoadLocal(slot));
+                        self.emit(BC::PushInt(0));
+                        self.emit(BC::Gt);
+                        let down_at = self.emit(BC::JumpIfFalse(0));
+                        self.emit(load_i.clone());
+                        self.emit(BC::LoadLocal(end_slot));
+                        self.emit(up_test);
+                        let test_at = self.emit(BC::Jump(0));
+                        self.patch_to_here(down_at)?;
+                        self.emit(load_i.clone());
+                        self.emit(BC::LoadLocal(end_slot));
+                        self.emit(down_test);
+                        self.patch_to_here(test_at)?;
+                    }
+                    (up, _) => {
+                        self.emit(load_i.clone());
+                        self.emit(BC::LoadLocal(end_slot));
+                        self.emit(if up.unwrap_or(true) { up_test } else { down_test });
+                    }
                 }
-                self.emit(BC::LoadLocal(end_slot));
-                self.emit(BC::Lt);
                 let jf_at = self.emit(BC::JumpIfFalse(0));
-                self.loop_stack.push(LoopCtx::new());
+                self.loop_stack.push(LoopCtx::new(label.clone()));
                 for s in body { self.emit_stmt(c, s)?; }
                 // continue target: increment
                 let incr_ip = self.here();
@@ -132,38 +300,73 @@ pub(crate) fn new(name: String, arity: usize, global_mode: bool) -> Self {
                     let ctx = self.loop_stack.last_mut().unwrap();
                     ctx.continue_target = Some(incr_ip);
                 }
-                if self.global_mode {
-                    self.emit(BC::LoadGlobal(var.clone()));
-                    self.emit(BC::PushInt(1));
-                    self.emit(BC::Add);
-                    self.emit(BC::StoreGlobal(var.clone()));
-                } else {
-                    let slot = self.resolve_var(var)?;
-                    self.emit(BC::LoadLocal(slot));
-                    self.emit(BC::PushInt(1));
-                    self.emit(BC::Add);
-                    self.emit(BC::StoreLocal(slot));
-                }
+                self.emit(load_i);
+                self.emit(match step_slot { Some(slot) => BC::LoadLocal(slot), None => BC::PushInt(1) });
+                // Stepping past i64's range ends the loop, as in the interpreter:
+                // the next value would be past `end` anyway
+                let step_at = self.emit(BC::AddOrJump(0));
+                self.emit(store_i);
                 self.emit(BC::Jump(loop_start));
                 // end label
                 self.patch_to_here(jf_at)?;
+                self.patch_to_here(step_at)?;
                 let ctx = self.loop_stack.pop().unwrap();
                 let end_ip = self.here();
                 for at in ctx.breaks { self.code[at] = BC::Jump(end_ip); }
                 let cont_ip = ctx.continue_target.unwrap_or(loop_start);
                 for at in ctx.continues { self.code[at] = BC::Jump(cont_ip); }
+                self.locals.unbind_loop_var(var, shadowed);
+                Ok(())
+            }
+            Stmt::ForEach { label, var, iterable, body } => {
+                // The iterable is evaluated once into a hidden list; a hidden index walks it
+                let items_slot = self.locals.alloc_temp();
+                let len_slot = self.locals.alloc_temp();
+                let idx_slot = self.locals.alloc_temp();
+                self.emit_expr(c, iterable)?;
+                self.emit(BC::IterList);
+                self.emit(BC::StoreLocal(items_slot));
+                self.emit(BC::LoadLocal(items_slot));
+                self.emit(BC::BuiltinCall(zirc_bytecode::Builtin::Len, 1));
+                self.emit(BC::StoreLocal(len_slot));
+                self.emit(BC::PushInt(0));
+                self.emit(BC::StoreLocal(idx_slot));
+                let (var_slot, shadowed) = self.locals.bind_loop_var(var);
+                let loop_start = self.here();
+                self.emit(BC::LoadLocal(idx_slot));
+                self.emit(BC::LoadLocal(len_slot));
+                self.emit(BC::Lt);
+                let jf_at = self.emit(BC::JumpIfFalse(0));
+                self.emit(BC::LoadLocal(items_slot));
+                self.emit(BC::LoadLocal(idx_slot));
+                self.emit(BC::Index);
+                self.emit(BC::StoreLocal(var_slot));
+                self.loop_stack.push(LoopCtx::new(label.clone()));
+                for s in body { self.emit_stmt(c, s)?; }
+                let incr_ip = self.here();
+                self.emit(BC::LoadLocal(idx_slot));
+                self.emit(BC::PushInt(1));
+                self.emit(BC::Add);
+                self.emit(BC::StoreLocal(idx_slot));
+                self.emit(BC::Jump(loop_start));
+                self.patch_to_here(jf_at)?;
+                let ctx = self.loop_stack.pop().unwrap();
+                let end_ip = self.here();
+                for at in ctx.breaks { self.code[at] = BC::Jump(end_ip); }
+                for at in ctx.continues { self.code[at] = BC::Jump(incr_ip); }
+                self.locals.unbind_loop_var(var, shadowed);
                 Ok(())
             }
-            Stmt::Break => {
+            Stmt::Break(target) => {
                 let at = self.emit(BC::Jump(0));
-                if let Some(ctx) = self.loop_stack.last_mut() {
+                if let Some(ctx) = self.loop_target(target) {
                     ctx.breaks.push(at);
                     Ok(())
                 } else { error("'break' outside of loop") }
             }
-            Stmt::Continue => {
+            Stmt::Continue(target) => {
                 let at = self.emit(BC::Jump(0));
-                if let Some(ctx) = self.loop_stack.last_mut() {
+                if let Some(ctx) = self.loop_target(target) {
                     ctx.continues.push(at);
                     Ok(())
                 } else { error("'continue' outside of loop") }
@@ -176,32 +379,130 @@ pub(crate) fn new(name: String, arity: usize, global_mode: bool) -> Self {
         }
     }
 
+    /// The loop a `break` or `continue` jumps in: the one labelled `target`, or the innermost.
+    fn loop_target(&mut self, target: &Option<String>) -> Option<&mut LoopCtx> {
+        match target {
+            None => self.loop_stack.last_mut(),
+            Some(_) => self.loop_stack.iter_mut().rev().find(|ctx| ctx.label == *target),
+        }
+    }
+
+    /// `push(xs, v)` and `pop(xs)` change a variable, but lists are values: load
+    /// it, change it on the stack and store it back. `push` yields the extended
+    /// list whatever its argument, `pop` the removed item.
+    fn emit_push_pop(&mut self, c: &Compiler, name: &str, args: &[Expr]) -> Result<()> {
+        let (argc, usage) = if name == "push" { (2, "exactly 2 arguments: list and value") } else { (1, "exactly 1 argument: list_variable") };
+        if args.len() != argc { return error(format!("{}() expects {}", name, usage)); }
+        self.emit_expr(c, &args[0])?;
+        if name == "push" {
+            self.emit_expr(c, &args[1])?;
+            self.emit(BC::ListPush);
+            // A variable is updated in place, then read back as the result
+            if let Expr::Ident(var, _) = &args[0] {
+                self.store_var(var)?;
+                self.emit_expr(c, &args[0])?;
+            }
+            return Ok(());
+        }
+        let Expr::Ident(var, _) = &args[0] else { return error("pop() first argument must be a variable name"); };
+        // Leaves the popped item once the shortened list is stored
+        self.emit(BC::ListPop);
+        self.store_var(var)?;
+        Ok(())
+    }
+
+    /// Pushes a literal value. Ints that fit in 32 bits and bools stay inline;
+    /// everything else goes through the pool.
+    fn emit_const(&mut self, v: Value) {
+        match v {
+            Value::Int(n) if i32::try_from(n).is_ok() => { self.emit(BC::PushInt(n)); }
+            Value::Bool(b) => { self.emit(BC::PushBool(b)); }
+            v => { let i = self.intern(v); self.emit(BC::PushConst(i)); }
+        }
+    }
+
+    /// The value pushed by the code from `start` on, when that code is a single
+    /// literal push: the operand of an operator is then a compile-time constant.
+    fn const_from(&self, start: usize) -> Option<Value> {
+        match &self.code[start..] {
+            [BC::PushInt(n)] => Some(Value::Int(*n)),
+            [BC::PushBool(b)] => Some(Value::Bool(*b)),
+            [BC::PushConst(i)] => self.constants.get(*i as usize).cloned(),
+            _ => None,
+        }
+    }
+
+    /// Emits `a op b`, folded into one push when both operands are constants
+    /// (see [`crate::fold`]). A folding error is reported at `at`, if given.
+    fn emit_binary(&mut self, c: &Compiler, a: &Expr, b: &Expr, op: BC, at: Option<Span>) -> Result<()> {
+        let start = self.here();
+        self.emit_expr(c, a)?;
+        let a_const = self.const_from(start);
+        let mid = self.here();
+        self.emit_expr(c, b)?;
+        if let (Some(x), Some(y)) = (a_const, self.const_from(mid)) {
+            let folded = fold::binary(&op, &x, &y).map_err(|e| match at {
+                Some(span) => e.or_span(span.line, span.col),
+                None => e,
+            })?;
+            if let Some(v) = folded {
+                self.code.truncate(start);
+                self.emit_const(v);
+                return Ok(());
+            }
+        }
+        self.emit(op);
+        Ok(())
+    }
+
+    /// Emits a unary `op` on `a`, folded like [`FuncBuilder::emit_binary`].
+    fn emit_unary(&mut self, c: &Compiler, a: &Expr, op: BC) -> Result<()> {
+        let start = self.here();
+        self.emit_expr(c, a)?;
+        if let Some(v) = self.const_from(start).and_then(|x| fold::unary(&op, &x)) {
+            self.code.truncate(start);
+            self.emit_const(v);
+            return Ok(());
+        }
+        self.emit(op);
+        Ok(())
+    }
+
     fn emit_expr(&mut self, c: &Compiler, e: &Expr) -> Result<()> {
         match e {
-            Expr::LiteralInt(n) => { self.emit(BC::PushInt(*n)); Ok(()) }
-            Expr::LiteralString(s) => { self.emit(BC::PushStr(s.clone())); Ok(()) }
-            Expr::LiteralBool(b) => { self.emit(BC::PushBool(*b)); Ok(()) }
-            Expr::Ident(name) => {
+            Expr::LiteralInt(n) => { self.emit_const(Value::Int(*n)); Ok(()) }
+            Expr::LiteralFloat(x) => { self.emit_const(Value::Float(*x)); Ok(()) }
+            Expr::LiteralString(s) => { self.emit_const(Value::Str(s.clone())); Ok(()) }
+            Expr::LiteralBool(b) => { self.emit_const(Value::Bool(*b)); Ok(()) }
+            Expr::Ident(name, _) => {
+                if name == "_" { return error("Undefined variable '_'"); }
                 if let Ok(slot) = self.resolve_var(name) {
                     self.emit(BC::LoadLocal(slot));
-                } else if self.global_mode {
-                    self.emit(BC::LoadGlobal(name.clone()));
                 } else {
-                    return error(format!("Undefined variable '{}'", name));
+                    // Resolved at run time, so a function may read a global defined after it
+                    self.emit(BC::LoadGlobal(name.clone()));
                 }
                 Ok(())
             }
-            Expr::BinaryAdd(a,b) => { self.emit_expr(c,a)?; self.emit_expr(c,b)?; self.emit(BC::Add); Ok(()) }
-            Expr::BinarySub(a,b) => { self.emit_expr(c,a)?; self.emit_expr(c,b)?; self.emit(BC::Sub); Ok(()) }
-            Expr::BinaryMul(a,b) => { self.emit_expr(c,a)?; self.emit_expr(c,b)?; self.emit(BC::Mul); Ok(()) }
-            Expr::BinaryDiv(a,b) => { self.emit_expr(c,a)?; self.emit_expr(c,b)?; self.emit(BC::Div); Ok(()) }
-            Expr::Eq(a,b) => { self.emit_expr(c,a)?; self.emit_expr(c,b)?; self.emit(BC::Eq); Ok(()) }
-            Expr::Ne(a,b) => { self.emit_expr(c,a)?; self.emit_expr(c,b)?; self.emit(BC::Ne); Ok(()) }
-            Expr::Lt(a,b) => { self.emit_expr(c,a)?; self.emit_expr(c,b)?; self.emit(BC::Lt); Ok(()) }
-            Expr::Le(a,b) => { self.emit_expr(c,a)?; self.emit_expr(c,b)?; self.emit(BC::Le); Ok(()) }
-            Expr::Gt(a,b) => { self.emit_expr(c,a)?; self.emit_expr(c,b)?; self.emit(BC::Gt); Ok(()) }
-            Expr::Ge(a,b) => { self.emit_expr(c,a)?; self.emit_expr(c,b)?; self.emit(BC::Ge); Ok(()) }
-            Expr::LogicalNot(a) => { self.emit_expr(c,a)?; self.emit(BC::Not); Ok(()) }
+            Expr::BinaryAdd(a,b) => self.emit_binary(c, a, b, BC::Add, None),
+            Expr::BinarySub(a,b) => self.emit_binary(c, a, b, BC::Sub, None),
+            Expr::BinaryMul(a,b) => self.emit_binary(c, a, b, BC::Mul, None),
+            Expr::BinaryDiv(a,b,span) => self.emit_binary(c, a, b, BC::Div, Some(*span)),
+            Expr::BinaryMod(a,b) => self.emit_binary(c, a, b, BC::Mod, None),
+            Expr::BitAnd(a,b) => self.emit_binary(c, a, b, BC::BitAnd, None),
+            Expr::BitOr(a,b) => self.emit_binary(c, a, b, BC::BitOr, None),
+            Expr::BitXor(a,b) => self.emit_binary(c, a, b, BC::BitXor, None),
+            Expr::Shl(a,b) => self.emit_binary(c, a, b, BC::Shl, None),
+            Expr::Shr(a,b) => self.emit_binary(c, a, b, BC::Shr, None),
+            Expr::Eq(a,b) => self.emit_binary(c, a, b, BC::Eq, None),
+            Expr::Ne(a,b) => self.emit_binary(c, a, b, BC::Ne, None),
+            Expr::Lt(a,b) => self.emit_binary(c, a, b, BC::Lt, None),
+            Expr::Le(a,b) => self.emit_binary(c, a, b, BC::Le, None),
+            Expr::Gt(a,b) => self.emit_binary(c, a, b, BC::Gt, None),
+            Expr::Ge(a,b) => self.emit_binary(c, a, b, BC::Ge, None),
+            // A dedicated `Neg` rather than `0 - x`, so `-"s"` reports "Cannot negate"
+            Expr::Negate(a) => self.emit_unary(c, a, BC::Neg),
+            Expr::LogicalNot(a) => self.emit_unary(c, a, BC::Not),
             Expr::LogicalAnd(a,b) => {
                 // short-circuit: if a is false, skip b
                 self.emit_expr(c, a)?;
@@ -227,13 +528,19 @@ pub(crate) fn new(name: String, arity: usize, global_mode: bool) -> Self {
                 self.patch_to_here(jend)?;
                 Ok(())
             }
-            Expr::Call { name, args } => {
+            Expr::Call { name, args, .. } if name == "push" || name == "pop" => self.emit_push_pop(c, name, args),
+            Expr::Call { name, args, .. } => {
                 // builtins
                 if let Some(bi) = builtin_of(name) {
                     for a in args { self.emit_expr(c, a)?; }
                     self.emit(BC::BuiltinCall(bi, args.len()));
                     return Ok(());
                 }
+                if let Some(ni) = c.native_index(name) {
+                    for a in args { self.emit_expr(c, a)?; }
+                    self.emit(BC::NativeCall(ni, args.len()));
+                    return Ok(());
+                }
                 let &fi = c.func_indices.get(name).ok_or_else(|| zirc_syntax::error::Error::new(format!("Undefined function '{}'", name)))?;
                 for a in args { self.emit_expr(c, a)?; }
                 self.emit(BC::Call(fi, args.len()));
@@ -244,7 +551,12 @@ pub(crate) fn new(name: String, arity: usize, global_mode: bool) -> Self {
                 self.emit(BC::MakeList(elems.len()));
                 Ok(())
             }
-            Expr::Index(base, idx) => {
+            Expr::Map(entries) => {
+                for (k, v) in entries { self.emit_expr(c, k)?; self.emit_expr(c, v)?; }
+                self.emit(BC::MakeMap(entries.len()));
+                Ok(())
+            }
+            Expr::Index(base, idx, _) => {
                 self.emit_expr(c, base)?;
                 self.emit_expr(c, idx)?;
                 self.emit(BC::Index);
@@ -263,6 +575,8 @@ struct Locals {
 impl Locals {
     fn new(start: u16) -> Self { Self { scopes: vec![HashMap::new()], next: start, max_alloc: start } }
     fn declare(&mut self, name: String) -> Result<u16> {
+        // `_` may be declared any number of times; each gets a slot no name resolves to
+        if name == "_" { return Ok(self.alloc_temp()); }
         if self.scopes.last().unwrap().contains_key(&name) { return error(format!("Variable '{}' already defined in scope", name)); }
         let idx = self.next; self.next = self.next.checked_add(1).ok_or_else(|| zirc_syntax::error::Error::new("too many locals"))?;
         self.scopes.last_mut().unwrap().insert(name, idx);
@@ -273,6 +587,21 @@ impl Locals {
         for scope in self.scopes.iter().rev() { if let Some(&i) = scope.get(name) { return Some(i); } }
         None
     }
+    /// Binds a loop variable to a fresh slot in the innermost scope until
+    /// [`Locals::unbind_loop_var`], returning the slot and the binding it shadows.
+    fn bind_loop_var(&mut self, name: &str) -> (u16, Option<u16>) {
+        let slot = self.alloc_temp();
+        if name == "_" { return (slot, None); }
+        (slot, self.scopes.last_mut().unwrap().insert(name.to_string(), slot))
+    }
+    fn unbind_loop_var(&mut self, name: &str, shadowed: Option<u16>) {
+        if name == "_" { return; }
+        let scope = self.scopes.last_mut().unwrap();
+        match shadowed {
+            Some(slot) => { scope.insert(name.to_string(), slot); }
+            None => { scope.remove(name); }
+        }
+    }
     fn alloc_temp(&mut self) -> u16 { let idx = self.next; self.next += 1; if idx + 1 > self.max_alloc { self.max_alloc = idx + 1; } idx }
     #[allow(dead_code)]
     fn push_scope(&mut self) { self.scopes.push(HashMap::new()); }
@@ -280,6 +609,22 @@ impl Locals {
     fn pop_scope(&mut self) { let _ = self.scopes.pop(); }
 }
 
-struct LoopCtx { breaks: Vec<usize>, continues: Vec<usize>, continue_target: Option<usize> }
-impl LoopCtx { fn new() -> Self { Self { breaks: Vec::new(), continues: Vec::new(), continue_target: None } } }
+struct LoopCtx { label: Option<String>, breaks: Vec<usize>, continues: Vec<usize>, continue_target: Option<usize> }
+impl LoopCtx { fn new(label: Option<String>) -> Self { Self { label, breaks: Vec::new(), continues: Vec::new(), continue_target: None } } }
 
+/// Drops the pool entries no `PushConst` uses any more, such as the operands
+/// of a folded operator, and renumbers the rest in order of first use.
+fn drop_unused_constants(constants: Vec<Value>, mut code: Vec<BC>) -> (Vec<Value>, Vec<BC>) {
+    let mut new_index: Vec<Option<u32>> = vec![None; constants.len()];
+    let mut kept = Vec::new();
+    for instr in &mut code {
+        if let BC::PushConst(i) = instr {
+            let old = *i as usize;
+            *i = *new_index[old].get_or_insert_with(|| {
+                kept.push(constants[old].clone());
+                (kept.len() - 1) as u32
+            });
+        }
+    }
+    (kept, code)
+}
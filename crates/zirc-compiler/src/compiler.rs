@@ -7,17 +7,60 @@ use zirc_syntax::ast::*;
 use zirc_syntax::error::{Result, error};
 
 use crate::builder::FuncBuilder;
+use crate::lint::lint;
 
 pub struct Compiler {
     pub(crate) func_indices: HashMap<String, usize>,
     pub(crate) functions: Vec<BcFunction>,
+    /// Host function names declared with `declare_native`, in `NativeCall` index order
+    pub(crate) natives: Vec<String>,
+    /// Warnings from the last `check`/`compile`, until taken with `take_warnings`
+    warnings: Vec<String>,
+    /// When set, any warning fails `check`/`compile`
+    strict: bool,
+    /// When set (the default), finished functions go through the peephole pass
+    optimize: bool,
 }
 
 impl Default for Compiler { fn default() -> Self { Self::new() } }
 
 impl Compiler {
     pub fn new() -> Self {
-        Self { func_indices: HashMap::new(), functions: Vec::new() }
+        Self { func_indices: HashMap::new(), functions: Vec::new(), natives: Vec::new(), warnings: Vec::new(), strict: false, optimize: true }
+    }
+
+    /// Makes warnings (unused variables, uncalled or shadowed functions) hard errors.
+    pub fn set_strict(&mut self, strict: bool) { self.strict = strict; }
+
+    /// Turns the peephole pass off or back on; output is the same either way, only longer without it.
+    pub fn set_optimize(&mut self, optimize: bool) { self.optimize = optimize; }
+
+    /// Returns and clears the warnings collected so far.
+    pub fn take_warnings(&mut self) -> Vec<String> { std::mem::take(&mut self.warnings) }
+
+    /// Runs the static checks without generating code. Warnings are kept for
+    /// `take_warnings`, or returned as an error in strict mode.
+    pub fn check(&mut self, program: &Program) -> Result<()> {
+        let found = lint(program);
+        if self.strict && !found.is_empty() {
+            return error(format!("strict mode: {}", found.join("; ")));
+        }
+        self.warnings.extend(found);
+        Ok(())
+    }
+
+    /// Declares a host function so calls to `name` compile to `NativeCall`.
+    /// The VM running the program must register a native with the same name.
+    pub fn declare_native(&mut self, name: impl Into<String>) -> Result<()> {
+        let name = name.into();
+        if builtin_of(&name).is_some() { return error(format!("Cannot declare native '{}': it is a builtin", name)); }
+        if self.natives.contains(&name) { return error(format!("Native '{}' already declared", name)); }
+        self.natives.push(name);
+        Ok(())
+    }
+
+    pub(crate) fn native_index(&self, name: &str) -> Option<u32> {
+        self.natives.iter().position(|n| n == name).map(|i| i as u32)
     }
 
     pub fn function_names(&self) -> Vec<String> {
@@ -27,13 +70,16 @@ impl Compiler {
     }
 
     pub fn compile(&mut self, program: Program) -> Result<BcProgram> {
+        self.check(&program)?;
         // First pass: collect function names to assign indices
         for item in &program.items {
             if let Item::Function(f) = item {
                 if self.func_indices.contains_key(&f.name) { return error(format!("Duplicate function '{}'", f.name)); }
+                if builtin_of(&f.name).is_some() { return error(format!("Function '{}' conflicts with a builtin", f.name)); }
+                if self.natives.contains(&f.name) { return error(format!("Function '{}' conflicts with a native", f.name)); }
                 let idx = self.functions.len();
                 self.func_indices.insert(f.name.clone(), idx);
-                self.functions.push(BcFunction { name: f.name.clone(), arity: f.params.len(), local_count: 0, code: Vec::new() });
+                self.functions.push(BcFunction { name: f.name.clone(), arity: f.params.len(), local_count: 0, constants: Vec::new(), code: Vec::new() });
             }
         }
         // Second pass: compile functions
@@ -47,55 +93,22 @@ impl Compiler {
         // Compile main (top-level statements)
         let mut main_builder = FuncBuilder::new("__main".to_string(), 0, true);
         for item in program.items.into_iter() {
-            if let Item::Stmt(s) = item { main_builder.emit_stmt(self, &s)?; }
+            if let Item::Stmt(s) = item { main_builder.emit_main_stmt(self, &s)?; }
         }
         main_builder.emit(BC::Halt);
-        let main = main_builder.finish();
-        Ok(BcProgram { functions: self.functions.clone(), main })
+        let main = main_builder.finish(self.optimize);
+        Ok(BcProgram { functions: self.functions.clone(), main, natives: self.natives.clone() })
     }
 
     fn compile_function(&mut self, f: &Function) -> Result<BcFunction> {
         let mut b = FuncBuilder::new(f.name.clone(), f.params.len(), false);
         for p in &f.params { b.declare_param(p.name.clone())?; }
-        for s in &f.body { b.emit_stmt(self, s)?; }
-        b.emit(BC::PushUnit);
-        b.emit(BC::Return);
-        Ok(b.finish())
+        b.emit_returning_body(self, &f.body)?;
+        Ok(b.finish(self.optimize))
     }
 }
 
 pub(crate) fn builtin_of(name: &str) -> Option<zirc_bytecode::Builtin> {
-    match name {
-        "show" => Some(zirc_bytecode::Builtin::Show),
-        "showf" => Some(zirc_bytecode::Builtin::ShowF),
-        "prompt" => Some(zirc_bytecode::Builtin::Prompt),
-        "rf" => Some(zirc_bytecode::Builtin::Rf),
-        "wf" => Some(zirc_bytecode::Builtin::Wf),
-        "len" => Some(zirc_bytecode::Builtin::Len),
-        "push" => Some(zirc_bytecode::Builtin::Push),
-        "pop" => Some(zirc_bytecode::Builtin::Pop),
-        "slice" => Some(zirc_bytecode::Builtin::Slice),
-        // Mathematical functions
-        "abs" => Some(zirc_bytecode::Builtin::Abs),
-        "min" => Some(zirc_bytecode::Builtin::Min),
-        "max" => Some(zirc_bytecode::Builtin::Max),
-        "pow" => Some(zirc_bytecode::Builtin::Pow),
-        "sqrt" => Some(zirc_bytecode::Builtin::Sqrt),
-        // TODO: check if hex/bin need special handling here or move separately
-        "bin" => Some(zirc_bytecode::Builtin::Bin),
-        "hex" => Some(zirc_bytecode::Builtin::Hex),
-        // String functions
-        "upper" => Some(zirc_bytecode::Builtin::Upper),
-        "lower" => Some(zirc_bytecode::Builtin::Lower),
-        "trim" => Some(zirc_bytecode::Builtin::Trim),
-        "split" => Some(zirc_bytecode::Builtin::Split),
-        "join" => Some(zirc_bytecode::Builtin::Join),
-        // Type conversion
-        "int" => Some(zirc_bytecode::Builtin::Int),
-        "str" => Some(zirc_bytecode::Builtin::Str),
-        // Utility functions
-        "type" => Some(zirc_bytecode::Builtin::Type),
-        _ => None,
-    }
+    zirc_bytecode::Builtin::ALL.into_iter().find(|b| b.name() == name)
 }
 
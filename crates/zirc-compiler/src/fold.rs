@@ -0,0 +1,61 @@
+//! Constant folding: operators applied to literal operands are evaluated at
+//! compile time, so `2 + 3 * 4` compiles to a single push.
+//!
+//! Folding never changes what a program does. An operation that would fail at
+//! run time (a type mismatch or an overflow) is left unfolded, so it still
+//! fails there with the usual message. The exception is division by a literal
+//! zero, which is reported as a compile error.
+
+use zirc_bytecode::{Instruction as BC, Value};
+use zirc_syntax::builtins;
+use zirc_syntax::error::{Result, error};
+use zirc_syntax::value::float_operands;
+
+/// The value of `op` applied to constant operands `a` and `b`, or `None` when
+/// `op` is not folded or the operation must be left to run time.
+pub(crate) fn binary(op: &BC, a: &Value, b: &Value) -> Result<Option<Value>> {
+    let int_or_float = |int: fn(i64, i64) -> Option<i64>, float: fn(f64, f64) -> f64| match (a, b) {
+        (Value::Int(x), Value::Int(y)) => int(*x, *y).map(Value::Int),
+        _ => float_operands(a, b).map(|(x, y)| Value::Float(float(x, y))),
+    };
+    Ok(match op {
+        BC::Add => Value::try_add(a.clone(), b.clone()).ok(),
+        BC::Sub => int_or_float(i64::checked_sub, |x, y| x - y),
+        BC::Mul => int_or_float(i64::checked_mul, |x, y| x * y),
+        BC::Div => match (a, b) {
+            (Value::Int(_), Value::Int(0)) => return error(builtins::DIVISION_BY_ZERO),
+            _ if matches!(float_operands(a, b), Some((_, y)) if y == 0.0) => return error(builtins::DIVISION_BY_ZERO),
+            _ => int_or_float(i64::checked_div, |x, y| x / y),
+        },
+        BC::Eq => Some(Value::Bool(a.lang_eq(b))),
+        BC::Ne => Some(Value::Bool(!a.lang_eq(b))),
+        BC::Lt | BC::Le | BC::Gt | BC::Ge => {
+            let ord = match (a, b) {
+                (Value::Int(x), Value::Int(y)) => x.partial_cmp(y),
+                (Value::Str(x), Value::Str(y)) => x.partial_cmp(y),
+                _ => match float_operands(a, b) {
+                    Some((x, y)) => x.partial_cmp(&y),
+                    None => return Ok(None),
+                },
+            };
+            // NaN is unordered, so every comparison with it is false
+            Some(Value::Bool(ord.is_some_and(|o| match op {
+                BC::Lt => o.is_lt(),
+                BC::Le => o.is_le(),
+                BC::Gt => o.is_gt(),
+                _ => o.is_ge(),
+            })))
+        }
+        _ => None,
+    })
+}
+
+/// Like [`binary`], for the unary `Neg` and `Not`.
+pub(crate) fn unary(op: &BC, a: &Value) -> Option<Value> {
+    match (op, a) {
+        (BC::Neg, Value::Int(n)) => n.checked_neg().map(Value::Int),
+        (BC::Neg, Value::Float(x)) => Some(Value::Float(-x)),
+        (BC::Not, Value::Bool(b)) => Some(Value::Bool(!b)),
+        _ => None,
+    }
+}
@@ -1,5 +1,8 @@
 pub mod builder;
 pub mod compiler;
+mod fold;
+mod lint;
+mod peephole;
 
 pub use compiler::Compiler;
 
@@ -7,7 +10,10 @@ pub use compiler::Compiler;
 mod tests {
     use super::*;
     use zirc_syntax::ast::*;
-    use zirc_bytecode::{Instruction, Builtin};
+    use zirc_bytecode::{Instruction, Builtin, Value};
+
+    /// Span for hand-built AST nodes
+    const AT: Span = Span { line: 1, col: 1 };
 
     fn create_simple_program(items: Vec<Item>) -> Program {
         Program { items }
@@ -19,6 +25,41 @@ mod tests {
         assert!(compiler.function_names().is_empty());
     }
 
+    #[test]
+    fn test_warnings_and_strict_mode() {
+        // let unused = 1; fun helper(): let tmp = 2 end; fun len(x): x end; show(len([1]))
+        let items = vec![
+            Item::Stmt(Stmt::Let { name: "unused".to_string(), ty: None, expr: Expr::LiteralInt(1) }),
+            Item::Function(Function {
+                name: "helper".to_string(),
+                params: vec![],
+                return_type: None,
+                body: vec![Stmt::Let { name: "tmp".to_string(), ty: None, expr: Expr::LiteralInt(2) }],
+            }),
+            Item::Function(Function {
+                name: "len".to_string(),
+                params: vec![Param { name: "x".to_string(), ty: None }],
+                return_type: None,
+                body: vec![Stmt::ExprStmt(Expr::Ident("x".to_string(), AT))],
+            }),
+        ];
+
+        let mut compiler = Compiler::new();
+        compiler.check(&create_simple_program(items.clone())).unwrap();
+        assert_eq!(compiler.take_warnings(), vec![
+            "function 'helper' is never called".to_string(),
+            "unused variable 'tmp' in function 'helper'".to_string(),
+            "function 'len' is shadowed by the builtin of the same name".to_string(),
+            "unused variable 'unused'".to_string(),
+        ]);
+        assert!(compiler.take_warnings().is_empty());
+
+        let mut strict = Compiler::new();
+        strict.set_strict(true);
+        let err = strict.compile(create_simple_program(items)).unwrap_err();
+        assert!(err.msg.starts_with("strict mode: function 'helper' is never called"));
+    }
+
     #[test]
     fn test_compile_simple_expression() {
         let mut compiler = Compiler::new();
@@ -41,11 +82,9 @@ mod tests {
         assert_eq!(bytecode.main.name, "__main");
         assert_eq!(bytecode.main.arity, 0);
         
-        // Check the bytecode instructions
+        // Check the bytecode instructions; the literal sum is folded
         let expected_ops = [
-            Instruction::PushInt(5),
-            Instruction::PushInt(3),
-            Instruction::Add,
+            Instruction::PushInt(8),
             Instruction::StoreGlobal("x".to_string()),
         ];
         
@@ -69,8 +108,8 @@ mod tests {
                 return_type: None,
                 body: vec![
                     Stmt::Return(Some(Expr::BinaryAdd(
-                        Box::new(Expr::Ident("a".to_string())),
-                        Box::new(Expr::Ident("b".to_string())),
+                        Box::new(Expr::Ident("a".to_string(), AT)),
+                        Box::new(Expr::Ident("b".to_string(), AT)),
                     ))),
                 ],
             }),
@@ -103,6 +142,7 @@ mod tests {
             Item::Stmt(Stmt::ExprStmt(Expr::Call {
                 name: "show".to_string(),
                 args: vec![Expr::LiteralInt(42)],
+                span: AT,
             })),
         ]);
         
@@ -146,6 +186,75 @@ mod tests {
         assert!(matches!(bytecode.main.code[1], Instruction::JumpIfFalse(_)));
     }
 
+    #[test]
+    fn test_compile_modulo() {
+        let mut compiler = Compiler::new();
+
+        // Program: 10 % 3 == 1
+        let program = create_simple_program(vec![
+            Item::Stmt(Stmt::ExprStmt(Expr::Eq(
+                Box::new(Expr::BinaryMod(Box::new(Expr::LiteralInt(10)), Box::new(Expr::LiteralInt(3)))),
+                Box::new(Expr::LiteralInt(1)),
+            ))),
+        ]);
+
+        let bytecode = compiler.compile(program).unwrap();
+        assert_eq!(bytecode.main.code, vec![
+            Instruction::PushInt(10),
+            Instruction::PushInt(3),
+            Instruction::Mod,
+            Instruction::PushInt(1),
+            Instruction::Eq,
+            Instruction::Pop,
+            Instruction::Halt,
+        ]);
+    }
+
+    #[test]
+    fn test_compile_negate() {
+        let mut compiler = Compiler::new();
+
+        // Program: -n (a literal like -5 is folded instead)
+        let program = create_simple_program(vec![
+            Item::Stmt(Stmt::ExprStmt(Expr::Negate(Box::new(Expr::Ident("n".to_string(), AT))))),
+        ]);
+
+        let bytecode = compiler.compile(program).unwrap();
+        assert_eq!(bytecode.main.code, vec![
+            Instruction::LoadGlobal("n".to_string()),
+            Instruction::Neg,
+            Instruction::Pop,
+            Instruction::Halt,
+        ]);
+    }
+
+    #[test]
+    fn test_compile_if_without_else() {
+        let mut compiler = Compiler::new();
+
+        // Program: if true: 42 end
+        let program = create_simple_program(vec![
+            Item::Stmt(Stmt::If {
+                cond: Expr::LiteralBool(true),
+                then_body: vec![Stmt::ExprStmt(Expr::LiteralInt(42))],
+                else_body: vec![],
+            }),
+        ]);
+
+        let bytecode = compiler.compile(program).unwrap();
+
+        // Top level seeds the last value with unit; no jump over an empty else
+        assert_eq!(bytecode.main.code, vec![
+            Instruction::PushUnit,
+            Instruction::Pop,
+            Instruction::PushBool(true),
+            Instruction::JumpIfFalse(6),
+            Instruction::PushInt(42),
+            Instruction::Pop,
+            Instruction::Halt,
+        ]);
+    }
+
     #[test]
     fn test_compile_while_loop() {
         let mut compiler = Compiler::new();
@@ -153,8 +262,9 @@ mod tests {
         // Program: while true: break end
         let program = create_simple_program(vec![
             Item::Stmt(Stmt::While {
+                label: None,
                 cond: Expr::LiteralBool(true),
-                body: vec![Stmt::Break],
+                body: vec![Stmt::Break(None)],
             }),
         ]);
         
@@ -182,8 +292,9 @@ mod tests {
                 ]),
             }),
             Item::Stmt(Stmt::ExprStmt(Expr::Index(
-                Box::new(Expr::Ident("arr".to_string())),
+                Box::new(Expr::Ident("arr".to_string(), AT)),
                 Box::new(Expr::LiteralInt(1)),
+                AT,
             ))),
         ]);
         
@@ -195,11 +306,64 @@ mod tests {
         assert_eq!(bytecode.main.code[2], Instruction::PushInt(3));
         assert_eq!(bytecode.main.code[3], Instruction::MakeList(3));
         assert_eq!(bytecode.main.code[4], Instruction::StoreGlobal("arr".to_string()));
+        // A top-level `let` leaves unit as the program's last value
+        assert_eq!(bytecode.main.code[5..7], [Instruction::PushUnit, Instruction::Pop]);
         
         // Check indexing
-        assert_eq!(bytecode.main.code[5], Instruction::LoadGlobal("arr".to_string()));
-        assert_eq!(bytecode.main.code[6], Instruction::PushInt(1));
-        assert_eq!(bytecode.main.code[7], Instruction::Index);
+        assert_eq!(bytecode.main.code[7], Instruction::LoadGlobal("arr".to_string()));
+        assert_eq!(bytecode.main.code[8], Instruction::PushInt(1));
+        assert_eq!(bytecode.main.code[9], Instruction::Index);
+    }
+
+    #[test]
+    fn test_constant_pool_deduplicates_literals() {
+        let mut compiler = Compiler::new();
+
+        // Program: show("hi") show("hi") show("bye") show(10000000000) show(7)
+        let show = |e: Expr| Item::Stmt(Stmt::ExprStmt(Expr::Call { name: "show".to_string(), args: vec![e], span: AT }));
+        let program = create_simple_program(vec![
+            show(Expr::LiteralString("hi".to_string())),
+            show(Expr::LiteralString("hi".to_string())),
+            show(Expr::LiteralString("bye".to_string())),
+            show(Expr::LiteralInt(10_000_000_000)),
+            show(Expr::LiteralInt(7)),
+        ]);
+
+        let bytecode = compiler.compile(program).unwrap();
+
+        assert_eq!(bytecode.main.constants, vec![
+            Value::Str("hi".to_string()),
+            Value::Str("bye".to_string()),
+            Value::Int(10_000_000_000),
+        ]);
+        assert_eq!(bytecode.main.code[0], Instruction::PushConst(0));
+        assert_eq!(bytecode.main.code[3], Instruction::PushConst(0));
+        assert_eq!(bytecode.main.code[6], Instruction::PushConst(1));
+        assert_eq!(bytecode.main.code[9], Instruction::PushConst(2));
+        assert_eq!(bytecode.main.code[12], Instruction::PushInt(7));
+    }
+
+    #[test]
+    fn test_compile_native_call() {
+        let mut compiler = Compiler::new();
+        compiler.declare_native("spawn").unwrap();
+        assert!(compiler.declare_native("spawn").is_err());
+        assert!(compiler.declare_native("len").is_err());
+
+        // Program: spawn(1)
+        let program = create_simple_program(vec![
+            Item::Stmt(Stmt::ExprStmt(Expr::Call { name: "spawn".to_string(), args: vec![Expr::LiteralInt(1)], span: AT })),
+        ]);
+        let bytecode = compiler.compile(program).unwrap();
+        assert_eq!(bytecode.natives, vec!["spawn".to_string()]);
+        assert_eq!(bytecode.main.code[1], Instruction::NativeCall(0, 1));
+
+        let mut compiler = Compiler::new();
+        compiler.declare_native("spawn").unwrap();
+        let program = create_simple_program(vec![
+            Item::Function(Function { name: "spawn".to_string(), params: vec![], return_type: None, body: vec![] }),
+        ]);
+        assert!(compiler.compile(program).is_err());
     }
 
     #[test]
@@ -208,10 +372,104 @@ mod tests {
         
         assert_eq!(builtin_of("show"), Some(Builtin::Show));
         assert_eq!(builtin_of("showf"), Some(Builtin::ShowF));
+        assert_eq!(builtin_of("format"), Some(Builtin::Format));
+        assert_eq!(builtin_of("bool"), Some(Builtin::Bool));
+        assert_eq!(builtin_of("push"), Some(Builtin::Push));
+        assert_eq!(builtin_of("index_of"), Some(Builtin::IndexOf));
+        assert_eq!(builtin_of("replace"), Some(Builtin::Replace));
+        assert_eq!(builtin_of("starts_with"), Some(Builtin::StartsWith));
+        assert_eq!(builtin_of("ends_with"), Some(Builtin::EndsWith));
+        assert_eq!(builtin_of("sort"), Some(Builtin::Sort));
+        assert_eq!(builtin_of("reverse"), Some(Builtin::Reverse));
+        assert_eq!(builtin_of("prompt_trimmed"), Some(Builtin::PromptTrimmed));
+        assert_eq!(builtin_of("chr"), Some(Builtin::Chr));
+        assert_eq!(builtin_of("ord"), Some(Builtin::Ord));
+        assert_eq!(builtin_of("range"), Some(Builtin::Range));
+        assert_eq!(builtin_of("round"), Some(Builtin::Round));
         assert_eq!(builtin_of("len"), Some(Builtin::Len));
+        assert_eq!(builtin_of("copy"), Some(Builtin::Copy));
+        assert_eq!(builtin_of("hash"), Some(Builtin::Hash));
+        assert_eq!(builtin_of("assert_eq"), Some(Builtin::AssertEq));
+        assert_eq!(builtin_of("get"), Some(Builtin::Get));
+        assert_eq!(builtin_of("left"), Some(Builtin::Left));
+        assert_eq!(builtin_of("right"), Some(Builtin::Right));
+        assert_eq!(builtin_of("set"), Some(Builtin::Set));
         assert_eq!(builtin_of("unknown"), None);
     }
 
+    #[test]
+    fn test_return_in_main_is_rejected() {
+        // The parser rejects this already; the compiler also guards hand-built ASTs
+        let program = create_simple_program(vec![Item::Stmt(Stmt::Return(Some(Expr::LiteralInt(5))))]);
+        assert_eq!(Compiler::new().compile(program).unwrap_err().msg, "'return' outside of function");
+    }
+
+    #[test]
+    fn test_constant_folding() {
+        let int = |n| Box::new(Expr::LiteralInt(n));
+        let main_code = |e: Expr| {
+            let program = create_simple_program(vec![Item::Stmt(Stmt::ExprStmt(e))]);
+            Compiler::new().compile(program).map(|p| p.main.code)
+        };
+
+        // 2 + 3 * 4 and !(1 < 2.5)
+        let sum = Expr::BinaryAdd(int(2), Box::new(Expr::BinaryMul(int(3), int(4))));
+        assert_eq!(main_code(sum).unwrap()[..2], [Instruction::PushInt(14), Instruction::Pop]);
+        let not = Expr::LogicalNot(Box::new(Expr::Lt(int(1), Box::new(Expr::LiteralFloat(2.5)))));
+        assert_eq!(main_code(not).unwrap()[0], Instruction::PushBool(false));
+
+        // An operand that is not a constant stops folding at that operator
+        let partial = Expr::BinaryAdd(Box::new(Expr::Ident("x".to_string(), AT)), Box::new(Expr::Negate(int(1))));
+        assert_eq!(main_code(partial).unwrap()[..3], [Instruction::LoadGlobal("x".to_string()), Instruction::PushInt(-1), Instruction::Add]);
+
+        // Errors other than division by zero are left to run time
+        let overflow = Expr::BinaryAdd(int(i64::MAX), int(1));
+        assert_eq!(main_code(overflow).unwrap()[2], Instruction::Add);
+        let mismatch = Expr::BinarySub(Box::new(Expr::LiteralString("a".to_string())), int(1));
+        assert_eq!(main_code(mismatch).unwrap()[2], Instruction::Sub);
+
+        // Division by zero is reported at the `/`
+        let slash = Span { line: 2, col: 7 };
+        let div_zero = Expr::BinaryDiv(int(1), Box::new(Expr::BinarySub(int(2), int(2))), slash);
+        let err = main_code(div_zero).unwrap_err();
+        assert_eq!((err.msg.as_str(), err.line, err.col), ("division by zero", Some(2), Some(7)));
+        let float_zero = Expr::BinaryDiv(Box::new(Expr::LiteralFloat(1.0)), int(0), slash);
+        assert_eq!(main_code(float_zero).unwrap_err().msg, "division by zero");
+    }
+
+    #[test]
+    fn test_compile_map_literal() {
+        let map = Expr::Map(vec![
+            (Expr::LiteralString("a".to_string()), Expr::LiteralInt(1)),
+            (Expr::LiteralString("b".to_string()), Expr::LiteralBool(true)),
+        ]);
+        let mut compiler = Compiler::new();
+        let program = create_simple_program(vec![Item::Stmt(Stmt::ExprStmt(map))]);
+        let bytecode = compiler.compile(program).unwrap();
+        // Keys and values alternate, in source order
+        assert_eq!(bytecode.main.code[..5], [
+            Instruction::PushConst(0),
+            Instruction::PushInt(1),
+            Instruction::PushConst(1),
+            Instruction::PushBool(true),
+            Instruction::MakeMap(2),
+        ]);
+    }
+
+    #[test]
+    fn test_underscore_can_be_redeclared() {
+        let let_stmt = |name: &str| Stmt::Let { name: name.to_string(), ty: None, expr: Expr::LiteralInt(1) };
+        let function = |body| Item::Function(Function { name: "f".to_string(), params: vec![], return_type: None, body });
+
+        let program = create_simple_program(vec![function(vec![let_stmt("_"), let_stmt("_")])]);
+        let bytecode = Compiler::new().compile(program).unwrap();
+        // Each `let _` evaluates its value and stores it in one slot no name resolves to
+        assert_eq!(bytecode.functions[0].local_count, 1);
+
+        let program = create_simple_program(vec![function(vec![let_stmt("x"), let_stmt("x")])]);
+        assert_eq!(Compiler::new().compile(program).unwrap_err().msg, "Variable 'x' already defined in scope");
+    }
+
     #[test]
     fn test_duplicate_function_error() {
         let mut compiler = Compiler::new();
@@ -242,4 +500,54 @@ mod tests {
         let compiler = Compiler::default();
         assert!(compiler.function_names().is_empty());
     }
+
+    #[test]
+    fn test_peephole_fixes_up_jump_targets() {
+        use Instruction::*;
+        let code = vec![
+            LoadLocal(0),
+            JumpIfFalse(6),   // lands on a Pop, so the pair before it stays
+            PushInt(1),
+            Pop,
+            Jump(5),          // to the next instruction
+            PushInt(2),
+            Pop,
+            Jump(8),          // to the next instruction
+            LoadLocal(0),
+            JumpIfTrue(2),    // into the removed pair: lands on the next kept instruction
+            PushUnit,
+            Return,
+        ];
+        assert_eq!(peephole::optimize(code, false), vec![
+            LoadLocal(0),
+            JumpIfFalse(3),
+            PushInt(2),
+            Pop,
+            LoadLocal(0),
+            JumpIfTrue(2),
+            PushUnit,
+            Return,
+        ]);
+
+        // Dropping the pair makes the jump point at the next instruction, so it goes too
+        assert_eq!(peephole::optimize(vec![Jump(3), PushInt(1), Pop, PushUnit, Return], false), vec![PushUnit, Return]);
+        // A jump to the end of the code stays at the (new) end
+        assert_eq!(peephole::optimize(vec![PushBool(true), JumpIfFalse(4), PushStr("x".to_string()), Pop], false), vec![PushBool(true), JumpIfFalse(2)]);
+    }
+
+    #[test]
+    fn test_peephole_keeps_main_last_value() {
+        use Instruction::*;
+        // In main a Pop sets the last value, so only a pop that is overwritten right away goes
+        let code = vec![PushInt(1), Pop, PushInt(2), Pop, Halt];
+        assert_eq!(peephole::optimize(code.clone(), true), vec![PushInt(2), Pop, Halt]);
+        assert_eq!(peephole::optimize(code, false), vec![Halt]);
+
+        // 1; 2 compiles to the shorter form unless the pass is turned off
+        let program = || create_simple_program(vec![Item::Stmt(Stmt::ExprStmt(Expr::LiteralInt(1))), Item::Stmt(Stmt::ExprStmt(Expr::LiteralInt(2)))]);
+        assert_eq!(Compiler::new().compile(program()).unwrap().main.code, vec![PushInt(2), Pop, Halt]);
+        let mut plain = Compiler::new();
+        plain.set_optimize(false);
+        assert_eq!(plain.compile(program()).unwrap().main.code.len(), 5);
+    }
 }
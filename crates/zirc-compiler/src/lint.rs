@@ -0,0 +1,126 @@
+//! Static checks that produce compiler warnings (unused variables, uncalled
+//! functions, functions shadowed by builtins).
+
+use std::collections::HashSet;
+
+use zirc_syntax::ast::*;
+
+use crate::compiler::builtin_of;
+
+/// Returns the warnings for `program`, in source order.
+pub(crate) fn lint(program: &Program) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    // Every read and call anywhere; functions may read globals
+    let mut reads = HashSet::new();
+    let mut calls = HashSet::new();
+    for item in &program.items {
+        match item {
+            Item::Function(f) => {
+                let mut own_calls = HashSet::new();
+                visit_stmts(&f.body, &mut reads, &mut own_calls);
+                // A function that only calls itself is still never called
+                own_calls.remove(&f.name);
+                calls.extend(own_calls);
+            }
+            Item::Stmt(s) => visit_stmts(std::slice::from_ref(s), &mut reads, &mut calls),
+        }
+    }
+
+    let mut globals = Vec::new();
+    for item in &program.items {
+        match item {
+            Item::Function(f) => {
+                if builtin_of(&f.name).is_some() {
+                    warnings.push(format!("function '{}' is shadowed by the builtin of the same name", f.name));
+                } else if !calls.contains(&f.name) {
+                    warnings.push(format!("function '{}' is never called", f.name));
+                }
+                let mut lets = Vec::new();
+                collect_lets(&f.body, &mut lets);
+                let mut local_reads = HashSet::new();
+                visit_stmts(&f.body, &mut local_reads, &mut HashSet::new());
+                for name in lets {
+                    if !local_reads.contains(name) && !name.starts_with('_') {
+                        warnings.push(format!("unused variable '{}' in function '{}'", name, f.name));
+                    }
+                }
+            }
+            Item::Stmt(s) => collect_lets(std::slice::from_ref(s), &mut globals),
+        }
+    }
+    for name in globals {
+        if !reads.contains(name) && !name.starts_with('_') {
+            warnings.push(format!("unused variable '{}'", name));
+        }
+    }
+    warnings
+}
+
+fn collect_lets<'a>(stmts: &'a [Stmt], out: &mut Vec<&'a String>) {
+    for s in stmts {
+        match s {
+            Stmt::Let { name, .. } if !out.contains(&name) => out.push(name),
+            Stmt::LetUnpack { names, .. } => for name in names { if !out.contains(&name) { out.push(name) } },
+            Stmt::If { then_body, else_body, .. } => { collect_lets(then_body, out); collect_lets(else_body, out); }
+            Stmt::While { body, .. } | Stmt::For { body, .. } | Stmt::ForEach { body, .. } => collect_lets(body, out),
+            _ => {}
+        }
+    }
+}
+
+fn visit_stmts(stmts: &[Stmt], reads: &mut HashSet<String>, calls: &mut HashSet<String>) {
+    for s in stmts {
+        match s {
+            Stmt::Let { expr, .. } | Stmt::LetUnpack { expr, .. } | Stmt::Assign { expr, .. } | Stmt::ExprStmt(expr) => visit_expr(expr, reads, calls),
+            Stmt::CompoundAssign { name, expr, .. } => {
+                reads.insert(name.clone());
+                visit_expr(expr, reads, calls);
+            }
+            Stmt::IndexAssign { base, index, expr } => {
+                visit_expr(base, reads, calls);
+                visit_expr(index, reads, calls);
+                visit_expr(expr, reads, calls);
+            }
+            Stmt::Return(opt) => if let Some(e) = opt { visit_expr(e, reads, calls) },
+            Stmt::If { cond, then_body, else_body } => {
+                visit_expr(cond, reads, calls);
+                visit_stmts(then_body, reads, calls);
+                visit_stmts(else_body, reads, calls);
+            }
+            Stmt::While { cond, body, .. } => { visit_expr(cond, reads, calls); visit_stmts(body, reads, calls); }
+            Stmt::ForEach { iterable, body, .. } => { visit_expr(iterable, reads, calls); visit_stmts(body, reads, calls); }
+            Stmt::For { start, end, step, body, .. } => {
+                visit_expr(start, reads, calls);
+                visit_expr(end, reads, calls);
+                if let Some(step) = step { visit_expr(step, reads, calls) }
+                visit_stmts(body, reads, calls);
+            }
+            Stmt::Break(_) | Stmt::Continue(_) => {}
+        }
+    }
+}
+
+fn visit_expr(e: &Expr, reads: &mut HashSet<String>, calls: &mut HashSet<String>) {
+    match e {
+        Expr::LiteralInt(_) | Expr::LiteralFloat(_) | Expr::LiteralString(_) | Expr::LiteralBool(_) => {}
+        Expr::Ident(name, _) => { reads.insert(name.clone()); }
+        Expr::BinaryAdd(a, b) | Expr::BinarySub(a, b) | Expr::BinaryMul(a, b) | Expr::BinaryDiv(a, b, _)
+        | Expr::BinaryMod(a, b)
+        | Expr::BitAnd(a, b) | Expr::BitOr(a, b) | Expr::BitXor(a, b) | Expr::Shl(a, b) | Expr::Shr(a, b)
+        | Expr::LogicalAnd(a, b) | Expr::LogicalOr(a, b)
+        | Expr::Eq(a, b) | Expr::Ne(a, b) | Expr::Lt(a, b) | Expr::Le(a, b) | Expr::Gt(a, b) | Expr::Ge(a, b)
+        | Expr::Index(a, b, _) => { visit_expr(a, reads, calls); visit_expr(b, reads, calls); }
+        Expr::LogicalNot(a) | Expr::Negate(a) => visit_expr(a, reads, calls),
+        Expr::Call { name, args, .. } => {
+            calls.insert(name.clone());
+            // reduce() and map() name their callback as a bare identifier
+            for a in args {
+                if let Expr::Ident(f, _) = a { calls.insert(f.clone()); }
+                visit_expr(a, reads, calls);
+            }
+        }
+        Expr::List(items) => for it in items { visit_expr(it, reads, calls) },
+        Expr::Map(entries) => for (k, v) in entries { visit_expr(k, reads, calls); visit_expr(v, reads, calls) },
+    }
+}
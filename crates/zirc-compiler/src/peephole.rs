@@ -0,0 +1,88 @@
+//! Peephole cleanup of a finished function's code.
+//!
+//! Two patterns are removed: a push of a literal or a local that is popped
+//! straight away (as after a `push(xs, v)` statement), and a `Jump` to the
+//! very next instruction. Removing one can expose another, so the pass
+//! repeats until nothing changes. Jump targets are then renumbered: a
+//! jump to a removed instruction lands on the next one that was kept.
+//!
+//! In `main`, `Pop` also records the program's last value, so a popped literal
+//! there is only dropped when another popped literal follows and overwrites it.
+
+use std::collections::HashSet;
+
+use zirc_bytecode::Instruction as BC;
+
+/// Returns `code` with the redundant instructions removed and every jump
+/// target fixed up. `in_main` keeps pops that set the program's last value.
+pub(crate) fn optimize(mut code: Vec<BC>, in_main: bool) -> Vec<BC> {
+    loop {
+        let removed = redundant(&code, in_main);
+        if !removed.contains(&true) {
+            return code;
+        }
+        code = compact(code, &removed);
+    }
+}
+
+/// Marks the instructions one pass can drop.
+fn redundant(code: &[BC], in_main: bool) -> Vec<bool> {
+    let targets: HashSet<usize> = code.iter().filter_map(jump_target).collect();
+    let popped_push = |i: usize| is_push(&code[i]) && code.get(i + 1) == Some(&BC::Pop);
+    let mut removed = vec![false; code.len()];
+    let mut i = 0;
+    while i < code.len() {
+        // A jump to the `Pop` means it can run without the push before it
+        if popped_push(i) && !targets.contains(&(i + 1)) && (!in_main || (i + 2 < code.len() && popped_push(i + 2))) {
+            removed[i] = true;
+            removed[i + 1] = true;
+            i += 2;
+            continue;
+        }
+        if code[i] == BC::Jump(i + 1) {
+            removed[i] = true;
+        }
+        i += 1;
+    }
+    removed
+}
+
+/// Drops the marked instructions and points each jump at the new index of
+/// its target, or of the first kept instruction after it.
+fn compact(code: Vec<BC>, removed: &[bool]) -> Vec<BC> {
+    // new_index[i] is how many instructions before i are kept; the extra
+    // entry covers a jump to the end of the code
+    let mut new_index = Vec::with_capacity(code.len() + 1);
+    let mut kept = 0;
+    for &r in removed {
+        new_index.push(kept);
+        if !r {
+            kept += 1;
+        }
+    }
+    new_index.push(kept);
+
+    code.into_iter()
+        .zip(removed)
+        .filter(|(_, r)| !**r)
+        .map(|(instr, _)| match instr {
+            BC::Jump(t) => BC::Jump(new_index[t]),
+            BC::JumpIfFalse(t) => BC::JumpIfFalse(new_index[t]),
+            BC::JumpIfTrue(t) => BC::JumpIfTrue(new_index[t]),
+            BC::AddOrJump(t) => BC::AddOrJump(new_index[t]),
+            other => other,
+        })
+        .collect()
+}
+
+fn jump_target(instr: &BC) -> Option<usize> {
+    match instr {
+        BC::Jump(t) | BC::JumpIfFalse(t) | BC::JumpIfTrue(t) | BC::AddOrJump(t) => Some(*t),
+        _ => None,
+    }
+}
+
+/// A push that cannot fail or have any other effect.
+fn is_push(instr: &BC) -> bool {
+    matches!(instr, BC::PushInt(_) | BC::PushStr(_) | BC::PushConst(_) | BC::PushBool(_) | BC::PushUnit | BC::LoadLocal(_))
+}
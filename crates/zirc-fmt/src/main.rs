@@ -41,7 +41,8 @@ fn main() {
         std::process::exit(1)
     });
 
-    let formatted = format_program(&program);
+    let gaps = blank_gaps(&src, parser.line_gaps());
+    let formatted = format_program(&program, &mut gaps.iter());
 
     if check {
         if normalize_newlines(&formatted) != normalize_newlines(&src) {
@@ -61,23 +62,47 @@ fn normalize_newlines(s: &str) -> String {
     s.replace("\r\n", "\n")
 }
 
-fn format_program(p: &Program) -> String {
+/// One flag per item and statement, in the parser's pre-order: whether the
+/// author separated it from the previous one with blank or comment lines.
+type Gaps<'a> = std::slice::Iter<'a, bool>;
+
+fn blank_gaps(src: &str, line_gaps: &[(usize, usize)]) -> Vec<bool> {
+    let lines: Vec<&str> = src.lines().collect();
+    let separator = |l: usize| lines.get(l - 1).is_some_and(|t| t.trim().is_empty() || t.trim_start().starts_with('~'));
+    line_gaps.iter().map(|&(prev, line)| (prev + 1..line).any(separator)).collect()
+}
+
+fn format_program(p: &Program, gaps: &mut Gaps) -> String {
     let mut out = String::new();
     for (i, item) in p.items.iter().enumerate() {
-        if i > 0 {
+        // Any run of separating lines becomes exactly one blank line
+        if gaps.next() == Some(&true) && i > 0 {
             out.push('\n');
         }
         match item {
-            Item::Function(f) => out.push_str(&format_function(f)),
-            Item::Stmt(s) => out.push_str(&format_stmt(s, 0)),
+            Item::Function(f) => out.push_str(&format_function(f, gaps)),
+            Item::Stmt(s) => out.push_str(&format_stmt(s, 0, gaps)),
         }
     }
     out
 }
 
+/// Formats a block body; blank lines before its first statement are dropped.
+fn format_block(body: &[Stmt], indent: usize, gaps: &mut Gaps) -> String {
+    let mut out = String::new();
+    for (i, st) in body.iter().enumerate() {
+        if gaps.next() == Some(&true) && i > 0 {
+            out.push('\n');
+        }
+        out.push_str(&format_stmt(st, indent, gaps));
+    }
+    out
+}
+
 fn format_type(t: &Type) -> &'static str {
     match t {
         Type::Int => "int",
+        Type::Float => "float",
         Type::String => "string",
         Type::Bool => "bool",
         Type::List => "list",
@@ -85,7 +110,7 @@ fn format_type(t: &Type) -> &'static str {
     }
 }
 
-fn format_function(f: &Function) -> String {
+fn format_function(f: &Function, gaps: &mut Gaps) -> String {
     let mut out = String::new();
     out.push_str("fun ");
     out.push_str(&f.name);
@@ -108,14 +133,12 @@ fn format_function(f: &Function) -> String {
         out.push(')');
     }
     out.push_str(":\n");
-    for s in &f.body {
-        out.push_str(&format_stmt(s, 2));
-    }
+    out.push_str(&format_block(&f.body, 2, gaps));
     out.push_str("end\n");
     out
 }
 
-fn format_stmt(s: &Stmt, indent: usize) -> String {
+fn format_stmt(s: &Stmt, indent: usize, gaps: &mut Gaps) -> String {
     let mut out = String::new();
     let pad = " ".repeat(indent);
     match s {
@@ -131,6 +154,14 @@ fn format_stmt(s: &Stmt, indent: usize) -> String {
             out.push_str(&format_expr(expr));
             out.push('\n');
         }
+        Stmt::LetUnpack { names, expr } => {
+            out.push_str(&pad);
+            out.push_str("let ");
+            out.push_str(&names.join(", "));
+            out.push_str(" = ");
+            out.push_str(&format_expr(expr));
+            out.push('\n');
+        }
         Stmt::Assign { name, expr } => {
             out.push_str(&pad);
             out.push_str(name);
@@ -138,6 +169,21 @@ fn format_stmt(s: &Stmt, indent: usize) -> String {
             out.push_str(&format_expr(expr));
             out.push('\n');
         }
+        Stmt::CompoundAssign { name, op, expr, .. } => {
+            out.push_str(&pad);
+            out.push_str(name);
+            out.push(' ');
+            out.push_str(op.symbol());
+            out.push(' ');
+            out.push_str(&format_expr(expr));
+            out.push('\n');
+        }
+        Stmt::IndexAssign { base, index, expr } => {
+            out.push_str(&pad);
+            out.push_str(&format!("{}[{}] = ", wrap(base), format_expr(index)));
+            out.push_str(&format_expr(expr));
+            out.push('\n');
+        }
         Stmt::Return(e) => {
             out.push_str(&pad);
             out.push_str("return");
@@ -154,59 +200,87 @@ fn format_stmt(s: &Stmt, indent: usize) -> String {
         } => {
             out.push_str(&pad);
             out.push_str("if ");
-            out.push_str(&format_expr(cond));
-            out.push_str(":\n");
-            for st in then_body {
-                out.push_str(&format_stmt(st, indent + 2));
-            }
-            if !else_body.is_empty() {
-                out.push_str(&pad);
-                out.push_str("else:\n");
-                for st in else_body {
-                    out.push_str(&format_stmt(st, indent + 2));
+            let mut branch = (cond, then_body, else_body);
+            loop {
+                let (cond, then_body, else_body) = branch;
+                out.push_str(&format_expr(cond));
+                out.push_str(":\n");
+                out.push_str(&format_block(then_body, indent + 2, gaps));
+                // An else holding only another if is an elif chain
+                if let [Stmt::If { cond, then_body, else_body }] = else_body.as_slice() {
+                    gaps.next();
+                    out.push_str(&pad);
+                    out.push_str("elif ");
+                    branch = (cond, then_body, else_body);
+                    continue;
+                }
+                if !else_body.is_empty() {
+                    out.push_str(&pad);
+                    out.push_str("else:\n");
+                    out.push_str(&format_block(else_body, indent + 2, gaps));
                 }
+                break;
             }
             out.push_str(&pad);
             out.push_str("end\n");
         }
-        Stmt::While { cond, body } => {
+        Stmt::While { label, cond, body } => {
             out.push_str(&pad);
+            push_label(&mut out, label);
             out.push_str("while ");
             out.push_str(&format_expr(cond));
             out.push_str(":\n");
-            for st in body {
-                out.push_str(&format_stmt(st, indent + 2));
-            }
+            out.push_str(&format_block(body, indent + 2, gaps));
             out.push_str(&pad);
             out.push_str("end\n");
         }
         Stmt::For {
+            label,
             var,
             start,
             end,
+            inclusive,
+            step,
             body,
         } => {
             out.push_str(&pad);
+            push_label(&mut out, label);
             out.push_str("for ");
             out.push_str(var);
             out.push_str(" in ");
             out.push_str(&format_expr(start));
-            out.push_str("..");
+            out.push_str(if *inclusive { "..=" } else { ".." });
             out.push_str(&format_expr(end));
-            out.push_str(":\n");
-            for st in body {
-                out.push_str(&format_stmt(st, indent + 2));
+            if let Some(step) = step {
+                out.push_str(" step ");
+                out.push_str(&format_expr(step));
             }
+            out.push_str(":\n");
+            out.push_str(&format_block(body, indent + 2, gaps));
+            out.push_str(&pad);
+            out.push_str("end\n");
+        }
+        Stmt::ForEach { label, var, iterable, body } => {
+            out.push_str(&pad);
+            push_label(&mut out, label);
+            out.push_str("for ");
+            out.push_str(var);
+            out.push_str(" in ");
+            out.push_str(&format_expr(iterable));
+            out.push_str(":\n");
+            out.push_str(&format_block(body, indent + 2, gaps));
             out.push_str(&pad);
             out.push_str("end\n");
         }
-        Stmt::Break => {
+        Stmt::Break(target) => {
             out.push_str(&pad);
-            out.push_str("break\n");
+            out.push_str("break");
+            push_target(&mut out, target);
         }
-        Stmt::Continue => {
+        Stmt::Continue(target) => {
             out.push_str(&pad);
-            out.push_str("continue\n");
+            out.push_str("continue");
+            push_target(&mut out, target);
         }
         Stmt::ExprStmt(e) => {
             out.push_str(&pad);
@@ -217,9 +291,26 @@ fn format_stmt(s: &Stmt, indent: usize) -> String {
     out
 }
 
+fn push_label(out: &mut String, label: &Option<String>) {
+    if let Some(label) = label {
+        out.push_str(label);
+        out.push_str(": ");
+    }
+}
+
+fn push_target(out: &mut String, target: &Option<String>) {
+    if let Some(target) = target {
+        out.push(' ');
+        out.push_str(target);
+    }
+    out.push('\n');
+}
+
 fn format_expr(e: &Expr) -> String {
     match e {
         Expr::LiteralInt(n) => n.to_string(),
+        // Debug keeps `3.0` as written and uses the lexer's exponent form for extremes
+        Expr::LiteralFloat(x) => format!("{:?}", x),
         Expr::LiteralString(s) => format!("\"{}\"", s.replace('\"', "\\\"")),
         Expr::LiteralBool(b) => {
             if *b {
@@ -228,11 +319,17 @@ fn format_expr(e: &Expr) -> String {
                 "false".into()
             }
         }
-        Expr::Ident(s) => s.clone(),
+        Expr::Ident(s, _) => s.clone(),
         Expr::BinaryAdd(a, b) => bin("+", a, b),
         Expr::BinarySub(a, b) => bin("-", a, b),
         Expr::BinaryMul(a, b) => bin("*", a, b),
-        Expr::BinaryDiv(a, b) => bin("/", a, b),
+        Expr::BinaryDiv(a, b, _) => bin("/", a, b),
+        Expr::BinaryMod(a, b) => bin("%", a, b),
+        Expr::BitAnd(a, b) => bin("&", a, b),
+        Expr::BitOr(a, b) => bin("|", a, b),
+        Expr::BitXor(a, b) => bin("^", a, b),
+        Expr::Shl(a, b) => bin("<<", a, b),
+        Expr::Shr(a, b) => bin(">>", a, b),
         Expr::Eq(a, b) => bin("==", a, b),
         Expr::Ne(a, b) => bin("!=", a, b),
         Expr::Lt(a, b) => bin("<", a, b),
@@ -242,6 +339,7 @@ fn format_expr(e: &Expr) -> String {
         Expr::LogicalAnd(a, b) => bin("&&", a, b),
         Expr::LogicalOr(a, b) => bin("||", a, b),
         Expr::LogicalNot(x) => format!("!{}", wrap(x)),
+        Expr::Negate(x) => format!("-{}", wrap(x)),
         Expr::List(items) => {
             let mut s = String::new();
             s.push('[');
@@ -254,8 +352,12 @@ fn format_expr(e: &Expr) -> String {
             s.push(']');
             s
         }
-        Expr::Index(base, idx) => format!("{}[{}]", wrap(base), format_expr(idx)),
-        Expr::Call { name, args } => {
+        Expr::Map(entries) => {
+            let parts: Vec<String> = entries.iter().map(|(k, v)| format!("{}: {}", format_expr(k), format_expr(v))).collect();
+            format!("{{{}}}", parts.join(", "))
+        }
+        Expr::Index(base, idx, _) => format!("{}[{}]", wrap(base), format_expr(idx)),
+        Expr::Call { name, args, .. } => {
             let mut s = String::new();
             s.push_str(name);
             s.push('(');
@@ -272,15 +374,107 @@ fn format_expr(e: &Expr) -> String {
 }
 
 fn bin(op: &str, a: &Expr, b: &Expr) -> String {
-    format!("{} {} {}", wrap(a), op, wrap(b))
+    format!("{} {} {}", operand(a), op, operand(b))
+}
+// Unary minus binds tighter than any binary operator, so `-x * 2` needs no parens
+fn operand(e: &Expr) -> String {
+    match e {
+        Expr::Negate(_) => format_expr(e),
+        _ => wrap(e),
+    }
 }
 fn wrap(e: &Expr) -> String {
     match e {
         Expr::LiteralInt(_)
+        | Expr::LiteralFloat(_)
         | Expr::LiteralString(_)
         | Expr::LiteralBool(_)
-        | Expr::Ident(_)
+        | Expr::Ident(..)
+        | Expr::Index(..)
         | Expr::Call { .. } => format_expr(e),
         _ => format!("({})", format_expr(e)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fmt(src: &str) -> String {
+        let tokens = Lexer::new(src).tokenize().expect("lex");
+        let mut parser = Parser::new(tokens);
+        let program = parser.parse_program().expect("parse");
+        format_program(&program, &mut blank_gaps(src, parser.line_gaps()).iter())
+    }
+
+    #[test]
+    fn formats_for_loops_lists_and_indexing() {
+        let once = fmt("let xs = [1,2,  3]\nfor i in 0..len(xs): show(xs[i]) end");
+        assert_eq!(once, "let xs = [1, 2, 3]\nfor i in 0..len(xs):\n  show(xs[i])\nend\n");
+        // `--check` compares against this, so formatted output must be a fixed point
+        assert_eq!(fmt(&once), once);
+        assert_eq!(fmt("g[i+1][0]=  -x"), "g[i + 1][0] = -x\n");
+        assert_eq!(fmt("for x in  keys( m ): show(x) end"), "for x in keys(m):\n  show(x)\nend\n");
+        assert_eq!(fmt("let m = {\"a\":1,\"b\" :[2]}\nm[\"a\"]"), "let m = {\"a\": 1, \"b\": [2]}\nm[\"a\"]\n");
+    }
+
+    #[test]
+    fn keeps_inclusive_ranges() {
+        assert_eq!(fmt("for i in 0..=n-1: show(i) end"), "for i in 0..=n - 1:\n  show(i)\nend\n");
+        assert_eq!(fmt("for i in n..0 step  -2: show(i) end"), "for i in n..0 step -2:\n  show(i)\nend\n");
+    }
+
+    #[test]
+    fn keeps_loop_labels() {
+        let once = fmt("outer:  while true: for x in xs: if x: break  outer end continue outer end end");
+        assert_eq!(once, "outer: while true:\n  for x in xs:\n    if x:\n      break outer\n    end\n    continue outer\n  end\nend\n");
+        assert_eq!(fmt(&once), once);
+    }
+
+    #[test]
+    fn keeps_compound_assignment() {
+        assert_eq!(fmt("x+=1\ny -=  2*z\nx*=y\nx /=2"), "x += 1\ny -= 2 * z\nx *= y\nx /= 2\n");
+    }
+
+    #[test]
+    fn formats_elif_chains() {
+        let once = fmt("if a: 1 elif b:\n\n show(2)\n\n show(3) else: 4 end\nlet y = 5");
+        assert_eq!(once, "if a:\n  1\nelif b:\n  show(2)\n\n  show(3)\nelse:\n  4\nend\nlet y = 5\n");
+        assert_eq!(fmt(&once), once);
+    }
+
+    #[test]
+    fn keeps_blank_line_grouping() {
+        let src = "fun a():\n  return 1\nend\n\nfun b():\n  return 2\nend\n";
+        assert_eq!(fmt(src), src);
+        // Several blank lines collapse to one; none are added where there were none
+        let src = "let x = 1\nlet y = 2\n\n\n\nshow(x + y)\n";
+        assert_eq!(fmt(src), "let x = 1\nlet y = 2\n\nshow(x + y)\n");
+        // Inside blocks too, but never right after the block opens
+        let src = "while true:\n\n  let a = 1\n\n  break\nend\n";
+        assert_eq!(fmt(src), "while true:\n  let a = 1\n\n  break\nend\n");
+        // A comment line (dropped by the lexer) still separates groups
+        assert_eq!(fmt("let a = 1\n~ next\nlet b = 2\n"), "let a = 1\n\nlet b = 2\n");
+    }
+
+    #[test]
+    fn formatting_is_idempotent_on_examples() {
+        let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+        for dir in ["examples", "benchmark/scripts"] {
+            let mut stack = vec![root.join(dir)];
+            while let Some(path) = stack.pop() {
+                if path.is_dir() {
+                    stack.extend(fs::read_dir(&path).unwrap().map(|e| e.unwrap().path()));
+                    continue;
+                }
+                if path.extension().and_then(|e| e.to_str()) != Some("zirc") { continue; }
+                let src = fs::read_to_string(&path).unwrap();
+                // Skip the deliberately broken examples
+                let Ok(tokens) = Lexer::new(&src).tokenize() else { continue };
+                if Parser::new(tokens).parse_program().is_err() { continue }
+                let once = fmt(&src);
+                assert_eq!(fmt(&once), once, "{}", path.display());
+            }
+        }
+    }
+}
@@ -36,11 +36,14 @@ impl<'a> Env<'a> {
         }
     }
 
+    /// Returns this scope's variables sorted by name, like `Vm::globals_snapshot`.
     pub fn vars_snapshot(&self) -> Vec<(String, Value)> {
-        self.vars
+        let mut v: Vec<_> = self.vars
             .iter()
             .map(|(k, b)| (k.clone(), b.value.clone()))
-            .collect()
+            .collect();
+        v.sort_by(|a, b| a.0.cmp(&b.0));
+        v
     }
 
     pub(crate) fn get(&self, name: &str) -> Option<Binding> {
@@ -51,10 +54,25 @@ impl<'a> Env<'a> {
         }
     }
 
+    /// Binds `name` in this scope. Binding `_` discards the value.
     pub(crate) fn define(&mut self, name: String, val: Value, ty: Option<Type>) {
+        if name == "_" { return; }
         self.vars.insert(name, Binding { value: val, ty });
     }
 
+    /// Removes `name` from this scope, returning its binding for [`Env::restore`].
+    pub(crate) fn take(&mut self, name: &str) -> Option<Binding> {
+        self.vars.remove(name)
+    }
+
+    /// Drops `name` from this scope and reinstates the binding [`Env::take`] returned.
+    pub(crate) fn restore(&mut self, name: &str, saved: Option<Binding>) {
+        match saved {
+            Some(b) => { self.vars.insert(name.to_string(), b); }
+            None => { self.vars.remove(name); }
+        }
+    }
+
     pub(crate) fn assign(&mut self, name: &str, val: Value) -> Result<()> {
         if let Some(b) = self.vars.get_mut(name) {
             if let Some(t) = &b.ty {
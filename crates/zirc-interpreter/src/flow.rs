@@ -8,9 +8,14 @@ pub(crate) enum Flow {
     Continue(Value),
     /// Return from function with the given value
     Return(Value),
-    /// Break out of current loop
-    Break,
-    /// Continue to next loop iteration
-    ContinueLoop,
+    /// Break out of the loop with this label, or the current loop for `None`
+    Break(Option<String>),
+    /// Continue to the next iteration of the loop with this label, or the current loop for `None`
+    ContinueLoop(Option<String>),
+}
+
+/// Whether a `break` or `continue` aimed at `target` stops at the loop labelled `label`.
+pub(crate) fn targets(target: &Option<String>, label: &Option<String>) -> bool {
+    target.is_none() || target == label
 }
 
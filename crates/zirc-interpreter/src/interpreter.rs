@@ -3,12 +3,16 @@
 use std::collections::HashMap;
 use std::fs;
 use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Instant;
 
 use crate::env::Env;
-use crate::flow::Flow;
+use crate::flow::{Flow, targets};
 use crate::value::Value;
 use zirc_syntax::ast::*;
-use zirc_syntax::error::{Result, error};
+use zirc_syntax::builtins;
+use zirc_syntax::error::{Error, Result, error};
+use zirc_syntax::value::float_operands;
 
 #[derive(Default, Debug, Clone)]
 pub struct MemoryStats {
@@ -16,13 +20,54 @@ pub struct MemoryStats {
     pub strings_allocated: usize,
     /// Total bytes allocated for string storage
     pub bytes_allocated: usize,
+    /// Number of list values built during execution (literals, `+`, list builtins)
+    pub lists_allocated: usize,
+    /// Total elements stored in those lists, plus one per `push`
+    pub list_elements_allocated: usize,
+    /// Bytes of list storage: one `Value` slot per element, not counting what
+    /// the elements themselves own
+    pub list_bytes_allocated: usize,
 }
 
+impl MemoryStats {
+    /// String and list storage together, in bytes.
+    pub fn total_bytes(&self) -> usize {
+        self.bytes_allocated + self.list_bytes_allocated
+    }
+}
+
+/// A host-provided builtin, called with the already-evaluated arguments.
+pub type HostFn = Box<dyn Fn(&[Value]) -> Result<Value>>;
+
+/// Names handled by the builtin dispatch in `eval_expr`; host builtins may not shadow them.
+const BUILTIN_NAMES: &[&str] = &[
+    "showf", "format", "show", "prompt", "prompt_trimmed", "rf", "wf", "len", "push", "pop", "slice", "reduce", "map",
+    "abs", "min", "max", "pow", "sqrt", "floor", "ceil", "round", "hex", "bin", "to_base",
+    "upper", "lower", "trim", "split", "join", "int", "str", "bool", "type", "panic", "assert", "assert_eq",
+    "script_path", "script_dir", "time_ns", "bench", "copy", "hash", "get", "set", "keys", "values", "left", "right",
+    "contains", "index_of", "replace", "starts_with", "ends_with", "sort", "reverse", "range",
+    "chr", "ord",
+];
+
 pub struct Interpreter {
     /// Global function definitions available to all scopes
     functions: HashMap<String, Function>,
+    /// Builtins registered by the embedding application
+    host_builtins: HashMap<String, HostFn>,
+    /// Path of the running script, reported by `script_path()`/`script_dir()`
+    script_path: Option<PathBuf>,
+    /// Deepest list nesting `show()` prints before eliding as `[...]`; `None` is unlimited
+    display_depth: Option<usize>,
+    /// Reference point for `time_ns()`
+    clock: Instant,
     /// Memory usage tracking for observability
     mem: MemoryStats,
+    /// Globals kept between `eval` calls
+    session: Env<'static>,
+    /// User function calls currently in progress
+    depth: usize,
+    /// Deepest call nesting allowed before `maximum recursion depth exceeded`
+    max_depth: usize,
 }
 
 impl Default for Interpreter {
@@ -30,14 +75,66 @@ impl Default for Interpreter {
 }
 
 impl Interpreter {
+    /// Creates an interpreter with the call-depth limit both backends share,
+    /// [`builtins::DEFAULT_MAX_DEPTH`]. Zirc calls recurse on the native stack,
+    /// a few kilobytes each and several times that in a debug build, so
+    /// reaching the limit needs far more than a standard 2 MB thread: run the
+    /// interpreter on a thread with a larger stack, as the CLI does with
+    /// 256 MB, or lower the limit with [`Interpreter::set_max_depth`].
     pub fn new() -> Self {
-        Self { functions: HashMap::new(), mem: MemoryStats::default() }
+        Self { functions: HashMap::new(), host_builtins: HashMap::new(), script_path: None, display_depth: None, clock: Instant::now(), mem: MemoryStats::default(), session: Env::new_root(), depth: 0, max_depth: builtins::DEFAULT_MAX_DEPTH }
+    }
+
+    /// Limits how deeply user function calls may nest; a call beyond it fails
+    /// with `maximum recursion depth exceeded` instead of overflowing the native stack,
+    /// as long as the thread's stack has room for `max_depth` calls.
+    /// Defaults to [`builtins::DEFAULT_MAX_DEPTH`].
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Limits how many levels of nested lists `show()` prints; deeper lists
+    /// appear as `[...]`. `None` (the default) prints everything.
+    pub fn set_display_depth(&mut self, max_depth: Option<usize>) {
+        self.display_depth = max_depth;
+    }
+
+    /// Sets the path `script_path()` and `script_dir()` report. Without one (REPL,
+    /// embedding) both builtins return unit. Survives `reset()`.
+    pub fn set_script_path(&mut self, path: impl Into<PathBuf>) {
+        self.script_path = Some(path.into());
+    }
+
+    /// Registers a host function callable from scripts as `name(...)`.
+    ///
+    /// Host builtins are looked up before user functions and survive `reset()`.
+    /// Registering a name already taken by a builtin, a user function or another
+    /// host builtin is an error, as is later defining a script function with it.
+    pub fn register_builtin(&mut self, name: impl Into<String>, f: HostFn) -> Result<()> {
+        let name = name.into();
+        if BUILTIN_NAMES.contains(&name.as_str()) { return error(format!("Cannot register '{}': it is a builtin", name)); }
+        if self.functions.contains_key(&name) { return error(format!("Cannot register '{}': a function with that name is defined", name)); }
+        if self.host_builtins.contains_key(&name) { return error(format!("Cannot register '{}': already registered", name)); }
+        self.host_builtins.insert(name, f);
+        Ok(())
     }
 
     pub fn memory_stats(&self) -> MemoryStats { self.mem.clone() }
 
+    /// Counts a newly built list in the memory stats; other values pass through.
+    fn track_list(&mut self, v: Value) -> Value {
+        if let Value::List(items) = &v {
+            self.mem.lists_allocated += 1;
+            self.mem.list_elements_allocated += items.len();
+            self.mem.list_bytes_allocated += items.len() * std::mem::size_of::<Value>();
+        }
+        v
+    }
+
+    /// Forgets the session's functions and globals and zeroes the memory stats.
     pub fn reset(&mut self) {
         self.functions.clear();
+        self.session = Env::new_root();
         self.mem = MemoryStats::default();
     }
 
@@ -47,15 +144,39 @@ impl Interpreter {
         v
     }
 
+    /// Runs `program` on its own: the functions and globals it defines are gone
+    /// once it finishes, and it cannot see those of earlier runs or the
+    /// [`eval`](Self::eval) session. Host builtins and memory stats carry over.
     pub fn run(&mut self, program: Program) -> Result<()> {
-        let mut env = Env::new_root();
-        let _ = self.run_with_env(program, &mut env)?;
-        Ok(())
+        let session_functions = std::mem::take(&mut self.functions);
+        let result = self.run_with_env(program, &mut Env::new_root());
+        self.functions = session_functions;
+        result.map(|_| ())
+    }
+
+    /// Runs `program` in the interpreter's session: functions and globals it
+    /// defines stay visible to later `eval` calls until [`reset`](Self::reset).
+    /// Returns the value of the last top-level statement.
+    pub fn eval(&mut self, program: Program) -> Result<Option<Value>> {
+        let mut session = std::mem::replace(&mut self.session, Env::new_root());
+        let result = self.run_with_env(program, &mut session);
+        self.session = session;
+        result
     }
 
+    /// Like [`eval`](Self::eval), but with globals in the caller's `env`
+    /// instead of the session; functions still join the session.
     pub fn run_with_env(&mut self, program: Program, env: &mut Env<'_>) -> Result<Option<Value>> {
         for item in &program.items {
             if let Item::Function(f) = item {
+                // Functions are keyed by name alone, so a second definition is an error
+                // whatever its arity, as in the compiler
+                if self.functions.contains_key(&f.name) { return error(format!("Duplicate function '{}'", f.name)); }
+                // Builtins are dispatched first, so the function could never be called
+                if BUILTIN_NAMES.contains(&f.name.as_str()) { return error(format!("Function '{}' conflicts with a builtin", f.name)); }
+                if self.host_builtins.contains_key(&f.name) {
+                    return error(format!("Function '{}' conflicts with a host builtin", f.name));
+                }
                 self.functions.insert(f.name.clone(), f.clone());
             }
         }
@@ -65,8 +186,8 @@ impl Interpreter {
                 match self.exec_stmt(env, &s)? {
                     Flow::Continue(v) => last = Some(v),
                     Flow::Return(_) => return error("'return' outside of function"),
-                    Flow::Break => return error("'break' outside of loop"),
-                    Flow::ContinueLoop => return error("'continue' outside of loop"),
+                    Flow::Break(_) => return error("'break' outside of loop"),
+                    Flow::ContinueLoop(_) => return error("'continue' outside of loop"),
                 }
             }
         }
@@ -79,77 +200,32 @@ impl Interpreter {
             match self.exec_stmt(env, s)? {
                 Flow::Continue(v) => { last = v; }
                 Flow::Return(v) => return Ok(Flow::Return(v)),
-                Flow::Break => return Ok(Flow::Break),
-                Flow::ContinueLoop => return Ok(Flow::ContinueLoop),
+                flow @ (Flow::Break(_) | Flow::ContinueLoop(_)) => return Ok(flow),
             }
         }
         Ok(Flow::Continue(last))
     }
 
+    // Every user-function call recurses through this frame, and a debug build
+    // gives each temporary of every arm its own stack slot, so the arms hand
+    // their work to methods whose frames are only live while they run
     fn exec_stmt(&mut self, env: &mut Env<'_>, stmt: &Stmt) -> Result<Flow> {
         match stmt {
-            Stmt::Let { name, ty, expr } => {
-                let v = self.eval_expr(env, expr)?;
-                if let Some(t) = ty { Interpreter::check_type(&v, t)?; }
-                env.define(name.clone(), v, ty.clone());
-                Ok(Flow::Continue(Value::Unit))
-            }
-            Stmt::Assign { name, expr } => {
-                let v = self.eval_expr(env, expr)?;
-                env.assign(name, v)?;
-                Ok(Flow::Continue(Value::Unit))
-            }
+            Stmt::Let { name, ty, expr } => self.exec_let(env, name, ty.as_ref(), expr),
+            Stmt::LetUnpack { names, expr } => self.exec_let_unpack(env, names, expr),
+            Stmt::Assign { name, expr } => self.exec_assign(env, name, expr),
+            Stmt::CompoundAssign { name, span, op, expr } => self.exec_assign(env, name, &op.desugar(name, *span, expr)),
+            Stmt::IndexAssign { base, index, expr } => self.exec_index_assign(env, base, index, expr),
             Stmt::Return(opt) => {
                 let v = match opt { Some(e) => self.eval_expr(env, e)?, None => Value::Unit };
                 Ok(Flow::Return(v))
             }
-            Stmt::If { cond, then_body, else_body } => {
-                let c = self.eval_expr(env, cond)?;
-                match c {
-                    Value::Bool(true) => self.exec_block(env, then_body),
-                    Value::Bool(false) => self.exec_block(env, else_body),
-                    other => error(format!("if condition must be bool, got {:?}", other)),
-                }
-            }
-            Stmt::While { cond, body } => {
-                loop {
-                    let c = self.eval_expr(env, cond)?;
-                    let go = match c { Value::Bool(b) => b, other => { return error(format!("while condition must be bool, got {:?}", other)); } };
-                    if !go { break; }
-                    match self.exec_block(env, body)? {
-                        Flow::Continue(_) => {}
-                        Flow::Return(v) => return Ok(Flow::Return(v)),
-                        Flow::Break => break,
-                        Flow::ContinueLoop => continue,
-                    }
-                }
-                Ok(Flow::Continue(Value::Unit))
-            }
-            Stmt::For { var, start, end, body } => {
-                let s = self.eval_expr(env, start)?;
-                let e = self.eval_expr(env, end)?;
-                let (mut i, e) = match (s, e) {
-                    (Value::Int(a), Value::Int(b)) => (a, b),
-                    (a, b) => { return error(format!("for bounds must be ints, got {:?} and {:?}", a, b)); }
-                };
-                while i < e {
-                    if env.get(var).is_some() {
-                        env.assign(var, Value::Int(i))?;
-                    } else {
-                        env.define(var.clone(), Value::Int(i), Some(Type::Int));
-                    }
-                    match self.exec_block(env, body)? {
-                        Flow::Continue(_) => {}
-                        Flow::Return(v) => return Ok(Flow::Return(v)),
-                        Flow::Break => break,
-                        Flow::ContinueLoop => { i += 1; continue; }
-                    }
-                    i += 1;
-                }
-                Ok(Flow::Continue(Value::Unit))
-            }
-            Stmt::Break => Ok(Flow::Break),
-            Stmt::Continue => Ok(Flow::ContinueLoop),
+            Stmt::If { cond, then_body, else_body } => self.exec_if(env, cond, then_body, else_body),
+            Stmt::While { label, cond, body } => self.exec_while(env, label, cond, body),
+            Stmt::For { label, var, start, end, inclusive, step, body } => self.exec_for(env, label, var, (start, end, *inclusive, step.as_ref()), body),
+            Stmt::ForEach { label, var, iterable, body } => self.exec_for_each(env, label, var, iterable, body),
+            Stmt::Break(target) => Ok(Flow::Break(target.clone())),
+            Stmt::Continue(target) => Ok(Flow::ContinueLoop(target.clone())),
             Stmt::ExprStmt(e) => {
                 let v = self.eval_expr(env, e)?;
                 Ok(Flow::Continue(v))
@@ -157,134 +233,418 @@ impl Interpreter {
         }
     }
 
+    fn exec_let(&mut self, env: &mut Env<'_>, name: &str, ty: Option<&Type>, expr: &Expr) -> Result<Flow> {
+        let v = self.eval_expr(env, expr)?;
+        if let Some(t) = ty { Interpreter::check_type(&v, t)?; }
+        env.define(name.to_string(), v, ty.cloned());
+        Ok(Flow::Continue(Value::Unit))
+    }
+
+    fn exec_assign(&mut self, env: &mut Env<'_>, name: &str, expr: &Expr) -> Result<Flow> {
+        let v = self.eval_expr(env, expr)?;
+        env.assign(name, v)?;
+        Ok(Flow::Continue(Value::Unit))
+    }
+
+    fn exec_if(&mut self, env: &mut Env<'_>, cond: &Expr, then_body: &[Stmt], else_body: &[Stmt]) -> Result<Flow> {
+        match self.eval_expr(env, cond)? {
+            Value::Bool(true) => self.exec_block(env, then_body),
+            Value::Bool(false) => self.exec_block(env, else_body),
+            other => error(format!("if condition must be bool, got {:?}", other)),
+        }
+    }
+
+    fn exec_let_unpack(&mut self, env: &mut Env<'_>, names: &[String], expr: &Expr) -> Result<Flow> {
+        let items = match self.eval_expr(env, expr)? {
+            Value::List(items) => items,
+            other => return error(format!("cannot unpack {:?}, expected a list", other)),
+        };
+        if items.len() != names.len() {
+            return error(format!("cannot unpack list of length {} into {} names", items.len(), names.len()));
+        }
+        for (name, v) in names.iter().zip(items) { env.define(name.clone(), v, None); }
+        Ok(Flow::Continue(Value::Unit))
+    }
+
+    fn exec_index_assign(&mut self, env: &mut Env<'_>, base: &Expr, index: &Expr, expr: &Expr) -> Result<Flow> {
+        // Walk `a[i][j]` down to `a`; like the VM, read it before evaluating the indices
+        let mut index_exprs = vec![index];
+        let mut root = base;
+        while let Expr::Index(b, i, _) = root {
+            index_exprs.push(i);
+            root = b;
+        }
+        let Expr::Ident(name, _) = root else { return error("index assignment target must be a variable"); };
+        let current = env.get(name).ok_or_else(|| format!("Undefined variable '{}'", name))?.value;
+        let mut path = Vec::with_capacity(index_exprs.len());
+        for e in index_exprs.into_iter().rev() {
+            match self.eval_expr(env, e)? {
+                Value::Int(n) => path.push(n),
+                other => return error(format!("index expects int, got {:?}", other)),
+            }
+        }
+        let v = self.eval_expr(env, expr)?;
+        env.assign(name, builtins::set_at(current, &path, v)?)?;
+        Ok(Flow::Continue(Value::Unit))
+    }
+
+    fn exec_while(&mut self, env: &mut Env<'_>, label: &Option<String>, cond: &Expr, body: &[Stmt]) -> Result<Flow> {
+        loop {
+            let c = self.eval_expr(env, cond)?;
+            let go = match c { Value::Bool(b) => b, other => { return error(format!("while condition must be bool, got {:?}", other)); } };
+            if !go { break; }
+            match self.exec_block(env, body)? {
+                Flow::Continue(_) => {}
+                Flow::Break(target) if targets(&target, label) => break,
+                Flow::ContinueLoop(target) if targets(&target, label) => continue,
+                // A return, or a jump to an outer loop
+                other => return Ok(other),
+            }
+        }
+        Ok(Flow::Continue(Value::Unit))
+    }
+
+    /// `for var in start..end step n`, with `range` holding the start, end,
+    /// inclusiveness and step.
+    fn exec_for(&mut self, env: &mut Env<'_>, label: &Option<String>, var: &str, range: (&Expr, &Expr, bool, Option<&Expr>), body: &[Stmt]) -> Result<Flow> {
+        let (start, end, inclusive, step) = range;
+        let values = self.for_range(env, start, end, inclusive, step)?;
+        self.exec_loop(env, label, var, values.map(Value::Int), Some(Type::Int), body)
+    }
+
+    /// The values a `for` over `start..end` (or `..=`) visits, in order.
+    fn for_range(&mut self, env: &mut Env<'_>, start: &Expr, end: &Expr, inclusive: bool, step: Option<&Expr>) -> Result<impl Iterator<Item = i64>> {
+        let s = self.eval_expr(env, start)?;
+        let e = self.eval_expr(env, end)?;
+        let (s, e) = match (s, e) {
+            (Value::Int(a), Value::Int(b)) => (a, b),
+            (a, b) => { return error(format!("for bounds must be ints, got {:?} and {:?}", a, b)); }
+        };
+        let step = match step {
+            None => 1,
+            Some(expr) => match self.eval_expr(env, expr)? {
+                Value::Int(0) => return error(builtins::FOR_STEP_ZERO),
+                Value::Int(n) => n,
+                other => return error(format!("for step must be int, got {:?}", other)),
+            },
+        };
+        let in_range = move |i: &i64| match (step > 0, inclusive) {
+            (true, false) => *i < e,
+            (true, true) => *i <= e,
+            (false, false) => *i > e,
+            (false, true) => *i >= e,
+        };
+        // Stepping past i64's range ends the loop: the next value would be past `e` anyway
+        Ok(std::iter::successors(Some(s), move |i| i.checked_add(step)).take_while(in_range))
+    }
+
+    /// `for var in iterable`, over a list's items or a string's chars.
+    fn exec_for_each(&mut self, env: &mut Env<'_>, label: &Option<String>, var: &str, iterable: &Expr, body: &[Stmt]) -> Result<Flow> {
+        let items = match self.eval_expr(env, iterable)? {
+            Value::List(items) => items,
+            Value::Str(s) => s.chars().map(|c| Value::Str(c.to_string())).collect(),
+            other => return error(format!("for-in expects list or string, got {:?}", other)),
+        };
+        self.exec_loop(env, label, var, items.into_iter(), None, body)
+    }
+
+    /// Runs `body` once per value with `var` bound to it. The loop variable is
+    /// scoped to the loop: afterwards, even after an error, `var` is whatever it
+    /// was before the loop, or undefined.
+    fn exec_loop(&mut self, env: &mut Env<'_>, label: &Option<String>, var: &str, values: impl Iterator<Item = Value>, ty: Option<Type>, body: &[Stmt]) -> Result<Flow> {
+        let saved = env.take(var);
+        let mut result = Ok(Flow::Continue(Value::Unit));
+        for v in values {
+            env.define(var.to_string(), v, ty.clone());
+            match self.exec_block(env, body) {
+                Ok(Flow::Continue(_)) => {}
+                Ok(Flow::ContinueLoop(target)) if targets(&target, label) => {}
+                Ok(Flow::Break(target)) if targets(&target, label) => break,
+                other => { result = other; break; }
+            }
+        }
+        env.restore(var, saved);
+        result
+    }
+
+    // Like `exec_stmt`, one short arm per case to keep the recursive frame small
     fn eval_expr(&mut self, env: &mut Env<'_>, expr: &Expr) -> Result<Value> {
-        match expr {
-            Expr::LiteralInt(n) => Ok(Value::Int(*n)),
-            Expr::LiteralString(s) => { self.mem.strings_allocated += 1; self.mem.bytes_allocated += s.len(); Ok(Value::Str(s.clone())) }
-            Expr::LiteralBool(b) => Ok(Value::Bool(*b)),
-            Expr::Ident(name) => match env.get(name) { Some(b) => Ok(b.value), None => zirc_syntax::error::error(format!("Undefined variable '{}'", name)) },
-            Expr::BinaryAdd(a, b) => match (self.eval_expr(env, a)?, self.eval_expr(env, b)?) {
-                (Value::Int(x), Value::Int(y)) => Ok(Value::Int(x + y)),
-                (Value::Str(x), Value::Str(y)) => { let r = format!("{}{}", x, y); self.mem.strings_allocated += 1; self.mem.bytes_allocated += r.len(); Ok(Value::Str(r)) }
-                (Value::List(mut x), Value::List(y)) => { x.extend(y); Ok(Value::List(x)) }
-                (x, y) => error(format!("Cannot add {:?} and {:?}", x, y)),
+        let (a, b, op) = match expr {
+            Expr::LiteralInt(n) => return Ok(Value::Int(*n)),
+            Expr::LiteralFloat(x) => return Ok(Value::Float(*x)),
+            Expr::LiteralString(s) => { self.mem.strings_allocated += 1; self.mem.bytes_allocated += s.len(); return Ok(Value::Str(s.clone())) }
+            Expr::LiteralBool(b) => return Ok(Value::Bool(*b)),
+            Expr::Ident(name, span) => return match env.get(name) {
+                Some(b) => Ok(b.value),
+                None => Err(Error::with_span(format!("Undefined variable '{}'", name), span.line, span.col)),
             },
-            Expr::BinarySub(a, b) => match (self.eval_expr(env, a)?, self.eval_expr(env, b)?) {
-                (Value::Int(x), Value::Int(y)) => Ok(Value::Int(x - y)),
-                (x, y) => error(format!("Cannot subtract {:?} and {:?}", x, y)),
+            Expr::BinaryAdd(a, b) => (a, b, "+"),
+            Expr::BinarySub(a, b) => (a, b, "-"),
+            Expr::BinaryMul(a, b) => (a, b, "*"),
+            Expr::BinaryDiv(a, b, _) => (a, b, "/"),
+            Expr::BinaryMod(a, b) => (a, b, "%"),
+            Expr::BitAnd(a, b) => (a, b, "&"),
+            Expr::BitOr(a, b) => (a, b, "|"),
+            Expr::BitXor(a, b) => (a, b, "^"),
+            Expr::Shl(a, b) => (a, b, "<<"),
+            Expr::Shr(a, b) => (a, b, ">>"),
+            Expr::Eq(a, b) => (a, b, "=="),
+            Expr::Ne(a, b) => (a, b, "!="),
+            Expr::Lt(a, b) => (a, b, "<"),
+            Expr::Le(a, b) => (a, b, "<="),
+            Expr::Gt(a, b) => (a, b, ">"),
+            Expr::Ge(a, b) => (a, b, ">="),
+            Expr::LogicalAnd(a, b) => return self.eval_logical(env, a, b, "&&"),
+            Expr::LogicalOr(a, b) => return self.eval_logical(env, a, b, "||"),
+            Expr::Negate(e) => return self.eval_unary(env, e, "-"),
+            Expr::LogicalNot(e) => return self.eval_unary(env, e, "!"),
+            Expr::List(elems) => return self.eval_list(env, elems),
+            Expr::Map(entries) => return self.eval_map(env, entries),
+            Expr::Index(base, idx, span) => return self.eval_index(env, base, idx).map_err(|e| e.or_span(span.line, span.col)),
+            Expr::Call { name, args, span } => return self.eval_call(env, name, args).map_err(|e| e.or_span(span.line, span.col)),
+        };
+        self.eval_binary(env, a, b, op)
+    }
+
+    /// `a op b` for every operator that evaluates both sides, left first.
+    fn eval_binary(&mut self, env: &mut Env<'_>, a: &Expr, b: &Expr, op: &str) -> Result<Value> {
+        let x = self.eval_expr(env, a)?;
+        let y = self.eval_expr(env, b)?;
+        self.apply_binary(x, y, op)
+    }
+
+    /// `a && b` and `a || b`; `b` is only evaluated when `a` does not decide.
+    fn eval_logical(&mut self, env: &mut Env<'_>, a: &Expr, b: &Expr, op: &str) -> Result<Value> {
+        let short = op == "||";
+        match self.eval_expr(env, a)? {
+            Value::Bool(x) if x == short => Ok(Value::Bool(short)),
+            Value::Bool(_) => match self.eval_expr(env, b)? { Value::Bool(y) => Ok(Value::Bool(y)), other => error(format!("{} expects bool, got {:?}", op, other)) },
+            other => error(format!("{} expects bool, got {:?}", op, other)),
+        }
+    }
+
+    fn apply_binary(&mut self, x: Value, y: Value, op: &str) -> Result<Value> {
+        match op {
+            "+" => match Value::try_add(x, y)? {
+                Value::Str(r) => { self.mem.strings_allocated += 1; self.mem.bytes_allocated += r.len(); Ok(Value::Str(r)) }
+                list @ Value::List(_) => Ok(self.track_list(list)),
+                other => Ok(other),
             },
-            Expr::BinaryMul(a, b) => match (self.eval_expr(env, a)?, self.eval_expr(env, b)?) {
-                (Value::Int(x), Value::Int(y)) => Ok(Value::Int(x * y)),
-                (x, y) => error(format!("Cannot multiply {:?} and {:?}", x, y)),
+            "-" => match (x, y) {
+                (Value::Int(x), Value::Int(y)) => Ok(Value::Int(x.checked_sub(y).ok_or(builtins::OVERFLOW_SUB)?)),
+                (x, y) => match float_operands(&x, &y) {
+                    Some((fx, fy)) => Ok(Value::Float(fx - fy)),
+                    None => error(format!("Cannot subtract {:?} and {:?}", x, y)),
+                },
             },
-            Expr::BinaryDiv(a, b) => match (self.eval_expr(env, a)?, self.eval_expr(env, b)?) {
-                (Value::Int(x), Value::Int(y)) => Ok(Value::Int(x / y)),
-                (x, y) => error(format!("Cannot divide {:?} and {:?}", x, y)),
+            "*" => match (x, y) {
+                (Value::Int(x), Value::Int(y)) => Ok(Value::Int(x.checked_mul(y).ok_or(builtins::OVERFLOW_MUL)?)),
+                (x, y) => match float_operands(&x, &y) {
+                    Some((fx, fy)) => Ok(Value::Float(fx * fy)),
+                    None => error(format!("Cannot multiply {:?} and {:?}", x, y)),
+                },
             },
-            Expr::Eq(a, b) => Ok(Value::Bool(self.eval_expr(env, a)? == self.eval_expr(env, b)?)),
-            Expr::Ne(a, b) => Ok(Value::Bool(self.eval_expr(env, a)? != self.eval_expr(env, b)?)),
-            Expr::LogicalAnd(a, b) => match self.eval_expr(env, a)? {
-                Value::Bool(false) => Ok(Value::Bool(false)),
-                Value::Bool(true) => match self.eval_expr(env, b)? { Value::Bool(bb) => Ok(Value::Bool(bb)), other => error(format!("&& expects bool, got {:?}", other)) },
-                other => error(format!("&& expects bool, got {:?}", other)),
+            "/" => match (x, y) {
+                (Value::Int(_), Value::Int(0)) => error(builtins::DIVISION_BY_ZERO),
+                // Zero is handled above, so checked_div only fails for i64::MIN / -1
+                (Value::Int(x), Value::Int(y)) => Ok(Value::Int(x.checked_div(y).ok_or(builtins::OVERFLOW_DIV)?)),
+                (x, y) => match float_operands(&x, &y) {
+                    Some((_, 0.0)) => error(builtins::DIVISION_BY_ZERO),
+                    Some((fx, fy)) => Ok(Value::Float(fx / fy)),
+                    None => error(format!("Cannot divide {:?} and {:?}", x, y)),
+                },
             },
-            Expr::LogicalOr(a, b) => match self.eval_expr(env, a)? {
-                Value::Bool(true) => Ok(Value::Bool(true)),
-                Value::Bool(false) => match self.eval_expr(env, b)? { Value::Bool(bb) => Ok(Value::Bool(bb)), other => error(format!("|| expects bool, got {:?}", other)) },
-                other => error(format!("|| expects bool, got {:?}", other)),
+            "%" => match (x, y) {
+                (Value::Int(_), Value::Int(0)) => error(builtins::DIVISION_BY_ZERO),
+                // wrapping_rem only differs for i64::MIN % -1, where the true remainder is 0
+                (Value::Int(x), Value::Int(y)) => Ok(Value::Int(x.wrapping_rem(y))),
+                (x, y) => error(format!("Cannot take remainder of {:?} and {:?}", x, y)),
             },
-            Expr::LogicalNot(e) => match self.eval_expr(env, e)? { Value::Bool(b) => Ok(Value::Bool(!b)), other => error(format!("! expects bool, got {:?}", other)) },
-            Expr::Lt(a, b) => match (self.eval_expr(env, a)?, self.eval_expr(env, b)?) { (Value::Int(x), Value::Int(y)) => Ok(Value::Bool(x < y)), _ => error("< expects ints") },
-            Expr::Le(a, b) => match (self.eval_expr(env, a)?, self.eval_expr(env, b)?) { (Value::Int(x), Value::Int(y)) => Ok(Value::Bool(x <= y)), _ => error("<= expects ints") },
-            Expr::Gt(a, b) => match (self.eval_expr(env, a)?, self.eval_expr(env, b)?) { (Value::Int(x), Value::Int(y)) => Ok(Value::Bool(x > y)), _ => error("> expects ints") },
-            Expr::Ge(a, b) => match (self.eval_expr(env, a)?, self.eval_expr(env, b)?) { (Value::Int(x), Value::Int(y)) => Ok(Value::Bool(x >= y)), _ => error(">= expects ints") },
-            Expr::List(elems) => { let mut v = Vec::with_capacity(elems.len()); for e in elems { v.push(self.eval_expr(env, e)?); } Ok(Value::List(v)) }
-            Expr::Index(base, idx) => {
-                let b = self.eval_expr(env, base)?;
-                let i = self.eval_expr(env, idx)?;
-                let ix = match i { Value::Int(n) => n, other => return error(format!("index expects int, got {:?}", other)) };
-                match b {
-                    Value::List(items) => {
-                        if ix < 0 || (ix as usize) >= items.len() { return error("index out of bounds"); }
-                        Ok(items[ix as usize].clone())
-                    }
-                    Value::Str(s) => {
-                        let chars: Vec<char> = s.chars().collect();
-                        if ix < 0 || (ix as usize) >= chars.len() { return error("index out of bounds"); }
-                        let ch = chars[ix as usize];
-                        let ss = ch.to_string();
-                        self.mem.strings_allocated += 1;
-                        self.mem.bytes_allocated += ss.len();
-                        Ok(Value::Str(ss))
-                    }
-                    other => error(format!("indexing not supported for {:?}", other)),
-                }
+            "&" | "|" | "^" | "<<" | ">>" => builtins::bitwise(x, y, op),
+            "==" => Ok(Value::Bool(x.lang_eq(&y))),
+            "!=" => Ok(Value::Bool(!x.lang_eq(&y))),
+            _ => builtins::compare(&x, &y, op).map(Value::Bool),
+        }
+    }
+
+    /// `-e` and `!e`.
+    fn eval_unary(&mut self, env: &mut Env<'_>, e: &Expr, op: &str) -> Result<Value> {
+        match (op, self.eval_expr(env, e)?) {
+            ("-", Value::Int(n)) => Ok(Value::Int(n.checked_neg().ok_or(builtins::OVERFLOW_NEG)?)),
+            ("-", Value::Float(x)) => Ok(Value::Float(-x)),
+            ("-", other) => error(format!("Cannot negate {:?}", other)),
+            (_, Value::Bool(b)) => Ok(Value::Bool(!b)),
+            (_, other) => error(format!("! expects bool, got {:?}", other)),
+        }
+    }
+
+    fn eval_list(&mut self, env: &mut Env<'_>, elems: &[Expr]) -> Result<Value> {
+        let mut v = Vec::with_capacity(elems.len());
+        for e in elems { v.push(self.eval_expr(env, e)?); }
+        Ok(self.track_list(Value::List(v)))
+    }
+
+    fn eval_map(&mut self, env: &mut Env<'_>, entries: &[(Expr, Expr)]) -> Result<Value> {
+        let mut map: Vec<(String, Value)> = Vec::with_capacity(entries.len());
+        for (k, v) in entries {
+            let key = match self.eval_expr(env, k)? { Value::Str(s) => s, other => return error(format!("map keys must be strings, got {:?}", other)) };
+            let v = self.eval_expr(env, v)?;
+            // A repeated key keeps its first position and its last value
+            match map.iter_mut().find(|(k2, _)| *k2 == key) {
+                Some(entry) => entry.1 = v,
+                None => map.push((key, v)),
             }
-            Expr::Call { name, args } => {
-                // builtins
-                match name.as_str() {
-                    "showf" => return self.call_showf(env, args),
-                    "show" => return self.call_show(env, args),
-                    "prompt" => return self.call_prompt(env, args),
-                    "rf" => return self.call_rf(env, args),
-                    "wf" => return self.call_wf(env, args),
-                    "len" => return self.call_len(env, args),
-                    "push" => return self.call_push(env, args),
-                    "pop" => return self.call_pop(env, args),
-                    "slice" => return self.call_slice(env, args),
-                    // Mathematical functions
-                    "abs" => return self.call_abs(env, args),
-                    "min" => return self.call_min(env, args),
-                    "max" => return self.call_max(env, args),
-                    "pow" => return self.call_pow(env, args),
-                    "sqrt" => return self.call_sqrt(env, args),
-                    "hex" => return self.call_hex(env, args),
-                    "bin" => return self.call_bin(env, args),
-                    // String functions
-                    "upper" => return self.call_upper(env, args),
-                    "lower" => return self.call_lower(env, args),
-                    "trim" => return self.call_trim(env, args),
-                    "split" => return self.call_split(env, args),
-                    "join" => return self.call_join(env, args),
-                    // Type conversion
-                    "int" => return self.call_int(env, args),
-                    "str" => return self.call_str(env, args),
-                    // Utility functions
-                    "type" => return self.call_type(env, args),
-                    _ => {}
-                }
-                let func = self
-                    .functions
-                    .get(name)
-                    .cloned()
-                    .ok_or_else(|| format!("Undefined function '{}'", name))?;
-                if func.params.len() != args.len() {
-                    return error(format!("Function '{}' expected {} args, got {}", name, func.params.len(), args.len()));
-                }
-                let mut evaluated_args = Vec::with_capacity(args.len());
-                for a in args.iter() { evaluated_args.push(self.eval_expr(env, a)?); }
-                let mut child = env.child();
-                for (p, v) in func.params.iter().zip(evaluated_args.into_iter()) {
-                    if let Some(t) = &p.ty { Interpreter::check_type(&v, t)?; }
-                    child.define(p.name.clone(), v, p.ty.clone());
-                }
-                let mut inner = child;
-                let flow = self.exec_block(&mut inner, &func.body)?;
-                let ret_val = match flow {
-                    Flow::Continue(v) => v, // implicit last value
-                    Flow::Return(v) => v,
-                    Flow::Break => return error("'break' outside of loop"),
-                    Flow::ContinueLoop => return error("'continue' outside of loop"),
-                };
-                if let Some(expected) = func.return_type.clone() { Interpreter::check_type(&ret_val, &expected)?; }
-                Ok(ret_val)
+        }
+        Ok(Value::Map(map))
+    }
+
+    /// `base[idx]` on a list, string or map.
+    fn eval_index(&mut self, env: &mut Env<'_>, base: &Expr, idx: &Expr) -> Result<Value> {
+        let b = self.eval_expr(env, base)?;
+        let i = self.eval_expr(env, idx)?;
+        if let Value::Map(entries) = b {
+            let key = match i { Value::Str(s) => s, other => return error(format!("map key must be string, got {:?}", other)) };
+            return match entries.into_iter().find(|(k, _)| *k == key) {
+                Some((_, v)) => Ok(v),
+                None => error(format!("key '{}' not found in map", key)),
+            };
+        }
+        let ix = match i { Value::Int(n) => n, other => return error(format!("index expects int, got {:?}", other)) };
+        match b {
+            Value::List(items) => {
+                if ix < 0 || (ix as usize) >= items.len() { return error(builtins::INDEX_OUT_OF_BOUNDS); }
+                Ok(items[ix as usize].clone())
+            }
+            Value::Str(s) => {
+                let chars: Vec<char> = s.chars().collect();
+                if ix < 0 || (ix as usize) >= chars.len() { return error(builtins::INDEX_OUT_OF_BOUNDS); }
+                let ch = chars[ix as usize];
+                let ss = ch.to_string();
+                self.mem.strings_allocated += 1;
+                self.mem.bytes_allocated += ss.len();
+                Ok(Value::Str(ss))
             }
+            other => error(format!("indexing not supported for {:?}", other)),
+        }
+    }
+
+    /// A call by name: a builtin, then a host builtin, then a user function.
+    fn eval_call(&mut self, env: &mut Env<'_>, name: &str, args: &[Expr]) -> Result<Value> {
+        // builtins
+        match name {
+            "showf" => return self.call_showf(env, args),
+            "format" => return self.call_format(env, args),
+            "show" => return self.call_show(env, args),
+            "prompt" => return self.call_prompt(env, args, false),
+            "prompt_trimmed" => return self.call_prompt(env, args, true),
+            "rf" => return self.call_rf(env, args),
+            "wf" => return self.call_wf(env, args),
+            "len" => return self.call_len(env, args),
+            "push" => return self.call_push(env, args),
+            "pop" => return self.call_pop(env, args),
+            "slice" => return self.call_slice(env, args),
+            "left" => return self.call_edge(env, args, false),
+            "right" => return self.call_edge(env, args, true),
+            "get" => return self.call_get(env, args),
+            "set" => return self.call_set(env, args),
+            "keys" => return self.call_map_entries(env, args, false),
+            "values" => return self.call_map_entries(env, args, true),
+            "sort" => return self.call_sort(env, args),
+            "reverse" => return self.call_reverse(env, args),
+            "range" => return self.call_range(env, args),
+            "reduce" => return self.call_reduce(env, args),
+            "map" => return self.call_map(env, args),
+            // Mathematical functions
+            "abs" => return self.call_abs(env, args),
+            "min" => return self.call_min(env, args),
+            "max" => return self.call_max(env, args),
+            "pow" => return self.call_pow(env, args),
+            "sqrt" => return self.call_sqrt(env, args),
+            "floor" | "ceil" | "round" => return self.call_round(env, args, name),
+            "hex" => return self.call_hex(env, args),
+            "bin" => return self.call_bin(env, args),
+            "to_base" => return self.call_to_base(env, args),
+            // String functions
+            "upper" => return self.call_upper(env, args),
+            "lower" => return self.call_lower(env, args),
+            "trim" => return self.call_trim(env, args),
+            "split" => return self.call_split(env, args),
+            "join" => return self.call_join(env, args),
+            "replace" => return self.call_replace(env, args),
+            "starts_with" => return self.call_affix(env, args, false),
+            "ends_with" => return self.call_affix(env, args, true),
+            // Type conversion
+            "int" => return self.call_int(env, args),
+            "str" => return self.call_str(env, args),
+            "bool" => return self.call_bool(env, args),
+            "chr" => return self.call_chr(env, args),
+            "ord" => return self.call_ord(env, args),
+            // Utility functions
+            "type" => return self.call_type(env, args),
+            "copy" => return self.call_copy(env, args),
+            "hash" => return self.call_hash(env, args),
+            "contains" => return self.call_contains(env, args),
+            "index_of" => return self.call_index_of(env, args),
+            "panic" => return self.call_panic(env, args),
+            "assert" => return self.call_assert(env, args),
+            "assert_eq" => return self.call_assert_eq(env, args),
+            "script_path" => return self.call_script_path(args, false),
+            "script_dir" => return self.call_script_path(args, true),
+            "time_ns" => return self.call_time_ns(args),
+            "bench" => return self.call_bench(env, args),
+            _ => {}
+        }
+        if self.host_builtins.contains_key(name) {
+            let evaluated_args = self.eval_args(env, args)?;
+            return (self.host_builtins[name])(&evaluated_args);
+        }
+        let func = self
+            .functions
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("Undefined function '{}'", name))?;
+        if func.params.len() != args.len() {
+            return error(format!("Function '{}' expected {} args, got {}", name, func.params.len(), args.len()));
+        }
+        let evaluated_args = self.eval_args(env, args)?;
+        self.invoke(env, &func, evaluated_args)
+    }
+
+    fn eval_args(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Vec<Value>> {
+        let mut evaluated_args = Vec::with_capacity(args.len());
+        for a in args.iter() { evaluated_args.push(self.eval_expr(env, a)?); }
+        Ok(evaluated_args)
+    }
+
+    /// Runs a user function with already-evaluated arguments (arity is checked by the caller).
+    fn invoke(&mut self, env: &mut Env<'_>, func: &Function, args: Vec<Value>) -> Result<Value> {
+        if self.depth >= self.max_depth { return error(builtins::max_recursion_depth(self.max_depth)); }
+        self.depth += 1;
+        let result = self.invoke_body(env, func, args);
+        self.depth -= 1;
+        result
+    }
+
+    fn invoke_body(&mut self, env: &mut Env<'_>, func: &Function, args: Vec<Value>) -> Result<Value> {
+        let mut child = env.child();
+        for (p, v) in func.params.iter().zip(args) {
+            if let Some(t) = &p.ty { Interpreter::check_type(&v, t)?; }
+            child.define(p.name.clone(), v, p.ty.clone());
         }
+        let ret_val = match self.exec_block(&mut child, &func.body)? {
+            Flow::Continue(v) => v, // implicit last value
+            Flow::Return(v) => v,
+            Flow::Break(_) => return error("'break' outside of loop"),
+            Flow::ContinueLoop(_) => return error("'continue' outside of loop"),
+        };
+        if let Some(expected) = &func.return_type { Interpreter::check_type(&ret_val, expected)?; }
+        Ok(ret_val)
     }
 
     pub(crate) fn check_type(val: &Value, ty: &Type) -> Result<()> {
         let ok = matches!((val, ty),
             (Value::Int(_), Type::Int)
+            | (Value::Float(_), Type::Float)
             | (Value::Str(_), Type::String)
             | (Value::Bool(_), Type::Bool)
             | (Value::Unit, Type::Unit)
@@ -293,52 +653,35 @@ impl Interpreter {
     }
 
     fn call_showf(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
-        if args.is_empty() { return error("showf requires at least a format string"); }
-        let fmt = match self.eval_expr(env, &args[0])? { Value::Str(s) => s, _ => return error("showf first argument must be a string") };
-        let mut out = String::new();
-        let mut arg_i = 1usize;
-        let mut chars = fmt.chars().peekable();
-        while let Some(c) = chars.next() {
-            if c == '%' {
-                match chars.next() {
-                    Some('d') => {
-                        if arg_i >= args.len() { return error("showf missing %d argument"); }
-                        match self.eval_expr(env, &args[arg_i])? { Value::Int(n) => out.push_str(&n.to_string()), other => return error(format!("%d expects int, got {:?}", other)) }
-                        arg_i += 1;
-                    }
-                    Some('s') => {
-                        if arg_i >= args.len() { return error("showf missing %s argument"); }
-                        match self.eval_expr(env, &args[arg_i])? {
-                            Value::Str(s) => out.push_str(&s),
-                            Value::Bool(b) => out.push_str(if b { "true" } else { "false" }),
-                            Value::List(items) => out.push_str(&format!("{}", Value::List(items))),
-                            other => { return error(format!("%s expects string/bool/list, got {:?}", other)); }
-                        }
-                        arg_i += 1;
-                    }
-                    Some('%') => out.push('%'),
-                    Some(other) => { return error(format!("Unsupported format specifier %{}", other)); }
-                    None => return error("Dangling % at end of format string"),
-                }
-            } else {
-                out.push(c);
-            }
-        }
+        let out = self.eval_format("showf", env, args)?;
         if std::env::var("ZIRC_BENCH_SILENT").is_err() { println!("{}", out); }
         Ok(Value::Unit)
     }
 
+    /// Format function - renders like showf() but returns the string
+    fn call_format(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
+        Ok(Value::Str(self.eval_format("format", env, args)?))
+    }
+
+    fn eval_format(&mut self, name: &str, env: &mut Env<'_>, args: &[Expr]) -> Result<String> {
+        let mut values = Vec::with_capacity(args.len());
+        for a in args { values.push(self.eval_expr(env, a)?); }
+        zirc_syntax::format::format_call(name, &values)
+    }
+
     /// Simple show function - prints a single value
     fn call_show(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
         if args.len() != 1 { return error("show() expects exactly 1 argument"); }
         let val = self.eval_expr(env, &args[0])?;
-        if std::env::var("ZIRC_BENCH_SILENT").is_err() { println!("{}", val); }
+        if std::env::var("ZIRC_BENCH_SILENT").is_err() { println!("{}", val.display_depth(self.display_depth)); }
         Ok(Value::Unit)
     }
 
-    /// Prompt function - reads a line from stdin and returns as string
-    fn call_prompt(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
-        if args.len() > 1 { return error("prompt() expects 0 or 1 arguments"); }
+    /// Prompt function - reads a line from stdin and returns as string;
+    /// `prompt_trimmed` also trims surrounding whitespace
+    fn call_prompt(&mut self, env: &mut Env<'_>, args: &[Expr], trim: bool) -> Result<Value> {
+        let name = if trim { "prompt_trimmed" } else { "prompt" };
+        if args.len() > 1 { return error(format!("{}() expects 0 or 1 arguments", name)); }
         let silent = std::env::var("ZIRC_BENCH_SILENT").is_ok();
         // Optional prompt string
         if args.len() == 1 {
@@ -347,7 +690,7 @@ impl Interpreter {
                 Value::Str(s) => {
                     if !silent { print!("{}", s); io::stdout().flush().map_err(|e| format!("IO error: {}", e))?; }
                 }
-                other => return error(format!("prompt() prompt must be string, got {:?}", other)),
+                other => return error(format!("{}() prompt must be string, got {:?}", name, other)),
             }
         }
         let input = if silent {
@@ -355,10 +698,9 @@ impl Interpreter {
         } else {
             let mut input = String::new();
             io::stdin().read_line(&mut input).map_err(|e| format!("IO error: {}", e))?;
-            // Remove trailing newline
-            if input.ends_with('\n') { input.pop(); if input.ends_with('\r') { input.pop(); } }
-            input
+            builtins::strip_line_end(input)
         };
+        let input = if trim { input.trim().to_string() } else { input };
         self.mem.strings_allocated += 1;
         self.mem.bytes_allocated += input.len();
         Ok(Value::Str(input))
@@ -390,18 +732,24 @@ impl Interpreter {
         match val {
             Value::Str(s) => Ok(Value::Int(s.chars().count() as i64)),
             Value::List(items) => Ok(Value::Int(items.len() as i64)),
-            other => error(format!("len() expects string or list, got {:?}", other)),
+            Value::Map(entries) => Ok(Value::Int(entries.len() as i64)),
+            other => error(format!("len() expects string, list or map, got {:?}", other)),
         }
     }
 
-    /// Push function - adds element to end of list (mutates the list)
+    /// Push function - adds element to end of list and returns the extended list;
+    /// a list variable is also updated in place, so `xs = push(xs, v)` and
+    /// `push(xs, v)` leave `xs` the same
     fn call_push(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
-        if args.len() != 2 { return error("push() expects exactly 2 arguments: list_variable and value"); }
+        if args.len() != 2 { return error("push() expects exactly 2 arguments: list and value"); }
         
-        // First argument must be an identifier (variable name)
-        let var_name = match &args[0] {
-            Expr::Ident(name) => name,
-            _ => return error("push() first argument must be a variable name"),
+        let Expr::Ident(var_name, _) = &args[0] else {
+            let mut list = match self.eval_expr(env, &args[0])? {
+                Value::List(items) => items,
+                other => return error(format!("push() expects list, got {:?}", other)),
+            };
+            list.push(self.eval_expr(env, &args[1])?);
+            return Ok(self.track_list(Value::List(list)));
         };
         
         // Get the current value and ensure it's a list
@@ -410,7 +758,7 @@ impl Interpreter {
         
         let mut list = match current.value {
             Value::List(items) => items,
-            other => return error(format!("push() expects list variable, got {:?}", other)),
+            other => return error(format!("push() expects list, got {:?}", other)),
         };
         
         // Evaluate the value to push
@@ -418,11 +766,13 @@ impl Interpreter {
         
         // Add the value to the list
         list.push(value);
+        self.mem.list_elements_allocated += 1;
+        self.mem.list_bytes_allocated += std::mem::size_of::<Value>();
         
         // Update the variable
-        env.assign(var_name, Value::List(list))?;
+        env.assign(var_name, Value::List(list.clone()))?;
         
-        Ok(Value::Unit)
+        Ok(Value::List(list))
     }
 
     /// Pop function - removes and returns last element from list
@@ -431,7 +781,7 @@ impl Interpreter {
         
         // First argument must be an identifier (variable name)
         let var_name = match &args[0] {
-            Expr::Ident(name) => name,
+            Expr::Ident(name, _) => name,
             _ => return error("pop() first argument must be a variable name"),
         };
         
@@ -445,7 +795,7 @@ impl Interpreter {
         };
         
         // Pop the last element
-        let popped = list.pop().ok_or_else(|| "Cannot pop from empty list")?;
+        let popped = list.pop().ok_or(builtins::POP_EMPTY_LIST)?;
         
         // Update the variable
         env.assign(var_name, Value::List(list))?;
@@ -467,8 +817,8 @@ impl Interpreter {
             other => return error(format!("slice() end index must be int, got {:?}", other)),
         };
         
-        if start < 0 { return error("slice() start index cannot be negative"); }
-        if end < start { return error("slice() end index must be >= start index"); }
+        if start < 0 { return error(builtins::SLICE_NEGATIVE_START); }
+        if end < start { return error(builtins::SLICE_END_BEFORE_START); }
         
         match collection {
             Value::Str(s) => {
@@ -492,15 +842,134 @@ impl Interpreter {
                 let end_idx = (end as usize).min(items.len());
                 
                 if start_idx >= items.len() {
-                    return Ok(Value::List(Vec::new()));
+                    return Ok(self.track_list(Value::List(Vec::new())));
                 }
                 
-                Ok(Value::List(items[start_idx..end_idx].to_vec()))
+                Ok(self.track_list(Value::List(items[start_idx..end_idx].to_vec())))
             },
             other => error(format!("slice() expects string or list, got {:?}", other)),
         }
     }
 
+    /// Reduce function - folds a list with a named two-argument function.
+    /// `reduce(list, f, init)` starts from `init`; `reduce(list, f)` seeds with the first element.
+    fn call_reduce(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
+        if args.len() != 2 && args.len() != 3 { return error("reduce() expects 2 or 3 arguments: list, function name and optional initial value"); }
+        let items = match self.eval_expr(env, &args[0])? {
+            Value::List(items) => items,
+            other => return error(format!("reduce() expects list, got {:?}", other)),
+        };
+        let func = match &args[1] {
+            Expr::Ident(name, _) => self.functions.get(name).cloned().ok_or_else(|| format!("Undefined function '{}'", name))?,
+            _ => return error("reduce() second argument must be a function name"),
+        };
+        if func.params.len() != 2 {
+            return error(format!("reduce() function '{}' must take 2 args, takes {}", func.name, func.params.len()));
+        }
+        let mut rest = items.into_iter();
+        let mut acc = match args.get(2) {
+            Some(init) => self.eval_expr(env, init)?,
+            None => rest.next().ok_or("reduce() of empty list with no initial value")?,
+        };
+        for item in rest { acc = self.invoke(env, &func, vec![acc, item])?; }
+        Ok(acc)
+    }
+
+    /// `left(x, n)` and `right(x, n)` on strings and lists
+    fn call_edge(&mut self, env: &mut Env<'_>, args: &[Expr], from_right: bool) -> Result<Value> {
+        if args.len() != 2 {
+            return error(format!("{}() expects exactly 2 arguments: collection and count", if from_right { "right" } else { "left" }));
+        }
+        let collection = self.eval_expr(env, &args[0])?;
+        let n = self.eval_expr(env, &args[1])?;
+        let out = builtins::take_edge(&collection, &n, from_right)?;
+        if let Value::Str(s) = &out {
+            self.mem.strings_allocated += 1;
+            self.mem.bytes_allocated += s.len();
+        }
+        Ok(self.track_list(out))
+    }
+
+    /// `get(collection, key, default)`: the list item at int `key` or the map value
+    /// at string `key`, or `default` when there is none
+    fn call_get(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
+        if args.len() != 3 { return error("get() expects exactly 3 arguments: collection, key and default"); }
+        let collection = self.eval_expr(env, &args[0])?;
+        let key = self.eval_expr(env, &args[1])?;
+        let default = self.eval_expr(env, &args[2])?;
+        builtins::get_or(&collection, &key, &default)
+    }
+
+    /// `keys(m)` / `values(m)`: a map's keys or values as a list, in insertion order
+    fn call_map_entries(&mut self, env: &mut Env<'_>, args: &[Expr], want_values: bool) -> Result<Value> {
+        let name = if want_values { "values" } else { "keys" };
+        if args.len() != 1 { return error(format!("{}() expects exactly 1 argument", name)); }
+        match self.eval_expr(env, &args[0])? {
+            Value::Map(entries) => Ok(self.track_list(builtins::map_entries(entries, want_values))),
+            other => error(format!("{}() expects map, got {:?}", name, other)),
+        }
+    }
+
+    /// `sort(list)`: a new sorted list; the argument is not modified
+    fn call_sort(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
+        if args.len() != 1 { return error("sort() expects exactly 1 argument"); }
+        match self.eval_expr(env, &args[0])? {
+            Value::List(items) => Ok(self.track_list(Value::List(builtins::sorted(&items)?))),
+            other => error(format!("sort() expects list, got {:?}", other)),
+        }
+    }
+
+    /// `reverse(list)` or `reverse(string)`: a reversed copy, strings by char
+    fn call_reverse(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
+        if args.len() != 1 { return error("reverse() expects exactly 1 argument"); }
+        match self.eval_expr(env, &args[0])? {
+            Value::List(items) => Ok(self.track_list(Value::List(items.into_iter().rev().collect()))),
+            Value::Str(s) => Ok(Value::Str(s.chars().rev().collect())),
+            other => error(format!("reverse() expects list or string, got {:?}", other)),
+        }
+    }
+
+    /// `range(start, end)` or `range(start, end, step)`: the ints of a `for` range as a list
+    fn call_range(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
+        if !(2..=3).contains(&args.len()) { return error("range() expects 2 or 3 arguments: start, end and optional step"); }
+        let mut vals = Vec::with_capacity(args.len());
+        for a in args { vals.push(self.eval_expr(env, a)?); }
+        Ok(self.track_list(Value::List(builtins::range_list(&vals)?)))
+    }
+
+    /// `set(collection, key, v)`: a new list or map with `key` set to `v`; the
+    /// argument is not modified
+    fn call_set(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
+        if args.len() != 3 { return error("set() expects exactly 3 arguments: collection, key and value"); }
+        let collection = self.eval_expr(env, &args[0])?;
+        let key = self.eval_expr(env, &args[1])?;
+        let v = self.eval_expr(env, &args[2])?;
+        match builtins::set_key(collection, &key, v)? {
+            list @ Value::List(_) => Ok(self.track_list(list)),
+            map => Ok(map),
+        }
+    }
+
+    /// `map(f, list)`: calls the named one-argument function on each item.
+    /// Interpreter-only, like `reduce`: the VM has no way to pass a function by name.
+    fn call_map(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
+        if args.len() != 2 { return error("map() expects exactly 2 arguments: function name and list"); }
+        let func = match &args[0] {
+            Expr::Ident(name, _) => self.functions.get(name).cloned().ok_or_else(|| format!("Undefined function '{}'", name))?,
+            _ => return error("map() first argument must be a function name"),
+        };
+        if func.params.len() != 1 {
+            return error(format!("map() function '{}' must take 1 arg, takes {}", func.name, func.params.len()));
+        }
+        let items = match self.eval_expr(env, &args[1])? {
+            Value::List(items) => items,
+            other => return error(format!("map() expects list, got {:?}", other)),
+        };
+        let mut out = Vec::with_capacity(items.len());
+        for item in items { out.push(self.invoke(env, &func, vec![item])?); }
+        Ok(self.track_list(Value::List(out)))
+    }
+
     // Mathematical functions
     
     /// Absolute value function
@@ -508,31 +977,24 @@ impl Interpreter {
         if args.len() != 1 { return error("abs() expects exactly 1 argument"); }
         let val = self.eval_expr(env, &args[0])?;
         match val {
-            Value::Int(n) => Ok(Value::Int(n.abs())),
-            other => error(format!("abs() expects int, got {:?}", other)),
+            Value::Int(n) => Ok(Value::Int(n.checked_abs().ok_or(builtins::OVERFLOW_ABS)?)),
+            Value::Float(x) => Ok(Value::Float(x.abs())),
+            other => error(format!("abs() expects a number, got {:?}", other)),
         }
     }
     
-    /// Minimum of two values
+    /// Minimum of a list or of 2+ values
     fn call_min(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
-        if args.len() != 2 { return error("min() expects exactly 2 arguments"); }
-        let a = self.eval_expr(env, &args[0])?;
-        let b = self.eval_expr(env, &args[1])?;
-        match (a, b) {
-            (Value::Int(x), Value::Int(y)) => Ok(Value::Int(x.min(y))),
-            _ => error("min() expects two ints"),
-        }
+        let mut vals = Vec::with_capacity(args.len());
+        for a in args { vals.push(self.eval_expr(env, a)?); }
+        builtins::min_max("min", &vals, false)
     }
     
-    /// Maximum of two values
+    /// Maximum of a list or of 2+ values
     fn call_max(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
-        if args.len() != 2 { return error("max() expects exactly 2 arguments"); }
-        let a = self.eval_expr(env, &args[0])?;
-        let b = self.eval_expr(env, &args[1])?;
-        match (a, b) {
-            (Value::Int(x), Value::Int(y)) => Ok(Value::Int(x.max(y))),
-            _ => error("max() expects two ints"),
-        }
+        let mut vals = Vec::with_capacity(args.len());
+        for a in args { vals.push(self.eval_expr(env, a)?); }
+        builtins::min_max("max", &vals, true)
     }
     
     /// Power function (base^exp)
@@ -542,11 +1004,13 @@ impl Interpreter {
         let exp = self.eval_expr(env, &args[1])?;
         match (base, exp) {
             (Value::Int(b), Value::Int(e)) => {
-                if e < 0 { return error("pow() exponent cannot be negative"); }
-                let result = (b as f64).powi(e as i32) as i64;
-                Ok(Value::Int(result))
+                if e < 0 { return error(builtins::POW_NEGATIVE_EXPONENT); }
+                Ok(Value::Int(builtins::int_pow(b, e)?))
+            },
+            (b, e) => match float_operands(&b, &e) {
+                Some((fb, fe)) => Ok(Value::Float(fb.powf(fe))),
+                None => error("pow() expects two numbers"),
             },
-            _ => error("pow() expects two ints"),
         }
     }
     
@@ -554,23 +1018,28 @@ impl Interpreter {
     fn call_sqrt(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
         if args.len() != 1 { return error("sqrt() expects exactly 1 argument"); }
         let val = self.eval_expr(env, &args[0])?;
-        match val {
-            Value::Int(n) => {
-                if n < 0 { return error("sqrt() argument cannot be negative"); }
-                let result = (n as f64).sqrt() as i64;
-                Ok(Value::Int(result))
-            },
-            other => error(format!("sqrt() expects int, got {:?}", other)),
+        match val.as_f64() {
+            Some(x) if x < 0.0 => error(builtins::SQRT_NEGATIVE),
+            Some(x) => Ok(Value::Float(x.sqrt())),
+            None => error(format!("sqrt() expects a number, got {:?}", val)),
         }
     }
 
-    /// Hexadecimal function converts integer to hex string
+    /// `floor(x)`, `ceil(x)` and `round(x)`: a float rounded to an int
+    fn call_round(&mut self, env: &mut Env<'_>, args: &[Expr], name: &str) -> Result<Value> {
+        if args.len() != 1 { return error(format!("{}() expects exactly 1 argument", name)); }
+        let val = self.eval_expr(env, &args[0])?;
+        builtins::round_to_int(name, &val)
+    }
+
+    /// Hexadecimal function converts integer to hex string (optional bool arg toggles the `0x` prefix)
     fn call_hex(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
-        if args.len() != 1 { return error("hex() expects exactly 1 argument"); }
+        if args.is_empty() || args.len() > 2 { return error("hex() expects 1 or 2 arguments: int and optional prefix flag"); }
         let val = self.eval_expr(env, &args[0])?;
+        let prefix = self.eval_prefix_flag(env, args, "hex")?;
         match val {
             Value::Int(n) => {
-                let result = format!("0x{:x}", n);
+                let result = builtins::radix_string(n, 16, if prefix { "0x" } else { "" });
                 self.mem.strings_allocated += 1;
                 self.mem.bytes_allocated += result.len();
                 Ok(Value::Str(result))
@@ -579,13 +1048,14 @@ impl Interpreter {
         }
     }
 
-    /// Binary function converts integer to binary string
+    /// Binary function converts integer to binary string (optional bool arg toggles the `0b` prefix)
     fn call_bin(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
-        if args.len() != 1 { return error("bin() expects exactly 1 argument"); }
+        if args.is_empty() || args.len() > 2 { return error("bin() expects 1 or 2 arguments: int and optional prefix flag"); }
         let val = self.eval_expr(env, &args[0])?;
+        let prefix = self.eval_prefix_flag(env, args, "bin")?;
         match val {
             Value::Int(n) => {
-                let result = format!("0b{:b}", n);
+                let result = builtins::radix_string(n, 2, if prefix { "0b" } else { "" });
                 self.mem.strings_allocated += 1;
                 self.mem.bytes_allocated += result.len();
                 Ok(Value::Str(result))
@@ -593,6 +1063,34 @@ impl Interpreter {
             other => error(format!("bin() expects int, got {:?}", other)),
         }
     }
+
+    /// Evaluates the optional prefix flag of `hex`/`bin`, defaulting to `true`
+    fn eval_prefix_flag(&mut self, env: &mut Env<'_>, args: &[Expr], name: &str) -> Result<bool> {
+        match args.get(1) {
+            None => Ok(true),
+            Some(e) => match self.eval_expr(env, e)? {
+                Value::Bool(b) => Ok(b),
+                other => error(format!("{}() prefix flag must be bool, got {:?}", name, other)),
+            },
+        }
+    }
+
+    /// Converts an integer to its representation in base 2-36 (no prefix)
+    fn call_to_base(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
+        if args.len() != 2 { return error("to_base() expects exactly 2 arguments: int and base"); }
+        let val = self.eval_expr(env, &args[0])?;
+        let base = self.eval_expr(env, &args[1])?;
+        match (val, base) {
+            (Value::Int(n), Value::Int(b)) => {
+                if !(2..=36).contains(&b) { return error(format!("to_base() base must be between 2 and 36, got {}", b)); }
+                let result = builtins::radix_string(n, b as u32, "");
+                self.mem.strings_allocated += 1;
+                self.mem.bytes_allocated += result.len();
+                Ok(Value::Str(result))
+            },
+            _ => error("to_base() expects two ints"),
+        }
+    }
     
     // String functions
     
@@ -655,7 +1153,7 @@ impl Interpreter {
                         Value::Str(part.to_string())
                     })
                     .collect();
-                Ok(Value::List(parts))
+                Ok(self.track_list(Value::List(parts)))
             },
             _ => error("split() expects two strings"),
         }
@@ -683,6 +1181,24 @@ impl Interpreter {
         }
     }
     
+    /// Replace every occurrence of a substring
+    fn call_replace(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
+        if args.len() != 3 { return error("replace() expects exactly 3 arguments: string, from and to"); }
+        let text = self.eval_expr(env, &args[0])?;
+        let from = self.eval_expr(env, &args[1])?;
+        let to = self.eval_expr(env, &args[2])?;
+        match (text, from, to) {
+            (Value::Str(_), Value::Str(from), Value::Str(_)) if from.is_empty() => error("replace() pattern must not be empty"),
+            (Value::Str(s), Value::Str(from), Value::Str(to)) => {
+                let result = s.replace(&from, &to);
+                self.mem.strings_allocated += 1;
+                self.mem.bytes_allocated += result.len();
+                Ok(Value::Str(result))
+            },
+            _ => error("replace() expects three strings"),
+        }
+    }
+    
     // Type conversion functions
     
     /// Convert value to integer
@@ -697,12 +1213,48 @@ impl Interpreter {
                     Err(_) => error(format!("Cannot convert '{}' to int", s)),
                 }
             },
+            Value::Float(x) => builtins::float_to_int(x),
             Value::Bool(true) => Ok(Value::Int(1)),
             Value::Bool(false) => Ok(Value::Int(0)),
             other => error(format!("Cannot convert {:?} to int", other)),
         }
     }
     
+    /// `starts_with(s, prefix)` or, with `suffix`, `ends_with(s, suffix)`
+    fn call_affix(&mut self, env: &mut Env<'_>, args: &[Expr], suffix: bool) -> Result<Value> {
+        let (name, part) = if suffix { ("ends_with", "suffix") } else { ("starts_with", "prefix") };
+        if args.len() != 2 { return error(format!("{}() expects exactly 2 arguments: string and {}", name, part)); }
+        let text = self.eval_expr(env, &args[0])?;
+        let affix = self.eval_expr(env, &args[1])?;
+        match (text, affix) {
+            (Value::Str(s), Value::Str(a)) => Ok(Value::Bool(if suffix { s.ends_with(&a) } else { s.starts_with(&a) })),
+            _ => error(format!("{}() expects two strings", name)),
+        }
+    }
+
+    /// Contains function - whether a list holds a value or a string a substring
+    fn call_contains(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
+        if args.len() != 2 { return error("contains() expects exactly 2 arguments: haystack and needle"); }
+        let haystack = self.eval_expr(env, &args[0])?;
+        let needle = self.eval_expr(env, &args[1])?;
+        Ok(Value::Bool(builtins::find(&haystack, &needle, "contains")?.is_some()))
+    }
+
+    /// Index-of function - first position of a value or substring, or -1
+    fn call_index_of(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
+        if args.len() != 2 { return error("index_of() expects exactly 2 arguments: haystack and needle"); }
+        let haystack = self.eval_expr(env, &args[0])?;
+        let needle = self.eval_expr(env, &args[1])?;
+        Ok(Value::Int(builtins::find(&haystack, &needle, "index_of")?.map_or(-1, |at| at as i64)))
+    }
+
+    /// Convert value to bool
+    fn call_bool(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
+        if args.len() != 1 { return error("bool() expects exactly 1 argument"); }
+        let val = self.eval_expr(env, &args[0])?;
+        Ok(Value::Bool(builtins::to_bool(&val)?))
+    }
+
     /// Convert value to string
     fn call_str(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
         if args.len() != 1 { return error("str() expects exactly 1 argument"); }
@@ -710,31 +1262,143 @@ impl Interpreter {
         let result = match val {
             Value::Str(s) => s,
             Value::Int(n) => n.to_string(),
+            Value::Float(x) => Value::Float(x).to_string(),
             Value::Bool(b) => if b { "true".to_string() } else { "false".to_string() },
-            Value::List(items) => format!("{}", Value::List(items)),
+            v @ (Value::List(_) | Value::Map(_)) => v.to_string(),
             Value::Unit => "<unit>".to_string(),
         };
         self.mem.strings_allocated += 1;
         self.mem.bytes_allocated += result.len();
         Ok(Value::Str(result))
     }
+
+    /// `chr(n)` - the one-char string for code point `n`
+    fn call_chr(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
+        if args.len() != 1 { return error("chr() expects exactly 1 argument"); }
+        match self.eval_expr(env, &args[0])? {
+            Value::Int(n) => match u32::try_from(n).ok().and_then(char::from_u32) {
+                Some(c) => {
+                    self.mem.strings_allocated += 1;
+                    self.mem.bytes_allocated += c.len_utf8();
+                    Ok(Value::Str(c.to_string()))
+                }
+                None => error(format!("chr() code point {} is out of range or a surrogate", n)),
+            },
+            other => error(format!("chr() expects an int, got {:?}", other)),
+        }
+    }
+
+    /// `ord(s)` - the code point of a one-char string
+    fn call_ord(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
+        if args.len() != 1 { return error("ord() expects exactly 1 argument"); }
+        match self.eval_expr(env, &args[0])? {
+            Value::Str(s) => {
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Ok(Value::Int(c as i64)),
+                    _ => error(format!("ord() expects a one-char string, got {:?}", s)),
+                }
+            }
+            other => error(format!("ord() expects a string, got {:?}", other)),
+        }
+    }
     
     // Utility functions
     
     /// Get type of value as string
     fn call_type(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
         if args.len() != 1 { return error("type() expects exactly 1 argument"); }
-        let val = self.eval_expr(env, &args[0])?;
-        let type_name = match val {
-            Value::Int(_) => "int",
-            Value::Str(_) => "string",
-            Value::Bool(_) => "bool",
-            Value::List(_) => "list",
-            Value::Unit => "unit",
-        };
+        let type_name = self.eval_expr(env, &args[0])?.type_name();
         self.mem.strings_allocated += 1;
         self.mem.bytes_allocated += type_name.len();
         Ok(Value::Str(type_name.to_string()))
     }
+
+    /// Values never alias: every read of a variable already yields an independent
+    /// value, so `copy` exists to make that explicit rather than to change it
+    fn call_copy(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
+        if args.len() != 1 { return error("copy() expects exactly 1 argument"); }
+        self.eval_expr(env, &args[0])
+    }
+
+    fn call_hash(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
+        if args.len() != 1 { return error("hash() expects exactly 1 argument"); }
+        let v = self.eval_expr(env, &args[0])?;
+        Ok(Value::Int(zirc_syntax::hash::hash_value(&v)?))
+    }
+
+    /// Monotonic nanoseconds since the interpreter was created; only differences are meaningful
+    fn call_time_ns(&mut self, args: &[Expr]) -> Result<Value> {
+        if !args.is_empty() { return error("time_ns() expects no arguments"); }
+        Ok(Value::Int(self.clock.elapsed().as_nanos() as i64))
+    }
+
+    /// Bench function - calls a named zero-argument function n times, returns the average nanoseconds
+    fn call_bench(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
+        if args.len() != 2 { return error("bench() expects exactly 2 arguments: function name and iteration count"); }
+        let func = match &args[0] {
+            Expr::Ident(name, _) => self.functions.get(name).cloned().ok_or_else(|| format!("Undefined function '{}'", name))?,
+            _ => return error("bench() first argument must be a function name"),
+        };
+        if !func.params.is_empty() {
+            return error(format!("bench() function '{}' must take no args, takes {}", func.name, func.params.len()));
+        }
+        let n = match self.eval_expr(env, &args[1])? {
+            Value::Int(n) if n > 0 => n,
+            other => return error(format!("bench() iteration count must be a positive int, got {:?}", other)),
+        };
+        let start = Instant::now();
+        for _ in 0..n { self.invoke(env, &func, Vec::new())?; }
+        Ok(Value::Int((start.elapsed().as_nanos() / n as u128) as i64))
+    }
+
+    /// Script path functions - path of the running file or its directory, unit when unknown
+    fn call_script_path(&mut self, args: &[Expr], dir: bool) -> Result<Value> {
+        let name = if dir { "script_dir" } else { "script_path" };
+        if !args.is_empty() { return error(format!("{}() expects no arguments", name)); }
+        let path = match &self.script_path {
+            Some(p) if dir => p.parent().map(|d| d.to_path_buf()),
+            Some(p) => Some(p.clone()),
+            None => None,
+        };
+        match path {
+            Some(p) => {
+                let s = p.to_string_lossy().into_owned();
+                self.mem.strings_allocated += 1;
+                self.mem.bytes_allocated += s.len();
+                Ok(Value::Str(s))
+            }
+            None => Ok(Value::Unit),
+        }
+    }
+
+    /// Panic function - unconditionally raises a runtime error carrying the given message
+    fn call_panic(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
+        if args.len() != 1 { return error("panic() expects exactly 1 argument"); }
+        let msg = self.eval_expr(env, &args[0])?;
+        error(msg.to_string())
+    }
+
+    /// `assert(cond)` or `assert(cond, msg)`: errors when `cond` is false, like `panic()`
+    fn call_assert(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
+        if args.is_empty() || args.len() > 2 { return error("assert() expects 1 or 2 arguments: condition and optional message"); }
+        match self.eval_expr(env, &args[0])? {
+            Value::Bool(true) => Ok(Value::Unit),
+            Value::Bool(false) => match args.get(1) {
+                Some(m) => error(format!("assertion failed: {}", self.eval_expr(env, m)?)),
+                None => error(builtins::ASSERTION_FAILED),
+            },
+            other => error(format!("assert() condition must be bool, got {:?}", other)),
+        }
+    }
+
+    /// `assert_eq(a, b)`: errors showing both values unless `a == b`
+    fn call_assert_eq(&mut self, env: &mut Env<'_>, args: &[Expr]) -> Result<Value> {
+        if args.len() != 2 { return error("assert_eq() expects exactly 2 arguments"); }
+        let a = self.eval_expr(env, &args[0])?;
+        let b = self.eval_expr(env, &args[1])?;
+        if !a.lang_eq(&b) { return error(format!("assertion failed: {} != {}", a, b)); }
+        Ok(Value::Unit)
+    }
 }
 
@@ -11,7 +11,7 @@ pub mod interpreter;
 
 pub use value::Value;
 pub use env::Env;
-pub use interpreter::{Interpreter, MemoryStats};
+pub use interpreter::{HostFn, Interpreter, MemoryStats};
 
 #[cfg(test)]
 mod tests {
@@ -44,9 +44,8 @@ mod tests {
     }
 
     fn expect_error(input: &str) {
-        match run_program(input) {
-            Ok(_) => panic!("Expected error but program succeeded: {}", input),
-            Err(_) => {}, // Good, we expected an error
+        if run_program(input).is_ok() {
+            panic!("Expected error but program succeeded: {}", input);
         }
     }
 
@@ -118,6 +117,228 @@ mod tests {
         expect_value("if true: 42 end", Value::Int(42));
     }
 
+    #[test]
+    fn test_memory_stats_count_lists() {
+        let run = |src: &str| {
+            let mut interp = Interpreter::new();
+            let program = Parser::new(Lexer::new(src).tokenize().unwrap()).parse_program().unwrap();
+            interp.run(program).unwrap();
+            interp.memory_stats()
+        };
+        let slot = std::mem::size_of::<Value>();
+
+        let none = run("let n = 1 + 2");
+        assert_eq!((none.lists_allocated, none.list_elements_allocated, none.list_bytes_allocated), (0, 0, 0));
+
+        // Two literals and their concatenation
+        let stats = run("let xs = [1, 2, 3]\nlet ys = xs + [4]");
+        assert_eq!(stats.lists_allocated, 3);
+        assert_eq!(stats.list_elements_allocated, 3 + 1 + 4);
+        assert_eq!(stats.list_bytes_allocated, 8 * slot);
+        assert_eq!(stats.total_bytes(), stats.bytes_allocated + 8 * slot);
+
+        // push() grows a list in place; slice() and split() build new ones
+        let stats = run("let xs = []\nfor i in 0..10: push(xs, i) end\nlet s = slice(xs, 0, 4)\nlet p = split(\"a,b\", \",\")");
+        assert_eq!(stats.lists_allocated, 3);
+        assert_eq!(stats.list_elements_allocated, 10 + 4 + 2);
+
+        let mut interp = Interpreter::new();
+        interp.run(Parser::new(Lexer::new("[1, 2]").tokenize().unwrap()).parse_program().unwrap()).unwrap();
+        interp.reset();
+        assert_eq!(interp.memory_stats().lists_allocated, 0);
+    }
+
+    #[test]
+    fn test_memory_stats_count_string_results() {
+        let run = |src: &str| {
+            let mut interp = Interpreter::new();
+            let program = Parser::new(Lexer::new(src).tokenize().unwrap()).parse_program().unwrap();
+            interp.run(program).unwrap();
+            interp.memory_stats()
+        };
+        let base = run("let s = \"a-b-c\"");
+        // replace() builds a new string; starts_with() and ends_with() only compare
+        let replaced = run("let s = \"a-b-c\"\nlet t = replace(s, \"-\", \"--\")");
+        assert_eq!(replaced.strings_allocated, base.strings_allocated + 3);
+        assert_eq!(replaced.bytes_allocated, base.bytes_allocated + 1 + 2 + "a--b--c".len());
+        let compared = run("let s = \"a-b-c\"\nlet t = starts_with(s, \"a\") && ends_with(s, \"c\")");
+        assert_eq!(compared.bytes_allocated, base.bytes_allocated + 2);
+    }
+
+    #[test]
+    fn test_contains_and_index_of() {
+        expect_value("contains([1, 2, 3], 2)", Value::Bool(true));
+        expect_value("contains([1, 2, 3], 4)", Value::Bool(false));
+        expect_value("contains([1, [2]], [2])", Value::Bool(true));
+        expect_value("contains([1, 2], 2.0)", Value::Bool(true));
+        expect_value("contains(\"hello\", \"ell\")", Value::Bool(true));
+        expect_value("index_of(\"hello\", \"ll\")", Value::Int(2));
+        expect_value("index_of(\"héllo\", \"llo\")", Value::Int(2));
+        expect_value("index_of(\"hello\", \"z\")", Value::Int(-1));
+        expect_value("index_of([\"a\", \"b\", \"b\"], \"b\")", Value::Int(1));
+        expect_value("index_of([], 1)", Value::Int(-1));
+        assert_eq!(run_program("contains(5, 1)").unwrap_err(), "Runtime error: contains() expects list or string, got Int(5)");
+        assert_eq!(
+            run_program("index_of(\"abc\", 1)").unwrap_err(),
+            "Runtime error: index_of() needle must be string when searching a string, got Int(1)"
+        );
+    }
+
+    #[test]
+    fn test_bool_conversion() {
+        for (src, expected) in [
+            ("bool(1)", true), ("bool(-3)", true), ("bool(0)", false),
+            ("bool(0.5)", true), ("bool(0.0)", false),
+            ("bool(\"true\")", true), ("bool(\"false\")", false), ("bool(\"\")", false),
+            ("bool([0])", true), ("bool([])", false),
+            ("bool(true)", true), ("bool(false)", false),
+            ("bool(show(1))", false),
+        ] {
+            expect_value(src, Value::Bool(expected));
+        }
+        assert_eq!(run_program("bool(\"maybe\")").unwrap_err(), "Runtime error: Cannot convert 'maybe' to bool");
+        assert_eq!(run_program("bool(\"True\")").unwrap_err(), "Runtime error: Cannot convert 'True' to bool");
+        expect_error("bool()");
+        expect_error("bool(1, 2)");
+    }
+
+    #[test]
+    fn test_format_returns_string() {
+        expect_value("format(\"%d + %d = %v\", 1, 2, [3])", Value::Str("1 + 2 = [3]".to_string()));
+        expect_value("let s = format(\"%s!\", \"hi\")\nlen(s)", Value::Int(3));
+        assert_eq!(
+            run_program("format(\"%d and %d\", 1)").unwrap_err(),
+            "Runtime error: format: format expects at least 2 arguments but got 1 (missing %d at position 7)"
+        );
+        assert_eq!(run_program("format()").unwrap_err(), "Runtime error: format requires at least a format string");
+        assert_eq!(run_program("format(1)").unwrap_err(), "Runtime error: format first argument must be a string");
+    }
+
+    #[test]
+    fn test_range_step() {
+        let list = |xs: &[i64]| Value::List(xs.iter().map(|&x| Value::Int(x)).collect());
+        expect_value("let a = []\nfor i in 0..10 step 3: a += [i] end\na", list(&[0, 3, 6, 9]));
+        expect_value("let a = []\nfor i in 10..0 step -2: a += [i] end\na", list(&[10, 8, 6, 4, 2]));
+        expect_value("let a = []\nfor i in 10..=0 step -5: a += [i] end\na", list(&[10, 5, 0]));
+        expect_value("let a = []\nfor i in 0..10 step -1: a += [i] end\na", list(&[]));
+        expect_value("let n = 0\nfor i in 0..9223372036854775807 step 9223372036854775807: n += 1 end\nn", Value::Int(1));
+        assert_eq!(run_program("for i in 0..3 step 0: show(i) end").unwrap_err(), "Runtime error: for step cannot be zero");
+        assert_eq!(run_program("for i in 0..3 step 0.5: show(i) end").unwrap_err(), "Runtime error: for step must be int, got Float(0.5)");
+    }
+
+    #[test]
+    fn test_inclusive_range() {
+        expect_value("let n = 0\nfor i in 0..3: n += 1 end\nn", Value::Int(3));
+        expect_value("let n = 0\nfor i in 0..=3: n += 1 end\nn", Value::Int(4));
+        expect_value("let n = 0\nfor i in 0..=0: n += 1 end\nn", Value::Int(1));
+        expect_value("let n = 0\nfor i in 1..=0: n += 1 end\nn", Value::Int(0));
+        expect_value("let s = 0\nfor i in 1..=10: s += i end\ns", Value::Int(55));
+    }
+
+    #[test]
+    fn test_for_each() {
+        expect_value("let total = 0\nfor x in [1, 2, 3]: total = total + x end\ntotal", Value::Int(6));
+        expect_value("let out = \"\"\nfor c in \"héllo\": out = c + out end\nout", Value::Str("olléh".to_string()));
+        expect_value("let n = 0\nfor x in []: n = n + 1 end\nn", Value::Int(0));
+        expect_value("let s = 0\nfor x in [1, 2, 3, 4]: if x == 2: continue end if x == 4: break end s = s + x end\ns", Value::Int(4));
+        assert_eq!(run_program("for x in 3: show(x) end").unwrap_err(), "Runtime error: for-in expects list or string, got Int(3)");
+        assert_eq!(run_program("for x in true: show(x) end").unwrap_err(), "Runtime error: for-in expects list or string, got Bool(true)");
+    }
+
+    #[test]
+    fn test_integer_overflow() {
+        let max = "let max = 9223372036854775807\n";
+        for (expr, op) in [("max + 1", "addition"), ("0 - max - 2", "subtraction"), ("max * 2", "multiplication"), ("(max / 2 + 1) * (0 - 4)", "multiplication"), ("-(0 - max - 1)", "negation"), ("abs(0 - max - 1)", "abs()")] {
+            assert_eq!(run_program(&format!("{}{}", max, expr)).unwrap_err(), format!("Runtime error: integer overflow in {}", op), "{}", expr);
+        }
+        // Results that fit are exact, right up to the bounds
+        expect_value(&format!("{}max - 1 + 1 == max", max), Value::Bool(true));
+        expect_value(&format!("{}(0 - max - 1) + max", max), Value::Int(-1));
+        expect_value("3037000499 * 3037000499", Value::Int(9223372030926249001));
+    }
+
+    #[test]
+    fn test_maps() {
+        let m = "let m = {\"a\": 1, \"b\": [2, 3], \"c\": \"x\" + \"y\"}\n";
+        expect_value(&format!("{}m[\"a\"]", m), Value::Int(1));
+        expect_value(&format!("{}m[\"b\"][1]", m), Value::Int(3));
+        expect_value(&format!("{}m[\"c\"]", m), Value::Str("xy".to_string()));
+        expect_value(&format!("{}len(m)", m), Value::Int(3));
+        expect_value(&format!("{}type(m)", m), Value::Str("map".to_string()));
+        expect_value(&format!("{}str(m)", m), Value::Str("{a: 1, b: [2, 3], c: xy}".to_string()));
+        expect_value("len({})", Value::Int(0));
+        // Order-insensitive equality; a repeated key keeps its first position and last value
+        expect_value("{\"a\": 1, \"b\": 2} == {\"b\": 2, \"a\": 1}", Value::Bool(true));
+        expect_value("{\"a\": 1} != {\"a\": 2}", Value::Bool(true));
+        expect_value("str({\"a\": 1, \"b\": 2, \"a\": 3})", Value::Str("{a: 3, b: 2}".to_string()));
+
+        expect_value(&format!("{}keys(m)", m), Value::List(vec![Value::Str("a".to_string()), Value::Str("b".to_string()), Value::Str("c".to_string())]));
+        expect_value("values({\"x\": 1, \"y\": true})", Value::List(vec![Value::Int(1), Value::Bool(true)]));
+        expect_value("keys({})", Value::List(vec![]));
+
+        assert_eq!(run_program(&format!("{}m[\"z\"]", m)).unwrap_err(), "Runtime error: key 'z' not found in map");
+        assert_eq!(run_program("keys([1])").unwrap_err(), "Runtime error: keys() expects map, got List([Int(1)])");
+        assert_eq!(run_program(&format!("{}m[0]", m)).unwrap_err(), "Runtime error: map key must be string, got Int(0)");
+        assert_eq!(run_program("{1: 2}").unwrap_err(), "Runtime error: map keys must be strings, got Int(1)");
+    }
+
+    #[test]
+    fn test_index_assign() {
+        expect_value("let a = [1,2,3]\na[1] = 9\na == [1,9,3]", Value::Bool(true));
+        expect_value("let g = [[1, 2], [3, 4]]\ng[1][0] = 7\ng", Value::List(vec![
+            Value::List(vec![Value::Int(1), Value::Int(2)]),
+            Value::List(vec![Value::Int(7), Value::Int(4)]),
+        ]));
+        expect_value("let s = \"cat\"\ns[0] = \"b\"\ns", Value::Str("bat".to_string()));
+        expect_unit("let a = [1]\na[0] = 2");
+        assert_eq!(run_program("let a = [1,2,3]\na[3] = 0").unwrap_err(), "Runtime error: index out of bounds");
+        assert_eq!(run_program("let a = [1]\na[-1] = 0").unwrap_err(), "Runtime error: index out of bounds");
+        assert_eq!(
+            run_program("let s = \"cat\"\ns[0] = \"ab\"").unwrap_err(),
+            "Runtime error: string index assignment expects a 1-character string, got Str(\"ab\")"
+        );
+        assert_eq!(run_program("let n = 5\nn[0] = 1").unwrap_err(), "Runtime error: index assignment not supported for Int(5)");
+        expect_error("[1, 2][0] = 5");
+    }
+
+    #[test]
+    fn test_loop_variable_is_scoped_to_the_loop() {
+        assert_eq!(run_program("for i in 0..5: if i == 2: break end end\ni").unwrap_err(), "Runtime error: Undefined variable 'i'");
+        assert_eq!(run_program("for x in [1, 2]: break end\nx").unwrap_err(), "Runtime error: Undefined variable 'x'");
+        expect_value("let i = 100\nfor i in 0..5: if i == 2: break end end\ni", Value::Int(100));
+        expect_value("let x = \"outer\"\nfor x in [1, 2]: x = x * 10 end\nx", Value::Str("outer".to_string()));
+        expect_value("fun f(): let i = 9\nfor i in 0..3: break end\nreturn i end\nf()", Value::Int(9));
+        // Restored when the loop body fails, so a REPL session keeps the outer value
+        let mut env = Env::new_root();
+        let mut interp = Interpreter::new();
+        let program = |src: &str| Parser::new(Lexer::new(src).tokenize().unwrap()).parse_program().unwrap();
+        interp.run_with_env(program("let i = 7"), &mut env).unwrap();
+        assert!(interp.run_with_env(program("for i in 0..3: panic(\"boom\") end"), &mut env).is_err());
+        assert_eq!(interp.run_with_env(program("i"), &mut env).unwrap(), Some(Value::Int(7)));
+    }
+
+    #[test]
+    fn test_compound_assign() {
+        expect_value("let i = 0\ni += 5\ni", Value::Int(5));
+        expect_value("let i = 10\ni -= 2 + 1\ni *= 2\ni /= 7\ni", Value::Int(2));
+        expect_value("let s = \"a\"\ns += \"b\"\ns", Value::Str("ab".to_string()));
+        expect_value("let xs = [1]\nxs += [2]\nxs", Value::List(vec![Value::Int(1), Value::Int(2)]));
+        expect_value("let n = 0\nfor i in 1..4: n += i end\nn", Value::Int(6));
+        expect_error("undefined += 1");
+        expect_error("let i = 1\ni /= 0");
+    }
+
+    #[test]
+    fn test_elif() {
+        let classify = "fun classify(n):\n  if n < 0: return \"neg\"\n  elif n == 0: return \"zero\"\n  elif n < 10: return \"small\"\n  else: return \"big\"\n  end\nend\n";
+        for (n, expected) in [("-5", "neg"), ("0", "zero"), ("7", "small"), ("42", "big")] {
+            expect_value(&format!("{}classify({})", classify, n), Value::Str(expected.to_string()));
+        }
+        // Without a final else, no branch runs when every condition is false
+        expect_value("let x = 0\nif false: x = 1 elif false: x = 2 end\nx", Value::Int(0));
+        expect_value("let x = 0\nif false: x = 1 elif true: x = 2 end\nx", Value::Int(2));
+    }
+
     #[test]
     fn test_loops() {
         expect_unit("while false: break end"); // Never executes body
@@ -152,6 +373,437 @@ mod tests {
         expect_error("len(42)"); // len expects string or list
     }
 
+    #[test]
+    fn test_negation() {
+        expect_value("-5 == 0 - 5", Value::Bool(true));
+        expect_value("let x = 3\n-x == -3", Value::Bool(true));
+        expect_value("-(2 * 3) == -6", Value::Bool(true));
+        expect_value("--4", Value::Int(4));
+        expect_value("- -5", Value::Int(5));
+        expect_value("let x = -3\n-x", Value::Int(3));
+        expect_value("let xs = [1, 2]\n-len(xs)", Value::Int(-2));
+        expect_value("-1.5", Value::Float(-1.5));
+        assert_eq!(run_program("-\"a\""), Err("Runtime error: Cannot negate Str(\"a\")".to_string()));
+        expect_error("-true");
+    }
+
+    #[test]
+    fn test_bitwise() {
+        expect_value("6 & 3 == 2", Value::Bool(true));
+        expect_value("6 | 3", Value::Int(7));
+        expect_value("6 ^ 3", Value::Int(5));
+        expect_value("1 << 4 == 16", Value::Bool(true));
+        expect_value("-16 >> 2", Value::Int(-4));
+        // `~` starts a comment, so bitwise not is spelled `x ^ -1`
+        expect_value("0 ^ -1 == -1", Value::Bool(true));
+        expect_value("1 << 63", Value::Int(i64::MIN));
+        assert_eq!(run_program("1 << 64"), Err("Runtime error: shift amount 64 out of range 0..64".to_string()));
+        assert_eq!(run_program("1 >> -1"), Err("Runtime error: shift amount -1 out of range 0..64".to_string()));
+        assert_eq!(run_program("1 & true"), Err("Runtime error: & expects ints, got Int(1) and Bool(true)".to_string()));
+        expect_error("1.0 | 2");
+    }
+
+    #[test]
+    fn test_modulo() {
+        expect_value("10 % 3", Value::Int(1));
+        expect_value("10 % 3 == 1", Value::Bool(true));
+        expect_value("1 + 7 % 4", Value::Int(4));
+        expect_value("(0 - 7) % 2", Value::Int(-1));
+        assert_eq!(run_program("5 % 0"), Err("Runtime error: division by zero".to_string()));
+        assert_eq!(run_program("5 / 0"), Err("Runtime error: division by zero".to_string()));
+        expect_error("10 / 0");
+        expect_error("10 / 0.0");
+        // The one int quotient that overflows is an error, not an abort
+        assert_eq!(run_program("let min = 0 - 9223372036854775807 - 1\nmin / (0 - 1)").unwrap_err(), "Runtime error: integer overflow in division");
+        expect_error("5.5 % 2");
+        expect_error("\"a\" % 2");
+    }
+
+    #[test]
+    fn test_float_arithmetic() {
+        expect_value("1.5 + 2.25", Value::Float(3.75));
+        expect_value("8 / 2.0", Value::Float(4.0));
+        expect_value("8 / 3", Value::Int(2));
+        expect_value("2 * 0.5 - 1", Value::Float(0.0));
+        expect_value("1 == 1.0", Value::Bool(true));
+        expect_value("[1, 2] == [1.0, 2]", Value::Bool(true));
+        expect_value("1.5 < 2", Value::Bool(true));
+        expect_value("2 >= 2.5", Value::Bool(false));
+        expect_value("sqrt(16)", Value::Float(4.0));
+        expect_value("sqrt(2.25)", Value::Float(1.5));
+        expect_value("pow(2.0, 3)", Value::Float(8.0));
+        expect_value("pow(2, 3)", Value::Int(8));
+        expect_value("abs(0.0 - 2.5)", Value::Float(2.5));
+        expect_value("min(1, 0.5)", Value::Float(0.5));
+        expect_value("type(1.0e-5)", Value::Str("float".to_string()));
+        expect_value("str(3.0)", Value::Str("3.0".to_string()));
+        expect_value("str(0.1 + 0.2)", Value::Str("0.30000000000000004".to_string()));
+        expect_value("floor(3.7)", Value::Int(3));
+        expect_value("ceil(3.2)", Value::Int(4));
+        expect_value("round(2.5)", Value::Int(3));
+        expect_value("round(0.0 - 2.5)", Value::Int(-3));
+        expect_value("floor(0.0 - 0.5)", Value::Int(-1));
+        expect_value("ceil(7)", Value::Int(7));
+        expect_error("round(\"1.5\")");
+        expect_error("floor(1.0e300)");
+        expect_value("int(2.9)", Value::Int(2));
+        expect_value("int(0.0 - 2.9)", Value::Int(-2));
+        expect_value("let x: float = 1.0\nx", Value::Float(1.0));
+        expect_value("let x: float = 2.5\nx", Value::Float(2.5));
+        expect_error("let x: int = 1.0");
+        expect_error("let x: float = 2");
+        expect_error("1.0 / 0");
+        expect_error("int(1.0e300)");
+        expect_error("1.5 + \"a\"");
+    }
+
+    #[test]
+    fn test_pow_is_exact_and_checks_overflow() {
+        // 3^39 needs 62 bits, more than an f64 mantissa holds
+        expect_value("pow(3, 39)", Value::Int(4052555153018976267));
+        expect_value("pow(0 - 2, 63)", Value::Int(i64::MIN));
+        expect_value("pow(1, 9000000000000000000)", Value::Int(1));
+        expect_value("pow(7, 0)", Value::Int(1));
+        match run_program("pow(3, 40)") {
+            Err(e) => assert_eq!(e, "Runtime error: integer overflow in pow"),
+            Ok(v) => panic!("expected overflow, got {:?}", v),
+        }
+        expect_error("pow(2, 64)");
+    }
+
+    #[test]
+    fn test_to_base_and_prefixes() {
+        expect_value("to_base(255, 16)", Value::Str("ff".to_string()));
+        expect_value("to_base(10, 2)", Value::Str("1010".to_string()));
+        expect_value("to_base(35, 36)", Value::Str("z".to_string()));
+        expect_value("to_base(0, 7)", Value::Str("0".to_string()));
+        expect_value("to_base(0 - 255, 16)", Value::Str("-ff".to_string()));
+        expect_value("hex(255)", Value::Str("0xff".to_string()));
+        expect_value("hex(255, false)", Value::Str("ff".to_string()));
+        expect_value("bin(0 - 5)", Value::Str("-0b101".to_string()));
+        expect_value("bin(5, false)", Value::Str("101".to_string()));
+        expect_error("to_base(10, 1)");
+        expect_error("to_base(10, 37)");
+        expect_error("hex(255, 1)");
+    }
+
+    #[test]
+    fn test_multibyte_strings_use_char_positions() {
+        expect_value("len(\"héllo🎉\")", Value::Int(6));
+        expect_value("\"héllo🎉\"[1]", Value::Str("é".to_string()));
+        expect_value("\"héllo🎉\"[5]", Value::Str("🎉".to_string()));
+        expect_value("slice(\"héllo🎉\", 1, 5)", Value::Str("éllo".to_string()));
+        expect_value("slice(\"héllo🎉\", 5, 6)", Value::Str("🎉".to_string()));
+        expect_value(
+            "split(\"ä,🎉,ö\", \",\")",
+            Value::List(vec![Value::Str("ä".to_string()), Value::Str("🎉".to_string()), Value::Str("ö".to_string())]),
+        );
+        // Every index below len() round-trips through slice()
+        let round_trip = r#"
+            let s = "aé🎉z"
+            let ok = true
+            for i in 0..len(s):
+                if slice(s, i, i + 1) != s[i]:
+                    ok = false
+                end
+            end
+            ok
+        "#;
+        expect_value(round_trip, Value::Bool(true));
+    }
+
+    fn run_with(interpreter: &mut Interpreter, input: &str) -> zirc_syntax::error::Result<Option<Value>> {
+        let tokens = Lexer::new(input).tokenize()?;
+        let program = Parser::new(tokens).parse_program()?;
+        interpreter.run_with_env(program, &mut Env::new_root())
+    }
+
+    #[test]
+    fn test_vars_snapshot_is_sorted() {
+        let src = "let zeta = 1\nlet alpha = 2\nlet mid = 3\nlet beta = 4";
+        for _ in 0..5 {
+            let mut env = Env::new_root();
+            let program = Parser::new(Lexer::new(src).tokenize().unwrap()).parse_program().unwrap();
+            Interpreter::new().run_with_env(program, &mut env).unwrap();
+            let names: Vec<String> = env.vars_snapshot().into_iter().map(|(k, _)| k).collect();
+            assert_eq!(names, ["alpha", "beta", "mid", "zeta"]);
+        }
+    }
+
+    #[test]
+    fn test_register_builtin() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register_builtin("double", Box::new(|args: &[Value]| match args {
+            [Value::Int(n)] => Ok(Value::Int(n * 2)),
+            _ => zirc_syntax::error::error("double() expects one int"),
+        })).unwrap();
+
+        let result = run_with(&mut interpreter, "let x = double(21)\ndouble(x)").unwrap();
+        assert_eq!(result, Some(Value::Int(84)));
+        assert_eq!(run_with(&mut interpreter, "double(\"a\")").unwrap_err().msg, "double() expects one int");
+
+        // Host builtins survive reset() and cannot be shadowed by script functions
+        interpreter.reset();
+        assert_eq!(run_with(&mut interpreter, "double(1)").unwrap(), Some(Value::Int(2)));
+        assert!(run_with(&mut interpreter, "fun double(x):\n return x\nend").is_err());
+    }
+
+    #[test]
+    fn test_runtime_error_spans() {
+        let span_of = |src: &str| {
+            let err = run_with(&mut Interpreter::new(), src).unwrap_err();
+            (err.msg, err.line, err.col)
+        };
+        assert_eq!(span_of("let a = 1\nshow(a + b)"), ("Undefined variable 'b'".to_string(), Some(2), Some(10)));
+        assert_eq!(span_of("let xs = [1]\n  xs[3]"), ("index out of bounds".to_string(), Some(2), Some(5)));
+        assert_eq!(span_of("show(1)\nlen(5)"), ("len() expects string, list or map, got Int(5)".to_string(), Some(2), Some(1)));
+        // The innermost located node wins over the enclosing call
+        assert_eq!(span_of("show(len([1])[0])"), ("indexing not supported for Int(1)".to_string(), Some(1), Some(14)));
+        assert_eq!(span_of("show(1 + nope)").2, Some(10));
+        assert_eq!(span_of("let n = 1\nn += missing").2, Some(6));
+        assert_eq!(span_of("missing += 1").2, Some(1));
+        // Errors with no located node inside still point at the nearest call
+        assert_eq!(span_of("fun f(x): x / 0 end\nshow(1)\nf(1)"), ("division by zero".to_string(), Some(3), Some(1)));
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_max_depth(5);
+        run_with(&mut interpreter, "fun down(n): if n > 0: down(n - 1) end\nn end").unwrap();
+        assert_eq!(run_with(&mut interpreter, "down(4)").unwrap(), Some(Value::Int(4)));
+        assert_eq!(run_with(&mut interpreter, "down(5)").unwrap_err().msg, "maximum recursion depth exceeded (5)");
+
+        // Unwinding from the error releases the depth it used
+        assert_eq!(run_with(&mut interpreter, "down(4)").unwrap(), Some(Value::Int(4)));
+    }
+
+    #[test]
+    fn test_run_is_stateless() {
+        let program = |src: &str| Parser::new(Lexer::new(src).tokenize().unwrap()).parse_program().unwrap();
+        let mut interp = Interpreter::new();
+        interp.run(program("fun f(): 1 end\nlet g = 1\nf()")).unwrap();
+        assert_eq!(interp.run(program("f()")).unwrap_err().msg, "Undefined function 'f'");
+        assert!(interp.run(program("g")).is_err());
+        // The same function can be defined again by the next program
+        interp.run(program("fun f(): 2 end\nf()")).unwrap();
+        assert!(interp.function_names().is_empty());
+
+        // Nor does run() see or disturb the eval() session
+        interp.eval(program("fun h(): 3 end")).unwrap();
+        assert!(interp.run(program("h()")).is_err());
+        interp.run(program("fun h(): 4 end")).unwrap();
+        assert_eq!(interp.eval(program("h()")).unwrap(), Some(Value::Int(3)));
+    }
+
+    #[test]
+    fn test_eval_keeps_session() {
+        let program = |src: &str| Parser::new(Lexer::new(src).tokenize().unwrap()).parse_program().unwrap();
+        let mut interp = Interpreter::new();
+        interp.eval(program("fun double(x): x * 2 end\nlet n = 21")).unwrap();
+        assert_eq!(interp.eval(program("double(n)")).unwrap(), Some(Value::Int(42)));
+        // A failed eval keeps what was defined before the error
+        assert!(interp.eval(program("let m = 1\npanic(\"boom\")")).is_err());
+        assert_eq!(interp.eval(program("n + m")).unwrap(), Some(Value::Int(22)));
+
+        interp.reset();
+        assert!(interp.eval(program("n")).is_err());
+        assert!(interp.eval(program("double(1)")).is_err());
+    }
+
+    #[test]
+    fn test_duplicate_function_is_rejected() {
+        expect_error("fun f(x): x end\nfun f(x, y): x + y end");
+        expect_error("fun f(x): x end\nfun f(y): y end");
+
+        // Redefining in a later run on the same interpreter is also rejected
+        let mut interpreter = Interpreter::new();
+        run_with(&mut interpreter, "fun f(x): x end").unwrap();
+        assert_eq!(run_with(&mut interpreter, "fun f(x, y): x end").unwrap_err().msg, "Duplicate function 'f'");
+        interpreter.reset();
+        run_with(&mut interpreter, "fun f(x, y): x end").unwrap();
+    }
+
+    #[test]
+    fn test_register_builtin_name_collisions() {
+        let noop = || -> HostFn { Box::new(|_: &[Value]| Ok(Value::Unit)) };
+        let mut interpreter = Interpreter::new();
+        assert!(interpreter.register_builtin("len", noop()).is_err());
+        run_with(&mut interpreter, "fun helper():\n return 1\nend").unwrap();
+        assert!(interpreter.register_builtin("helper", noop()).is_err());
+        interpreter.register_builtin("spawn_entity", noop()).unwrap();
+        assert!(interpreter.register_builtin("spawn_entity", noop()).is_err());
+    }
+
+    #[test]
+    fn test_showf_argument_errors() {
+        assert_eq!(
+            run_program("showf(\"%s has %d items, total %d\", \"cart\", 3)"),
+            Err("Runtime error: showf: format expects at least 3 arguments but got 2 (missing %d at position 23)".to_string())
+        );
+        assert_eq!(
+            run_program("showf(\"é %s\")"),
+            Err("Runtime error: showf: format expects at least 1 arguments but got 0 (missing %s at position 2)".to_string())
+        );
+        assert_eq!(run_program("showf(\"%d\", \"x\")"), Err("Runtime error: %d at position 0 expects int, got Str(\"x\")".to_string()));
+        assert_eq!(run_program("showf(\"ab%\")"), Err("Runtime error: Dangling % at end of format string (position 2)".to_string()));
+    }
+
+    #[test]
+    fn test_script_path_builtins() {
+        // No script path outside the CLI runner (e.g. REPL, embedding)
+        expect_value("script_path()", Value::Unit);
+        expect_value("script_dir()", Value::Unit);
+        expect_error("script_dir(1)");
+
+        let mut interpreter = Interpreter::new();
+        interpreter.set_script_path("data/jobs/run.zirc");
+        let dir = run_with(&mut interpreter, "script_dir()").unwrap();
+        assert_eq!(dir, Some(Value::Str(std::path::Path::new("data/jobs").to_string_lossy().into_owned())));
+        let path = run_with(&mut interpreter, "script_path()").unwrap();
+        assert_eq!(path, Some(Value::Str(std::path::Path::new("data/jobs/run.zirc").to_string_lossy().into_owned())));
+    }
+
+    #[test]
+    fn test_copy_is_independent() {
+        // Lists are values: pushing onto a copy leaves the original untouched
+        let src = "let a = [1, [2]]\nlet b = copy(a)\npush(b, 3)\nstr(a) + \" \" + str(b)";
+        expect_value(src, Value::Str("[1, [2]] [1, [2], 3]".to_string()));
+        expect_value("copy(\"s\") == \"s\"", Value::Bool(true));
+        expect_error("copy()");
+    }
+
+    #[test]
+    fn test_time_ns_and_bench() {
+        let elapsed = run_program("let t0 = time_ns()\nlet t1 = time_ns()\nt1 - t0").unwrap();
+        assert!(matches!(elapsed, Some(Value::Int(d)) if d >= 0));
+        expect_error("time_ns(1)");
+
+        let avg = run_program("fun work():\n return 1 + 1\nend\nbench(work, 50)").unwrap();
+        assert!(matches!(avg, Some(Value::Int(ns)) if ns > 0), "{:?}", avg);
+        expect_error("fun work():\n return 1\nend\nbench(work, 0)");
+        expect_error("fun one(a):\n return a\nend\nbench(one, 5)");
+        expect_error("bench(nope, 5)");
+    }
+
+    #[test]
+    fn test_reduce() {
+        let add = "fun add(a, b):\n return a + b\nend\n";
+        expect_value(&format!("{}reduce([1, 2, 3], add)", add), Value::Int(6));
+        expect_value(&format!("{}reduce([1, 2, 3], add, 10)", add), Value::Int(16));
+        expect_value(&format!("{}reduce([], add, 10)", add), Value::Int(10));
+        expect_value(&format!("{}reduce([5], add)", add), Value::Int(5));
+        expect_value(&format!("{}reduce([\"a\", \"b\", \"c\"], add)", add), Value::Str("abc".to_string()));
+        assert_eq!(
+            run_program(&format!("{}reduce([], add)", add)),
+            Err("Runtime error: reduce() of empty list with no initial value".to_string())
+        );
+        expect_error(&format!("{}reduce(5, add)", add));
+        expect_error("reduce([1, 2], nope)");
+        expect_error("fun one(a):\n return a\nend\nreduce([1, 2], one)");
+    }
+
+    #[test]
+    fn test_left_and_right() {
+        expect_value("left(\"hello\", 3) == \"hel\"", Value::Bool(true));
+        expect_value("right(\"hello\", 2) == \"lo\"", Value::Bool(true));
+        expect_value("left(\"hi\", 10)", Value::Str("hi".to_string()));
+        expect_value("right(\"hi\", 10)", Value::Str("hi".to_string()));
+        expect_value("right(\"hi\", 0)", Value::Str(String::new()));
+        expect_value("left([1, 2, 3], 2)", Value::List(vec![Value::Int(1), Value::Int(2)]));
+        expect_value("right([1, 2, 3], 5)", Value::List(vec![Value::Int(1), Value::Int(2), Value::Int(3)]));
+        assert_eq!(run_program("left(\"abc\", -1)"), Err("Runtime error: left() count cannot be negative".to_string()));
+        expect_error("right(5, 1)");
+        expect_error("left(\"abc\")");
+    }
+
+    #[test]
+    fn test_get_and_set() {
+        expect_value("get([1, 2], 1, 0)", Value::Int(2));
+        expect_value("get([1, 2], 5, 0)", Value::Int(0));
+        expect_value("get([1, 2], 0 - 1, \"none\")", Value::Str("none".to_string()));
+        expect_value("get([], 0, [])", Value::List(vec![]));
+        // set() returns a new list and leaves its argument alone
+        expect_value("let a = [1, 2]\nlet b = set(a, 0, 9)\nstr(a) + str(b)", Value::Str("[1, 2][9, 2]".to_string()));
+        assert_eq!(run_program("set([1], 1, 0)"), Err("Runtime error: index out of bounds".to_string()));
+        expect_error("get(\"ab\", 0, 0)");
+        expect_error("get([1], \"0\", 0)");
+        expect_error("set([1], 0)");
+        expect_value("get({\"a\": 1}, \"a\", 0)", Value::Int(1));
+        expect_value("get({\"a\": 1}, \"b\", 0)", Value::Int(0));
+        expect_error("get({\"a\": 1}, 0, 0)");
+        expect_error("fun get(x):\n return x\nend\nget(1)");
+    }
+
+    #[test]
+    fn test_map() {
+        let double = "fun double(x):\n return x * 2\nend\n";
+        expect_value(&format!("{}map(double, [1, 2, 3]) == [2, 4, 6]", double), Value::Bool(true));
+        expect_value(&format!("{}len(map(double, []))", double), Value::Int(0));
+        expect_value(&format!("{}map(double, map(double, [1]))", double), Value::List(vec![Value::Int(4)]));
+        assert_eq!(run_program("map(nope, [1])"), Err("Runtime error: Undefined function 'nope'".to_string()));
+        assert_eq!(
+            run_program("fun add(a, b):\n return a + b\nend\nmap(add, [1])"),
+            Err("Runtime error: map() function 'add' must take 1 arg, takes 2".to_string())
+        );
+        expect_error(&format!("{}map(double, 5)", double));
+        expect_error("map(len, [\"a\"])");
+    }
+
+    #[test]
+    fn test_let_unpack() {
+        expect_value("let a, b = [1, 2]\na * 10 + b", Value::Int(12));
+        let from_function = r#"
+            fun divmod(a, b):
+                return [a / b, a - (a / b) * b]
+            end
+            let q, r = divmod(17, 5)
+            q * 10 + r
+        "#;
+        expect_value(from_function, Value::Int(32));
+        let multi_return = r#"
+            fun divmod(a, b):
+                return a / b, a % b
+            end
+            let q, r = divmod(17, 5)
+            q * 10 + r
+        "#;
+        expect_value(multi_return, Value::Int(32));
+        expect_value("fun pair():\n return 1, [2]\nend\npair()", Value::List(vec![Value::Int(1), Value::List(vec![Value::Int(2)])]));
+        expect_value("let a, b = [[1], \"x\"]\nb", Value::Str("x".to_string()));
+        assert_eq!(run_program("let a, b = [1, 2, 3]"), Err("Runtime error: cannot unpack list of length 3 into 2 names".to_string()));
+        assert_eq!(run_program("let a, b = [1]"), Err("Runtime error: cannot unpack list of length 1 into 2 names".to_string()));
+        expect_error("let a, b = 5");
+    }
+
+    #[test]
+    fn test_underscore_discards() {
+        expect_value("let _, x = [1, 2]\nx", Value::Int(2));
+        expect_value("let _ = 1\nlet _ = 2\nlet _, _ = [3, 4]\n5", Value::Int(5));
+        expect_value("fun second(_, b):\n return b\nend\nsecond(1, 2)", Value::Int(2));
+        expect_value("let n = 0\nfor _ in 0..3:\n n = n + 1\nend\nn", Value::Int(3));
+        assert_eq!(run_program("let _ = 1\n_"), Err("Runtime error: Undefined variable '_'".to_string()));
+    }
+
+    #[test]
+    fn test_panic() {
+        assert_eq!(run_program("panic(\"boom\")"), Err("Runtime error: boom".to_string()));
+        assert_eq!(run_program("panic(42)"), Err("Runtime error: 42".to_string()));
+        let guarded = r#"
+            fun check(n):
+                if n < 0:
+                    panic("negative input")
+                end
+                return n
+            end
+            check(1)
+            check(0 - 1)
+            show("unreachable")
+        "#;
+        assert_eq!(run_program(guarded), Err("Runtime error: negative input".to_string()));
+        expect_error("panic()");
+    }
+
     #[test]
     fn test_type_checking() {
         expect_value("let x: int = 42\nx", Value::Int(42));
@@ -40,14 +40,6 @@ impl Lexer {
         ch
     }
 
-    fn make_token(&self, kind: TokenKind) -> Token {
-        Token {
-            kind,
-            line: self.line,
-            col: self.col,
-        }
-    }
-
     fn skip_whitespace(&mut self) {
         while let Some(c) = self.peek() {
             if c.is_whitespace() {
@@ -69,6 +61,43 @@ impl Lexer {
         let start_line = self.line;
         let start_col = self.col;
         let mut s = String::new();
+        self.read_digits(&mut s);
+        let mut is_float = false;
+        // A fraction needs a digit after the dot, so `0..n` stays a range
+        if self.peek() == Some('.') && self.peek_next().is_some_and(|c| c.is_ascii_digit()) {
+            is_float = true;
+            s.push('.');
+            self.advance();
+            self.read_digits(&mut s);
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            let sign = matches!(self.peek_next(), Some('+' | '-'));
+            let digit_at = if sign { self.pos + 2 } else { self.pos + 1 };
+            if self.src.get(digit_at).is_some_and(|c| c.is_ascii_digit()) {
+                is_float = true;
+                s.push('e');
+                self.advance();
+                if sign {
+                    s.push(self.advance().unwrap());
+                }
+                self.read_digits(&mut s);
+            }
+        }
+        let invalid = |msg| zirc_syntax::error::Error::with_span(msg, start_line, start_col);
+        let kind = if is_float {
+            TokenKind::Float(s.parse().map_err(|_| invalid("Invalid number"))?)
+        } else {
+            // Only digits were read, so overflow is the one way this can fail
+            TokenKind::Number(s.parse().map_err(|_| invalid("integer literal too large for 64-bit"))?)
+        };
+        Ok(Token {
+            kind,
+            line: start_line,
+            col: start_col,
+        })
+    }
+
+    fn read_digits(&mut self, s: &mut String) {
         while let Some(c) = self.peek() {
             if c.is_ascii_digit() {
                 s.push(c);
@@ -77,14 +106,6 @@ impl Lexer {
                 break;
             }
         }
-        let val: i64 = s.parse().map_err(|_| {
-            zirc_syntax::error::Error::with_span("Invalid number", start_line, start_col)
-        })?;
-        Ok(Token {
-            kind: TokenKind::Number(val),
-            line: start_line,
-            col: start_col,
-        })
     }
 
     fn read_ident_or_keyword(&mut self) -> Token {
@@ -104,6 +125,7 @@ impl Lexer {
             "end" => TokenKind::End,
             "if" => TokenKind::If,
             "else" => TokenKind::Else,
+            "elif" => TokenKind::Elif,
             "return" => TokenKind::Return,
             "let" => TokenKind::Let,
             "while" => TokenKind::While,
@@ -125,6 +147,8 @@ impl Lexer {
     fn read_string(&mut self) -> Result<Token> {
         let start_line = self.line;
         let start_col = self.col;
+        // The token starts at the opening quote
+        self.advance();
         let mut s = String::new();
         while let Some(c) = self.advance() {
             match c {
@@ -178,27 +202,67 @@ impl Lexer {
                 }
                 Some('(') => {
                     self.advance();
-                    self.make_token(TokenKind::LParen)
+                    Token {
+                        kind: TokenKind::LParen,
+                        line,
+                        col,
+                    }
                 }
                 Some(')') => {
                     self.advance();
-                    self.make_token(TokenKind::RParen)
+                    Token {
+                        kind: TokenKind::RParen,
+                        line,
+                        col,
+                    }
                 }
                 Some(',') => {
                     self.advance();
-                    self.make_token(TokenKind::Comma)
+                    Token {
+                        kind: TokenKind::Comma,
+                        line,
+                        col,
+                    }
                 }
                 Some(':') => {
                     self.advance();
-                    self.make_token(TokenKind::Colon)
+                    Token {
+                        kind: TokenKind::Colon,
+                        line,
+                        col,
+                    }
                 }
                 Some('[') => {
                     self.advance();
-                    self.make_token(TokenKind::LBracket)
+                    Token {
+                        kind: TokenKind::LBracket,
+                        line,
+                        col,
+                    }
                 }
                 Some(']') => {
                     self.advance();
-                    self.make_token(TokenKind::RBracket)
+                    Token {
+                        kind: TokenKind::RBracket,
+                        line,
+                        col,
+                    }
+                }
+                Some('{') => {
+                    self.advance();
+                    Token {
+                        kind: TokenKind::LBrace,
+                        line,
+                        col,
+                    }
+                }
+                Some('}') => {
+                    self.advance();
+                    Token {
+                        kind: TokenKind::RBrace,
+                        line,
+                        col,
+                    }
                 }
                 Some('=') => {
                     if self.peek_next() == Some('=') {
@@ -211,7 +275,11 @@ impl Lexer {
                         }
                     } else {
                         self.advance();
-                        self.make_token(TokenKind::Equal)
+                        Token {
+                            kind: TokenKind::Equal,
+                            line,
+                            col,
+                        }
                     }
                 }
                 Some('!') => {
@@ -234,7 +302,14 @@ impl Lexer {
                 }
                 Some('<') => {
                     self.advance();
-                    if self.peek() == Some('=') {
+                    if self.peek() == Some('<') {
+                        self.advance();
+                        Token {
+                            kind: TokenKind::ShiftLeft,
+                            line,
+                            col,
+                        }
+                    } else if self.peek() == Some('=') {
                         self.advance();
                         Token {
                             kind: TokenKind::LessEq,
@@ -251,7 +326,14 @@ impl Lexer {
                 }
                 Some('>') => {
                     self.advance();
-                    if self.peek() == Some('=') {
+                    if self.peek() == Some('>') {
+                        self.advance();
+                        Token {
+                            kind: TokenKind::ShiftRight,
+                            line,
+                            col,
+                        }
+                    } else if self.peek() == Some('=') {
                         self.advance();
                         Token {
                             kind: TokenKind::GreaterEq,
@@ -268,19 +350,79 @@ impl Lexer {
                 }
                 Some('+') => {
                     self.advance();
-                    self.make_token(TokenKind::Plus)
+                    if self.peek() == Some('=') {
+                        self.advance();
+                        Token {
+                            kind: TokenKind::PlusEq,
+                            line,
+                            col,
+                        }
+                    } else {
+                        Token {
+                            kind: TokenKind::Plus,
+                            line,
+                            col,
+                        }
+                    }
                 }
                 Some('-') => {
                     self.advance();
-                    self.make_token(TokenKind::Minus)
+                    if self.peek() == Some('=') {
+                        self.advance();
+                        Token {
+                            kind: TokenKind::MinusEq,
+                            line,
+                            col,
+                        }
+                    } else {
+                        Token {
+                            kind: TokenKind::Minus,
+                            line,
+                            col,
+                        }
+                    }
                 }
                 Some('*') => {
                     self.advance();
-                    self.make_token(TokenKind::Star)
+                    if self.peek() == Some('=') {
+                        self.advance();
+                        Token {
+                            kind: TokenKind::StarEq,
+                            line,
+                            col,
+                        }
+                    } else {
+                        Token {
+                            kind: TokenKind::Star,
+                            line,
+                            col,
+                        }
+                    }
                 }
                 Some('/') => {
                     self.advance();
-                    self.make_token(TokenKind::Slash)
+                    if self.peek() == Some('=') {
+                        self.advance();
+                        Token {
+                            kind: TokenKind::SlashEq,
+                            line,
+                            col,
+                        }
+                    } else {
+                        Token {
+                            kind: TokenKind::Slash,
+                            line,
+                            col,
+                        }
+                    }
+                }
+                Some('%') => {
+                    self.advance();
+                    Token {
+                        kind: TokenKind::Percent,
+                        line,
+                        col,
+                    }
                 }
                 Some('&') => {
                     if self.peek_next() == Some('&') {
@@ -292,11 +434,12 @@ impl Lexer {
                             col,
                         }
                     } else {
-                        return zirc_syntax::error::error_at(
+                        self.advance();
+                        Token {
+                            kind: TokenKind::Amp,
                             line,
                             col,
-                            "Unexpected '&' (did you mean '&&'?)",
-                        );
+                        }
                     }
                 }
                 Some('|') => {
@@ -309,19 +452,34 @@ impl Lexer {
                             col,
                         }
                     } else {
-                        return zirc_syntax::error::error_at(
+                        self.advance();
+                        Token {
+                            kind: TokenKind::Pipe,
                             line,
                             col,
-                            "Unexpected '|' (did you mean '||'?)",
-                        );
+                        }
+                    }
+                }
+                Some('^') => {
+                    self.advance();
+                    Token {
+                        kind: TokenKind::Caret,
+                        line,
+                        col,
                     }
                 }
                 Some('.') => {
                     if self.peek_next() == Some('.') {
                         self.advance();
                         self.advance();
+                        let kind = if self.peek() == Some('=') {
+                            self.advance();
+                            TokenKind::DotDotEq
+                        } else {
+                            TokenKind::DotDot
+                        };
                         Token {
-                            kind: TokenKind::DotDot,
+                            kind,
                             line,
                             col,
                         }
@@ -333,10 +491,7 @@ impl Lexer {
                         );
                     }
                 }
-                Some('"') => {
-                    self.advance();
-                    self.read_string()?
-                }
+                Some('"') => self.read_string()?,
                 Some(c) if c.is_ascii_digit() => self.read_number()?,
                 Some(c) if c.is_ascii_alphabetic() || c == '_' => self.read_ident_or_keyword(),
                 Some(other) => {
@@ -21,6 +21,7 @@ mod tests {
     fn test_basic_tokens() {
         expect_tokens("()", vec![TokenKind::LParen, TokenKind::RParen, TokenKind::Eof]);
         expect_tokens("[]", vec![TokenKind::LBracket, TokenKind::RBracket, TokenKind::Eof]);
+        expect_tokens("{}", vec![TokenKind::LBrace, TokenKind::RBrace, TokenKind::Eof]);
         expect_tokens(",:", vec![TokenKind::Comma, TokenKind::Colon, TokenKind::Eof]);
     }
 
@@ -30,6 +31,7 @@ mod tests {
         expect_tokens("-", vec![TokenKind::Minus, TokenKind::Eof]);
         expect_tokens("*", vec![TokenKind::Star, TokenKind::Eof]);
         expect_tokens("/", vec![TokenKind::Slash, TokenKind::Eof]);
+        expect_tokens("%", vec![TokenKind::Percent, TokenKind::Eof]);
         expect_tokens("=", vec![TokenKind::Equal, TokenKind::Eof]);
         expect_tokens("==", vec![TokenKind::EqEq, TokenKind::Eof]);
         expect_tokens("!=", vec![TokenKind::NotEq, TokenKind::Eof]);
@@ -40,6 +42,15 @@ mod tests {
         expect_tokens(">=", vec![TokenKind::GreaterEq, TokenKind::Eof]);
         expect_tokens("&&", vec![TokenKind::AndAnd, TokenKind::Eof]);
         expect_tokens("||", vec![TokenKind::OrOr, TokenKind::Eof]);
+        expect_tokens("&", vec![TokenKind::Amp, TokenKind::Eof]);
+        expect_tokens("|", vec![TokenKind::Pipe, TokenKind::Eof]);
+        expect_tokens("^", vec![TokenKind::Caret, TokenKind::Eof]);
+        expect_tokens("<<", vec![TokenKind::ShiftLeft, TokenKind::Eof]);
+        expect_tokens(">>", vec![TokenKind::ShiftRight, TokenKind::Eof]);
+        expect_tokens("+= -= *= /=", vec![TokenKind::PlusEq, TokenKind::MinusEq, TokenKind::StarEq, TokenKind::SlashEq, TokenKind::Eof]);
+        expect_tokens("+ =", vec![TokenKind::Plus, TokenKind::Equal, TokenKind::Eof]);
+        expect_tokens("& &&", vec![TokenKind::Amp, TokenKind::AndAnd, TokenKind::Eof]);
+        expect_tokens("<<=", vec![TokenKind::ShiftLeft, TokenKind::Equal, TokenKind::Eof]);
         expect_tokens("..", vec![TokenKind::DotDot, TokenKind::Eof]);
     }
 
@@ -49,6 +60,7 @@ mod tests {
         expect_tokens("end", vec![TokenKind::End, TokenKind::Eof]);
         expect_tokens("if", vec![TokenKind::If, TokenKind::Eof]);
         expect_tokens("else", vec![TokenKind::Else, TokenKind::Eof]);
+        expect_tokens("elif", vec![TokenKind::Elif, TokenKind::Eof]);
         expect_tokens("while", vec![TokenKind::While, TokenKind::Eof]);
         expect_tokens("for", vec![TokenKind::For, TokenKind::Eof]);
         expect_tokens("in", vec![TokenKind::In, TokenKind::Eof]);
@@ -67,6 +79,18 @@ mod tests {
         expect_tokens("123456789", vec![TokenKind::Number(123456789), TokenKind::Eof]);
     }
 
+    #[test]
+    fn test_floats() {
+        expect_tokens("2.75", vec![TokenKind::Float(2.75), TokenKind::Eof]);
+        expect_tokens("1.0e-5", vec![TokenKind::Float(1.0e-5), TokenKind::Eof]);
+        expect_tokens("2E+3", vec![TokenKind::Float(2000.0), TokenKind::Eof]);
+        expect_tokens("0..10", vec![TokenKind::Number(0), TokenKind::DotDot, TokenKind::Number(10), TokenKind::Eof]);
+        expect_tokens("0..=10", vec![TokenKind::Number(0), TokenKind::DotDotEq, TokenKind::Number(10), TokenKind::Eof]);
+        expect_tokens("0.. =10", vec![TokenKind::Number(0), TokenKind::DotDot, TokenKind::Equal, TokenKind::Number(10), TokenKind::Eof]);
+        // An `e` not followed by digits is an identifier, not an exponent
+        expect_tokens("2e", vec![TokenKind::Number(2), TokenKind::Ident("e".to_string()), TokenKind::Eof]);
+    }
+
     #[test]
     fn test_strings() {
         expect_tokens("\"hello\"", vec![TokenKind::String("hello".to_string()), TokenKind::Eof]);
@@ -147,6 +171,38 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn test_unexpected_character_span() {
+        let err = Lexer::new("let x = @").tokenize().unwrap_err();
+        assert_eq!(err.msg, "Unexpected character '@'");
+        assert_eq!((err.line, err.col), (Some(1), Some(9)));
+
+        // Columns count characters, not bytes, and restart on each line
+        let err = Lexer::new("show(\"é\")\nlet y = $5").tokenize().unwrap_err();
+        assert_eq!(err.msg, "Unexpected character '$'");
+        assert_eq!((err.line, err.col), (Some(2), Some(9)));
+
+        let err = Lexer::new("\"é\" @").tokenize().unwrap_err();
+        assert_eq!((err.line, err.col), (Some(1), Some(5)));
+    }
+
+    #[test]
+    fn test_integer_literal_too_large() {
+        let err = Lexer::new("let x = 1\nshow(99999999999999999999)").tokenize().unwrap_err();
+        assert_eq!(err.msg, "integer literal too large for 64-bit");
+        assert_eq!((err.line, err.col), (Some(2), Some(6)));
+
+        // i64::MAX itself still fits
+        expect_tokens("9223372036854775807", vec![TokenKind::Number(i64::MAX), TokenKind::Eof]);
+    }
+
+    #[test]
+    fn test_punctuation_columns_are_token_starts() {
+        let tokens = Lexer::new("xs[i] = (a)").tokenize().unwrap();
+        let cols: Vec<usize> = tokens.iter().map(|t| t.col).collect();
+        assert_eq!(cols, vec![1, 3, 4, 5, 7, 9, 10, 11, 12]);
+    }
+
     #[test]
     fn test_line_and_column_tracking() {
         let mut lexer = Lexer::new("hello\nworld");
@@ -157,4 +213,17 @@ mod tests {
         assert_eq!(tokens[1].line, 2);
         assert_eq!(tokens[1].col, 1);
     }
+
+    #[test]
+    fn test_columns_count_unicode_scalars() {
+        // 2-, 3- and 4-byte UTF-8 chars each advance the column by one
+        let tokens = Lexer::new("show(\"é€😀\", x) ~ ü€\n  \"ß\" y").tokenize().unwrap();
+        let positions: Vec<(usize, usize)> = tokens.iter().map(|t| (t.line, t.col)).collect();
+        assert_eq!(positions, vec![(1, 1), (1, 5), (1, 6), (1, 11), (1, 13), (1, 14), (2, 3), (2, 7), (2, 8)]);
+
+        // A non-ASCII char outside a string is reported at its own column
+        let err = Lexer::new("let naïve = 1").tokenize().unwrap_err();
+        assert_eq!(err.msg, "Unexpected character 'ï'");
+        assert_eq!((err.line, err.col), (Some(1), Some(7)));
+    }
 }
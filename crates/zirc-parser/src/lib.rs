@@ -25,6 +25,7 @@ mod tests {
     #[test]
     fn test_literal_expressions() {
         assert!(matches!(parse_expr_str("42"), Expr::LiteralInt(42)));
+        assert!(matches!(parse_expr_str("2.5"), Expr::LiteralFloat(x) if x == 2.5));
         assert!(matches!(parse_expr_str("\"hello\""), Expr::LiteralString(s) if s == "hello"));
         assert!(matches!(parse_expr_str("true"), Expr::LiteralBool(true)));
         assert!(matches!(parse_expr_str("false"), Expr::LiteralBool(false)));
@@ -32,8 +33,8 @@ mod tests {
 
     #[test]
     fn test_identifier_expressions() {
-        assert!(matches!(parse_expr_str("variable"), Expr::Ident(s) if s == "variable"));
-        assert!(matches!(parse_expr_str("my_var"), Expr::Ident(s) if s == "my_var"));
+        assert!(matches!(parse_expr_str("variable"), Expr::Ident(s, _) if s == "variable"));
+        assert!(matches!(parse_expr_str("my_var"), Expr::Ident(s, _) if s == "my_var"));
     }
 
     #[test]
@@ -41,7 +42,27 @@ mod tests {
         assert!(matches!(parse_expr_str("1 + 2"), Expr::BinaryAdd(_, _)));
         assert!(matches!(parse_expr_str("5 - 3"), Expr::BinarySub(_, _)));
         assert!(matches!(parse_expr_str("4 * 6"), Expr::BinaryMul(_, _)));
-        assert!(matches!(parse_expr_str("8 / 2"), Expr::BinaryDiv(_, _)));
+        assert!(matches!(parse_expr_str("8 / 2"), Expr::BinaryDiv(_, _, Span { line: 1, col: 3 })));
+        assert!(matches!(parse_expr_str("8 % 3"), Expr::BinaryMod(_, _)));
+        // Same precedence as `*`, so `1 + 7 % 4` is `1 + (7 % 4)`
+        assert!(matches!(parse_expr_str("1 + 7 % 4"), Expr::BinaryAdd(_, r) if matches!(*r, Expr::BinaryMod(_, _))));
+    }
+
+    #[test]
+    fn test_bitwise_operations() {
+        assert!(matches!(parse_expr_str("6 & 3"), Expr::BitAnd(_, _)));
+        assert!(matches!(parse_expr_str("6 | 3"), Expr::BitOr(_, _)));
+        assert!(matches!(parse_expr_str("6 ^ 3"), Expr::BitXor(_, _)));
+        assert!(matches!(parse_expr_str("1 << 4"), Expr::Shl(_, _)));
+        assert!(matches!(parse_expr_str("16 >> 4"), Expr::Shr(_, _)));
+        // Tighter than comparisons: `6 & 3 == 2` is `(6 & 3) == 2`
+        assert!(matches!(parse_expr_str("6 & 3 == 2"), Expr::Eq(l, _) if matches!(*l, Expr::BitAnd(_, _))));
+        // `|` < `^` < `&` < shifts < `+`
+        assert!(matches!(parse_expr_str("1 | 2 ^ 3"), Expr::BitOr(_, r) if matches!(*r, Expr::BitXor(_, _))));
+        assert!(matches!(parse_expr_str("1 ^ 2 & 3"), Expr::BitXor(_, r) if matches!(*r, Expr::BitAnd(_, _))));
+        assert!(matches!(parse_expr_str("1 & 2 << 3"), Expr::BitAnd(_, r) if matches!(*r, Expr::Shl(_, _))));
+        assert!(matches!(parse_expr_str("1 << 2 + 3"), Expr::Shl(_, r) if matches!(*r, Expr::BinaryAdd(_, _))));
+        assert!(matches!(parse_expr_str("a & b && c"), Expr::LogicalAnd(l, _) if matches!(*l, Expr::BitAnd(_, _))));
     }
 
     #[test]
@@ -61,6 +82,20 @@ mod tests {
         assert!(matches!(parse_expr_str("!true"), Expr::LogicalNot(_)));
     }
 
+    #[test]
+    fn test_unary_minus() {
+        assert!(matches!(parse_expr_str("-5"), Expr::Negate(e) if matches!(*e, Expr::LiteralInt(5))));
+        assert!(matches!(parse_expr_str("-(a + b)"), Expr::Negate(e) if matches!(*e, Expr::BinaryAdd(_, _))));
+        // Binds tighter than `*`: `-2 * 3` is `(-2) * 3`
+        assert!(matches!(parse_expr_str("-2 * 3"), Expr::BinaryMul(l, _) if matches!(*l, Expr::Negate(_))));
+        assert!(matches!(parse_expr_str("1 - -2"), Expr::BinarySub(_, r) if matches!(*r, Expr::Negate(_))));
+        assert!(matches!(parse_expr_str("- -5"), Expr::Negate(e) if matches!(*e, Expr::Negate(_))));
+        assert!(matches!(parse_expr_str("-len(xs)"), Expr::Negate(e) if matches!(*e, Expr::Call { .. })));
+        // A `-` at the start of a line begins a new statement
+        let program = parse_program_str("let x = 3\n-x == -3");
+        assert_eq!(program.items.len(), 2);
+    }
+
     #[test]
     fn test_parenthesized_expressions() {
         assert!(matches!(parse_expr_str("(1 + 2)"), Expr::BinaryAdd(_, _)));
@@ -90,19 +125,19 @@ mod tests {
 
     #[test]
     fn test_index_expressions() {
-        assert!(matches!(parse_expr_str("arr[0]"), Expr::Index(_, _)));
+        assert!(matches!(parse_expr_str("arr[0]"), Expr::Index(..)));
     }
 
     #[test]
     fn test_function_calls() {
-        if let Expr::Call { name, args } = parse_expr_str("foo()") {
+        if let Expr::Call { name, args, .. } = parse_expr_str("foo()") {
             assert_eq!(name, "foo");
             assert_eq!(args.len(), 0);
         } else {
             panic!("Expected Call");
         }
 
-        if let Expr::Call { name, args } = parse_expr_str("add(1, 2)") {
+        if let Expr::Call { name, args, .. } = parse_expr_str("add(1, 2)") {
             assert_eq!(name, "add");
             assert_eq!(args.len(), 2);
         } else {
@@ -122,8 +157,220 @@ mod tests {
         let _ = parse_program_str("for i in 0..10: break end");
         let _ = parse_program_str("fun add(x, y): x + y end");
         let _ = parse_program_str("fun add(x: int, y: int) (int): x + y end");
-        let _ = parse_program_str("return 42");
+        let _ = parse_program_str("fun f(): return 42 end");
         let _ = parse_program_str("break");
         let _ = parse_program_str("continue");
     }
+
+    #[test]
+    fn test_compound_assign() {
+        let program = parse_program_str("x += 1\nx -= y * 2\nx *= 3\nx /= 4");
+        let ops: Vec<_> = program.items.iter().map(|item| match item {
+            Item::Stmt(Stmt::CompoundAssign { name, op, .. }) if name == "x" => *op,
+            other => panic!("Expected CompoundAssign, got {:?}", other),
+        }).collect();
+        assert_eq!(ops, vec![AssignOp::Add, AssignOp::Sub, AssignOp::Mul, AssignOp::Div]);
+        // The whole right-hand side is the operand
+        assert!(matches!(&program.items[1], Item::Stmt(Stmt::CompoundAssign { expr: Expr::BinaryMul(..), .. })));
+    }
+
+    #[test]
+    fn test_elif_nests_in_else() {
+        let program = parse_program_str("if a: 1 elif b: 2 elif c: 3 else: 4 end");
+        assert_eq!(program.items.len(), 1);
+        let Item::Stmt(Stmt::If { cond, else_body, .. }) = &program.items[0] else { panic!("Expected If") };
+        assert!(matches!(cond, Expr::Ident(n, _) if n == "a"));
+        let [Stmt::If { cond, else_body, .. }] = else_body.as_slice() else { panic!("Expected elif b") };
+        assert!(matches!(cond, Expr::Ident(n, _) if n == "b"));
+        let [Stmt::If { cond, else_body, .. }] = else_body.as_slice() else { panic!("Expected elif c") };
+        assert!(matches!(cond, Expr::Ident(n, _) if n == "c"));
+        assert!(matches!(else_body.as_slice(), [Stmt::ExprStmt(Expr::LiteralInt(4))]));
+
+        // Without a final else the innermost If has an empty else body
+        let program = parse_program_str("if a: 1 elif b: 2 end\nlet x = 3");
+        assert_eq!(program.items.len(), 2);
+        let Item::Stmt(Stmt::If { else_body, .. }) = &program.items[0] else { panic!("Expected If") };
+        let [Stmt::If { cond, then_body, else_body }] = else_body.as_slice() else { panic!("Expected elif b") };
+        assert!(matches!(cond, Expr::Ident(n, _) if n == "b"));
+        assert!(matches!(then_body.as_slice(), [Stmt::ExprStmt(Expr::LiteralInt(2))]));
+        assert!(else_body.is_empty());
+    }
+
+    #[test]
+    fn test_line_gaps() {
+        let src = "let a = 1\n\nfun f():\n  if a:\n    show(a)\n  end\nend\nf()";
+        let mut parser = Parser::new(Lexer::new(src).tokenize().unwrap());
+        parser.parse_program().unwrap();
+        // let, fun, if, show, f(): each statement before the ones nested in it
+        assert_eq!(parser.line_gaps(), &[(0, 1), (1, 3), (3, 4), (4, 5), (7, 8)]);
+    }
+
+    #[test]
+    fn test_return_outside_function() {
+        for src in ["return 42", "if true: return end", "fun f(): return 1 end\nreturn 2"] {
+            let tokens = Lexer::new(src).tokenize().unwrap();
+            let err = Parser::new(tokens).parse_program().unwrap_err();
+            assert_eq!(err.msg, "'return' outside of function", "{}", src);
+        }
+        let tokens = Lexer::new("let x = 1\n  return x").tokenize().unwrap();
+        let err = Parser::new(tokens).parse_program().unwrap_err();
+        assert_eq!((err.line, err.col), (Some(2), Some(3)));
+    }
+
+    #[test]
+    fn test_loops_are_not_expressions() {
+        let tokens = Lexer::new("let x = while true: break end").tokenize().unwrap();
+        let err = Parser::new(tokens).parse_program().unwrap_err();
+        assert_eq!(err.msg, "'while' is a statement and has no value; assign to a variable inside the loop instead");
+        assert_eq!((err.line, err.col), (Some(1), Some(9)));
+
+        for src in ["show(for i in 0..3: i end)", "let n = 1 + while false: 1 end", "x = for c in \"ab\": c end"] {
+            let tokens = Lexer::new(src).tokenize().unwrap();
+            let err = Parser::new(tokens).parse_program().unwrap_err();
+            assert!(err.msg.contains("is a statement and has no value"), "{}: {}", src, err.msg);
+        }
+    }
+
+    #[test]
+    fn test_loop_labels() {
+        let program = parse_program_str("outer: for i in 0..3:\n  inner: while true: break outer end\n  continue outer\nend");
+        let Item::Stmt(Stmt::For { label: Some(label), body, .. }) = &program.items[0] else { panic!("Expected labelled For") };
+        assert_eq!(label, "outer");
+        assert!(matches!(&body[0], Stmt::While { label: Some(l), body, .. } if l == "inner" && matches!(&body[0], Stmt::Break(Some(t)) if t == "outer")));
+        assert!(matches!(&body[1], Stmt::Continue(Some(t)) if t == "outer"));
+        // A name on the line after `break` starts a new statement
+        let program = parse_program_str("while true:\n  break\n  x = 1\nend");
+        let Item::Stmt(Stmt::While { label: None, body, .. }) = &program.items[0] else { panic!("Expected While") };
+        assert!(matches!(&body[..], [Stmt::Break(None), Stmt::Assign { .. }]));
+
+        for (src, msg, col) in [
+            ("a: while true: break b end", "Unknown loop label 'b'", 22),
+            ("while true: continue a end", "Unknown loop label 'a'", 22),
+            ("a: while true: a: for x in xs: break end end", "Duplicate loop label 'a'", 16),
+        ] {
+            let tokens = Lexer::new(src).tokenize().unwrap();
+            let err = Parser::new(tokens).parse_program().unwrap_err();
+            assert_eq!((err.msg.as_str(), err.col), (msg, Some(col)), "{}", src);
+        }
+    }
+
+    #[test]
+    fn test_for_each_vs_range() {
+        let program = parse_program_str("for x in xs: show(x) end\nfor i in 0..n: show(i) end");
+        assert!(matches!(&program.items[0], Item::Stmt(Stmt::ForEach { var, iterable: Expr::Ident(it, _), body, label: None }) if var == "x" && it == "xs" && body.len() == 1));
+        assert!(matches!(&program.items[1], Item::Stmt(Stmt::For { var, inclusive: false, .. }) if var == "i"));
+        let program = parse_program_str("for i in 0..=n: show(i) end");
+        assert!(matches!(&program.items[0], Item::Stmt(Stmt::For { inclusive: true, step: None, .. })));
+        // `step` is contextual: a step clause after the range, a plain name elsewhere
+        let program = parse_program_str("let step = 2\nfor i in 10..0 step -step: show(step) end");
+        assert!(matches!(&program.items[1], Item::Stmt(Stmt::For { step: Some(Expr::Negate(_)), .. })));
+        // Any expression can be iterated; only a `..` after it makes a range
+        let program = parse_program_str("for c in split(s, \",\") + [\"x\"]: show(c) end");
+        assert!(matches!(&program.items[0], Item::Stmt(Stmt::ForEach { iterable: Expr::BinaryAdd(..), .. })));
+    }
+
+    #[test]
+    fn test_map_literal() {
+        let Expr::Map(entries) = parse_expr_str("{\"a\": 1, \"b\": [2]}") else { panic!("Expected Map") };
+        assert_eq!(entries.len(), 2);
+        assert!(matches!(&entries[0], (Expr::LiteralString(k), Expr::LiteralInt(1)) if k == "a"));
+        assert!(matches!(&entries[1], (Expr::LiteralString(k), Expr::List(_)) if k == "b"));
+        assert!(matches!(parse_expr_str("{}"), Expr::Map(e) if e.is_empty()));
+        assert!(matches!(parse_expr_str("{\"a\": 1}[\"a\"]"), Expr::Index(..)));
+
+        let tokens = Lexer::new("{\"a\" 1}").tokenize().unwrap();
+        assert_eq!(Parser::new(tokens).parse_program().unwrap_err().msg, "Expected Colon, found Number(1)");
+    }
+
+    #[test]
+    fn test_call_on_index_is_rejected() {
+        for src in ["handlers[0](1)", "let r = fs[\"add\"](1, 2)", "xs[0][1] (2)"] {
+            let tokens = Lexer::new(src).tokenize().unwrap();
+            let err = Parser::new(tokens).parse_program().unwrap_err();
+            assert_eq!(err.msg, "Cannot call the result of an index; only functions called by name are supported", "{}", src);
+        }
+        // A parenthesized expression on the next line is a separate statement
+        assert_eq!(parse_program_str("let a = xs[0]\n(1 + 2)").items.len(), 2);
+    }
+
+    #[test]
+    fn test_index_assign() {
+        let program = parse_program_str("grid[1][x] = 9");
+        let Item::Stmt(Stmt::IndexAssign { base, index, expr }) = &program.items[0] else { panic!("Expected IndexAssign") };
+        assert!(matches!(base, Expr::Index(b, i, _) if matches!((&**b, &**i), (Expr::Ident(g, _), Expr::LiteralInt(1)) if g == "grid")));
+        assert!(matches!(index, Expr::Ident(x, _) if x == "x"));
+        assert!(matches!(expr, Expr::LiteralInt(9)));
+
+        let tokens = Lexer::new("f(1) = 2").tokenize().unwrap();
+        let err = Parser::new(tokens).parse_program().unwrap_err();
+        assert_eq!(err.msg, "Invalid assignment target");
+    }
+
+    #[test]
+    fn test_return_multiple_values() {
+        let program = parse_program_str("fun f():\n return 1, 2 + 3\nend");
+        let Item::Function(f) = &program.items[0] else { panic!("expected a function") };
+        assert!(matches!(&f.body[0], Stmt::Return(Some(Expr::List(items))) if items.len() == 2));
+
+        let program = parse_program_str("fun g():\n return 1\nend");
+        let Item::Function(g) = &program.items[0] else { panic!("expected a function") };
+        assert!(matches!(&g.body[0], Stmt::Return(Some(Expr::LiteralInt(1)))));
+    }
+
+    #[test]
+    fn test_let_unpack() {
+        let program = parse_program_str("let a, b, c = [1, 2, 3]");
+        assert!(matches!(&program.items[0], Item::Stmt(Stmt::LetUnpack { names, expr: Expr::List(_) }) if names == &["a", "b", "c"]));
+
+        let tokens = Lexer::new("let a, a = [1, 2]").tokenize().unwrap();
+        let err = Parser::new(tokens).parse_program().unwrap_err();
+        assert!(err.msg.contains("Duplicate name 'a'"));
+        assert_eq!((err.line, err.col), (Some(1), Some(8)));
+        // `_` discards its value, so it may repeat
+        let program = parse_program_str("let _, x, _ = [1, 2, 3]");
+        assert!(matches!(&program.items[0], Item::Stmt(Stmt::LetUnpack { names, .. }) if names == &["_", "x", "_"]));
+
+        let tokens = Lexer::new("let a, = [1]").tokenize().unwrap();
+        assert!(Parser::new(tokens).parse_program().is_err());
+    }
+
+    #[test]
+    fn test_pretty_print_parsed_program() {
+        let program = parse_program_str("fun area(w: int, h) (int):\n  return w * h + 1\nend\nfor i in 0..=3 step 2:\n  if i > 1: break else: show(area(i, [1, 2][0])) end\nend");
+        assert_eq!(zirc_syntax::pretty::pretty_print(&program), "\
+Program
+  Function area(w: int, h) (int)
+    Return
+      BinaryAdd
+        BinaryMul
+          Ident w @2:10
+          Ident h @2:14
+        LiteralInt 1
+  For i in start..=end
+    start:
+      LiteralInt 0
+    end:
+      LiteralInt 3
+    step:
+      LiteralInt 2
+    body:
+      If
+        cond:
+          Gt
+            Ident i @5:6
+            LiteralInt 1
+        then:
+          Break
+        else:
+          ExprStmt
+            Call show @5:25
+              Call area @5:30
+                Ident i @5:35
+                Index @5:44
+                  List
+                    LiteralInt 1
+                    LiteralInt 2
+                  LiteralInt 0
+");
+    }
 }
@@ -6,12 +6,31 @@ use zirc_syntax::token::{Token, TokenKind};
 pub struct Parser {
     tokens: Vec<Token>,
     pos: usize,
+    /// Set while parsing a function body, where `return` is allowed
+    in_function: bool,
+    /// Labels of the enclosing labelled loops, innermost last
+    loop_labels: Vec<String>,
+    /// See [`Parser::line_gaps`]
+    line_gaps: Vec<(usize, usize)>,
 }
 
 impl Parser {
     /// Create a new parser from a vector of tokens.
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, pos: 0 }
+        Self { tokens, pos: 0, in_function: false, loop_labels: Vec::new(), line_gaps: Vec::new() }
+    }
+
+    /// For every item and statement parsed so far, in pre-order (a statement
+    /// before the statements nested in it): the line of the token before it and
+    /// the line it starts on. Lines in between held only blanks or comments,
+    /// which `zirc-fmt` uses to keep the author's grouping.
+    pub fn line_gaps(&self) -> &[(usize, usize)] {
+        &self.line_gaps
+    }
+
+    fn mark_start(&mut self) {
+        let prev = if self.pos == 0 { 0 } else { self.tokens[self.pos - 1].line };
+        self.line_gaps.push((prev, self.peek().line));
     }
 
     fn peek(&self) -> &Token {
@@ -42,6 +61,7 @@ impl Parser {
         let mut items = Vec::new();
         while !self.is_eof() {
             if matches!(self.peek().kind, TokenKind::Fun) {
+                self.mark_start();
                 items.push(Item::Function(self.parse_function()?));
             } else {
                 items.push(Item::Stmt(self.parse_stmt()?));
@@ -54,6 +74,7 @@ impl Parser {
         let name = self.consume_ident()?;
         let ty = match name.as_str() {
             "int" => Type::Int,
+            "float" => Type::Float,
             "string" => Type::String,
             "bool" => Type::Bool,
             "unit" => Type::Unit,
@@ -90,7 +111,10 @@ impl Parser {
             return_type = Some(ty);
         }
         self.expect(TokenKind::Colon)?;
-        let body = self.parse_block_until_end()?;
+        self.in_function = true;
+        let body = self.parse_block_until_end();
+        self.in_function = false;
+        let body = body?;
         self.expect(TokenKind::End)?;
         Ok(Function {
             name,
@@ -122,18 +146,127 @@ impl Parser {
         let mut stmts = Vec::new();
         while !matches!(
             self.peek().kind,
-            TokenKind::Else | TokenKind::End | TokenKind::Eof
+            TokenKind::Else | TokenKind::Elif | TokenKind::End | TokenKind::Eof
         ) {
             stmts.push(self.parse_stmt()?);
         }
         Ok(stmts)
     }
 
+    /// Parses an `if` after its keyword, up to but not including the `end`.
+    /// `elif c:` becomes an `else` body holding just the nested `If`, so the
+    /// whole chain shares one `end`.
+    fn parse_if_rest(&mut self) -> Result<Stmt> {
+        let cond = self.parse_expr()?;
+        self.expect(TokenKind::Colon)?;
+        let then_body = self.parse_block_until_else_or_end()?;
+        let else_body = match self.peek().kind {
+            TokenKind::Elif => {
+                self.mark_start();
+                self.advance();
+                vec![self.parse_if_rest()?]
+            }
+            TokenKind::Else => {
+                self.advance();
+                self.expect(TokenKind::Colon)?;
+                self.parse_block_until_end()?
+            }
+            _ => Vec::new(),
+        };
+        Ok(Stmt::If {
+            cond,
+            then_body,
+            else_body,
+        })
+    }
+
+    /// Parses a `while` or `for` loop from its keyword through its `end`.
+    fn parse_loop(&mut self, label: Option<String>) -> Result<Stmt> {
+        if let Some(name) = &label { self.loop_labels.push(name.clone()); }
+        let stmt = self.parse_loop_rest(label.clone());
+        if label.is_some() { self.loop_labels.pop(); }
+        stmt
+    }
+
+    fn parse_loop_rest(&mut self, label: Option<String>) -> Result<Stmt> {
+        if matches!(self.peek().kind, TokenKind::While) {
+            self.advance();
+            let cond = self.parse_expr()?;
+            self.expect(TokenKind::Colon)?;
+            let body = self.parse_block_until_end()?;
+            self.expect(TokenKind::End)?;
+            return Ok(Stmt::While { label, cond, body });
+        }
+        self.expect(TokenKind::For)?;
+        let var = self.consume_ident()?;
+        self.expect(TokenKind::In)?;
+        let start = self.parse_expr()?;
+        if !matches!(self.peek().kind, TokenKind::DotDot | TokenKind::DotDotEq) {
+            self.expect(TokenKind::Colon)?;
+            let body = self.parse_block_until_end()?;
+            self.expect(TokenKind::End)?;
+            return Ok(Stmt::ForEach { label, var, iterable: start, body });
+        }
+        let inclusive = matches!(self.peek().kind, TokenKind::DotDotEq);
+        self.advance();
+        let end = self.parse_expr()?;
+        // `step` is only special here, so it stays usable as a variable name
+        let step = if matches!(&self.peek().kind, TokenKind::Ident(w) if w == "step") {
+            self.advance();
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        self.expect(TokenKind::Colon)?;
+        let body = self.parse_block_until_end()?;
+        self.expect(TokenKind::End)?;
+        Ok(Stmt::For {
+            label,
+            var,
+            start,
+            end,
+            inclusive,
+            step,
+            body,
+        })
+    }
+
+    /// The optional label after `break` or `continue`. It must be on the same
+    /// line, so a statement starting with a name on the next line is not taken
+    /// for one, and must name an enclosing loop.
+    fn parse_jump_label(&mut self) -> Result<Option<String>> {
+        let keyword_line = self.tokens[self.pos - 1].line;
+        let tk = self.peek().clone();
+        let TokenKind::Ident(name) = tk.kind else { return Ok(None) };
+        if tk.line != keyword_line { return Ok(None); }
+        if !self.loop_labels.contains(&name) {
+            return zirc_syntax::error::error_at(tk.line, tk.col, format!("Unknown loop label '{}'", name));
+        }
+        self.advance();
+        Ok(Some(name))
+    }
+
     fn parse_stmt(&mut self) -> Result<Stmt> {
+        self.mark_start();
         match self.peek().kind.clone() {
             TokenKind::Let => {
                 self.advance();
                 let name = self.consume_ident()?;
+                if matches!(self.peek().kind, TokenKind::Comma) {
+                    let mut names = vec![name];
+                    while matches!(self.peek().kind, TokenKind::Comma) {
+                        self.advance();
+                        let tok = self.peek().clone();
+                        let next = self.consume_ident()?;
+                        if next != "_" && names.contains(&next) {
+                            return zirc_syntax::error::error_at(tok.line, tok.col, format!("Duplicate name '{}' in let", next));
+                        }
+                        names.push(next);
+                    }
+                    self.expect(TokenKind::Equal)?;
+                    let expr = self.parse_expr()?;
+                    return Ok(Stmt::LetUnpack { names, expr });
+                }
                 let mut ty = None;
                 if matches!(self.peek().kind, TokenKind::Colon) {
                     self.advance();
@@ -144,69 +277,57 @@ impl Parser {
                 Ok(Stmt::Let { name, ty, expr })
             }
             TokenKind::Return => {
+                // Rejected here so both backends agree; the VM would otherwise end `main` early
+                if !self.in_function {
+                    return zirc_syntax::error::error_at(self.peek().line, self.peek().col, "'return' outside of function");
+                }
                 self.advance();
                 // optional expression (return without value)
-                if matches!(self.peek().kind, TokenKind::End | TokenKind::Else) {
+                if matches!(self.peek().kind, TokenKind::End | TokenKind::Else | TokenKind::Elif) {
                     Ok(Stmt::Return(None))
                 } else {
                     let expr = self.parse_expr()?;
-                    Ok(Stmt::Return(Some(expr)))
+                    if !matches!(self.peek().kind, TokenKind::Comma) {
+                        return Ok(Stmt::Return(Some(expr)));
+                    }
+                    // `return a, b` is sugar for `return [a, b]`, for unpacking with `let x, y = f()`
+                    let mut items = vec![expr];
+                    while matches!(self.peek().kind, TokenKind::Comma) {
+                        self.advance();
+                        items.push(self.parse_expr()?);
+                    }
+                    Ok(Stmt::Return(Some(Expr::List(items))))
                 }
             }
             TokenKind::If => {
                 self.advance();
-                let cond = self.parse_expr()?;
-                self.expect(TokenKind::Colon)?;
-                let then_body = self.parse_block_until_else_or_end()?;
-                let mut else_body = Vec::new();
-                if matches!(self.peek().kind, TokenKind::Else) {
-                    self.advance();
-                    self.expect(TokenKind::Colon)?;
-                    else_body = self.parse_block_until_end()?;
-                }
-                self.expect(TokenKind::End)?;
-                Ok(Stmt::If {
-                    cond,
-                    then_body,
-                    else_body,
-                })
-            }
-            TokenKind::While => {
-                self.advance();
-                let cond = self.parse_expr()?;
-                self.expect(TokenKind::Colon)?;
-                let body = self.parse_block_until_end()?;
+                let stmt = self.parse_if_rest()?;
                 self.expect(TokenKind::End)?;
-                Ok(Stmt::While { cond, body })
-            }
-            TokenKind::For => {
-                self.advance();
-                let var = self.consume_ident()?;
-                self.expect(TokenKind::In)?;
-                let start = self.parse_expr()?;
-                self.expect(TokenKind::DotDot)?;
-                let end = self.parse_expr()?;
-                self.expect(TokenKind::Colon)?;
-                let body = self.parse_block_until_end()?;
-                self.expect(TokenKind::End)?;
-                Ok(Stmt::For {
-                    var,
-                    start,
-                    end,
-                    body,
-                })
+                Ok(stmt)
             }
+            TokenKind::While | TokenKind::For => self.parse_loop(None),
             TokenKind::Break => {
                 self.advance();
-                Ok(Stmt::Break)
+                Ok(Stmt::Break(self.parse_jump_label()?))
             }
             TokenKind::Continue => {
                 self.advance();
-                Ok(Stmt::Continue)
+                Ok(Stmt::Continue(self.parse_jump_label()?))
             }
             TokenKind::Ident(_) => {
-                // assignment or expression statement
-                if let TokenKind::Ident(name) = self.peek().kind.clone() {
+                // labelled loop, assignment or expression statement
+                let tk = self.peek().clone();
+                if let TokenKind::Ident(name) = tk.kind {
+                    let labels_loop = matches!(self.tokens.get(self.pos + 1).map(|t| &t.kind), Some(TokenKind::Colon))
+                        && matches!(self.tokens.get(self.pos + 2).map(|t| &t.kind), Some(TokenKind::While | TokenKind::For));
+                    if labels_loop {
+                        if self.loop_labels.contains(&name) {
+                            return zirc_syntax::error::error_at(tk.line, tk.col, format!("Duplicate loop label '{}'", name));
+                        }
+                        self.advance();
+                        self.advance();
+                        return self.parse_loop(Some(name));
+                    }
                     let is_assign = matches!(
                         self.tokens.get(self.pos + 1).map(|t| &t.kind),
                         Some(TokenKind::Equal)
@@ -217,8 +338,30 @@ impl Parser {
                         let expr = self.parse_expr()?;
                         return Ok(Stmt::Assign { name, expr });
                     }
+                    let op = match self.tokens.get(self.pos + 1).map(|t| &t.kind) {
+                        Some(TokenKind::PlusEq) => Some(AssignOp::Add),
+                        Some(TokenKind::MinusEq) => Some(AssignOp::Sub),
+                        Some(TokenKind::StarEq) => Some(AssignOp::Mul),
+                        Some(TokenKind::SlashEq) => Some(AssignOp::Div),
+                        _ => None,
+                    };
+                    if let Some(op) = op {
+                        self.advance();
+                        self.advance();
+                        let expr = self.parse_expr()?;
+                        return Ok(Stmt::CompoundAssign { name, span: Span { line: tk.line, col: tk.col }, op, expr });
+                    }
                 }
                 let expr = self.parse_expr()?;
+                if matches!(self.peek().kind, TokenKind::Equal) {
+                    let tk = self.peek().clone();
+                    let Expr::Index(base, index, _) = expr else {
+                        return zirc_syntax::error::error_at(tk.line, tk.col, "Invalid assignment target");
+                    };
+                    self.advance();
+                    let expr = self.parse_expr()?;
+                    return Ok(Stmt::IndexAssign { base: *base, index: *index, expr });
+                }
                 Ok(Stmt::ExprStmt(expr))
             }
             _ => {
@@ -273,27 +416,27 @@ impl Parser {
     }
 
     fn parse_comparison(&mut self) -> Result<Expr> {
-        let mut left = self.parse_term()?;
+        let mut left = self.parse_bit_or()?;
         loop {
             match self.peek().kind.clone() {
                 TokenKind::Less => {
                     self.advance();
-                    let right = self.parse_term()?;
+                    let right = self.parse_bit_or()?;
                     left = Expr::Lt(Box::new(left), Box::new(right));
                 }
                 TokenKind::LessEq => {
                     self.advance();
-                    let right = self.parse_term()?;
+                    let right = self.parse_bit_or()?;
                     left = Expr::Le(Box::new(left), Box::new(right));
                 }
                 TokenKind::Greater => {
                     self.advance();
-                    let right = self.parse_term()?;
+                    let right = self.parse_bit_or()?;
                     left = Expr::Gt(Box::new(left), Box::new(right));
                 }
                 TokenKind::GreaterEq => {
                     self.advance();
-                    let right = self.parse_term()?;
+                    let right = self.parse_bit_or()?;
                     left = Expr::Ge(Box::new(left), Box::new(right));
                 }
                 _ => break,
@@ -302,6 +445,58 @@ impl Parser {
         Ok(left)
     }
 
+    // Bitwise operators bind tighter than comparisons (as in Rust, not C), so
+    // `x & 1 == 0` tests the masked value: `|` < `^` < `&` < shifts < `+`
+    fn parse_bit_or(&mut self) -> Result<Expr> {
+        let mut left = self.parse_bit_xor()?;
+        while matches!(self.peek().kind, TokenKind::Pipe) {
+            self.advance();
+            let right = self.parse_bit_xor()?;
+            left = Expr::BitOr(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_bit_xor(&mut self) -> Result<Expr> {
+        let mut left = self.parse_bit_and()?;
+        while matches!(self.peek().kind, TokenKind::Caret) {
+            self.advance();
+            let right = self.parse_bit_and()?;
+            left = Expr::BitXor(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_bit_and(&mut self) -> Result<Expr> {
+        let mut left = self.parse_shift()?;
+        while matches!(self.peek().kind, TokenKind::Amp) {
+            self.advance();
+            let right = self.parse_shift()?;
+            left = Expr::BitAnd(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_shift(&mut self) -> Result<Expr> {
+        let mut left = self.parse_term()?;
+        loop {
+            match self.peek().kind.clone() {
+                TokenKind::ShiftLeft => {
+                    self.advance();
+                    let right = self.parse_term()?;
+                    left = Expr::Shl(Box::new(left), Box::new(right));
+                }
+                TokenKind::ShiftRight => {
+                    self.advance();
+                    let right = self.parse_term()?;
+                    left = Expr::Shr(Box::new(left), Box::new(right));
+                }
+                _ => break,
+            }
+        }
+        Ok(left)
+    }
+
     fn parse_term(&mut self) -> Result<Expr> {
         let mut left = self.parse_factor()?;
         loop {
@@ -311,6 +506,9 @@ impl Parser {
                     let right = self.parse_factor()?;
                     left = Expr::BinaryAdd(Box::new(left), Box::new(right));
                 }
+                // Statements aren't newline-terminated, so a `-` opening a new line
+                // negates the next expression instead of continuing this one
+                TokenKind::Minus if self.peek().line > self.tokens[self.pos - 1].line => break,
                 TokenKind::Minus => {
                     self.advance();
                     let right = self.parse_factor()?;
@@ -332,9 +530,15 @@ impl Parser {
                     left = Expr::BinaryMul(Box::new(left), Box::new(right));
                 }
                 TokenKind::Slash => {
+                    let tk = self.peek().clone();
+                    self.advance();
+                    let right = self.parse_unary()?;
+                    left = Expr::BinaryDiv(Box::new(left), Box::new(right), Span { line: tk.line, col: tk.col });
+                }
+                TokenKind::Percent => {
                     self.advance();
                     let right = self.parse_unary()?;
-                    left = Expr::BinaryDiv(Box::new(left), Box::new(right));
+                    left = Expr::BinaryMod(Box::new(left), Box::new(right));
                 }
                 _ => break,
             }
@@ -349,6 +553,11 @@ impl Parser {
                 let e = self.parse_unary()?;
                 Ok(Expr::LogicalNot(Box::new(e)))
             }
+            TokenKind::Minus => {
+                self.advance();
+                let e = self.parse_unary()?;
+                Ok(Expr::Negate(Box::new(e)))
+            }
             _ => self.parse_primary(),
         }
     }
@@ -360,6 +569,10 @@ impl Parser {
                 self.advance();
                 Ok(Expr::LiteralInt(n))
             }
+            TokenKind::Float(x) => {
+                self.advance();
+                Ok(Expr::LiteralFloat(x))
+            }
             TokenKind::String(s) => {
                 self.advance();
                 Ok(Expr::LiteralString(s))
@@ -388,10 +601,10 @@ impl Parser {
                         }
                     }
                     self.expect(TokenKind::RParen)?;
-                    Ok(Expr::Call { name, args })
+                    Ok(Expr::Call { name, args, span: Span { line: tk.line, col: tk.col } })
                 } else {
                     self.advance();
-                    Ok(Expr::Ident(name))
+                    Ok(Expr::Ident(name, Span { line: tk.line, col: tk.col }))
                 }
             }
             TokenKind::LParen => {
@@ -414,6 +627,30 @@ impl Parser {
                 self.expect(TokenKind::RBracket)?;
                 Ok(Expr::List(elems))
             }
+            TokenKind::LBrace => {
+                // map literal
+                self.advance();
+                let mut entries = Vec::new();
+                while !matches!(self.peek().kind, TokenKind::RBrace) {
+                    if !entries.is_empty() {
+                        self.expect(TokenKind::Comma)?;
+                    }
+                    let key = self.parse_expr()?;
+                    self.expect(TokenKind::Colon)?;
+                    entries.push((key, self.parse_expr()?));
+                }
+                self.expect(TokenKind::RBrace)?;
+                Ok(Expr::Map(entries))
+            }
+            // Loops yield no value, so a program cannot read one
+            TokenKind::While | TokenKind::For => zirc_syntax::error::error_at(
+                tk.line,
+                tk.col,
+                format!(
+                    "'{}' is a statement and has no value; assign to a variable inside the loop instead",
+                    if tk.kind == TokenKind::While { "while" } else { "for" }
+                ),
+            ),
             _ => zirc_syntax::error::error_at(
                 tk.line,
                 tk.col,
@@ -422,11 +659,18 @@ impl Parser {
         }?;
         // Postfix indexing
         loop {
-            if matches!(self.peek().kind, TokenKind::LBracket) {
+            let open = self.peek();
+            if matches!(open.kind, TokenKind::LBracket) {
+                let span = Span { line: open.line, col: open.col };
                 self.advance();
                 let idx = self.parse_expr()?;
                 self.expect(TokenKind::RBracket)?;
-                node = Expr::Index(Box::new(node), Box::new(idx));
+                node = Expr::Index(Box::new(node), Box::new(idx), span);
+                // `xs[0](1)` would otherwise parse as two statements; only named functions are callable
+                let tk = self.peek();
+                if matches!(tk.kind, TokenKind::LParen) && tk.line == self.tokens[self.pos - 1].line {
+                    return zirc_syntax::error::error_at(tk.line, tk.col, "Cannot call the result of an index; only functions called by name are supported");
+                }
             } else {
                 break;
             }
@@ -4,24 +4,44 @@
 #[derive(Debug, Clone, PartialEq)]
 pub enum Type {
     Int,
+    Float,
     String,
     Bool,
     List,
     Unit,
 }
 
+/// Where a node starts in the source, as 1-based line and column (in chars),
+/// counted the same way the lexer positions tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub col: usize,
+}
+
 /// Expressions (literals, operations, calls, containers).
 #[derive(Debug, Clone)]
 pub enum Expr {
     LiteralInt(i64),
+    LiteralFloat(f64),
     LiteralString(String),
     LiteralBool(bool),
-    Ident(String),
+    Ident(String, Span),
     // arithmetic
     BinaryAdd(Box<Expr>, Box<Expr>),
     BinarySub(Box<Expr>, Box<Expr>),
     BinaryMul(Box<Expr>, Box<Expr>),
-    BinaryDiv(Box<Expr>, Box<Expr>),
+    // the span is that of the `/`, where a literal division by zero is reported
+    BinaryDiv(Box<Expr>, Box<Expr>, Span),
+    // remainder; the sign follows the dividend, as in Rust and C
+    BinaryMod(Box<Expr>, Box<Expr>),
+    Negate(Box<Expr>),
+    // bitwise, on ints only; shifts are arithmetic and take an amount in 0..64
+    BitAnd(Box<Expr>, Box<Expr>),
+    BitOr(Box<Expr>, Box<Expr>),
+    BitXor(Box<Expr>, Box<Expr>),
+    Shl(Box<Expr>, Box<Expr>),
+    Shr(Box<Expr>, Box<Expr>),
     // logical
     LogicalAnd(Box<Expr>, Box<Expr>),
     LogicalOr(Box<Expr>, Box<Expr>),
@@ -33,9 +53,13 @@ pub enum Expr {
     Le(Box<Expr>, Box<Expr>),
     Gt(Box<Expr>, Box<Expr>),
     Ge(Box<Expr>, Box<Expr>),
-    Call { name: String, args: Vec<Expr> },
+    // the span is that of the function name
+    Call { name: String, args: Vec<Expr>, span: Span },
     List(Vec<Expr>),
-    Index(Box<Expr>, Box<Expr>),
+    // {"key": value, ...}; keys are checked to be strings at runtime
+    Map(Vec<(Expr, Expr)>),
+    // the span is that of the `[`
+    Index(Box<Expr>, Box<Expr>, Span),
 }
 
 /// Statements (variable bindings, control flow, etc.).
@@ -46,31 +70,100 @@ pub enum Stmt {
         ty: Option<Type>,
         expr: Expr,
     },
+    // let a, b = expr  (expr must evaluate to a list of exactly names.len() items)
+    LetUnpack {
+        names: Vec<String>,
+        expr: Expr,
+    },
     Assign {
         name: String,
         expr: Expr,
     },
+    // name += expr (and -=, *=, /=); runs as `name = name + expr`, see [`AssignOp::desugar`]
+    CompoundAssign {
+        name: String,
+        span: Span,
+        op: AssignOp,
+        expr: Expr,
+    },
+    // base[index] = expr  (base is a variable or another index into one, e.g. `grid[i][j] = v`)
+    IndexAssign {
+        base: Expr,
+        index: Expr,
+        expr: Expr,
+    },
     Return(Option<Expr>),
     If {
         cond: Expr,
         then_body: Vec<Stmt>,
         else_body: Vec<Stmt>,
     },
+    // `label: while ...` names the loop for `break label` / `continue label`;
+    // the same goes for both kinds of `for`
     While {
+        label: Option<String>,
         cond: Expr,
         body: Vec<Stmt>,
     },
+    // for var in start..end step n:  (or start..=end when inclusive); a negative
+    // step counts down while var > end, and no step means 1
     For {
+        label: Option<String>,
         var: String,
         start: Expr,
         end: Expr,
+        inclusive: bool,
+        step: Option<Expr>,
         body: Vec<Stmt>,
     },
-    Break,
-    Continue,
+    // for x in iterable:  (a list, or a string for its 1-character strings)
+    ForEach {
+        label: Option<String>,
+        var: String,
+        iterable: Expr,
+        body: Vec<Stmt>,
+    },
+    // the label of an enclosing loop, or None for the innermost one
+    Break(Option<String>),
+    Continue(Option<String>),
     ExprStmt(Expr),
 }
 
+/// The arithmetic operator of a compound assignment like `x += 1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssignOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl AssignOp {
+    /// The operator as written in source, e.g. `+=`.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            AssignOp::Add => "+=",
+            AssignOp::Sub => "-=",
+            AssignOp::Mul => "*=",
+            AssignOp::Div => "/=",
+        }
+    }
+
+    /// The value expression `name op expr` that gets assigned back to `name`,
+    /// so both backends run compound assignment as a plain [`Stmt::Assign`].
+    /// `span` locates the name, for errors reading it or dividing by zero.
+    pub fn desugar(self, name: &str, span: Span, expr: &Expr) -> Expr {
+        let lhs = Box::new(Expr::Ident(name.to_string(), span));
+        let rhs = Box::new(expr.clone());
+        match self {
+            AssignOp::Add => Expr::BinaryAdd(lhs, rhs),
+            AssignOp::Sub => Expr::BinarySub(lhs, rhs),
+            AssignOp::Mul => Expr::BinaryMul(lhs, rhs),
+            AssignOp::Div => Expr::BinaryDiv(lhs, rhs, span),
+        }
+    }
+}
+
 /// Function parameter with optional type annotation.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Param {
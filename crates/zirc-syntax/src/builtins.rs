@@ -0,0 +1,346 @@
+//! Runtime behaviour shared by both execution backends.
+//!
+//! The interpreter and the VM implement builtins and operators separately, so
+//! any message they share lives here and both reference it; scripts then fail
+//! with byte-identical errors whichever backend runs them, and the CLI's error
+//! hints match either one. The value-level work behind builtins such as
+//! `sort()`, `min()` or `range()`, which does not depend on how a backend
+//! evaluates its arguments, lives here too.
+
+use crate::error::{Result, error};
+use crate::value::{Value, float_operands};
+
+/// `pop()` on an empty list.
+pub const POP_EMPTY_LIST: &str = "Cannot pop from empty list";
+/// Integer or float division or modulo by zero.
+pub const DIVISION_BY_ZERO: &str = "division by zero";
+/// Indexing a list or string outside `0..len`, by `[]`, `set()` or assignment.
+pub const INDEX_OUT_OF_BOUNDS: &str = "index out of bounds";
+
+/// `+` on ints past the `i64` range.
+pub const OVERFLOW_ADD: &str = "integer overflow in addition";
+/// `-` on ints past the `i64` range.
+pub const OVERFLOW_SUB: &str = "integer overflow in subtraction";
+/// `*` on ints past the `i64` range.
+pub const OVERFLOW_MUL: &str = "integer overflow in multiplication";
+/// `i64::MIN / -1`.
+pub const OVERFLOW_DIV: &str = "integer overflow in division";
+/// `-i64::MIN`.
+pub const OVERFLOW_NEG: &str = "integer overflow in negation";
+/// `abs(i64::MIN)`.
+pub const OVERFLOW_ABS: &str = "integer overflow in abs()";
+/// `pow()` on ints past the `i64` range.
+pub const OVERFLOW_POW: &str = "integer overflow in pow";
+
+/// `pow()` with a negative int exponent.
+pub const POW_NEGATIVE_EXPONENT: &str = "pow() exponent cannot be negative";
+/// `sqrt()` of a negative number.
+pub const SQRT_NEGATIVE: &str = "sqrt() argument cannot be negative";
+/// `slice()` with a negative start.
+pub const SLICE_NEGATIVE_START: &str = "slice() start index cannot be negative";
+/// `slice()` with an end before its start.
+pub const SLICE_END_BEFORE_START: &str = "slice() end index must be >= start index";
+/// A `for` range whose `step` is 0.
+pub const FOR_STEP_ZERO: &str = "for step cannot be zero";
+/// A `range()` call whose `step` is 0.
+pub const RANGE_STEP_ZERO: &str = "range() step cannot be zero";
+/// `assert()` on false without a message.
+pub const ASSERTION_FAILED: &str = "assertion failed";
+/// A user function call nested deeper than the backend's call-depth limit.
+/// The full message also names the limit, see [`max_recursion_depth`].
+pub const MAX_RECURSION_DEPTH: &str = "maximum recursion depth exceeded";
+
+/// Both backends' default call-depth limit, so a program recurses equally
+/// deep on either.
+pub const DEFAULT_MAX_DEPTH: usize = 1_000;
+
+/// The error message for a call nested deeper than `limit`.
+pub fn max_recursion_depth(limit: usize) -> String {
+    format!("{} ({})", MAX_RECURSION_DEPTH, limit)
+}
+
+
+/// Truncates toward zero; NaN, infinities and out-of-range values are errors.
+pub fn float_to_int(x: f64) -> Result<Value> {
+    // i64::MAX as f64 rounds up to 2^63, so the upper bound is exclusive
+    if x.is_finite() && x >= i64::MIN as f64 && x < i64::MAX as f64 {
+        Ok(Value::Int(x.trunc() as i64))
+    } else {
+        error(format!("Cannot convert {:?} to int", x))
+    }
+}
+
+/// `floor()`, `ceil()` and `round()` as ints; ints pass through unchanged.
+/// `round()` takes halves away from zero, so `round(2.5)` is 3 and `round(-2.5)` is -3.
+pub fn round_to_int(name: &str, v: &Value) -> Result<Value> {
+    let x = match v {
+        Value::Int(n) => return Ok(Value::Int(*n)),
+        Value::Float(x) => *x,
+        other => return error(format!("{}() expects a number, got {:?}", name, other)),
+    };
+    float_to_int(match name {
+        "floor" => x.floor(),
+        "ceil" => x.ceil(),
+        _ => x.round(),
+    })
+}
+
+/// The ints from `start` toward `end` (exclusive) by `step`, for `range()`.
+/// A negative step counts down; stepping past i64's range ends the list.
+pub fn range_list(args: &[Value]) -> Result<Vec<Value>> {
+    let mut ints = Vec::with_capacity(args.len());
+    for a in args {
+        match a {
+            Value::Int(n) => ints.push(*n),
+            other => return error(format!("range() arguments must be ints, got {:?}", other)),
+        }
+    }
+    let (start, end, step) = match ints[..] {
+        [start, end] => (start, end, 1),
+        [_, _, 0] => return error(RANGE_STEP_ZERO),
+        [start, end, step] => (start, end, step),
+        _ => return error("range() expects 2 or 3 arguments: start, end and optional step"),
+    };
+    let values = std::iter::successors(Some(start), |i| i.checked_add(step))
+        .take_while(|i| if step > 0 { *i < end } else { *i > end });
+    Ok(values.map(Value::Int).collect())
+}
+
+/// A line read by `prompt()` without its `\n` or `\r\n` terminator; any
+/// other whitespace, including a `\r` elsewhere, is kept.
+pub fn strip_line_end(mut line: String) -> String {
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') { line.pop(); }
+    }
+    line
+}
+
+/// Position of the first `needle` in a list (by `==`) or string (as a
+/// substring, counted in chars), for `contains()` and `index_of()`.
+pub fn find(haystack: &Value, needle: &Value, name: &str) -> Result<Option<usize>> {
+    match (haystack, needle) {
+        (Value::List(items), _) => Ok(items.iter().position(|it| it.lang_eq(needle))),
+        (Value::Str(s), Value::Str(sub)) => Ok(s.find(sub.as_str()).map(|at| s[..at].chars().count())),
+        (Value::Str(_), other) => error(format!("{}() needle must be string when searching a string, got {:?}", name, other)),
+        (other, _) => error(format!("{}() expects list or string, got {:?}", name, other)),
+    }
+}
+
+/// Converts for `bool()`: numbers are true when nonzero; lists, maps and the
+/// empty string are true when non-empty; unit is false. Other strings must
+/// spell "true" or "false".
+pub fn to_bool(v: &Value) -> Result<bool> {
+    match v {
+        Value::Bool(b) => Ok(*b),
+        Value::Int(n) => Ok(*n != 0),
+        Value::Float(x) => Ok(*x != 0.0),
+        Value::Str(s) => match s.as_str() {
+            "true" => Ok(true),
+            "false" | "" => Ok(false),
+            _ => error(format!("Cannot convert '{}' to bool", s)),
+        },
+        Value::List(items) => Ok(!items.is_empty()),
+        Value::Map(entries) => Ok(!entries.is_empty()),
+        Value::Unit => Ok(false),
+    }
+}
+
+/// A sorted copy of a list of all ints, all floats or all strings, for `sort()`.
+pub fn sorted(items: &[Value]) -> Result<Vec<Value>> {
+    let same_type = [Value::is_int, Value::is_float, Value::is_str].iter().any(|is| items.iter().all(is));
+    if !same_type { return error("sort() requires all elements to be the same comparable type"); }
+    let mut out = items.to_vec();
+    out.sort_by(|a, b| match (a, b) {
+        (Value::Int(x), Value::Int(y)) => x.cmp(y),
+        (Value::Float(x), Value::Float(y)) => x.total_cmp(y),
+        (Value::Str(x), Value::Str(y)) => x.cmp(y),
+        _ => std::cmp::Ordering::Equal,
+    });
+    Ok(out)
+}
+
+/// Orders two numbers for `op`, promoting an int to float when mixed with one.
+/// Two strings order by code point, so one-char strings compare like chars.
+pub fn compare(a: &Value, b: &Value, op: &str) -> Result<bool> {
+    let ord = match (a, b) {
+        (Value::Int(x), Value::Int(y)) => Some(x.cmp(y)),
+        (Value::Str(x), Value::Str(y)) => Some(x.cmp(y)),
+        _ => match float_operands(a, b) {
+            Some((x, y)) => x.partial_cmp(&y),
+            None => return error(format!("{} expects two numbers or two strings", op)),
+        },
+    };
+    // NaN is unordered, so every comparison with it is false
+    Ok(ord.is_some_and(|o| match op {
+        "<" => o.is_lt(),
+        "<=" => o.is_le(),
+        ">" => o.is_gt(),
+        _ => o.is_ge(),
+    }))
+}
+
+/// `min()` or `max()` (`want_max`) of either one list or 2+ values, ordered as
+/// [`compare`] orders them. Numbers follow the usual promotion rule: two ints
+/// give an int, a float makes it a float.
+pub fn min_max(name: &str, args: &[Value], want_max: bool) -> Result<Value> {
+    let items = match args {
+        [Value::List(items)] if items.is_empty() => return error(format!("{}() of empty list", name)),
+        [Value::List(items)] => items.as_slice(),
+        [_, _, ..] => args,
+        _ => return error(format!("{}() expects a list or at least 2 numbers", name)),
+    };
+    let mut best = items[0].clone();
+    for v in items {
+        best = match (&best, v) {
+            (Value::Int(x), Value::Int(y)) => Value::Int(if want_max { *x.max(y) } else { *x.min(y) }),
+            (Value::Str(x), Value::Str(y)) => Value::Str(if want_max { x.max(y) } else { x.min(y) }.clone()),
+            (x, y) => match float_operands(x, y) {
+                Some((fx, fy)) => Value::Float(if want_max { fx.max(fy) } else { fx.min(fy) }),
+                None => return error(format!("{}() cannot compare {:?} and {:?}", name, x, y)),
+            },
+        };
+    }
+    Ok(best)
+}
+
+/// The keys, or the values when `want_values` is set, of a map in entry order.
+pub fn map_entries(entries: Vec<(String, Value)>, want_values: bool) -> Value {
+    Value::List(entries.into_iter().map(|(k, v)| if want_values { v } else { Value::Str(k) }).collect())
+}
+
+/// Returns `base` with the element at `path` (one index per nesting level) replaced by `v`.
+/// A string element can only be replaced by a 1-character string.
+pub fn set_at(base: Value, path: &[i64], v: Value) -> Result<Value> {
+    let Some((&ix, rest)) = path.split_first() else { return Ok(v) };
+    match base {
+        Value::List(mut items) => {
+            if ix < 0 || (ix as usize) >= items.len() { return error(INDEX_OUT_OF_BOUNDS); }
+            let slot = &mut items[ix as usize];
+            *slot = set_at(std::mem::replace(slot, Value::Unit), rest, v)?;
+            Ok(Value::List(items))
+        }
+        Value::Str(s) => {
+            let mut chars: Vec<char> = s.chars().collect();
+            if ix < 0 || (ix as usize) >= chars.len() { return error(INDEX_OUT_OF_BOUNDS); }
+            let ch = set_at(Value::Str(chars[ix as usize].to_string()), rest, v)?;
+            let mut it = ch.as_str().map(|s| s.chars()).into_iter().flatten();
+            match (it.next(), it.next()) {
+                (Some(c), None) => chars[ix as usize] = c,
+                _ => return error(format!("string index assignment expects a 1-character string, got {:?}", ch)),
+            }
+            Ok(Value::Str(chars.into_iter().collect()))
+        }
+        other => error(format!("index assignment not supported for {:?}", other)),
+    }
+}
+
+/// `get(collection, key, default)`: a list item by int index or a map value by
+/// string key, or `default` when there is none.
+pub fn get_or(collection: &Value, key: &Value, default: &Value) -> Result<Value> {
+    let found = match (collection, key) {
+        (Value::List(items), Value::Int(ix)) => usize::try_from(*ix).ok().and_then(|i| items.get(i)),
+        (Value::List(_), other) => return error(format!("get() index must be int, got {:?}", other)),
+        (Value::Map(entries), Value::Str(k)) => entries.iter().find(|(k2, _)| k2 == k).map(|(_, v)| v),
+        (Value::Map(_), other) => return error(format!("get() map key must be string, got {:?}", other)),
+        (other, _) => return error(format!("get() expects list or map, got {:?}", other)),
+    };
+    Ok(found.unwrap_or(default).clone())
+}
+
+/// `set(collection, key, v)`: the collection with a list item or map value
+/// replaced. A new map key is appended, as in a map literal.
+pub fn set_key(collection: Value, key: &Value, v: Value) -> Result<Value> {
+    match (collection, key) {
+        (Value::List(mut items), Value::Int(ix)) => {
+            match usize::try_from(*ix).ok().and_then(|i| items.get_mut(i)) {
+                Some(slot) => *slot = v,
+                None => return error(INDEX_OUT_OF_BOUNDS),
+            }
+            Ok(Value::List(items))
+        }
+        (Value::List(_), other) => error(format!("set() index must be int, got {:?}", other)),
+        (Value::Map(mut entries), Value::Str(k)) => {
+            match entries.iter_mut().find(|(k2, _)| k2 == k) {
+                Some(entry) => entry.1 = v,
+                None => entries.push((k.clone(), v)),
+            }
+            Ok(Value::Map(entries))
+        }
+        (Value::Map(_), other) => error(format!("set() map key must be string, got {:?}", other)),
+        (other, _) => error(format!("set() expects list or map, got {:?}", other)),
+    }
+}
+
+/// `left`/`right`: the first or last `n` chars or items, with `n` clamped to the length.
+pub fn take_edge(collection: &Value, n: &Value, from_right: bool) -> Result<Value> {
+    let name = if from_right { "right" } else { "left" };
+    let n = match n {
+        Value::Int(n) if *n < 0 => return error(format!("{}() count cannot be negative", name)),
+        Value::Int(n) => *n as usize,
+        other => return error(format!("{}() count must be int, got {:?}", name, other)),
+    };
+    match collection {
+        Value::Str(s) => {
+            let len = s.chars().count();
+            let skip = if from_right { len.saturating_sub(n) } else { 0 };
+            Ok(Value::Str(s.chars().skip(skip).take(n).collect()))
+        }
+        Value::List(items) => {
+            let skip = if from_right { items.len().saturating_sub(n) } else { 0 };
+            Ok(Value::List(items.iter().skip(skip).take(n).cloned().collect()))
+        }
+        other => error(format!("{}() expects string or list, got {:?}", name, other)),
+    }
+}
+
+/// Applies a bitwise operator to two ints; shift amounts outside 0..64 are an error.
+pub fn bitwise(a: Value, b: Value, op: &str) -> Result<Value> {
+    let (Value::Int(x), Value::Int(y)) = (&a, &b) else {
+        return error(format!("{} expects ints, got {:?} and {:?}", op, a, b));
+    };
+    let (x, y) = (*x, *y);
+    if matches!(op, "<<" | ">>") && !(0..64).contains(&y) {
+        return error(format!("shift amount {} out of range 0..64", y));
+    }
+    Ok(Value::Int(match op {
+        "&" => x & y,
+        "|" => x | y,
+        "^" => x ^ y,
+        "<<" => x << y,
+        _ => x >> y,
+    }))
+}
+
+/// Computes `base^exp` exactly by square-and-multiply, failing instead of wrapping.
+pub fn int_pow(base: i64, exp: i64) -> Result<i64> {
+    let (mut acc, mut b, mut e) = (1i64, base, exp);
+    while e > 0 {
+        if e & 1 == 1 {
+            let Some(next) = acc.checked_mul(b) else { return error(OVERFLOW_POW) };
+            acc = next;
+        }
+        e >>= 1;
+        if e > 0 {
+            let Some(sq) = b.checked_mul(b) else { return error(OVERFLOW_POW) };
+            b = sq;
+        }
+    }
+    Ok(acc)
+}
+
+/// Renders `n` in `base` (2-36) with lowercase digits; negatives get a `-` before the prefix.
+pub fn radix_string(n: i64, base: u32, prefix: &str) -> String {
+    let mut m = n.unsigned_abs();
+    let mut digits = Vec::new();
+    loop {
+        digits.push(std::char::from_digit((m % base as u64) as u32, base).unwrap());
+        m /= base as u64;
+        if m == 0 { break; }
+    }
+    let mut out = String::with_capacity(digits.len() + prefix.len() + 1);
+    if n < 0 { out.push('-'); }
+    out.push_str(prefix);
+    out.extend(digits.iter().rev());
+    out
+}
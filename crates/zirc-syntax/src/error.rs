@@ -153,6 +153,22 @@ impl Error {
             col: Some(col),
         }
     }
+
+    /// Gives the error a location if it has none yet; one set closer to the
+    /// cause is kept.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use zirc_syntax::Error;
+    ///
+    /// let err = Error::new("index out of bounds").or_span(3, 7);
+    /// assert_eq!((err.line, err.col), (Some(3), Some(7)));
+    /// assert_eq!(err.or_span(1, 1).to_string(), "index out of bounds at 3:7");
+    /// ```
+    pub fn or_span(self, line: usize, col: usize) -> Self {
+        if self.line.is_some() { self } else { Self { line: Some(line), col: Some(col), ..self } }
+    }
 }
 
 impl fmt::Display for Error {
@@ -237,7 +253,7 @@ pub fn error<T>(msg: impl Into<String>) -> Result<T> {
 ///
 /// fn parse_token_at_location(line: usize, col: usize) -> Result<String> {
 ///     // Simulate a parse error at a specific location
-///     error_at(line, col, "Unexpected character '&'")
+///     error_at(line, col, "Unexpected character '@'")
 /// }
 /// ```
 pub fn error_at<T>(line: usize, col: usize, msg: impl Into<String>) -> Result<T> {
@@ -0,0 +1,111 @@
+//! The format-string engine behind `showf` and `format`, shared by both
+//! execution backends.
+//!
+//! Supported specifiers are `%d` (int), `%s` (string, bool or list), `%v` (any
+//! value, as `show()` prints it) and `%%` (a literal percent sign). Arguments
+//! beyond those consumed are ignored.
+//!
+//! `%s` also takes a width in chars, padding with spaces: `%10s` right-justifies
+//! and `%-10s` left-justifies. Text longer than the width is not cut.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use zirc_syntax::{format::showf, Value};
+//!
+//! let out = showf("%s is %d%%", &[Value::Str("zirc".to_string()), Value::Int(100)]).unwrap();
+//! assert_eq!(out, "zirc is 100%");
+//!
+//! let list = Value::List(vec![Value::Int(1), Value::Int(2)]);
+//! assert_eq!(showf("%v %v", &[list, Value::Unit]).unwrap(), "[1, 2] <unit>");
+//!
+//! let ab = Value::Str("ab".to_string());
+//! assert_eq!(showf("[%-4s|%4s]", &[ab.clone(), ab]).unwrap(), "[ab  |  ab]");
+//!
+//! let err = showf("%d + %d", &[Value::Int(1)]).unwrap_err();
+//! assert_eq!(err.msg, "showf: format expects at least 2 arguments but got 1 (missing %d at position 5)");
+//! ```
+
+use crate::error::{Result, error};
+use crate::value::Value;
+
+/// Renders `fmt` with `args` substituted for its specifiers.
+///
+/// Errors name the offending specifier and its 0-based char position in `fmt`.
+pub fn showf(fmt: &str, args: &[Value]) -> Result<String> {
+    render("showf", fmt, args)
+}
+
+/// Renders the arguments of a call to the builtin `name` (`showf` or
+/// `format`): the format string first, then the values for its specifiers.
+///
+/// # Examples
+///
+/// ```rust
+/// use zirc_syntax::{format::format_call, Value};
+///
+/// let args = [Value::Str("%d%%".to_string()), Value::Int(50)];
+/// assert_eq!(format_call("format", &args).unwrap(), "50%");
+/// assert_eq!(format_call("format", &[]).unwrap_err().msg, "format requires at least a format string");
+/// ```
+pub fn format_call(name: &str, args: &[Value]) -> Result<String> {
+    match args.first() {
+        Some(Value::Str(fmt)) => render(name, fmt, &args[1..]),
+        Some(_) => error(format!("{} first argument must be a string", name)),
+        None => error(format!("{} requires at least a format string", name)),
+    }
+}
+
+fn render(name: &str, fmt: &str, args: &[Value]) -> Result<String> {
+    let mut out = String::new();
+    let mut arg_i = 0usize;
+    let mut chars = fmt.chars().enumerate().peekable();
+    while let Some((pos, c)) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let left = chars.next_if(|&(_, c)| c == '-').is_some();
+        let mut width = None;
+        while let Some((_, d)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+            let digit = d.to_digit(10).unwrap_or(0) as usize;
+            width = Some(width.unwrap_or(0usize).saturating_mul(10).saturating_add(digit));
+        }
+        let spec = chars.next().map(|(_, spec)| spec);
+        if (left || width.is_some()) && spec.is_some() && spec != Some('s') {
+            return error(format!("Width and alignment are only supported for %s (position {})", pos));
+        }
+        match spec {
+            Some(spec @ ('d' | 's' | 'v')) => {
+                let Some(arg) = args.get(arg_i) else {
+                    return error(format!(
+                        "{}: format expects at least {} arguments but got {} (missing %{} at position {})",
+                        name, arg_i + 1, args.len(), spec, pos
+                    ));
+                };
+                match (spec, arg) {
+                    ('d', Value::Int(n)) => out.push_str(&n.to_string()),
+                    ('v', v) => out.push_str(&v.to_string()),
+                    ('d', other) => return error(format!("%d at position {} expects int, got {:?}", pos, other)),
+                    (_, v @ (Value::Str(_) | Value::Bool(_) | Value::List(_))) => {
+                        let text = v.to_string();
+                        let pad = " ".repeat(width.unwrap_or(0).saturating_sub(text.chars().count()));
+                        if left {
+                            out.push_str(&text);
+                            out.push_str(&pad);
+                        } else {
+                            out.push_str(&pad);
+                            out.push_str(&text);
+                        }
+                    }
+                    (_, other) => return error(format!("%s at position {} expects string/bool/list, got {:?}", pos, other)),
+                }
+                arg_i += 1;
+            }
+            Some('%') => out.push('%'),
+            Some(other) => return error(format!("Unsupported format specifier %{} at position {}", other, pos)),
+            None => return error(format!("Dangling % at end of format string (position {})", pos)),
+        }
+    }
+    Ok(out)
+}
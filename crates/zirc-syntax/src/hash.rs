@@ -0,0 +1,67 @@
+//! The stable hash behind the `hash()` builtin, shared by both backends.
+//!
+//! Values are hashed with 64-bit FNV-1a over a tagged byte encoding, so the
+//! result never depends on the process, the platform or the backend. Each
+//! value starts with a type tag, so `1`, `"1"` and `true` hash differently,
+//! and strings and lists carry their length, so `["ab", "c"]` and
+//! `["a", "bc"]` do too.
+//!
+//! Floats are rejected: `2 == 2.0`, so hashing them apart would break the
+//! rule that equal values hash equal.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use zirc_syntax::{hash::hash_value, Value};
+//!
+//! let a = hash_value(&Value::Str("zirc".to_string())).unwrap();
+//! assert_eq!(a, hash_value(&Value::Str("zirc".to_string())).unwrap());
+//! assert_ne!(a, hash_value(&Value::Str("Zirc".to_string())).unwrap());
+//!
+//! let err = hash_value(&Value::Float(1.5)).unwrap_err();
+//! assert_eq!(err.msg, "hash() expects int, string, bool or list, got Float(1.5)");
+//! ```
+
+use crate::error::{Result, error};
+use crate::value::Value;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Returns the FNV-1a hash of `v`, reinterpreted as `i64`.
+pub fn hash_value(v: &Value) -> Result<i64> {
+    let mut h = FNV_OFFSET;
+    write_value(&mut h, v)?;
+    Ok(h as i64)
+}
+
+fn write_bytes(h: &mut u64, bytes: &[u8]) {
+    for &b in bytes {
+        *h ^= b as u64;
+        *h = h.wrapping_mul(FNV_PRIME);
+    }
+}
+
+fn write_value(h: &mut u64, v: &Value) -> Result<()> {
+    match v {
+        Value::Int(n) => {
+            write_bytes(h, &[0]);
+            write_bytes(h, &n.to_le_bytes());
+        }
+        Value::Str(s) => {
+            write_bytes(h, &[1]);
+            write_bytes(h, &(s.len() as u64).to_le_bytes());
+            write_bytes(h, s.as_bytes());
+        }
+        Value::Bool(b) => write_bytes(h, &[2, *b as u8]),
+        Value::List(items) => {
+            write_bytes(h, &[3]);
+            write_bytes(h, &(items.len() as u64).to_le_bytes());
+            for it in items {
+                write_value(h, it)?;
+            }
+        }
+        other => return error(format!("hash() expects int, string, bool or list, got {:?}", other)),
+    }
+    Ok(())
+}
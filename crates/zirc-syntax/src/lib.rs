@@ -6,11 +6,16 @@
 //!
 //! # Overview
 //!
-//! The crate is organized into three main modules:
+//! The crate is organized into eight main modules:
 //!
 //! - [`token`]: Token types and lexical elements
 //! - [`ast`]: Abstract syntax tree node definitions  
+//! - [`pretty`]: An indented tree view of the AST
 //! - [`error`]: Error handling types and utilities
+//! - [`value`]: Runtime values shared by the interpreter and the VM
+//! - [`format`]: The `showf`/`format` format-string engine
+//! - [`hash`]: The stable hash behind `hash()`
+//! - [`builtins`]: Runtime error messages and builtin helpers both backends share
 //!
 //! # Architecture
 //!
@@ -68,13 +73,208 @@ pub mod token;
 /// including expressions, statements, types, and program structure.
 pub mod ast;
 
+/// Indented tree rendering of the AST.
+///
+/// [`pretty::pretty_print`] shows a program's structure one node per line,
+/// for `zirc --dump-ast`.
+pub mod pretty;
+
 /// Error handling utilities and types.
 ///
 /// This module provides error types, result types, and utility functions
 /// for consistent error handling across the Zirc toolchain.
 pub mod error;
 
+/// Runtime value type shared by both execution backends.
+///
+/// This module defines [`Value`] along with its `Display` rendering and
+/// accessors for embedders inspecting program results.
+pub mod value;
+
+/// Format-string rendering for `showf` and `format`.
+///
+/// Both backends delegate to [`format::format_call`] so their output and error
+/// messages stay identical.
+pub mod format;
+
+/// Stable value hashing for `hash()`.
+///
+/// Both backends delegate to [`hash::hash_value`] so a value hashes the same
+/// everywhere and on every run.
+pub mod hash;
+
+/// Runtime error messages shared by both execution backends.
+///
+/// Builtins and operators implemented twice reference these constants, so a
+/// failing script reports the same message under either backend.
+pub mod builtins;
+
 // Re-export all public items for convenience
 pub use ast::*;
 pub use error::*;
 pub use token::*;
+pub use value::Value;
+
+#[cfg(test)]
+mod tests {
+    use super::Value;
+
+    fn all_variants() -> Vec<Value> {
+        vec![
+            Value::Int(7),
+            Value::Str("s".to_string()),
+            Value::Bool(true),
+            Value::List(vec![Value::Unit]),
+            Value::Unit,
+        ]
+    }
+
+    #[test]
+    fn test_value_predicates() {
+        let expected = [
+            [true, false, false, false, false],
+            [false, true, false, false, false],
+            [false, false, true, false, false],
+            [false, false, false, true, false],
+            [false, false, false, false, true],
+        ];
+        for (v, want) in all_variants().iter().zip(expected) {
+            let got = [v.is_int(), v.is_str(), v.is_bool(), v.is_list(), v.is_unit()];
+            assert_eq!(got, want, "{:?}", v);
+        }
+    }
+
+    #[test]
+    fn test_value_display_depth() {
+        let three = Value::List(vec![
+            Value::Int(1),
+            Value::List(vec![Value::Int(2), Value::List(vec![Value::Int(3)])]),
+        ]);
+        assert_eq!(three.display_depth(Some(3)), "[1, [2, [3]]]");
+        assert_eq!(three.display_depth(Some(2)), "[1, [2, [...]]]");
+        assert_eq!(three.display_depth(None), three.to_string());
+        assert_eq!(Value::Int(5).display_depth(Some(0)), "5");
+    }
+
+    #[test]
+    fn test_showf_v_specifier() {
+        use super::format::showf;
+        let list = Value::List(vec![Value::Int(1), Value::Int(2), Value::Int(3)]);
+        assert_eq!(showf("%v", &[list]).unwrap(), "[1, 2, 3]");
+        assert_eq!(showf("%v %v", &[Value::Int(1), Value::Bool(true)]).unwrap(), "1 true");
+        assert_eq!(
+            showf("x=%v", &[]).unwrap_err().msg,
+            "showf: format expects at least 1 arguments but got 0 (missing %v at position 2)"
+        );
+    }
+
+    #[test]
+    fn test_showf_s_width() {
+        use super::format::format_call;
+        let s = |x: &str| Value::Str(x.to_string());
+        assert_eq!(format_call("format", &[s("%-5s|"), s("ab")]).unwrap(), "ab   |");
+        assert_eq!(format_call("format", &[s("%5s|"), s("ab")]).unwrap(), "   ab|");
+        // Width counts chars, and longer text is not truncated
+        assert_eq!(format_call("format", &[s("%4s|"), s("né")]).unwrap(), "  né|");
+        assert_eq!(format_call("format", &[s("%2s|%-s|"), s("long"), Value::Bool(true)]).unwrap(), "long|true|");
+        assert_eq!(format_call("format", &[s("%6s"), Value::List(vec![Value::Int(1)])]).unwrap(), "   [1]");
+        assert_eq!(
+            format_call("format", &[s("a %5d"), Value::Int(1)]).unwrap_err().msg,
+            "Width and alignment are only supported for %s (position 2)"
+        );
+        assert_eq!(
+            format_call("format", &[s("%-5")]).unwrap_err().msg,
+            "Dangling % at end of format string (position 0)"
+        );
+    }
+
+    #[test]
+    fn test_value_accessors() {
+        let vs = all_variants();
+        assert_eq!(vs.iter().map(|v| v.as_int()).collect::<Vec<_>>(), [Some(7), None, None, None, None]);
+        assert_eq!(vs.iter().map(|v| v.as_str()).collect::<Vec<_>>(), [None, Some("s"), None, None, None]);
+        assert_eq!(vs.iter().map(|v| v.as_bool()).collect::<Vec<_>>(), [None, None, Some(true), None, None]);
+        let unit_list = [Value::Unit];
+        assert_eq!(vs.iter().map(|v| v.as_list()).collect::<Vec<_>>(), [None, None, None, Some(&unit_list[..]), None]);
+    }
+
+    #[test]
+    fn test_float_values() {
+        use super::value::float_operands;
+        let cases = [(3.0, "3.0"), (0.5, "0.5"), (2.75, "2.75"), (1e-7, "1e-7"), (1e20, "1e20")];
+        for (x, shown) in cases {
+            assert_eq!(Value::Float(x).to_string(), shown);
+        }
+        assert!(Value::Float(1.0).is_float());
+        assert_eq!(Value::Float(1.0).as_int(), None);
+        assert!(Value::List(vec![Value::Int(1)]).lang_eq(&Value::List(vec![Value::Float(1.0)])));
+        assert!(!Value::Float(f64::NAN).lang_eq(&Value::Float(f64::NAN)));
+        assert_eq!(float_operands(&Value::Float(1.5), &Value::Str("x".to_string())), None);
+    }
+
+    #[test]
+    fn test_list_equality() {
+        let ints = |ns: &[i64]| Value::List(ns.iter().map(|&n| Value::Int(n)).collect());
+        assert!(ints(&[1, 2, 3]).lang_eq(&ints(&[1, 2, 3])));
+        assert!(!ints(&[1, 2, 3]).lang_eq(&ints(&[1, 2])));
+        assert!(!ints(&[]).lang_eq(&ints(&[0])));
+        assert!(ints(&[]).lang_eq(&ints(&[])));
+
+        let nested = |inner: Value| Value::List(vec![Value::Int(1), inner]);
+        assert!(nested(ints(&[2, 3])).lang_eq(&nested(ints(&[2, 3]))));
+        assert!(!nested(ints(&[2, 3])).lang_eq(&nested(ints(&[2]))));
+        assert!(!nested(ints(&[2, 3])).lang_eq(&nested(ints(&[2, 4]))));
+
+        // Large lists: equal ones compare every element, unequal lengths compare none
+        let big: Vec<i64> = (0..100_000).collect();
+        assert!(ints(&big).lang_eq(&ints(&big)));
+        assert!(!ints(&big).lang_eq(&ints(&big[1..])));
+    }
+
+    #[test]
+    fn test_map_equality_and_display() {
+        let map = |entries: &[(&str, i64)]| Value::Map(entries.iter().map(|&(k, n)| (k.to_string(), Value::Int(n))).collect());
+        assert!(map(&[("a", 1), ("b", 2)]).lang_eq(&map(&[("b", 2), ("a", 1)])));
+        assert!(!map(&[("a", 1), ("b", 2)]).lang_eq(&map(&[("a", 1), ("b", 3)])));
+        assert!(!map(&[("a", 1)]).lang_eq(&map(&[("a", 1), ("b", 2)])));
+        assert!(!map(&[("a", 1)]).lang_eq(&map(&[("b", 1)])));
+        assert!(map(&[]).lang_eq(&map(&[])));
+        assert!(Value::Map(vec![("x".to_string(), Value::Float(1.0))]).lang_eq(&map(&[("x", 1)])));
+
+        let nested = Value::Map(vec![("k".to_string(), map(&[("a", 1)])), ("l".to_string(), Value::List(vec![]))]);
+        assert_eq!(nested.to_string(), "{k: {a: 1}, l: []}");
+        assert_eq!(nested.display_depth(Some(1)), "{k: {...}, l: [...]}");
+        assert_eq!(map(&[]).to_string(), "{}");
+    }
+
+    #[test]
+    fn test_value_type_name_and_display() {
+        let vs = all_variants();
+        assert_eq!(vs.iter().map(|v| v.type_name()).collect::<Vec<_>>(), ["int", "string", "bool", "list", "unit"]);
+        assert_eq!(vs.iter().map(|v| v.to_string()).collect::<Vec<_>>(), ["7", "s", "true", "[<unit>]", "<unit>"]);
+    }
+
+    #[test]
+    fn test_hash_is_pinned() {
+        use super::hash::hash_value;
+        let s = |x: &str| Value::Str(x.to_string());
+        // Pinned so the algorithm cannot drift between releases or backends
+        let cases = [
+            (Value::Int(0), -1872228358846142017),
+            (Value::Int(42), -8101521626687829867),
+            (Value::Int(-1), 8811690462992864311),
+            (s(""), 5952119183343170476),
+            (s("zirc"), -7998348951831333974),
+            (Value::Bool(true), 592596118541513868),
+            (Value::Bool(false), 592597218053142079),
+            (Value::List(vec![]), 8750168172674097106),
+            (Value::List(vec![Value::Int(1), s("a")]), -3563948975058001968),
+            (Value::List(vec![s("ab"), s("c")]), -8491078613236029371),
+            (Value::List(vec![s("a"), s("bc")]), 594406422262685409),
+        ];
+        for (v, want) in cases {
+            assert_eq!(hash_value(&v).unwrap(), want, "hash({})", v);
+        }
+        assert_eq!(hash_value(&Value::Unit).unwrap_err().msg, "hash() expects int, string, bool or list, got Unit");
+    }
+}
@@ -0,0 +1,263 @@
+//! An indented tree view of a parsed [`Program`], for teaching and debugging.
+//!
+//! Each node is one line naming its variant, with its children indented two
+//! spaces below it. Statements with several parts label them (`cond:`,
+//! `then:`, `body:`, ...) so nested blocks stay readable. Identifiers, calls,
+//! indexing and division show their source position as `@line:col`.
+//!
+//! Unlike `zirc-fmt`, which prints source code back, this shows structure.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use zirc_syntax::{ast::*, pretty::pretty_print};
+//!
+//! let sum = Expr::BinaryAdd(
+//!     Box::new(Expr::BinaryMul(Box::new(Expr::LiteralInt(2)), Box::new(Expr::LiteralInt(3)))),
+//!     Box::new(Expr::Ident("x".to_string(), Span { line: 1, col: 13 })),
+//! );
+//! let program = Program { items: vec![Item::Stmt(Stmt::Let { name: "y".to_string(), ty: None, expr: sum })] };
+//! assert_eq!(pretty_print(&program), "\
+//! Program
+//!   Let y
+//!     BinaryAdd
+//!       BinaryMul
+//!         LiteralInt 2
+//!         LiteralInt 3
+//!       Ident x @1:13
+//! ");
+//! ```
+
+use crate::ast::*;
+
+/// Renders `program` as an indented tree, one node per line.
+pub fn pretty_print(program: &Program) -> String {
+    let mut p = Printer { out: String::new(), depth: 0 };
+    p.line("Program");
+    p.nested(|p| {
+        for item in &program.items {
+            match item {
+                Item::Function(f) => p.function(f),
+                Item::Stmt(s) => p.stmt(s),
+            }
+        }
+    });
+    p.out
+}
+
+struct Printer {
+    out: String,
+    depth: usize,
+}
+
+impl Printer {
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.out.push_str("  ");
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn nested(&mut self, f: impl FnOnce(&mut Self)) {
+        self.depth += 1;
+        f(self);
+        self.depth -= 1;
+    }
+
+    /// A `label:` line with the statements indented under it.
+    fn block(&mut self, label: &str, stmts: &[Stmt]) {
+        self.line(&format!("{}:", label));
+        self.nested(|p| for s in stmts { p.stmt(s) });
+    }
+
+    /// A `label:` line with one expression indented under it.
+    fn part(&mut self, label: &str, e: &Expr) {
+        self.line(&format!("{}:", label));
+        self.nested(|p| p.expr(e));
+    }
+
+    fn function(&mut self, f: &Function) {
+        let params: Vec<String> = f.params.iter().map(|p| with_type(&p.name, &p.ty)).collect();
+        let ret = f.return_type.as_ref().map(|t| format!(" ({})", type_name(t))).unwrap_or_default();
+        self.line(&format!("Function {}({}){}", f.name, params.join(", "), ret));
+        self.nested(|p| for s in &f.body { p.stmt(s) });
+    }
+
+    fn stmt(&mut self, s: &Stmt) {
+        match s {
+            Stmt::Let { name, ty, expr } => {
+                self.line(&format!("Let {}", with_type(name, ty)));
+                self.nested(|p| p.expr(expr));
+            }
+            Stmt::LetUnpack { names, expr } => {
+                self.line(&format!("LetUnpack {}", names.join(", ")));
+                self.nested(|p| p.expr(expr));
+            }
+            Stmt::Assign { name, expr } => {
+                self.line(&format!("Assign {}", name));
+                self.nested(|p| p.expr(expr));
+            }
+            Stmt::CompoundAssign { name, span, op, expr } => {
+                self.line(&format!("CompoundAssign {} {} {}", name, op.symbol(), at(span)));
+                self.nested(|p| p.expr(expr));
+            }
+            Stmt::IndexAssign { base, index, expr } => {
+                self.line("IndexAssign");
+                self.nested(|p| {
+                    p.part("base", base);
+                    p.part("index", index);
+                    p.part("value", expr);
+                });
+            }
+            Stmt::Return(value) => {
+                self.line("Return");
+                if let Some(e) = value { self.nested(|p| p.expr(e)) }
+            }
+            Stmt::If { cond, then_body, else_body } => {
+                self.line("If");
+                self.nested(|p| {
+                    p.part("cond", cond);
+                    p.block("then", then_body);
+                    if !else_body.is_empty() { p.block("else", else_body) }
+                });
+            }
+            Stmt::While { label, cond, body } => {
+                self.line(&labelled("While", label));
+                self.nested(|p| {
+                    p.part("cond", cond);
+                    p.block("body", body);
+                });
+            }
+            Stmt::For { label, var, start, end, inclusive, step, body } => {
+                let range = if *inclusive { "start..=end" } else { "start..end" };
+                self.line(&format!("{} {} in {}", labelled("For", label), var, range));
+                self.nested(|p| {
+                    p.part("start", start);
+                    p.part("end", end);
+                    if let Some(step) = step { p.part("step", step) }
+                    p.block("body", body);
+                });
+            }
+            Stmt::ForEach { label, var, iterable, body } => {
+                self.line(&format!("{} {}", labelled("ForEach", label), var));
+                self.nested(|p| {
+                    p.part("iterable", iterable);
+                    p.block("body", body);
+                });
+            }
+            Stmt::Break(target) => self.line(&with_target("Break", target)),
+            Stmt::Continue(target) => self.line(&with_target("Continue", target)),
+            Stmt::ExprStmt(e) => {
+                self.line("ExprStmt");
+                self.nested(|p| p.expr(e));
+            }
+        }
+    }
+
+    fn expr(&mut self, e: &Expr) {
+        let (name, a, b) = match e {
+            Expr::LiteralInt(n) => return self.line(&format!("LiteralInt {}", n)),
+            Expr::LiteralFloat(x) => return self.line(&format!("LiteralFloat {:?}", x)),
+            Expr::LiteralString(s) => return self.line(&format!("LiteralString {:?}", s)),
+            Expr::LiteralBool(b) => return self.line(&format!("LiteralBool {}", b)),
+            Expr::Ident(name, span) => return self.line(&format!("Ident {} {}", name, at(span))),
+            Expr::Negate(a) => return self.unary("Negate", a),
+            Expr::LogicalNot(a) => return self.unary("LogicalNot", a),
+            Expr::Call { name, args, span } => {
+                self.line(&format!("Call {} {}", name, at(span)));
+                return self.nested(|p| for a in args { p.expr(a) });
+            }
+            Expr::List(items) => {
+                self.line("List");
+                return self.nested(|p| for it in items { p.expr(it) });
+            }
+            Expr::Map(entries) => {
+                self.line("Map");
+                return self.nested(|p| {
+                    for (k, v) in entries {
+                        p.part("key", k);
+                        p.part("value", v);
+                    }
+                });
+            }
+            Expr::BinaryDiv(a, b, span) => {
+                self.line(&format!("BinaryDiv {}", at(span)));
+                return self.nested(|p| {
+                    p.expr(a);
+                    p.expr(b);
+                });
+            }
+            Expr::Index(base, index, span) => {
+                self.line(&format!("Index {}", at(span)));
+                return self.nested(|p| {
+                    p.expr(base);
+                    p.expr(index);
+                });
+            }
+            Expr::BinaryAdd(a, b) => ("BinaryAdd", a, b),
+            Expr::BinarySub(a, b) => ("BinarySub", a, b),
+            Expr::BinaryMul(a, b) => ("BinaryMul", a, b),
+            Expr::BinaryMod(a, b) => ("BinaryMod", a, b),
+            Expr::BitAnd(a, b) => ("BitAnd", a, b),
+            Expr::BitOr(a, b) => ("BitOr", a, b),
+            Expr::BitXor(a, b) => ("BitXor", a, b),
+            Expr::Shl(a, b) => ("Shl", a, b),
+            Expr::Shr(a, b) => ("Shr", a, b),
+            Expr::LogicalAnd(a, b) => ("LogicalAnd", a, b),
+            Expr::LogicalOr(a, b) => ("LogicalOr", a, b),
+            Expr::Eq(a, b) => ("Eq", a, b),
+            Expr::Ne(a, b) => ("Ne", a, b),
+            Expr::Lt(a, b) => ("Lt", a, b),
+            Expr::Le(a, b) => ("Le", a, b),
+            Expr::Gt(a, b) => ("Gt", a, b),
+            Expr::Ge(a, b) => ("Ge", a, b),
+        };
+        self.line(name);
+        self.nested(|p| {
+            p.expr(a);
+            p.expr(b);
+        });
+    }
+
+    fn unary(&mut self, name: &str, a: &Expr) {
+        self.line(name);
+        self.nested(|p| p.expr(a));
+    }
+}
+
+fn at(span: &Span) -> String {
+    format!("@{}:{}", span.line, span.col)
+}
+
+fn type_name(t: &Type) -> &'static str {
+    match t {
+        Type::Int => "int",
+        Type::Float => "float",
+        Type::String => "string",
+        Type::Bool => "bool",
+        Type::List => "list",
+        Type::Unit => "unit",
+    }
+}
+
+fn with_type(name: &str, ty: &Option<Type>) -> String {
+    match ty {
+        Some(t) => format!("{}: {}", name, type_name(t)),
+        None => name.to_string(),
+    }
+}
+
+fn labelled(kind: &str, label: &Option<String>) -> String {
+    match label {
+        Some(l) => format!("{} {}:", kind, l),
+        None => kind.to_string(),
+    }
+}
+
+fn with_target(kind: &str, target: &Option<String>) -> String {
+    match target {
+        Some(t) => format!("{} {}", kind, t),
+        None => kind.to_string(),
+    }
+}
@@ -53,6 +53,7 @@
 /// ## Literals
 /// - [`Ident`](TokenKind::Ident): Variable and function names
 /// - [`Number`](TokenKind::Number): Integer literals  
+/// - [`Float`](TokenKind::Float): Floating-point literals
 /// - [`String`](TokenKind::String): String literals
 ///
 /// ## Keywords
@@ -90,6 +91,11 @@ pub enum TokenKind {
     /// Examples: `42`, `-123`, `0`
     Number(i64),
     
+    /// A floating-point literal token (64-bit IEEE 754)
+    /// 
+    /// Examples: `3.14`, `1.0e-5`, `2e10`
+    Float(f64),
+    
     /// A string literal token
     /// 
     /// Examples: `"hello"`, `"world!"`, `""`
@@ -109,6 +115,9 @@ pub enum TokenKind {
     /// The `else` keyword - used for alternative branches
     Else,
     
+    /// The `elif` keyword - chains another condition onto an `if`
+    Elif,
+    
     /// The `while` keyword - used for loops
     While,
     
@@ -156,6 +165,12 @@ pub enum TokenKind {
     /// Right square bracket `]`
     RBracket,
     
+    /// Left curly brace `{`
+    LBrace,
+    
+    /// Right curly brace `}`
+    RBrace,
+    
     // === Operators ===
     
     /// Assignment operator `=`
@@ -173,6 +188,36 @@ pub enum TokenKind {
     /// Division operator `/`
     Slash,
     
+    /// Remainder operator `%`
+    Percent,
+    
+    /// Compound addition assignment `+=`
+    PlusEq,
+    
+    /// Compound subtraction assignment `-=`
+    MinusEq,
+    
+    /// Compound multiplication assignment `*=`
+    StarEq,
+    
+    /// Compound division assignment `/=`
+    SlashEq,
+    
+    /// Bitwise AND operator `&`
+    Amp,
+    
+    /// Bitwise OR operator `|`
+    Pipe,
+    
+    /// Bitwise XOR operator `^`
+    Caret,
+    
+    /// Left shift operator `<<`
+    ShiftLeft,
+    
+    /// Arithmetic right shift operator `>>`
+    ShiftRight,
+    
     /// Equality comparison operator `==`
     EqEq,
     
@@ -203,6 +248,9 @@ pub enum TokenKind {
     /// Range operator `..` used in for-loops
     DotDot,
     
+    /// Inclusive range operator `..=` used in for-loops
+    DotDotEq,
+    
     /// End-of-file marker - indicates no more tokens
     Eof,
 }
@@ -0,0 +1,349 @@
+//! Runtime values shared by the Zirc interpreter and bytecode VM.
+//!
+//! Both execution backends produce and consume the same [`Value`] type, so host
+//! code embedding either one can inspect results the same way.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use zirc_syntax::Value;
+//!
+//! let v = Value::List(vec![Value::Int(1), Value::Str("two".to_string())]);
+//! assert_eq!(v.type_name(), "list");
+//! assert_eq!(v.to_string(), "[1, two]");
+//!
+//! let first = v.as_list().and_then(|items| items[0].as_int());
+//! assert_eq!(first, Some(1));
+//! ```
+
+use std::fmt;
+
+use crate::builtins;
+use crate::error::{Result, error};
+
+/// A runtime value in a Zirc program.
+///
+/// The `Display` implementation renders values the way `show()` prints them:
+/// strings without quotes, lists as `[a, b]`, maps as `{k: v}`, and unit as `<unit>`. Floats
+/// always show a fraction or exponent (`3.0`, `0.5`, `1e-7`) so they never
+/// look like ints.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// A 64-bit signed integer value
+    Int(i64),
+    /// A 64-bit IEEE 754 floating-point value
+    Float(f64),
+    /// A UTF-8 encoded string value
+    Str(String),
+    /// A boolean value (true or false)
+    Bool(bool),
+    /// A dynamic list containing other values
+    List(Vec<Value>),
+    /// String-keyed entries in insertion order, each key at most once.
+    Map(Vec<(String, Value)>),
+    /// The unit value representing "no value"
+    Unit,
+}
+
+impl Value {
+    /// Returns the name `type()` reports for this value.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use zirc_syntax::Value;
+    ///
+    /// assert_eq!(Value::Int(1).type_name(), "int");
+    /// assert_eq!(Value::Float(1.5).type_name(), "float");
+    /// assert_eq!(Value::Str(String::new()).type_name(), "string");
+    /// assert_eq!(Value::Bool(true).type_name(), "bool");
+    /// assert_eq!(Value::List(vec![]).type_name(), "list");
+    /// assert_eq!(Value::Map(vec![]).type_name(), "map");
+    /// assert_eq!(Value::Unit.type_name(), "unit");
+    /// ```
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Str(_) => "string",
+            Value::Bool(_) => "bool",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Unit => "unit",
+        }
+    }
+
+    /// Returns `true` if the value is an [`Int`](Value::Int).
+    pub fn is_int(&self) -> bool {
+        matches!(self, Value::Int(_))
+    }
+
+    /// Returns `true` if the value is a [`Float`](Value::Float).
+    pub fn is_float(&self) -> bool {
+        matches!(self, Value::Float(_))
+    }
+
+    /// Returns `true` if the value is a [`Str`](Value::Str).
+    pub fn is_str(&self) -> bool {
+        matches!(self, Value::Str(_))
+    }
+
+    /// Returns `true` if the value is a [`Bool`](Value::Bool).
+    pub fn is_bool(&self) -> bool {
+        matches!(self, Value::Bool(_))
+    }
+
+    /// Returns `true` if the value is a [`List`](Value::List).
+    pub fn is_list(&self) -> bool {
+        matches!(self, Value::List(_))
+    }
+
+    /// Returns `true` if the value is a [`Map`](Value::Map).
+    pub fn is_map(&self) -> bool {
+        matches!(self, Value::Map(_))
+    }
+
+    /// Returns `true` if the value is [`Unit`](Value::Unit).
+    pub fn is_unit(&self) -> bool {
+        matches!(self, Value::Unit)
+    }
+
+    /// Returns the integer, or `None` for any other variant.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use zirc_syntax::Value;
+    ///
+    /// assert_eq!(Value::Int(42).as_int(), Some(42));
+    /// assert_eq!(Value::Str("42".to_string()).as_int(), None);
+    /// ```
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as `f64` for both ints and floats, or `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use zirc_syntax::Value;
+    ///
+    /// assert_eq!(Value::Float(2.5).as_f64(), Some(2.5));
+    /// assert_eq!(Value::Int(2).as_f64(), Some(2.0));
+    /// assert_eq!(Value::Bool(true).as_f64(), None);
+    /// ```
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Int(n) => Some(*n as f64),
+            Value::Float(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// Equality as the `==` operator defines it: an int and a float are equal
+    /// when they have the same numeric value, element-wise inside lists.
+    /// Lists of different lengths compare unequal without visiting any element.
+    /// Maps are equal when they hold the same keys with equal values, in any order.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use zirc_syntax::Value;
+    ///
+    /// assert!(Value::Int(2).lang_eq(&Value::Float(2.0)));
+    /// assert!(!Value::Int(2).lang_eq(&Value::Str("2".to_string())));
+    /// ```
+    pub fn lang_eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::List(a), Value::List(b)) => a.len() == b.len() && a.iter().zip(b).all(|(x, y)| x.lang_eq(y)),
+            // Keys are unique, so equal lengths and every entry found means the same key set
+            (Value::Map(a), Value::Map(b)) => {
+                a.len() == b.len() && a.iter().all(|(k, x)| b.iter().any(|(k2, y)| k == k2 && x.lang_eq(y)))
+            }
+            _ => match float_operands(self, other) {
+                Some((x, y)) => x == y,
+                None => self == other,
+            },
+        }
+    }
+
+    /// `a + b`, shared by both backends: int, float (an int mixed with
+    /// a float is promoted), string concatenation and list concatenation.
+    /// Anything else is an error naming both operands.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use zirc_syntax::Value;
+    ///
+    /// assert_eq!(Value::try_add(Value::Int(2), Value::Float(0.5)).unwrap(), Value::Float(2.5));
+    /// let ab = Value::try_add(Value::Str("a".to_string()), Value::Str("b".to_string())).unwrap();
+    /// assert_eq!(ab, Value::Str("ab".to_string()));
+    /// assert_eq!(Value::try_add(Value::Int(1), Value::Unit).unwrap_err().msg, "Cannot add Int(1) and Unit");
+    /// ```
+    pub fn try_add(a: Value, b: Value) -> Result<Value> {
+        match (a, b) {
+            (Value::Int(x), Value::Int(y)) => Ok(Value::Int(x.checked_add(y).ok_or(builtins::OVERFLOW_ADD)?)),
+            (Value::Str(x), Value::Str(y)) => Ok(Value::Str(x + &y)),
+            (Value::List(mut x), Value::List(y)) => { x.extend(y); Ok(Value::List(x)) }
+            (x, y) => match float_operands(&x, &y) {
+                Some((fx, fy)) => Ok(Value::Float(fx + fy)),
+                None => error(format!("Cannot add {:?} and {:?}", x, y)),
+            },
+        }
+    }
+
+    /// Returns the string contents, or `None` for any other variant.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use zirc_syntax::Value;
+    ///
+    /// assert_eq!(Value::Str("hi".to_string()).as_str(), Some("hi"));
+    /// assert_eq!(Value::Unit.as_str(), None);
+    /// ```
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the boolean, or `None` for any other variant.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use zirc_syntax::Value;
+    ///
+    /// assert_eq!(Value::Bool(false).as_bool(), Some(false));
+    /// assert_eq!(Value::Int(0).as_bool(), None);
+    /// ```
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Returns the list items, or `None` for any other variant.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use zirc_syntax::Value;
+    ///
+    /// let list = Value::List(vec![Value::Int(1), Value::Int(2)]);
+    /// assert_eq!(list.as_list().map(|items| items.len()), Some(2));
+    /// assert_eq!(Value::Str("[1, 2]".to_string()).as_list(), None);
+    /// ```
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Returns the map entries, or `None` for any other variant.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use zirc_syntax::Value;
+    ///
+    /// let map = Value::Map(vec![("a".to_string(), Value::Int(1))]);
+    /// assert_eq!(map.as_map().map(|entries| entries.len()), Some(1));
+    /// assert_eq!(Value::List(vec![]).as_map(), None);
+    /// ```
+    pub fn as_map(&self) -> Option<&[(String, Value)]> {
+        match self {
+            Value::Map(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    /// Renders the value like `Display`, but shows lists and maps nested deeper
+    /// than `max_depth` levels as `[...]` and `{...}`. `None` means no limit.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use zirc_syntax::Value;
+    ///
+    /// let v = Value::List(vec![Value::Int(1), Value::List(vec![Value::Int(2)])]);
+    /// assert_eq!(v.display_depth(None), "[1, [2]]");
+    /// assert_eq!(v.display_depth(Some(1)), "[1, [...]]");
+    /// assert_eq!(v.display_depth(Some(0)), "[...]");
+    /// ```
+    pub fn display_depth(&self, max_depth: Option<usize>) -> String {
+        let mut out = String::new();
+        self.write_depth(&mut out, max_depth).expect("writing to a String cannot fail");
+        out
+    }
+
+    fn write_depth(&self, f: &mut impl fmt::Write, remaining: Option<usize>) -> fmt::Result {
+        match self {
+            Value::Int(n) => write!(f, "{}", n),
+            // Debug keeps the `.0` on integral floats and switches to an exponent for extremes
+            Value::Float(x) => write!(f, "{:?}", x),
+            Value::Str(s) => write!(f, "{}", s),
+            Value::Bool(b) => write!(f, "{}", if *b { "true" } else { "false" }),
+            Value::List(_) if remaining == Some(0) => write!(f, "[...]"),
+            Value::List(items) => {
+                let inner = remaining.map(|d| d - 1);
+                write!(f, "[")?;
+                for (i, it) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    it.write_depth(f, inner)?;
+                }
+                write!(f, "]")
+            }
+            Value::Map(_) if remaining == Some(0) => write!(f, "{{...}}"),
+            Value::Map(entries) => {
+                let inner = remaining.map(|d| d - 1);
+                write!(f, "{{")?;
+                for (i, (k, v)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: ", k)?;
+                    v.write_depth(f, inner)?;
+                }
+                write!(f, "}}")
+            }
+            Value::Unit => write!(f, "<unit>"),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_depth(f, None)
+    }
+}
+
+/// Returns both operands as `f64` when both are numbers and at least one is a
+/// float: the promotion rule for mixed arithmetic and comparisons. Two ints
+/// give `None` so callers keep exact integer math.
+///
+/// # Examples
+///
+/// ```rust
+/// use zirc_syntax::{value::float_operands, Value};
+///
+/// assert_eq!(float_operands(&Value::Int(1), &Value::Float(0.5)), Some((1.0, 0.5)));
+/// assert_eq!(float_operands(&Value::Int(1), &Value::Int(2)), None);
+/// ```
+pub fn float_operands(a: &Value, b: &Value) -> Option<(f64, f64)> {
+    match (a, b) {
+        (Value::Int(_), Value::Int(_)) => None,
+        _ => Some((a.as_f64()?, b.as_f64()?)),
+    }
+}
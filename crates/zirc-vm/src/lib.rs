@@ -3,5 +3,5 @@
 pub mod display;
 pub mod vm;
 
-pub use vm::Vm;
+pub use vm::{NativeFn, Vm};
 
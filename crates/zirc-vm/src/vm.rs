@@ -3,17 +3,14 @@
 use std::io::{self, Write};
 use std::fs;
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Instant;
 
 use crate::display::display_value;
 use zirc_bytecode::{Builtin, Instruction, Program, Value};
+use zirc_syntax::builtins;
 use zirc_syntax::error::{Result, error};
-
-#[derive(Clone)]
-struct Frame {
-    func_ref: CodeRef,
-    ip: usize,
-    locals: Vec<Value>,
-}
+use zirc_syntax::value::float_operands;
 
 #[cfg(test)]
 mod tests {
@@ -27,8 +24,10 @@ mod tests {
                 name: "main".to_string(),
                 arity: 0,
                 local_count: 1,
+                constants: Vec::new(),
                 code: main_code,
             },
+            natives: Vec::new(),
         }
     }
 
@@ -58,6 +57,15 @@ mod tests {
             (vec![Instruction::PushInt(10), Instruction::PushInt(3), Instruction::Sub], Value::Int(7)),
             (vec![Instruction::PushInt(10), Instruction::PushInt(3), Instruction::Mul], Value::Int(30)),
             (vec![Instruction::PushInt(10), Instruction::PushInt(3), Instruction::Div], Value::Int(3)),
+            (vec![Instruction::PushInt(10), Instruction::PushInt(3), Instruction::Mod], Value::Int(1)),
+            (vec![Instruction::PushInt(10), Instruction::PushInt(3), Instruction::Mod, Instruction::PushInt(1), Instruction::Eq], Value::Bool(true)),
+            (vec![Instruction::PushInt(-7), Instruction::PushInt(2), Instruction::Mod], Value::Int(-1)),
+            (vec![Instruction::PushInt(7), Instruction::Neg], Value::Int(-7)),
+            (vec![Instruction::PushInt(6), Instruction::PushInt(3), Instruction::BitAnd], Value::Int(2)),
+            (vec![Instruction::PushInt(6), Instruction::PushInt(3), Instruction::BitOr], Value::Int(7)),
+            (vec![Instruction::PushInt(6), Instruction::PushInt(3), Instruction::BitXor], Value::Int(5)),
+            (vec![Instruction::PushInt(1), Instruction::PushInt(4), Instruction::Shl], Value::Int(16)),
+            (vec![Instruction::PushInt(-16), Instruction::PushInt(2), Instruction::Shr], Value::Int(-4)),
         ];
         
         for (code, expected) in test_cases {
@@ -152,6 +160,33 @@ mod tests {
         assert_eq!(globals[0], ("x".to_string(), Value::Str("test".to_string())));
     }
 
+    #[test]
+    fn test_vm_push_pop_update_global() {
+        let mut vm = Vm::new();
+
+        // let xs = []; push(xs, 1); push(xs, 2); pop(xs), as the compiler emits it
+        let xs = || "xs".to_string();
+        let program = make_simple_program(vec![
+            Instruction::MakeList(0),
+            Instruction::StoreGlobal(xs()),
+            Instruction::LoadGlobal(xs()),
+            Instruction::PushInt(1),
+            Instruction::ListPush,
+            Instruction::StoreGlobal(xs()),
+            Instruction::LoadGlobal(xs()),
+            Instruction::PushInt(2),
+            Instruction::ListPush,
+            Instruction::StoreGlobal(xs()),
+            Instruction::LoadGlobal(xs()),
+            Instruction::ListPop,
+            Instruction::StoreGlobal(xs()),
+        ]);
+
+        vm.run(&program).unwrap();
+        assert_eq!(vm.stack, vec![Value::Int(2)]);
+        assert_eq!(vm.globals_snapshot(), vec![(xs(), Value::List(vec![Value::Int(1)]))]);
+    }
+
     #[test]
     fn test_vm_list_operations() {
         let mut vm = Vm::new();
@@ -170,6 +205,33 @@ mod tests {
         assert_eq!(vm.stack[0], Value::Int(2)); // Index 1 should be 2
     }
 
+    #[test]
+    fn test_vm_map_operations() {
+        let key = |k: &str| Instruction::PushStr(k.to_string());
+        let map = |entries: &[(&str, i64)]| Value::Map(entries.iter().map(|(k, v)| (k.to_string(), Value::Int(*v))).collect());
+        let cases = vec![
+            (vec![key("a"), Instruction::PushInt(1), key("b"), Instruction::PushInt(2), Instruction::MakeMap(2)], map(&[("a", 1), ("b", 2)])),
+            (vec![Instruction::MakeMap(0)], map(&[])),
+            // A repeated key keeps its first position and its last value
+            (
+                vec![key("a"), Instruction::PushInt(1), key("b"), Instruction::PushInt(2), key("a"), Instruction::PushInt(3), Instruction::MakeMap(3)],
+                map(&[("a", 3), ("b", 2)]),
+            ),
+            (vec![key("a"), Instruction::PushInt(1), key("b"), Instruction::PushInt(2), Instruction::MakeMap(2), key("b"), Instruction::Index], Value::Int(2)),
+        ];
+        for (code, expected) in cases {
+            let mut vm = Vm::new();
+            vm.run(&make_simple_program(code)).unwrap();
+            assert_eq!(vm.stack, vec![expected]);
+        }
+
+        let err = |code| Vm::new().run(&make_simple_program(code)).unwrap_err().msg;
+        assert_eq!(err(vec![Instruction::PushInt(1), Instruction::PushInt(2), Instruction::MakeMap(1)]), "map keys must be strings, got Int(1)");
+        assert_eq!(err(vec![key("a"), Instruction::MakeMap(1)]), "stack underflow in MakeMap");
+        assert_eq!(err(vec![Instruction::MakeMap(0), key("z"), Instruction::Index]), "key 'z' not found in map");
+        assert_eq!(err(vec![Instruction::MakeMap(0), Instruction::PushInt(0), Instruction::Index]), "map key must be string, got Int(0)");
+    }
+
     #[test]
     fn test_vm_conditional_jumps() {
         let mut vm = Vm::new();
@@ -202,6 +264,13 @@ mod tests {
         let result = vm.run(&program);
         assert!(result.is_err());
         assert!(result.unwrap_err().msg.contains("division by zero"));
+
+        let program = make_simple_program(vec![
+            Instruction::PushInt(10),
+            Instruction::PushInt(0),
+            Instruction::Mod,
+        ]);
+        assert_eq!(Vm::new().run(&program).unwrap_err().msg, "division by zero");
     }
 
     #[test]
@@ -265,24 +334,268 @@ mod tests {
         assert_eq!(result, Some(Value::Int(42))); // Pop sets last_value
         assert_eq!(vm.stack.len(), 0); // Stack should be empty
     }
+
+    #[test]
+    fn test_vm_multibyte_strings_use_char_positions() {
+        let s = "héllo🎉".to_string();
+        let cases = vec![
+            (vec![Instruction::PushStr(s.clone()), Instruction::BuiltinCall(Builtin::Len, 1)], Value::Int(6)),
+            (vec![Instruction::PushStr(s.clone()), Instruction::PushInt(1), Instruction::Index], Value::Str("é".to_string())),
+            (vec![Instruction::PushStr(s.clone()), Instruction::PushInt(5), Instruction::Index], Value::Str("🎉".to_string())),
+            (vec![Instruction::PushStr(s.clone()), Instruction::PushInt(1), Instruction::PushInt(5), Instruction::BuiltinCall(Builtin::Slice, 3)], Value::Str("éllo".to_string())),
+            (vec![Instruction::PushStr(s.clone()), Instruction::PushInt(2), Instruction::BuiltinCall(Builtin::Left, 2)], Value::Str("hé".to_string())),
+            (vec![Instruction::PushStr(s.clone()), Instruction::PushInt(2), Instruction::BuiltinCall(Builtin::Right, 2)], Value::Str("o🎉".to_string())),
+            (
+                vec![Instruction::PushStr("ä,🎉,ö".to_string()), Instruction::PushStr(",".to_string()), Instruction::BuiltinCall(Builtin::Split, 2)],
+                Value::List(vec![Value::Str("ä".to_string()), Value::Str("🎉".to_string()), Value::Str("ö".to_string())]),
+            ),
+        ];
+
+        for (code, expected) in cases {
+            let mut vm = Vm::new();
+            vm.run(&make_simple_program(code)).unwrap();
+            assert_eq!(vm.stack[0], expected);
+        }
+    }
+
+    #[test]
+    fn test_vm_push_const() {
+        let mut vm = Vm::new();
+        let mut program = make_simple_program(vec![
+            Instruction::PushConst(0),
+            Instruction::PushConst(0),
+            Instruction::Add,
+            Instruction::PushConst(1),
+        ]);
+        program.main.constants = vec![Value::Str("ab".to_string()), Value::Int(i64::MAX)];
+
+        vm.run(&program).unwrap();
+        assert_eq!(vm.stack, vec![Value::Str("abab".to_string()), Value::Int(i64::MAX)]);
+
+        let bad = make_simple_program(vec![Instruction::PushConst(3)]);
+        assert!(Vm::new().run(&bad).unwrap_err().msg.contains("invalid constant index"));
+    }
+
+    #[test]
+    fn test_vm_call_with_too_few_locals_is_an_error() {
+        // Program::from_bytes rejects this, but a program built in memory is not checked
+        let mut program = make_simple_program(vec![Instruction::PushInt(1), Instruction::Call(0, 1)]);
+        program.functions.push(Function { name: "f".to_string(), arity: 1, local_count: 0, constants: Vec::new(), code: vec![Instruction::Return] });
+        assert_eq!(Vm::new().run(&program).unwrap_err().msg, "invalid local index");
+    }
+
+    #[test]
+    fn test_vm_showf_missing_argument() {
+        let mut vm = Vm::new();
+        let program = make_simple_program(vec![
+            Instruction::PushStr("%s has %d items, total %d".to_string()),
+            Instruction::PushStr("cart".to_string()),
+            Instruction::PushInt(3),
+            Instruction::BuiltinCall(Builtin::ShowF, 3),
+        ]);
+
+        let err = vm.run(&program).unwrap_err();
+        assert_eq!(err.msg, "showf: format expects at least 3 arguments but got 2 (missing %d at position 23)");
+    }
+
+    #[test]
+    fn test_vm_native_call() {
+        let mut vm = Vm::new();
+        vm.register_native("sum", Box::new(|args: &[Value]| Ok(Value::Int(args.iter().filter_map(Value::as_int).sum())))).unwrap();
+        assert!(vm.register_native("sum", Box::new(|_: &[Value]| Ok(Value::Unit))).is_err());
+
+        let mut program = make_simple_program(vec![
+            Instruction::PushInt(1),
+            Instruction::PushInt(2),
+            Instruction::PushInt(3),
+            Instruction::NativeCall(0, 3),
+        ]);
+        program.natives = vec!["sum".to_string()];
+        vm.run(&program).unwrap();
+        assert_eq!(vm.stack, vec![Value::Int(6)]);
+
+        program.natives = vec!["missing".to_string()];
+        assert!(Vm::new().run(&program).unwrap_err().msg.contains("Native function 'missing' is not registered"));
+    }
+
+    #[test]
+    fn test_vm_builtin_panic() {
+        let mut vm = Vm::new();
+        let program = make_simple_program(vec![
+            Instruction::PushStr("boom".to_string()),
+            Instruction::BuiltinCall(Builtin::Panic, 1),
+            Instruction::PushInt(1), // never reached
+        ]);
+
+        let result = vm.run(&program);
+        assert_eq!(result.unwrap_err().msg, "boom");
+        assert!(vm.stack.is_empty());
+    }
+
+    #[test]
+    fn test_vm_float_arithmetic() {
+        let mut program = make_simple_program(vec![
+            Instruction::PushInt(1),
+            Instruction::PushConst(0),
+            Instruction::Add,
+            Instruction::PushInt(2),
+            Instruction::Div,
+        ]);
+        program.main.constants = vec![Value::Float(0.5)];
+        let mut vm = Vm::new();
+        vm.run(&program).unwrap();
+        assert_eq!(vm.stack, vec![Value::Float(0.75)]);
+
+        let mut program = make_simple_program(vec![
+            Instruction::PushInt(2),
+            Instruction::PushConst(0),
+            Instruction::Eq,
+            Instruction::PushConst(0),
+            Instruction::PushInt(3),
+            Instruction::Lt,
+        ]);
+        program.main.constants = vec![Value::Float(2.0)];
+        let mut vm = Vm::new();
+        vm.run(&program).unwrap();
+        assert_eq!(vm.stack, vec![Value::Bool(true), Value::Bool(true)]);
+    }
+
+    #[test]
+    fn test_vm_builtin_copy() {
+        let list = Value::List(vec![Value::Int(1), Value::List(vec![Value::Int(2)])]);
+        let program = make_simple_program(vec![
+            Instruction::PushInt(1),
+            Instruction::PushInt(2),
+            Instruction::MakeList(1),
+            Instruction::MakeList(2),
+            Instruction::BuiltinCall(Builtin::Copy, 1),
+        ]);
+        let mut vm = Vm::new();
+        vm.run(&program).unwrap();
+        assert_eq!(vm.stack, vec![list]);
+    }
+
+    #[test]
+    fn test_vm_builtin_time_ns() {
+        let mut vm = Vm::new();
+        let program = make_simple_program(vec![
+            Instruction::BuiltinCall(Builtin::TimeNs, 0),
+            Instruction::BuiltinCall(Builtin::TimeNs, 0),
+        ]);
+        vm.run(&program).unwrap();
+        match (&vm.stack[0], &vm.stack[1]) {
+            (Value::Int(t0), Value::Int(t1)) => assert!(t1 >= t0 && *t0 >= 0),
+            other => panic!("expected two ints, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_vm_builtin_pow_exact_and_overflow() {
+        let mut vm = Vm::new();
+        let program = make_simple_program(vec![
+            Instruction::PushInt(3),
+            Instruction::PushInt(39),
+            Instruction::BuiltinCall(Builtin::Pow, 2),
+        ]);
+        vm.run(&program).unwrap();
+        assert_eq!(vm.stack[0], Value::Int(4052555153018976267));
+
+        let mut vm = Vm::new();
+        let program = make_simple_program(vec![
+            Instruction::PushInt(3),
+            Instruction::PushInt(40),
+            Instruction::BuiltinCall(Builtin::Pow, 2),
+        ]);
+        assert_eq!(vm.run(&program).unwrap_err().msg, "integer overflow in pow");
+    }
+
+    #[test]
+    fn test_vm_builtin_to_base() {
+        let cases = vec![
+            (vec![Instruction::PushInt(255), Instruction::PushInt(16), Instruction::BuiltinCall(Builtin::ToBase, 2)], "ff"),
+            (vec![Instruction::PushInt(10), Instruction::PushInt(2), Instruction::BuiltinCall(Builtin::ToBase, 2)], "1010"),
+            (vec![Instruction::PushInt(35), Instruction::PushInt(36), Instruction::BuiltinCall(Builtin::ToBase, 2)], "z"),
+            (vec![Instruction::PushInt(-255), Instruction::PushInt(16), Instruction::BuiltinCall(Builtin::ToBase, 2)], "-ff"),
+            (vec![Instruction::PushInt(255), Instruction::BuiltinCall(Builtin::Hex, 1)], "0xff"),
+            (vec![Instruction::PushInt(255), Instruction::PushBool(false), Instruction::BuiltinCall(Builtin::Hex, 2)], "ff"),
+            (vec![Instruction::PushInt(-5), Instruction::BuiltinCall(Builtin::Bin, 1)], "-0b101"),
+            (vec![Instruction::PushInt(5), Instruction::PushBool(false), Instruction::BuiltinCall(Builtin::Bin, 2)], "101"),
+        ];
+
+        for (code, expected) in cases {
+            let mut vm = Vm::new();
+            vm.run(&make_simple_program(code)).unwrap();
+            assert_eq!(vm.stack[0], Value::Str(expected.to_string()));
+        }
+
+        for base in [1, 37] {
+            let mut vm = Vm::new();
+            let program = make_simple_program(vec![
+                Instruction::PushInt(10),
+                Instruction::PushInt(base),
+                Instruction::BuiltinCall(Builtin::ToBase, 2),
+            ]);
+            let result = vm.run(&program);
+            assert!(result.unwrap_err().msg.contains("between 2 and 36"));
+        }
+    }
 }
 
-#[derive(Clone, Copy)]
-enum CodeRef {
-    Main,
-    Func(usize),
+#[derive(Clone)]
+struct Frame {
+    /// Uniform index of the running function, see `Program::function`
+    func: usize,
+    ip: usize,
+    locals: Vec<Value>,
 }
 
+/// A host function callable from bytecode via `NativeCall`, given the popped arguments in order.
+pub type NativeFn = Box<dyn Fn(&[Value]) -> Result<Value>>;
+
 pub struct Vm {
     stack: Vec<Value>,
     globals: HashMap<String, Value>,
+    natives: HashMap<String, NativeFn>,
+    script_path: Option<PathBuf>,
+    /// Deepest list nesting `show()` prints before eliding as `[...]`; `None` is unlimited
+    display_depth: Option<usize>,
+    /// Reference point for `time_ns()`
+    clock: Instant,
+    /// Deepest call nesting allowed before `maximum recursion depth exceeded`
+    max_depth: usize,
 }
 
 impl Default for Vm { fn default() -> Self { Self::new() } }
 
 impl Vm {
     pub fn new() -> Self {
-        Self { stack: Vec::new(), globals: HashMap::new() }
+        Self { stack: Vec::new(), globals: HashMap::new(), natives: HashMap::new(), script_path: None, display_depth: None, clock: Instant::now(), max_depth: builtins::DEFAULT_MAX_DEPTH }
+    }
+
+    /// Limits how deeply function calls may nest; a call beyond it fails with
+    /// `maximum recursion depth exceeded`. Defaults to [`builtins::DEFAULT_MAX_DEPTH`].
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Limits how many levels of nested lists `show()` prints; deeper lists
+    /// appear as `[...]`. `None` (the default) prints everything.
+    pub fn set_display_depth(&mut self, max_depth: Option<usize>) {
+        self.display_depth = max_depth;
+    }
+
+    /// Sets the path `script_path()` and `script_dir()` report; without one both return unit.
+    pub fn set_script_path(&mut self, path: impl Into<PathBuf>) {
+        self.script_path = Some(path.into());
+    }
+
+    /// Registers a host function under `name`; programs compiled with
+    /// `Compiler::declare_native(name)` call it through `NativeCall`.
+    pub fn register_native(&mut self, name: impl Into<String>, f: NativeFn) -> Result<()> {
+        let name = name.into();
+        if self.natives.contains_key(&name) { return error(format!("Native '{}' already registered", name)); }
+        self.natives.insert(name, f);
+        Ok(())
     }
 
     pub fn globals_snapshot(&self) -> Vec<(String, Value)> {
@@ -292,19 +605,21 @@ impl Vm {
     }
 
     pub fn run(&mut self, program: &Program) -> Result<Option<Value>> {
+        // Resolve the program's native table against the registered host functions up front
+        let mut natives: Vec<&NativeFn> = Vec::with_capacity(program.natives.len());
+        for name in &program.natives {
+            natives.push(self.natives.get(name).ok_or_else(|| format!("Native function '{}' is not registered", name))?);
+        }
         let mut frames: Vec<Frame> = Vec::new();
         frames.push(Frame {
-            func_ref: CodeRef::Main,
+            func: Program::MAIN,
             ip: 0,
             locals: vec![Value::Unit; program.main.local_count],
         });
 
         let mut last_value: Option<Value> = None;
         while let Some(frame) = frames.last_mut() {
-            let func = match frame.func_ref {
-                CodeRef::Main => &program.main,
-                CodeRef::Func(i) => &program.functions[i],
-            };
+            let func = program.function(frame.func).ok_or("invalid function index")?;
             if frame.ip >= func.code.len() {
                 // Implicit return Unit if we run off the end
                 if frames.len() == 1 { break; } // main returns ends program
@@ -313,12 +628,17 @@ impl Vm {
                 self.stack.push(ret);
                 continue;
             }
-            let instr = func.code[frame.ip].clone();
+            // Borrow the instruction rather than cloning it; `func` points into `program`, not `frame`.
+            let ip = frame.ip;
             // default ip increment; jumps will override
             frame.ip += 1;
-            match instr {
+            match func.code[ip] {
                 Instruction::PushInt(n) => self.stack.push(Value::Int(n)),
-                Instruction::PushStr(s) => self.stack.push(Value::Str(s)),
+                Instruction::PushStr(ref s) => self.stack.push(Value::Str(s.clone())),
+                Instruction::PushConst(i) => {
+                    let v = func.constants.get(i as usize).ok_or("invalid constant index")?.clone();
+                    self.stack.push(v);
+                }
                 Instruction::PushBool(b) => self.stack.push(Value::Bool(b)),
                 Instruction::PushUnit => self.stack.push(Value::Unit),
                 Instruction::MakeList(n) => {
@@ -328,138 +648,250 @@ impl Vm {
                     // elems are in original order already because we drained a slice
                     self.stack.push(Value::List(elems));
                 }
+                Instruction::MakeMap(n) => {
+                    if self.stack.len() < 2 * n { return error("stack underflow in MakeMap"); }
+                    let start = self.stack.len() - 2 * n;
+                    let mut flat = self.stack.drain(start..);
+                    let mut map: Vec<(String, Value)> = Vec::with_capacity(n);
+                    while let (Some(k), Some(v)) = (flat.next(), flat.next()) {
+                        let key = match k { Value::Str(s) => s, other => return error(format!("map keys must be strings, got {:?}", other)) };
+                        // A repeated key keeps its first position and its last value
+                        match map.iter_mut().find(|(k2, _)| *k2 == key) {
+                            Some(entry) => entry.1 = v,
+                            None => map.push((key, v)),
+                        }
+                    }
+                    drop(flat);
+                    self.stack.push(Value::Map(map));
+                }
                 Instruction::Index => {
-                    let idx = self.stack.pop().ok_or_else(|| "stack underflow in Index")?;
-                    let base = self.stack.pop().ok_or_else(|| "stack underflow in Index")?;
+                    let idx = self.stack.pop().ok_or("stack underflow in Index")?;
+                    let base = self.stack.pop().ok_or("stack underflow in Index")?;
+                    if let Value::Map(entries) = base {
+                        let key = match idx { Value::Str(s) => s, other => return error(format!("map key must be string, got {:?}", other)) };
+                        match entries.into_iter().find(|(k, _)| *k == key) {
+                            Some((_, v)) => self.stack.push(v),
+                            None => return error(format!("key '{}' not found in map", key)),
+                        }
+                        continue;
+                    }
                     let ix = match idx { Value::Int(n) => n, other => return error(format!("index expects int, got {:?}", other)) };
                     match base {
                         Value::List(items) => {
-                            if ix < 0 || (ix as usize) >= items.len() { return error("index out of bounds"); }
+                            if ix < 0 || (ix as usize) >= items.len() { return error(builtins::INDEX_OUT_OF_BOUNDS); }
                             self.stack.push(items[ix as usize].clone());
                         }
                         Value::Str(s) => {
                             let chars: Vec<char> = s.chars().collect();
-                            if ix < 0 || (ix as usize) >= chars.len() { return error("index out of bounds"); }
+                            if ix < 0 || (ix as usize) >= chars.len() { return error(builtins::INDEX_OUT_OF_BOUNDS); }
                             self.stack.push(Value::Str(chars[ix as usize].to_string()));
                         }
                         other => return error(format!("indexing not supported for {:?}", other)),
                     }
                 }
+                Instruction::SetIndex(n) => {
+                    let v = self.stack.pop().ok_or("stack underflow in SetIndex")?;
+                    if self.stack.len() < n + 1 { return error("stack underflow in SetIndex"); }
+                    let start = self.stack.len() - n;
+                    let mut path = Vec::with_capacity(n);
+                    for i in self.stack.drain(start..) {
+                        match i {
+                            Value::Int(ix) => path.push(ix),
+                            other => return error(format!("index expects int, got {:?}", other)),
+                        }
+                    }
+                    let base = self.stack.pop().ok_or("stack underflow in SetIndex")?;
+                    self.stack.push(builtins::set_at(base, &path, v)?);
+                }
+                Instruction::IterList => {
+                    let v = self.stack.pop().ok_or("stack underflow in IterList")?;
+                    match v {
+                        Value::List(_) => self.stack.push(v),
+                        Value::Str(s) => self.stack.push(Value::List(s.chars().map(|c| Value::Str(c.to_string())).collect())),
+                        other => return error(format!("for-in expects list or string, got {:?}", other)),
+                    }
+                }
+                Instruction::ListPush => {
+                    let v = self.stack.pop().ok_or("stack underflow in ListPush")?;
+                    match self.stack.pop().ok_or("stack underflow in ListPush")? {
+                        Value::List(mut items) => { items.push(v); self.stack.push(Value::List(items)); }
+                        other => return error(format!("push() expects list, got {:?}", other)),
+                    }
+                }
+                Instruction::ListPop => {
+                    match self.stack.pop().ok_or("stack underflow in ListPop")? {
+                        Value::List(mut items) => {
+                            let last = items.pop().ok_or(builtins::POP_EMPTY_LIST)?;
+                            self.stack.push(last);
+                            self.stack.push(Value::List(items));
+                        }
+                        other => return error(format!("pop() expects list variable, got {:?}", other)),
+                    }
+                }
+                Instruction::CheckStep => {
+                    match self.stack.last().ok_or("stack underflow in CheckStep")? {
+                        Value::Int(0) => return error(builtins::FOR_STEP_ZERO),
+                        Value::Int(_) => {}
+                        other => return error(format!("for step must be int, got {:?}", other)),
+                    }
+                }
+                Instruction::Unpack(n) => {
+                    let v = self.stack.pop().ok_or("stack underflow in Unpack")?;
+                    let items = match v { Value::List(items) => items, other => return error(format!("cannot unpack {:?}, expected a list", other)) };
+                    if items.len() != n { return error(format!("cannot unpack list of length {} into {} names", items.len(), n)); }
+                    self.stack.extend(items.into_iter().rev());
+                }
                 Instruction::LoadLocal(i) => {
                     let i = i as usize;
-                    let v = frame.locals.get(i).ok_or_else(|| "invalid local index")?.clone();
+                    let v = frame.locals.get(i).ok_or("invalid local index")?.clone();
                     self.stack.push(v);
                 }
                 Instruction::StoreLocal(i) => {
                     let i = i as usize;
-                    let v = self.stack.pop().ok_or_else(|| "stack underflow in StoreLocal")?;
-                    let slot = frame.locals.get_mut(i).ok_or_else(|| "invalid local index")?;
+                    let v = self.stack.pop().ok_or("stack underflow in StoreLocal")?;
+                    let slot = frame.locals.get_mut(i).ok_or("invalid local index")?;
                     *slot = v;
                 }
                 Instruction::Pop => { let v = self.stack.pop(); if let Some(val) = v { last_value = Some(val); } }
                 Instruction::Add => {
-                    let b = self.stack.pop().ok_or_else(|| "stack underflow in Add")?;
-                    let a = self.stack.pop().ok_or_else(|| "stack underflow in Add")?;
-                    match (a, b) {
-                        (Value::Int(x), Value::Int(y)) => self.stack.push(Value::Int(x + y)),
-                        (Value::Str(x), Value::Str(y)) => self.stack.push(Value::Str(format!("{}{}", x, y))),
-                        (Value::List(mut x), Value::List(y)) => { x.extend(y); self.stack.push(Value::List(x)); }
-                        (x, y) => return error(format!("Cannot add {:?} and {:?}", x, y)),
-                    }
+                    let b = self.stack.pop().ok_or("stack underflow in Add")?;
+                    let a = self.stack.pop().ok_or("stack underflow in Add")?;
+                    self.stack.push(Value::try_add(a, b)?);
                 }
                 Instruction::Sub => {
-                    let b = self.stack.pop().ok_or_else(|| "stack underflow in Sub")?;
-                    let a = self.stack.pop().ok_or_else(|| "stack underflow in Sub")?;
+                    let b = self.stack.pop().ok_or("stack underflow in Sub")?;
+                    let a = self.stack.pop().ok_or("stack underflow in Sub")?;
                     match (a, b) {
-                        (Value::Int(x), Value::Int(y)) => self.stack.push(Value::Int(x - y)),
-                        (x, y) => return error(format!("Cannot subtract {:?} and {:?}", x, y)),
+                        (Value::Int(x), Value::Int(y)) => self.stack.push(Value::Int(x.checked_sub(y).ok_or(builtins::OVERFLOW_SUB)?)),
+                        (x, y) => match float_operands(&x, &y) {
+                            Some((fx, fy)) => self.stack.push(Value::Float(fx - fy)),
+                            None => return error(format!("Cannot subtract {:?} and {:?}", x, y)),
+                        },
                     }
                 }
                 Instruction::Mul => {
-                    let b = self.stack.pop().ok_or_else(|| "stack underflow in Mul")?;
-                    let a = self.stack.pop().ok_or_else(|| "stack underflow in Mul")?;
+                    let b = self.stack.pop().ok_or("stack underflow in Mul")?;
+                    let a = self.stack.pop().ok_or("stack underflow in Mul")?;
                     match (a, b) {
-                        (Value::Int(x), Value::Int(y)) => self.stack.push(Value::Int(x * y)),
-                        (x, y) => return error(format!("Cannot multiply {:?} and {:?}", x, y)),
+                        (Value::Int(x), Value::Int(y)) => self.stack.push(Value::Int(x.checked_mul(y).ok_or(builtins::OVERFLOW_MUL)?)),
+                        (x, y) => match float_operands(&x, &y) {
+                            Some((fx, fy)) => self.stack.push(Value::Float(fx * fy)),
+                            None => return error(format!("Cannot multiply {:?} and {:?}", x, y)),
+                        },
                     }
                 }
                 Instruction::Div => {
-                    let b = self.stack.pop().ok_or_else(|| "stack underflow in Div")?;
-                    let a = self.stack.pop().ok_or_else(|| "stack underflow in Div")?;
+                    let b = self.stack.pop().ok_or("stack underflow in Div")?;
+                    let a = self.stack.pop().ok_or("stack underflow in Div")?;
+                    match (a, b) {
+                        (Value::Int(_), Value::Int(0)) => return error(builtins::DIVISION_BY_ZERO),
+                        // Zero is handled above, so checked_div only fails for i64::MIN / -1
+                        (Value::Int(x), Value::Int(y)) => self.stack.push(Value::Int(x.checked_div(y).ok_or(builtins::OVERFLOW_DIV)?)),
+                        (x, y) => match float_operands(&x, &y) {
+                            Some((_, 0.0)) => return error(builtins::DIVISION_BY_ZERO),
+                            Some((fx, fy)) => self.stack.push(Value::Float(fx / fy)),
+                            None => return error(format!("Cannot divide {:?} and {:?}", x, y)),
+                        },
+                    }
+                }
+                Instruction::Mod => {
+                    let b = self.stack.pop().ok_or("stack underflow in Mod")?;
+                    let a = self.stack.pop().ok_or("stack underflow in Mod")?;
                     match (a, b) {
-                        (Value::Int(_), Value::Int(0)) => return error("division by zero"),
-                        (Value::Int(x), Value::Int(y)) => self.stack.push(Value::Int(x / y)),
-                        (x, y) => return error(format!("Cannot divide {:?} and {:?}", x, y)),
+                        (Value::Int(_), Value::Int(0)) => return error(builtins::DIVISION_BY_ZERO),
+                        // wrapping_rem only differs for i64::MIN % -1, where the true remainder is 0
+                        (Value::Int(x), Value::Int(y)) => self.stack.push(Value::Int(x.wrapping_rem(y))),
+                        (x, y) => return error(format!("Cannot take remainder of {:?} and {:?}", x, y)),
                     }
                 }
+                ref ins @ (Instruction::BitAnd | Instruction::BitOr | Instruction::BitXor | Instruction::Shl | Instruction::Shr) => {
+                    let b = self.stack.pop().ok_or("stack underflow in bitwise op")?;
+                    let a = self.stack.pop().ok_or("stack underflow in bitwise op")?;
+                    let op = match ins {
+                        Instruction::BitAnd => "&",
+                        Instruction::BitOr => "|",
+                        Instruction::BitXor => "^",
+                        Instruction::Shl => "<<",
+                        _ => ">>",
+                    };
+                    self.stack.push(builtins::bitwise(a, b, op)?);
+                }
                 Instruction::Eq => {
-                    let b = self.stack.pop().ok_or_else(|| "stack underflow in Eq")?;
-                    let a = self.stack.pop().ok_or_else(|| "stack underflow in Eq")?;
-                    self.stack.push(Value::Bool(a == b));
+                    let b = self.stack.pop().ok_or("stack underflow in Eq")?;
+                    let a = self.stack.pop().ok_or("stack underflow in Eq")?;
+                    self.stack.push(Value::Bool(a.lang_eq(&b)));
                 }
                 Instruction::Ne => {
-                    let b = self.stack.pop().ok_or_else(|| "stack underflow in Ne")?;
-                    let a = self.stack.pop().ok_or_else(|| "stack underflow in Ne")?;
-                    self.stack.push(Value::Bool(a != b));
+                    let b = self.stack.pop().ok_or("stack underflow in Ne")?;
+                    let a = self.stack.pop().ok_or("stack underflow in Ne")?;
+                    self.stack.push(Value::Bool(!a.lang_eq(&b)));
                 }
                 Instruction::Lt => {
-                    let b = self.stack.pop().ok_or_else(|| "stack underflow in Lt")?;
-                    let a = self.stack.pop().ok_or_else(|| "stack underflow in Lt")?;
-                    match (a, b) {
-                        (Value::Int(x), Value::Int(y)) => self.stack.push(Value::Bool(x < y)),
-                        _ => return error("< expects ints"),
-                    }
+                    let b = self.stack.pop().ok_or("stack underflow in Lt")?;
+                    let a = self.stack.pop().ok_or("stack underflow in Lt")?;
+                    self.stack.push(Value::Bool(builtins::compare(&a, &b, "<")?));
                 }
                 Instruction::Le => {
-                    let b = self.stack.pop().ok_or_else(|| "stack underflow in Le")?;
-                    let a = self.stack.pop().ok_or_else(|| "stack underflow in Le")?;
-                    match (a, b) {
-                        (Value::Int(x), Value::Int(y)) => self.stack.push(Value::Bool(x <= y)),
-                        _ => return error("<= expects ints"),
-                    }
+                    let b = self.stack.pop().ok_or("stack underflow in Le")?;
+                    let a = self.stack.pop().ok_or("stack underflow in Le")?;
+                    self.stack.push(Value::Bool(builtins::compare(&a, &b, "<=")?));
                 }
                 Instruction::Gt => {
-                    let b = self.stack.pop().ok_or_else(|| "stack underflow in Gt")?;
-                    let a = self.stack.pop().ok_or_else(|| "stack underflow in Gt")?;
-                    match (a, b) {
-                        (Value::Int(x), Value::Int(y)) => self.stack.push(Value::Bool(x > y)),
-                        _ => return error("> expects ints"),
-                    }
+                    let b = self.stack.pop().ok_or("stack underflow in Gt")?;
+                    let a = self.stack.pop().ok_or("stack underflow in Gt")?;
+                    self.stack.push(Value::Bool(builtins::compare(&a, &b, ">")?));
                 }
                 Instruction::Ge => {
-                    let b = self.stack.pop().ok_or_else(|| "stack underflow in Ge")?;
-                    let a = self.stack.pop().ok_or_else(|| "stack underflow in Ge")?;
-                    match (a, b) {
-                        (Value::Int(x), Value::Int(y)) => self.stack.push(Value::Bool(x >= y)),
-                        _ => return error(">= expects ints"),
+                    let b = self.stack.pop().ok_or("stack underflow in Ge")?;
+                    let a = self.stack.pop().ok_or("stack underflow in Ge")?;
+                    self.stack.push(Value::Bool(builtins::compare(&a, &b, ">=")?));
+                }
+                Instruction::Neg => {
+                    match self.stack.pop().ok_or("stack underflow in Neg")? {
+                        Value::Int(n) => self.stack.push(Value::Int(n.checked_neg().ok_or(builtins::OVERFLOW_NEG)?)),
+                        Value::Float(x) => self.stack.push(Value::Float(-x)),
+                        other => return error(format!("Cannot negate {:?}", other)),
                     }
                 }
                 Instruction::Not => {
-                    let a = self.stack.pop().ok_or_else(|| "stack underflow in Not")?;
+                    let a = self.stack.pop().ok_or("stack underflow in Not")?;
                     match a { Value::Bool(b) => self.stack.push(Value::Bool(!b)), other => return error(format!("! expects bool, got {:?}", other)) }
                 }
                 Instruction::Jump(tgt) => { frame.ip = tgt; }
                 Instruction::JumpIfFalse(tgt) => {
-                    let c = self.stack.pop().ok_or_else(|| "stack underflow in JumpIfFalse")?;
+                    let c = self.stack.pop().ok_or("stack underflow in JumpIfFalse")?;
                     match c { Value::Bool(false) => frame.ip = tgt, Value::Bool(true) => (), other => return error(format!("condition must be bool, got {:?}", other)) }
                 }
                 Instruction::JumpIfTrue(tgt) => {
-                    let c = self.stack.pop().ok_or_else(|| "stack underflow in JumpIfTrue")?;
+                    let c = self.stack.pop().ok_or("stack underflow in JumpIfTrue")?;
                     match c { Value::Bool(true) => frame.ip = tgt, Value::Bool(false) => (), other => return error(format!("condition must be bool, got {:?}", other)) }
                 }
+                Instruction::AddOrJump(tgt) => {
+                    let b = self.stack.pop().ok_or("stack underflow in AddOrJump")?;
+                    let a = self.stack.pop().ok_or("stack underflow in AddOrJump")?;
+                    match (a, b) {
+                        (Value::Int(x), Value::Int(y)) => match x.checked_add(y) {
+                            Some(n) => self.stack.push(Value::Int(n)),
+                            None => frame.ip = tgt,
+                        },
+                        (x, y) => self.stack.push(Value::try_add(x, y)?),
+                    }
+                }
                 Instruction::Call(fi, argc) => {
                     // collect args
                     if self.stack.len() < argc { return error("stack underflow in Call"); }
                     let start = self.stack.len() - argc;
                     let mut args = self.stack.drain(start..).collect::<Vec<_>>();
                     // args now in original order
-                    let func = program.functions.get(fi).ok_or_else(|| "invalid function index")?;
+                    let func = program.functions.get(fi).ok_or("invalid function index")?;
                     if func.arity != argc { return error(format!("Function '{}' expected {} args, got {}", func.name, func.arity, argc)); }
+                    // The main frame is not a call
+                    if frames.len() > self.max_depth { return error(builtins::max_recursion_depth(self.max_depth)); }
                     // prepare locals
                     let mut locals = vec![Value::Unit; func.local_count];
-                    for (i, v) in args.drain(..).enumerate() { locals[i] = v; }
+                    for (i, v) in args.drain(..).enumerate() { *locals.get_mut(i).ok_or("invalid local index")? = v; }
                     // push frame
-                    frames.push(Frame { func_ref: CodeRef::Func(fi), ip: 0, locals });
+                    frames.push(Frame { func: fi + 1, ip: 0, locals });
                 }
                 Instruction::Return => {
                     let ret = self.stack.pop().unwrap_or(Value::Unit);
@@ -479,61 +911,35 @@ impl Vm {
                     match which {
                         Builtin::Show => {
                             if args.len() != 1 { return error("show() expects exactly 1 argument"); }
-                            if !silent { println!("{}", display_value(&args[0])); }
+                            if !silent { println!("{}", args[0].display_depth(self.display_depth)); }
                             self.stack.push(Value::Unit);
                         }
                         Builtin::ShowF => {
-                            if args.is_empty() { return error("showf requires at least a format string"); }
-                            let fmt = match &args[0] { Value::Str(s) => s.clone(), _ => return error("showf first argument must be a string") };
-                            let mut out = String::new();
-                            let mut arg_i = 1usize;
-                            let mut chars = fmt.chars().peekable();
-                            while let Some(c) = chars.next() {
-                                if c == '%' {
-                                    match chars.next() {
-                                        Some('d') => {
-                                            if arg_i >= args.len() { return error("showf missing %d argument"); }
-                                            match &args[arg_i] { Value::Int(n) => out.push_str(&n.to_string()), other => return error(format!("%d expects int, got {:?}", other)) }
-                                            arg_i += 1;
-                                        }
-                                        Some('s') => {
-                                            if arg_i >= args.len() { return error("showf missing %s argument"); }
-                                            match &args[arg_i] {
-                                                Value::Str(s) => out.push_str(s),
-                                                Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
-                                                Value::List(items) => out.push_str(&display_value(&Value::List(items.clone()))),
-                                                other => return error(format!("%s expects string/bool/list, got {:?}", other)),
-                                            }
-                                            arg_i += 1;
-                                        }
-                                        Some('%') => out.push('%'),
-                                        Some(other) => return error(format!("Unsupported format specifier %{}", other)),
-                                        None => return error("Dangling % at end of format string"),
-                                    }
-                                } else {
-                                    out.push(c);
-                                }
-                            }
+                            let out = zirc_syntax::format::format_call("showf", &args)?;
                             if !silent { println!("{}", out); }
                             self.stack.push(Value::Unit);
                         }
-                        Builtin::Prompt => {
-                            if args.len() > 1 { return error("prompt() expects 0 or 1 arguments"); }
+                        Builtin::Format => {
+                            self.stack.push(Value::Str(zirc_syntax::format::format_call("format", &args)?));
+                        }
+                        Builtin::Prompt | Builtin::PromptTrimmed => {
+                            let trim = which == Builtin::PromptTrimmed;
+                            let name = if trim { "prompt_trimmed" } else { "prompt" };
+                            if args.len() > 1 { return error(format!("{}() expects 0 or 1 arguments", name)); }
                             let silent = std::env::var("ZIRC_BENCH_SILENT").is_ok();
                             if args.len() == 1 {
                                 if let Value::Str(s) = &args[0] {
                                     if !silent { print!("{}", s); io::stdout().flush().map_err(|e| format!("IO error: {}", e))?; }
-                                } else { return error("prompt() prompt must be string"); }
+                                } else { return error(format!("{}() prompt must be string, got {:?}", name, args[0])); }
                             }
                             let input = if silent {
                                 std::env::var("ZIRC_BENCH_PROMPT_REPLY").unwrap_or_default()
                             } else {
                                 let mut input = String::new();
                                 io::stdin().read_line(&mut input).map_err(|e| format!("IO error: {}", e))?;
-                                if input.ends_with('\n') { input.pop(); if input.ends_with('\r') { input.pop(); } }
-                                input
+                                builtins::strip_line_end(input)
                             };
-                            self.stack.push(Value::Str(input));
+                            self.stack.push(Value::Str(if trim { input.trim().to_string() } else { input }));
                         }
                         Builtin::Rf => {
                             if args.len() != 1 { return error("rf() expects exactly 1 argument"); }
@@ -553,14 +959,13 @@ impl Vm {
                             match &args[0] {
                                 Value::Str(s) => self.stack.push(Value::Int(s.chars().count() as i64)),
                                 Value::List(items) => self.stack.push(Value::Int(items.len() as i64)),
-                                other => return error(format!("len() expects string or list, got {:?}", other)),
+                                Value::Map(entries) => self.stack.push(Value::Int(entries.len() as i64)),
+                                other => return error(format!("len() expects string, list or map, got {:?}", other)),
                             }
                         }
-                        Builtin::Push => {
-                            return error("push() is not supported in VM mode - use the interpreter backend");
-                        }
-                        Builtin::Pop => {
-                            return error("pop() is not supported in VM mode - use the interpreter backend");
+                        // The compiler turns push()/pop() into ListPush/ListPop plus a store
+                        Builtin::Push | Builtin::Pop => {
+                            return error("push() and pop() must be compiled to ListPush/ListPop");
                         }
                         Builtin::Slice => {
                             if args.len() != 3 { return error("slice() expects exactly 3 arguments: collection, start, end"); }
@@ -574,8 +979,8 @@ impl Vm {
                                 other => return error(format!("slice() end index must be int, got {:?}", other)),
                             };
                             
-                            if start < 0 { return error("slice() start index cannot be negative"); }
-                            if end < start { return error("slice() end index must be >= start index"); }
+                            if start < 0 { return error(builtins::SLICE_NEGATIVE_START); }
+                            if end < start { return error(builtins::SLICE_END_BEFORE_START); }
                             
                             match &args[0] {
                                 Value::Str(s) => {
@@ -607,60 +1012,65 @@ impl Vm {
                         Builtin::Abs => {
                             if args.len() != 1 { return error("abs() expects exactly 1 argument"); }
                             match &args[0] {
-                                Value::Int(n) => self.stack.push(Value::Int(n.abs())),
-                                other => return error(format!("abs() expects int, got {:?}", other)),
-                            }
-                        }
-                        Builtin::Min => {
-                            if args.len() != 2 { return error("min() expects exactly 2 arguments"); }
-                            match (&args[0], &args[1]) {
-                                (Value::Int(x), Value::Int(y)) => self.stack.push(Value::Int(*x.min(y))),
-                                _ => return error("min() expects two ints"),
-                            }
-                        }
-                        Builtin::Max => {
-                            if args.len() != 2 { return error("max() expects exactly 2 arguments"); }
-                            match (&args[0], &args[1]) {
-                                (Value::Int(x), Value::Int(y)) => self.stack.push(Value::Int(*x.max(y))),
-                                _ => return error("max() expects two ints"),
+                                Value::Int(n) => self.stack.push(Value::Int(n.checked_abs().ok_or(builtins::OVERFLOW_ABS)?)),
+                                Value::Float(x) => self.stack.push(Value::Float(x.abs())),
+                                other => return error(format!("abs() expects a number, got {:?}", other)),
                             }
                         }
+                        Builtin::Min => self.stack.push(builtins::min_max("min", &args, false)?),
+                        Builtin::Max => self.stack.push(builtins::min_max("max", &args, true)?),
                         Builtin::Pow => {
                             if args.len() != 2 { return error("pow() expects exactly 2 arguments: base and exponent"); }
                             match (&args[0], &args[1]) {
                                 (Value::Int(b), Value::Int(e)) => {
-                                    if *e < 0 { return error("pow() exponent cannot be negative"); }
-                                    let result = (*b as f64).powi(*e as i32) as i64;
-                                    self.stack.push(Value::Int(result));
+                                    if *e < 0 { return error(builtins::POW_NEGATIVE_EXPONENT); }
+                                    self.stack.push(Value::Int(builtins::int_pow(*b, *e)?));
+                                },
+                                (b, e) => match float_operands(b, e) {
+                                    Some((fb, fe)) => self.stack.push(Value::Float(fb.powf(fe))),
+                                    None => return error("pow() expects two numbers"),
                                 },
-                                _ => return error("pow() expects two ints"),
                             }
                         }
                         Builtin::Sqrt => {
                             if args.len() != 1 { return error("sqrt() expects exactly 1 argument"); }
-                            match &args[0] {
-                                Value::Int(n) => {
-                                    if *n < 0 { return error("sqrt() argument cannot be negative"); }
-                                    let result = (*n as f64).sqrt() as i64;
-                                    self.stack.push(Value::Int(result));
-                                },
-                                other => return error(format!("sqrt() expects int, got {:?}", other)),
+                            match args[0].as_f64() {
+                                Some(x) if x < 0.0 => return error(builtins::SQRT_NEGATIVE),
+                                Some(x) => self.stack.push(Value::Float(x.sqrt())),
+                                None => return error(format!("sqrt() expects a number, got {:?}", args[0])),
                             }
                         }
+                        Builtin::Floor | Builtin::Ceil | Builtin::Round => {
+                            let name = match which { Builtin::Floor => "floor", Builtin::Ceil => "ceil", _ => "round" };
+                            if args.len() != 1 { return error(format!("{}() expects exactly 1 argument", name)); }
+                            self.stack.push(builtins::round_to_int(name, &args[0])?);
+                        }
                         Builtin::Hex => {
-                            if args.len() != 1 { return error("hex() expects exactly 1 argument"); }
+                            if args.is_empty() || args.len() > 2 { return error("hex() expects 1 or 2 arguments: int and optional prefix flag"); }
+                            let prefix = prefix_flag(&args, "hex")?;
                             match &args[0] {
-                                Value::Int(n) => self.stack.push(Value::Str(format!("{:x}", n))),
+                                Value::Int(n) => self.stack.push(Value::Str(builtins::radix_string(*n, 16, if prefix { "0x" } else { "" }))),
                                 other => return error(format!("hex() expects int, got {:?}", other)),
                             }
                         }
                         Builtin::Bin => {
-                            if args.len() != 1 { return error("bin() expects exactly 1 argument"); }
+                            if args.is_empty() || args.len() > 2 { return error("bin() expects 1 or 2 arguments: int and optional prefix flag"); }
+                            let prefix = prefix_flag(&args, "bin")?;
                             match &args[0] {
-                                Value::Int(n) => self.stack.push(Value::Str(format!("{:b}", n))),
+                                Value::Int(n) => self.stack.push(Value::Str(builtins::radix_string(*n, 2, if prefix { "0b" } else { "" }))),
                                 other => return error(format!("bin() expects int, got {:?}", other)),
                             }
                         }
+                        Builtin::ToBase => {
+                            if args.len() != 2 { return error("to_base() expects exactly 2 arguments: int and base"); }
+                            match (&args[0], &args[1]) {
+                                (Value::Int(n), Value::Int(b)) => {
+                                    if !(2..=36).contains(b) { return error(format!("to_base() base must be between 2 and 36, got {}", b)); }
+                                    self.stack.push(Value::Str(builtins::radix_string(*n, *b as u32, "")));
+                                },
+                                _ => return error("to_base() expects two ints"),
+                            }
+                        }
                         // String functions
                         Builtin::Upper => {
                             if args.len() != 1 { return error("upper() expects exactly 1 argument"); }
@@ -711,6 +1121,23 @@ impl Vm {
                                 _ => return error("join() expects list and string"),
                             }
                         }
+                        Builtin::Replace => {
+                            if args.len() != 3 { return error("replace() expects exactly 3 arguments: string, from and to"); }
+                            match (&args[0], &args[1], &args[2]) {
+                                (Value::Str(_), Value::Str(from), Value::Str(_)) if from.is_empty() => return error("replace() pattern must not be empty"),
+                                (Value::Str(s), Value::Str(from), Value::Str(to)) => self.stack.push(Value::Str(s.replace(from.as_str(), to))),
+                                _ => return error("replace() expects three strings"),
+                            }
+                        }
+                        Builtin::StartsWith | Builtin::EndsWith => {
+                            let suffix = which == Builtin::EndsWith;
+                            let (name, part) = if suffix { ("ends_with", "suffix") } else { ("starts_with", "prefix") };
+                            if args.len() != 2 { return error(format!("{}() expects exactly 2 arguments: string and {}", name, part)); }
+                            match (&args[0], &args[1]) {
+                                (Value::Str(s), Value::Str(a)) => self.stack.push(Value::Bool(if suffix { s.ends_with(a.as_str()) } else { s.starts_with(a.as_str()) })),
+                                _ => return error(format!("{}() expects two strings", name)),
+                            }
+                        }
                         // Type conversion functions
                         Builtin::Int => {
                             if args.len() != 1 { return error("int() expects exactly 1 argument"); }
@@ -722,44 +1149,171 @@ impl Vm {
                                         Err(_) => return error(format!("Cannot convert '{}' to int", s)),
                                     }
                                 },
+                                Value::Float(x) => self.stack.push(builtins::float_to_int(*x)?),
                                 Value::Bool(true) => self.stack.push(Value::Int(1)),
                                 Value::Bool(false) => self.stack.push(Value::Int(0)),
                                 other => return error(format!("Cannot convert {:?} to int", other)),
                             }
                         }
+                        Builtin::Bool => {
+                            if args.len() != 1 { return error("bool() expects exactly 1 argument"); }
+                            self.stack.push(Value::Bool(builtins::to_bool(&args[0])?));
+                        }
                         Builtin::Str => {
                             if args.len() != 1 { return error("str() expects exactly 1 argument"); }
                             let result = match &args[0] {
                                 Value::Str(s) => s.clone(),
                                 Value::Int(n) => n.to_string(),
+                                float @ Value::Float(_) => display_value(float),
                                 Value::Bool(b) => if *b { "true".to_string() } else { "false".to_string() },
-                                Value::List(items) => format!("{}", display_value(&Value::List(items.clone()))),
+                                v @ (Value::List(_) | Value::Map(_)) => display_value(v),
                                 Value::Unit => "<unit>".to_string(),
                             };
                             self.stack.push(Value::Str(result));
                         }
+                        Builtin::Chr => {
+                            if args.len() != 1 { return error("chr() expects exactly 1 argument"); }
+                            match &args[0] {
+                                Value::Int(n) => match u32::try_from(*n).ok().and_then(char::from_u32) {
+                                    Some(c) => self.stack.push(Value::Str(c.to_string())),
+                                    None => return error(format!("chr() code point {} is out of range or a surrogate", n)),
+                                },
+                                other => return error(format!("chr() expects an int, got {:?}", other)),
+                            }
+                        }
+                        Builtin::Ord => {
+                            if args.len() != 1 { return error("ord() expects exactly 1 argument"); }
+                            match &args[0] {
+                                Value::Str(s) => {
+                                    let mut chars = s.chars();
+                                    match (chars.next(), chars.next()) {
+                                        (Some(c), None) => self.stack.push(Value::Int(c as i64)),
+                                        _ => return error(format!("ord() expects a one-char string, got {:?}", s)),
+                                    }
+                                }
+                                other => return error(format!("ord() expects a string, got {:?}", other)),
+                            }
+                        }
                         // Utility functions
                         Builtin::Type => {
                             if args.len() != 1 { return error("type() expects exactly 1 argument"); }
-                            let type_name = match &args[0] {
-                                Value::Int(_) => "int",
-                                Value::Str(_) => "string",
-                                Value::Bool(_) => "bool",
-                                Value::List(_) => "list",
-                                Value::Unit => "unit",
+                            self.stack.push(Value::Str(args[0].type_name().to_string()));
+                        }
+                        Builtin::ScriptPath | Builtin::ScriptDir => {
+                            let dir = which == Builtin::ScriptDir;
+                            if argc != 0 { return error(format!("{}() expects no arguments", if dir { "script_dir" } else { "script_path" })); }
+                            let path = match &self.script_path {
+                                Some(p) if dir => p.parent(),
+                                Some(p) => Some(p.as_path()),
+                                None => None,
                             };
-                            self.stack.push(Value::Str(type_name.to_string()));
+                            self.stack.push(path.map_or(Value::Unit, |p| Value::Str(p.to_string_lossy().into_owned())));
+                        }
+                        Builtin::TimeNs => {
+                            if argc != 0 { return error("time_ns() expects no arguments"); }
+                            self.stack.push(Value::Int(self.clock.elapsed().as_nanos() as i64));
+                        }
+                        Builtin::Left | Builtin::Right => {
+                            let from_right = which == Builtin::Right;
+                            if args.len() != 2 {
+                                return error(format!("{}() expects exactly 2 arguments: collection and count", if from_right { "right" } else { "left" }));
+                            }
+                            self.stack.push(builtins::take_edge(&args[0], &args[1], from_right)?);
+                        }
+                        Builtin::Get => {
+                            if args.len() != 3 { return error("get() expects exactly 3 arguments: collection, key and default"); }
+                            self.stack.push(builtins::get_or(&args[0], &args[1], &args[2])?);
+                        }
+                        Builtin::Keys | Builtin::Values => {
+                            let name = if which == Builtin::Values { "values" } else { "keys" };
+                            if args.len() != 1 { return error(format!("{}() expects exactly 1 argument", name)); }
+                            match &args[0] {
+                                Value::Map(entries) => self.stack.push(builtins::map_entries(entries.clone(), which == Builtin::Values)),
+                                other => return error(format!("{}() expects map, got {:?}", name, other)),
+                            }
+                        }
+                        Builtin::Sort => {
+                            if args.len() != 1 { return error("sort() expects exactly 1 argument"); }
+                            match &args[0] {
+                                Value::List(items) => self.stack.push(Value::List(builtins::sorted(items)?)),
+                                other => return error(format!("sort() expects list, got {:?}", other)),
+                            }
+                        }
+                        Builtin::Reverse => {
+                            if args.len() != 1 { return error("reverse() expects exactly 1 argument"); }
+                            match &args[0] {
+                                Value::List(items) => self.stack.push(Value::List(items.iter().rev().cloned().collect())),
+                                Value::Str(s) => self.stack.push(Value::Str(s.chars().rev().collect())),
+                                other => return error(format!("reverse() expects list or string, got {:?}", other)),
+                            }
+                        }
+                        Builtin::Range => self.stack.push(Value::List(builtins::range_list(&args)?)),
+                        Builtin::Set => {
+                            if args.len() != 3 { return error("set() expects exactly 3 arguments: collection, key and value"); }
+                            self.stack.push(builtins::set_key(args[0].clone(), &args[1], args[2].clone())?);
+                        }
+                        Builtin::Copy => {
+                            if args.len() != 1 { return error("copy() expects exactly 1 argument"); }
+                            self.stack.push(args[0].clone());
+                        }
+                        Builtin::Contains => {
+                            if args.len() != 2 { return error("contains() expects exactly 2 arguments: haystack and needle"); }
+                            self.stack.push(Value::Bool(builtins::find(&args[0], &args[1], "contains")?.is_some()));
+                        }
+                        Builtin::IndexOf => {
+                            if args.len() != 2 { return error("index_of() expects exactly 2 arguments: haystack and needle"); }
+                            self.stack.push(Value::Int(builtins::find(&args[0], &args[1], "index_of")?.map_or(-1, |at| at as i64)));
+                        }
+                        Builtin::Hash => {
+                            if args.len() != 1 { return error("hash() expects exactly 1 argument"); }
+                            self.stack.push(Value::Int(zirc_syntax::hash::hash_value(&args[0])?));
+                        }
+                        Builtin::Panic => {
+                            if args.len() != 1 { return error("panic() expects exactly 1 argument"); }
+                            return error(display_value(&args[0]));
+                        }
+                        Builtin::Assert => {
+                            let ok = match args.first() {
+                                Some(Value::Bool(b)) if args.len() <= 2 => *b,
+                                Some(other) if args.len() <= 2 => return error(format!("assert() condition must be bool, got {:?}", other)),
+                                _ => return error("assert() expects 1 or 2 arguments: condition and optional message"),
+                            };
+                            if !ok {
+                                return match args.get(1) {
+                                    Some(msg) => error(format!("assertion failed: {}", display_value(msg))),
+                                    None => error(builtins::ASSERTION_FAILED),
+                                };
+                            }
+                            self.stack.push(Value::Unit);
+                        }
+                        Builtin::AssertEq => {
+                            if args.len() != 2 { return error("assert_eq() expects exactly 2 arguments"); }
+                            if !args[0].lang_eq(&args[1]) {
+                                return error(format!("assertion failed: {} != {}", display_value(&args[0]), display_value(&args[1])));
+                            }
+                            self.stack.push(Value::Unit);
                         }
                     }
                 }
+                Instruction::NativeCall(ni, argc) => {
+                    if self.stack.len() < argc { return error("stack underflow in NativeCall"); }
+                    let native = natives.get(ni as usize).ok_or("invalid native index")?;
+                    let start = self.stack.len() - argc;
+                    let args = self.stack.split_off(start);
+                    let v = native(&args)?;
+                    self.stack.push(v);
+                }
                 Instruction::Halt => { break; }
-                Instruction::LoadGlobal(name) => {
-                    let v = self.globals.get(&name).cloned().ok_or_else(|| format!("Undefined variable '{}'", name))?;
+                Instruction::LoadGlobal(ref name) => {
+                    let v = self.globals.get(name).cloned().ok_or_else(|| format!("Undefined variable '{}'", name))?;
                     self.stack.push(v);
                 }
-                Instruction::StoreGlobal(name) => {
-                    let v = self.stack.pop().ok_or_else(|| "stack underflow in StoreGlobal")?;
-                    self.globals.insert(name, v);
+                Instruction::StoreGlobal(ref name) => {
+                    let v = self.stack.pop().ok_or("stack underflow in StoreGlobal")?;
+                    match self.globals.get_mut(name) {
+                        Some(slot) => *slot = v,
+                        None => { self.globals.insert(name.clone(), v); }
+                    }
                 }
             }
         }
@@ -767,3 +1321,12 @@ impl Vm {
     }
 }
 
+/// Reads the optional prefix flag of `hex`/`bin`, defaulting to `true`.
+fn prefix_flag(args: &[Value], name: &str) -> Result<bool> {
+    match args.get(1) {
+        None => Ok(true),
+        Some(Value::Bool(b)) => Ok(*b),
+        Some(other) => error(format!("{}() prefix flag must be bool, got {:?}", name, other)),
+    }
+}
+